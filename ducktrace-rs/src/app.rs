@@ -1,9 +1,27 @@
-use crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind};
-use log::{debug, info};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use crate::data::{ChartData, ExplainData, HistoryEntry};
-use crate::ui::query::get_query_line_count;
-use crate::watcher::{get_data_path, load_data, load_history_entries};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use log::{debug, info, warn};
+
+use crate::config::{self, Config, ExplainSize, Profile, UiState};
+use crate::db::{target_label, ConnectionState, MOTHERDUCK};
+use crate::editor::SqlEditor;
+use crate::hooks::Hook;
+use crate::tasks::{TaskKind, TaskRegistry};
+use crate::export::ExportJob;
+use crate::logging;
+use crate::keymap::VimKeys;
+use crate::mode::{edit_text, Command, CommandKind, Confirm, Edit, Mode, SearchTarget};
+use crate::data::{bind_placeholders, compare_numbers, count_query, diff_lines, fold_regions, innermost_fold, visible_lines, ColumnFilter, file_drill_down, file_query, page_query, parse_tags, placeholder_names, Attachment, BoundQuery, BarMode, BarOrientation, ChartData, ChartSort, DiffLine, ExplainData, HistoryEntry, QueryLogEntry, XRange, value_to_string};
+use crate::ui::chart::{hit_test, row_at, ChartHit};
+use crate::ui::theme::{parse_color, Palette};
+use crate::ui::query::{format_sql, SQL_FUNCTIONS, SQL_KEYWORDS};
+use crate::watcher::{
+    append_query_log, get_data_dir, get_data_path, load_data, load_dir_entries, load_history_entries, reads_stdin,
+    remove_history_entry, rename_history_entry, save_history_meta,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Tab {
@@ -12,6 +30,7 @@ pub enum Tab {
     Mask = 2,
     Data = 3,
     Chart = 4,
+    Sql = 5,
 }
 
 impl Tab {
@@ -22,29 +41,175 @@ impl Tab {
             2 => Tab::Mask,
             3 => Tab::Data,
             4 => Tab::Chart,
+            5 => Tab::Sql,
             _ => Tab::Home,
         }
     }
 
     pub fn next(&self) -> Self {
-        Tab::from_index((*self as usize + 1) % 5)
+        Tab::from_index((*self as usize + 1) % 6)
     }
 
     pub fn prev(&self) -> Self {
-        Tab::from_index((*self as usize + 4) % 5)
+        Tab::from_index((*self as usize + 5) % 6)
     }
 }
 
+/// Most drill-downs kept in the in-memory query log
+const MAX_QUERY_LOG: usize = 200;
+
+/// Most charts kept open in the workspace; opening another closes the oldest
+const WORKSPACE_LIMIT: usize = 9;
+
+/// Lines of the log file the log tail overlay keeps
+const APP_LOG_LINES: usize = 1000;
+
+/// Choices for the auto-refresh interval, stepped through with `R`
+const AUTO_REFRESH_INTERVALS: [Duration; 3] = [
+    Duration::from_secs(30),
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+];
+
+/// Most drill-down results kept in the cache
+const MAX_CACHED_DRILL_DOWNS: usize = 50;
+
+/// History entries per page of the Home tab list
+pub const HISTORY_PAGE: usize = 10;
+
+/// How long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// Most toasts kept at once; a new one pushes out the oldest
+const MAX_TOASTS: usize = 4;
+
+/// Longest gap between two clicks on a Data tab row that drills down
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Drill-downs slower than this get a toast with their time
+const SLOW_QUERY_MS: u64 = 5000;
+
+/// Outcome of dry-running the chart's drill-down templates, shown on the Mask tab
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateCheck {
+    Running,
+    /// Problems found, one per line; empty when every template planned cleanly
+    Done(Vec<String>),
+}
+
+/// Two history charts opened together from the Home tab, older first
+pub struct Comparison {
+    pub entries: [HistoryEntry; 2],
+    pub charts: [ChartData; 2],
+    /// Both charts drawn as one, when they plot the same x values
+    pub overlay: Option<ChartData>,
+    /// Draw the overlay rather than the charts stacked (Tab toggles)
+    pub show_overlay: bool,
+    /// The older chart's query turned into the newer one's, formatted line by line
+    pub query_diff: Vec<DiffLine>,
+    pub diff_scroll: usize,
+    /// Each chart's colors; the overlay uses the older one's
+    pub palettes: [Palette; 2],
+}
+
+/// A chart kept open in the workspace, to switch back to with `Tab`
+pub struct OpenChart {
+    /// File it was loaded from; None for a chart from stdin or the socket
+    pub path: Option<PathBuf>,
+    /// Results of a SQL tab query, which each run replaces
+    pub sql: bool,
+    /// The chart, or None for the one on screen, which is [`App::data`]
+    data: Option<ChartData>,
+    /// Where it was left, restored when it's switched back to
+    active_tab: Tab,
+    selected_point: usize,
+    scroll_offset: usize,
+    query_folds: BTreeSet<usize>,
+}
+
+/// A short-lived message in the top-right corner about something that happened
+/// in the background
+pub struct Toast {
+    pub text: String,
+    pub kind: ToastKind,
+    expires: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Success,
+    Error,
+}
+
+/// A finished drill-down's rows and when they were fetched
+struct CachedDrillDown {
+    data: ExplainData,
+    fetched_at: Instant,
+}
+
 pub struct App {
+    pub config: Config,
+    /// Colors from the config's palette and the open chart's, resolved when
+    /// either changes rather than every frame
+    pub palette: Palette,
+    /// Why the edited config file couldn't be loaded; the previous config stays in use
+    pub config_error: Option<String>,
+    /// Connection profile in use, by name in `config.profiles`
+    pub active_profile: Option<String>,
+    /// Why the last profile switch failed
+    pub profile_error: Option<String>,
+    /// Profile whose token to read before switching to it (polled by main loop)
+    pending_profile: Option<(Option<String>, Option<Profile>)>,
+    /// Profile being switched to while its token is read
+    switching_profile: Option<Option<String>>,
+    /// Overlays and prompts open, innermost last; keys go to the last one
+    pub modes: Vec<Mode>,
+    /// Vim-style navigation keys, with a pending `g`
+    vim_keys: VimKeys,
     pub data: Option<ChartData>,
+    /// File the open chart was loaded from
+    pub data_path: Option<PathBuf>,
+    /// Charts opened this session, in the order they were opened; the one on
+    /// screen is at `workspace_current` while `data` is set
+    pub workspace: Vec<OpenChart>,
+    pub workspace_current: usize,
+    /// A watched file that changed but didn't parse, and why; the chart on screen is
+    /// the last good one
+    pub file_error: Option<(PathBuf, String)>,
     pub active_tab: Tab,
+    /// Top row of the Query tab, counting a folded region as one
     pub scroll_offset: usize,
+    /// Start lines of the folded regions in the formatted query
+    pub query_folds: BTreeSet<usize>,
+    /// A `z` was pressed on the Query tab, so the next key is a fold command
+    pub fold_pending: bool,
     pub selected_point: usize,
-    pub show_help: bool,
+    /// Fixed end of a range brush on the Chart tab; the selected point is the other end
+    pub range_anchor: Option<usize>,
+    /// Bar layout override for multi-series bar charts (None = chart's default)
+    pub bar_mode: Option<BarMode>,
+    /// Bar orientation override for single-series bar charts (None = chart's default)
+    pub bar_orientation: Option<BarOrientation>,
+    /// Drawing order of single-series bar and line charts; the rows keep theirs
+    pub chart_sort: ChartSort,
+    /// Data tab filter text; rows without a matching cell are hidden
+    pub search_query: String,
+    /// Query tab search text; `n`/`N` scroll between lines containing it
+    pub query_search: String,
+    /// Chart tab search text; x labels containing it are highlighted
+    pub chart_search: String,
+    /// Click targets from the last chart render
+    pub chart_hits: Vec<ChartHit>,
+    /// Click targets for the Data tab's rows on screen
+    pub data_hits: Vec<ChartHit>,
+    /// Click targets for the drill-down table's rows, by position in `explain_sorted_indices`
+    pub explain_hits: Vec<ChartHit>,
+    /// Row and time of the last click on a Data tab row, to spot a double click
+    last_click: Option<(usize, Instant)>,
     pub running: bool,
     pub frame: u32,
     // Explain mode state
-    pub show_explain: bool,
     pub explain_data: Option<ExplainData>,
     pub explain_loading: bool,
     pub explain_error: Option<String>,
@@ -53,24 +218,150 @@ pub struct App {
     pub explain_sort_column: Option<usize>,
     pub explain_sort_asc: bool,
     pub explain_sorted_indices: Vec<usize>,
+    /// Filters on the explain table by column; rows must pass them all
+    pub explain_filters: HashMap<usize, ColumnFilter>,
+    /// Show the selected column's profile above the explain table (`s` toggles)
+    pub explain_show_stats: bool,
+    /// What the last `y`/`Y` copied, shown in the explain overlay until the next key
+    pub explain_notice: Option<String>,
+    /// Size of the explain overlay (`+`/`-` step it, `z` toggles fullscreen), saved between runs
+    pub explain_size: ExplainSize,
+    /// Size to return to when `z` leaves fullscreen
+    explain_unzoomed_size: Option<ExplainSize>,
+    /// Query behind the explain overlay, kept for refreshing it
+    explain_query: Option<BoundQuery>,
+    /// Title shown on the explain overlay for `explain_query`'s results
+    explain_title: String,
+    /// When the overlay's rows were fetched, if they came from the cache
+    pub explain_cached_at: Option<Instant>,
+    /// Rows already shown when the page being fetched with `n` was requested
+    explain_page_offset: Option<usize>,
+    /// Finished drill-down results by target, SQL, and parameters
+    drill_down_cache: HashMap<String, CachedDrillDown>,
     /// Pending drill-down query to execute (polled by main loop)
-    pending_drill_down_query: Option<String>,
+    pending_drill_down_query: Option<BoundQuery>,
+    /// Bumped whenever a drill-down is queued or the overlay closes; results
+    /// tagged with an older generation belong to a query the user moved on from
+    drill_down_generation: u64,
+    /// Hook commands to run and the JSON each gets on stdin (taken by main loop)
+    pending_hooks: Vec<(String, serde_json::Value)>,
+    /// Latest connection status and the target it belongs to
+    pub connection: Option<(String, ConnectionState)>,
+    // Query plan (EXPLAIN ANALYZE) overlay state
+    pub plan_text: Option<String>,
+    pub plan_loading: bool,
+    pub plan_error: Option<String>,
+    pub plan_scroll: usize,
+    pub plan_hscroll: usize,
+    /// Pending (query, database) to explain (polled by main loop)
+    pending_plan_query: Option<(String, Option<String>)>,
+    // Re-run of the chart's own query from the Query tab
+    pub rerun_loading: bool,
+    pub rerun_error: Option<String>,
+    /// Pending (query, database) to re-run (polled by main loop)
+    pending_rerun_query: Option<(String, Option<String>)>,
+    /// Query text to open in an external editor (polled by main loop)
+    pending_external_edit: Option<String>,
+    /// The query from the last `e` edit: running, or kept after it failed so the
+    /// next `e` reopens it
+    edited_query: Option<String>,
+    /// Show the Query tab as a diff of the original query against the edited one
+    pub query_diff: bool,
+    /// Interval for re-running the chart's query automatically (None = off)
+    pub auto_refresh: Option<Duration>,
+    /// When the next automatic re-run is due
+    next_refresh: Option<Instant>,
+    // Ad-hoc SQL tab state
+    pub sql_editor: SqlEditor,
+    pub sql_running: bool,
+    pub sql_error: Option<String>,
+    /// Pending (sql, database) to run from the SQL tab (polled by main loop)
+    pending_sql_query: Option<(String, Option<String>)>,
+    /// Table, column, and database names offered by Tab completion
+    pub schema_names: Vec<String>,
+    /// Target and database `schema_names` were loaded for
+    schema_source: Option<(String, Option<String>)>,
+    /// Pending database to load schema names for (polled by main loop)
+    pending_schema: Option<Option<String>>,
+    /// Text to put on the clipboard (polled by main loop)
+    pending_clipboard: Option<String>,
+    /// Chart export to draw (polled by main loop)
+    pending_export: Option<ExportJob>,
+    /// Result of validating the drill-down templates, once the Mask tab has been viewed
+    pub template_check: Option<TemplateCheck>,
+    /// Target and templates `template_check` belongs to
+    template_check_source: Option<String>,
+    /// Problems found before the templates were sent to `EXPLAIN`
+    template_check_problems: Vec<String>,
+    // Drill-down query log overlay state
+    /// Executed drill-downs, oldest first
+    pub query_log: Vec<QueryLogEntry>,
+    /// Selected entry, counted from the newest
+    pub query_log_selected: usize,
+    // Log tail overlay state
+    /// Last lines of the log file, re-read while the overlay is open
+    pub app_log_lines: Vec<String>,
+    /// Why the log couldn't be read
+    pub app_log_error: Option<String>,
+    /// Lines scrolled up from the end; 0 follows new lines
+    pub app_log_scroll: usize,
+    /// Show only WARN and ERROR lines
+    pub app_log_warnings_only: bool,
     // History state for Home tab data selector
     pub history: Vec<HistoryEntry>,
     pub history_selected: usize,
+    /// Home tab filter over history titles, databases, and x/y fields
+    pub history_filter: String,
+    /// Background notifications, oldest first; dropped once expired
+    pub toasts: Vec<Toast>,
+    /// What `y`/`Y` on the Query tab just copied; cleared by the next key
+    pub copy_notice: Option<String>,
+    /// Work spawned by the main loop, for the task panel (`T`)
+    pub tasks: TaskRegistry,
+    /// History entries marked for comparison (`m` on the Home tab), at most two
+    pub history_marked: Vec<PathBuf>,
+    /// Comparison overlay of the two marked entries (`C` on the Home tab)
+    pub comparison: Option<Comparison>,
+    /// Column highlighted on the Mask tab, for picking the x and y columns
+    pub mask_selected: usize,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(config: Config) -> Self {
+        let auto_refresh = config.auto_refresh.filter(|&secs| secs > 0).map(Duration::from_secs);
         Self {
+            palette: Palette::resolve(&config.palette, None),
+            config,
+            config_error: None,
+            active_profile: None,
+            profile_error: None,
+            pending_profile: None,
+            switching_profile: None,
+            modes: Vec::new(),
+            vim_keys: VimKeys::default(),
             data: None,
+            data_path: None,
+            workspace: Vec::new(),
+            workspace_current: 0,
+            file_error: None,
             active_tab: Tab::Home,
             scroll_offset: 0,
+            query_folds: BTreeSet::new(),
+            fold_pending: false,
             selected_point: 0,
-            show_help: false,
+            range_anchor: None,
+            bar_mode: None,
+            bar_orientation: None,
+            chart_sort: ChartSort::Original,
+            search_query: String::new(),
+            query_search: String::new(),
+            chart_search: String::new(),
+            chart_hits: Vec::new(),
+            data_hits: Vec::new(),
+            explain_hits: Vec::new(),
+            last_click: None,
             running: true,
             frame: 0,
-            show_explain: false,
             explain_data: None,
             explain_loading: false,
             explain_error: None,
@@ -79,94 +370,868 @@ impl App {
             explain_sort_column: None,
             explain_sort_asc: true,
             explain_sorted_indices: Vec::new(),
+            explain_filters: HashMap::new(),
+            explain_show_stats: false,
+            explain_notice: None,
+            explain_size: ExplainSize::default(),
+            explain_unzoomed_size: None,
+            explain_query: None,
+            explain_title: String::new(),
+            explain_cached_at: None,
+            explain_page_offset: None,
+            drill_down_cache: HashMap::new(),
             pending_drill_down_query: None,
+            drill_down_generation: 0,
+            pending_hooks: Vec::new(),
+            connection: None,
+            plan_text: None,
+            plan_loading: false,
+            plan_error: None,
+            plan_scroll: 0,
+            plan_hscroll: 0,
+            pending_plan_query: None,
+            rerun_loading: false,
+            rerun_error: None,
+            pending_rerun_query: None,
+            pending_external_edit: None,
+            edited_query: None,
+            query_diff: false,
+            auto_refresh,
+            next_refresh: None,
+            sql_editor: SqlEditor::default(),
+            sql_running: false,
+            sql_error: None,
+            pending_sql_query: None,
+            schema_names: Vec::new(),
+            schema_source: None,
+            pending_schema: None,
+            pending_clipboard: None,
+            pending_export: None,
+            template_check: None,
+            template_check_source: None,
+            template_check_problems: Vec::new(),
+            query_log: Vec::new(),
+            query_log_selected: 0,
+            app_log_lines: Vec::new(),
+            app_log_error: None,
+            app_log_scroll: 0,
+            app_log_warnings_only: false,
             history: Vec::new(),
             history_selected: 0,
+            history_filter: String::new(),
+            toasts: Vec::new(),
+            copy_notice: None,
+            tasks: TaskRegistry::default(),
+            history_marked: Vec::new(),
+            comparison: None,
+            mask_selected: 0,
         }
     }
 
     pub fn on_data_update(&mut self, data: ChartData) {
         self.selected_point = 0;
+        self.mask_selected = 0;
+        self.range_anchor = None;
         self.scroll_offset = 0;
+        self.query_folds.clear();
+        self.fold_pending = false;
+        self.clear_search();
+        self.clear_query_search();
+        self.clear_chart_search();
+        self.edited_query = None;
+        self.query_diff = false;
+        self.bar_mode = None;
+        self.bar_orientation = None;
+        self.chart_sort = ChartSort::Original;
+        // A re-run still in flight was for the previous chart
+        self.rerun_loading = false;
+        self.rerun_error = None;
+        self.next_refresh = self.auto_refresh.map(|interval| Instant::now() + interval);
+        // Threshold colors are parsed as they're drawn, so a bad one is reported here, once
+        for color in data.thresholds.iter().filter_map(|t| t.color.as_deref()) {
+            if parse_color(color).is_none() {
+                warn!("Ignoring unrecognized threshold color {:?}", color);
+            }
+        }
         self.data = Some(data);
+        self.resolve_palette();
         self.active_tab = Tab::Query;
     }
 
-    pub fn clear_data(&mut self) {
-        let path = get_data_path();
-        let _ = std::fs::remove_file(&path);
+    /// Resolve the colors for the config and the open chart, after either changes
+    fn resolve_palette(&mut self) {
+        self.palette = Palette::resolve(&self.config.palette, self.data.as_ref().and_then(|d| d.palette.as_ref()));
+    }
+
+    /// A watched file changed, or a chart arrived on the socket. Outside directory mode
+    /// it always replaces the open chart; in directory mode a change to one of the
+    /// directory's charts replaces it only if it's the open one, and others just
+    /// update the list.
+    pub fn on_file_change(&mut self, path: PathBuf, data: ChartData) {
+        let is_open = self.data.is_none() || self.data_path.as_ref() == Some(&path);
+        let in_dir = get_data_dir().is_some_and(|dir| path.parent() == Some(dir.as_path()));
+        if self.file_error.as_ref().is_some_and(|(p, _)| *p == path) {
+            self.file_error = None;
+            self.leave_mode(|m| *m == Mode::FileProblems);
+        }
+        if !in_dir || is_open {
+            if self.data.is_some() {
+                self.toast(ToastKind::Info, format!("New chart: {}", data.title));
+            }
+            let mut data = data;
+            let data_file = file_query(&path).is_some();
+            // A rewritten CSV or Parquet file keeps the columns picked on the Mask tab
+            if let Some(ref old) = self.data {
+                if data_file
+                    && self.data_path.as_ref() == Some(&path)
+                    && data.columns.contains(&old.x_field)
+                    && data.columns.contains(&old.y_field)
+                {
+                    data.x_field = old.x_field.clone();
+                    data.y_field = old.y_field.clone();
+                    data.drill_down = Some(file_drill_down(&data.query, &data.x_field));
+                }
+            }
+            self.show_chart(Some(path), data);
+            // A data file has no chart spec, so start where its columns are mapped
+            if data_file {
+                self.active_tab = Tab::Mask;
+            }
+        } else if let Some(open) = self.workspace.iter_mut().find(|c| c.path.as_ref() == Some(&path)) {
+            // A directory chart open in the background picks up the change too
+            let title = data.title.clone();
+            open.data = Some(data);
+            self.toast(ToastKind::Info, format!("Updated in the background: {}", title));
+        }
+    }
+
+    /// A watched file changed but couldn't be parsed. Only problems with the file
+    /// that would have been shown are surfaced, in a panel listing them; the
+    /// current chart stays up.
+    pub fn on_file_error(&mut self, path: PathBuf, error: String) {
+        let is_open = self.data.is_none() || self.data_path.as_ref() == Some(&path);
+        let in_dir = get_data_dir().is_some_and(|dir| path.parent() == Some(dir.as_path()));
+        if !in_dir || is_open {
+            self.file_error = Some((path, error));
+            self.enter_mode(Mode::FileProblems);
+        }
+    }
+
+    /// The config file was edited. Settings are read where they're used, so a good
+    /// config takes effect from the next render or query; a bad one is reported and
+    /// the previous config kept.
+    pub fn on_config_change(&mut self, result: Result<Config, String>) {
+        match result {
+            Ok(config) => {
+                info!("Config reloaded");
+                if config.auto_refresh != self.config.auto_refresh {
+                    self.auto_refresh = config.auto_refresh.filter(|&secs| secs > 0).map(Duration::from_secs);
+                    self.next_refresh = self.auto_refresh.map(|interval| Instant::now() + interval);
+                }
+                self.config = config;
+                self.config_error = None;
+                self.resolve_palette();
+            }
+            Err(e) => {
+                warn!("Keeping previous config: {}", e);
+                self.config_error = Some(e);
+            }
+        }
+    }
+
+    /// Lines appended to the append file extend the open chart. A selection on the
+    /// last row follows new rows in, so a live chart's newest point stays selected.
+    pub fn on_append_rows(&mut self, rows: Vec<serde_json::Value>) {
+        let Some(ref mut data) = self.data else {
+            debug!("Ignoring {} appended rows with no chart open", rows.len());
+            return;
+        };
+        let len = data.rows.len();
+        let following = self.selected_point + 1 >= len;
+        let added = data.append_rows(rows);
+        debug!("Appended {} rows to the open chart", added);
+        // Rows dropped off the front shift the selection up
+        let dropped = (len + added).saturating_sub(data.rows.len());
+        self.selected_point = if following {
+            data.rows.len().saturating_sub(1)
+        } else {
+            self.selected_point.saturating_sub(dropped)
+        };
+        self.range_anchor = self.range_anchor.map(|a| a.saturating_sub(dropped));
+    }
+
+    /// A chart piped in on stdin replaces whatever is open
+    pub fn on_stdin_chart(&mut self, data: ChartData) {
+        if self.data.is_some() {
+            self.toast(ToastKind::Info, format!("New chart: {}", data.title));
+        }
+        self.show_chart(None, data);
+    }
+
+    /// Put `data` on screen, in the workspace slot of the chart from the same file
+    /// (or the one from stdin), or a new one. The chart being replaced stays in the
+    /// workspace unless it's the same one.
+    fn show_chart(&mut self, path: Option<PathBuf>, data: ChartData) {
+        self.show_in_slot(path, false, data);
+    }
+
+    /// `show_chart` for a chart from `path`, or for SQL tab results with `sql`,
+    /// which get a slot of their own so the chart they were run from stays open
+    fn show_in_slot(&mut self, path: Option<PathBuf>, sql: bool, data: ChartData) {
+        match self.workspace.iter().position(|c| c.path == path && c.sql == sql) {
+            Some(i) if i == self.workspace_current && self.data.is_some() => {}
+            Some(i) => {
+                self.stash_chart();
+                self.workspace_current = i;
+            }
+            None => {
+                self.stash_chart();
+                if self.workspace.len() >= WORKSPACE_LIMIT {
+                    self.workspace.remove(0);
+                }
+                self.workspace.push(OpenChart {
+                    path: path.clone(),
+                    sql,
+                    data: None,
+                    active_tab: Tab::Query,
+                    selected_point: 0,
+                    scroll_offset: 0,
+                    query_folds: BTreeSet::new(),
+                });
+                self.workspace_current = self.workspace.len() - 1;
+            }
+        }
+        self.workspace[self.workspace_current].data = None;
+        self.on_data_update(data);
+        self.data_path = path;
+        self.fire_hook(Hook::ChartLoaded, |app| {
+            let data = app.data.as_ref()?;
+            Some(serde_json::json!({
+                "path": app.data_path,
+                "title": data.title,
+                "query": data.query,
+                "x": data.x_field,
+                "y": data.y_field,
+                "chartType": data.chart_type,
+                "columns": data.columns,
+                "rowCount": data.rows.len(),
+            }))
+        });
+    }
+
+    /// Queue `hook`'s command, if one is configured, with the event's JSON
+    fn fire_hook(&mut self, hook: Hook, payload: impl FnOnce(&Self) -> Option<serde_json::Value>) {
+        let Some(command) = self.config.hooks.command(hook) else { return };
+        let Some(mut payload) = payload(self) else { return };
+        payload["event"] = hook.name().into();
+        debug!("Running {} hook", hook.name());
+        self.pending_hooks.push((command.to_string(), payload));
+    }
+
+    /// Show `text` in the top-right corner for a few seconds
+    fn toast(&mut self, kind: ToastKind, text: impl Into<String>) {
+        if self.toasts.len() >= MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push(Toast { text: text.into(), kind, expires: Instant::now() + TOAST_DURATION });
+    }
+
+    /// Take hook commands to run with their JSON (called by main loop)
+    pub fn take_pending_hooks(&mut self) -> Vec<(String, serde_json::Value)> {
+        std::mem::take(&mut self.pending_hooks)
+    }
+
+    /// Run the point_selected hook when a key or click moved the selection on the
+    /// Chart or Data tab
+    fn after_selection(&mut self, previous: usize) {
+        if self.selected_point == previous || !matches!(self.active_tab, Tab::Chart | Tab::Data) {
+            return;
+        }
+        self.fire_hook(Hook::PointSelected, |app| {
+            let data = app.data.as_ref()?;
+            let row = data.rows.get(app.selected_point)?;
+            let values: serde_json::Map<String, serde_json::Value> =
+                data.columns.iter().cloned().zip(row.iter().cloned()).collect();
+            Some(serde_json::json!({
+                "title": data.title,
+                "row": app.selected_point,
+                "x": row.get(data.get_x_index()),
+                "y": row.get(data.get_y_index()),
+                "values": values,
+            }))
+        });
+    }
+
+    /// Run the drilldown_completed hook for the overlay's query
+    fn drill_down_hook(&mut self) {
+        self.fire_hook(Hook::DrilldownCompleted, |app| {
+            let query = app.explain_query.as_ref()?;
+            let (columns, rows, total) = match app.explain_data {
+                Some(ref data) => (data.columns.clone(), data.rows.clone(), data.total_count),
+                None => Default::default(),
+            };
+            Some(serde_json::json!({
+                "title": app.explain_title,
+                "sql": query.sql,
+                "params": query.params,
+                "columns": columns,
+                "rows": rows,
+                "totalCount": total,
+                "error": app.explain_error,
+            }))
+        });
+    }
+
+    /// Move the chart on screen back into its workspace slot, with where it was left
+    fn stash_chart(&mut self) {
+        let Some(data) = self.data.take() else { return };
+        if let Some(open) = self.workspace.get_mut(self.workspace_current) {
+            open.data = Some(data);
+            open.active_tab = self.active_tab;
+            open.selected_point = self.selected_point;
+            open.scroll_offset = self.scroll_offset;
+            open.query_folds = std::mem::take(&mut self.query_folds);
+        }
+        self.data_path = None;
+        self.resolve_palette();
+        self.close_explain();
+    }
+
+    /// Switch to the workspace chart at `index`, where it was left
+    fn switch_chart(&mut self, index: usize) {
+        if index >= self.workspace.len() || (index == self.workspace_current && self.data.is_some()) {
+            return;
+        }
+        self.stash_chart();
+        self.workspace_current = index;
+        let open = &mut self.workspace[index];
+        let Some(data) = open.data.take() else { return };
+        let (tab, point, scroll) = (open.active_tab, open.selected_point, open.scroll_offset);
+        let (path, folds) = (open.path.clone(), std::mem::take(&mut open.query_folds));
+        self.on_data_update(data);
+        self.data_path = path;
+        self.active_tab = tab;
+        self.selected_point = point;
+        self.scroll_offset = scroll;
+        self.query_folds = folds;
+    }
+
+    /// Step to the previous or next chart in the workspace, wrapping around
+    fn cycle_chart(&mut self, forward: bool) {
+        let len = self.workspace.len();
+        if len == 0 {
+            return;
+        }
+        let index = match (self.data.is_some(), forward) {
+            (false, true) => 0,
+            (false, false) => len - 1,
+            (true, true) => (self.workspace_current + 1) % len,
+            (true, false) => (self.workspace_current + len - 1) % len,
+        };
+        self.switch_chart(index);
+    }
+
+    /// Drop the workspace chart at `index`. Closing the one on screen switches to
+    /// the chart before it, or to the Home tab when it was the last.
+    fn close_chart(&mut self, index: usize) {
+        if index >= self.workspace.len() {
+            return;
+        }
+        let on_screen = self.data.is_some() && index == self.workspace_current;
+        if on_screen {
+            self.data = None;
+            self.data_path = None;
+            self.resolve_palette();
+            self.close_explain();
+        }
+        self.workspace.remove(index);
+        if self.workspace_current > index || self.workspace_current >= self.workspace.len() {
+            self.workspace_current = self.workspace_current.saturating_sub(1);
+        }
+        if on_screen {
+            if self.workspace.is_empty() {
+                self.active_tab = Tab::Home;
+            } else {
+                let previous = index.saturating_sub(1).min(self.workspace.len() - 1);
+                self.switch_chart(previous);
+            }
+        }
+    }
+
+    /// The chart in workspace slot `index`, whether on screen or not
+    pub fn workspace_chart(&self, index: usize) -> Option<&ChartData> {
+        let open = self.workspace.get(index)?;
+        open.data.as_ref().or(if index == self.workspace_current { self.data.as_ref() } else { None })
+    }
+
+    /// Whether clearing deletes the data file: a directory's charts are the user's
+    /// own files, so they are only closed, and with --stdin the data file isn't in use
+    pub fn clear_deletes_file(&self) -> bool {
+        get_data_dir().is_none() && !reads_stdin()
+    }
+
+    fn clear_data(&mut self) {
+        if self.clear_deletes_file() {
+            let _ = std::fs::remove_file(get_data_path());
+        }
+        if self.data.is_some() && self.workspace_current < self.workspace.len() {
+            self.workspace.remove(self.workspace_current);
+            self.workspace_current = self.workspace_current.saturating_sub(1);
+        }
         self.data = None;
+        self.data_path = None;
+        self.resolve_palette();
         self.selected_point = 0;
+        self.range_anchor = None;
         self.scroll_offset = 0;
+        self.clear_search();
+        self.rerun_loading = false;
+        self.rerun_error = None;
         self.active_tab = Tab::Home;
         self.close_explain();
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) {
-        // Any key closes help
-        if self.show_help {
-            self.show_help = false;
-            return;
+        self.copy_notice = None;
+        let selected = self.selected_point;
+
+        let key = match self.modes.last() {
+            Some(mode) if mode.takes_text() => Some(key),
+            Some(Mode::Help | Mode::FileProblems) => Some(key),
+            // Keys rebound in the config stand in for the defaults, then vim keys for
+            // the arrows, so a binding can claim a vim key
+            None => self.config.keys.translate(key).and_then(|key| self.vim_keys.translate(key)),
+            Some(_) => self.vim_keys.translate(key),
+        };
+        let Some(key) = key else { return };
+
+        match self.modes.last().cloned() {
+            None => self.handle_normal_key(key),
+            // Any key closes help and the file problems panel; `!` brings the panel back
+            Some(Mode::Help | Mode::FileProblems) => {
+                self.modes.pop();
+            }
+            Some(Mode::Explain) => self.handle_explain_key(key),
+            Some(Mode::Plan) => self.handle_plan_key(key),
+            Some(Mode::Compare) => self.handle_compare_key(key),
+            Some(Mode::QueryLog) => self.handle_query_log_key(key),
+            Some(Mode::AppLog) => self.handle_app_log_key(key),
+            Some(Mode::ProfilePicker(selected)) => self.handle_profile_picker_key(selected, key),
+            Some(Mode::ChartPicker(selected)) => self.handle_chart_picker_key(selected, key),
+            Some(Mode::Tasks(selected)) => self.handle_tasks_key(selected, key),
+            Some(Mode::Search(target)) => self.handle_search_key(target, key),
+            Some(Mode::Command(_)) => self.handle_command_key(key),
+            Some(Mode::Editor) => self.handle_sql_key(key),
+            Some(Mode::Confirm(action)) => self.handle_confirm_key(action, key),
         }
+        self.after_selection(selected);
+    }
 
-        // Handle explain overlay
-        if self.show_explain {
-            match key.code {
-                KeyCode::Esc | KeyCode::Char('q') => {
-                    self.close_explain();
-                }
-                KeyCode::Up => {
-                    self.explain_scroll = self.explain_scroll.saturating_sub(1);
-                }
-                KeyCode::Down => {
-                    let max_scroll = self.explain_sorted_indices.len().saturating_sub(1);
-                    self.explain_scroll = (self.explain_scroll + 1).min(max_scroll);
+    /// The mode keys go to, `None` in normal mode
+    pub fn mode(&self) -> Option<&Mode> {
+        self.modes.last()
+    }
+
+    /// Whether `mode` is open, whether or not it has the keys
+    pub fn in_mode(&self, mode: &Mode) -> bool {
+        self.modes.contains(mode)
+    }
+
+    /// Give keys to `mode`, moving it to the top if it's already open
+    fn enter_mode(&mut self, mode: Mode) {
+        self.modes.retain(|m| *m != mode);
+        self.modes.push(mode);
+    }
+
+    /// Close every open mode matching `which`
+    fn leave_mode(&mut self, which: impl Fn(&Mode) -> bool) {
+        self.modes.retain(|m| !which(m));
+    }
+
+    /// Swap the mode on top for `mode`, e.g. to move a picker's selection
+    fn replace_mode(&mut self, mode: Mode) {
+        self.modes.pop();
+        self.modes.push(mode);
+    }
+
+    /// The prompt being typed into, if one has the keys
+    pub fn command(&self) -> Option<&Command> {
+        match self.mode() {
+            Some(Mode::Command(command)) => Some(command),
+            _ => None,
+        }
+    }
+
+    pub fn profile_picker(&self) -> Option<usize> {
+        self.modes.iter().find_map(|m| match m {
+            Mode::ProfilePicker(selected) => Some(*selected),
+            _ => None,
+        })
+    }
+
+    pub fn tasks_panel(&self) -> Option<usize> {
+        self.modes.iter().find_map(|m| match m {
+            Mode::Tasks(selected) => Some(*selected),
+            _ => None,
+        })
+    }
+
+    pub fn chart_picker(&self) -> Option<usize> {
+        self.modes.iter().find_map(|m| match m {
+            Mode::ChartPicker(selected) => Some(*selected),
+            _ => None,
+        })
+    }
+
+    fn handle_explain_key(&mut self, key: KeyEvent) {
+        self.explain_notice = None;
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.close_explain();
+            }
+            KeyCode::Up => {
+                self.explain_scroll = self.explain_scroll.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                let max_scroll = self.explain_sorted_indices.len().saturating_sub(1);
+                self.explain_scroll = (self.explain_scroll + 1).min(max_scroll);
+            }
+            KeyCode::PageUp => {
+                self.explain_scroll = self.explain_scroll.saturating_sub(10);
+            }
+            KeyCode::PageDown => {
+                let max_scroll = self.explain_sorted_indices.len().saturating_sub(1);
+                self.explain_scroll = (self.explain_scroll + 10).min(max_scroll);
+            }
+            KeyCode::Home => {
+                self.explain_scroll = 0;
+            }
+            KeyCode::End => {
+                let max_scroll = self.explain_sorted_indices.len().saturating_sub(1);
+                self.explain_scroll = max_scroll;
+            }
+            KeyCode::Left => {
+                if let Some(ref data) = self.explain_data {
+                    let cols = data.columns.len();
+                    if cols > 0 {
+                        self.explain_selected_col = (self.explain_selected_col + cols - 1) % cols;
+                    }
                 }
-                KeyCode::PageUp => {
-                    self.explain_scroll = self.explain_scroll.saturating_sub(10);
+            }
+            KeyCode::Right => {
+                if let Some(ref data) = self.explain_data {
+                    let cols = data.columns.len();
+                    if cols > 0 {
+                        self.explain_selected_col = (self.explain_selected_col + 1) % cols;
+                    }
                 }
-                KeyCode::PageDown => {
-                    let max_scroll = self.explain_sorted_indices.len().saturating_sub(1);
-                    self.explain_scroll = (self.explain_scroll + 10).min(max_scroll);
+            }
+            KeyCode::Enter => {
+                self.toggle_explain_sort();
+            }
+            KeyCode::Char('r') => self.refresh_drill_down(),
+            KeyCode::Char('n') => self.fetch_next_drill_down_page(),
+            KeyCode::Char('s') => self.explain_show_stats = !self.explain_show_stats,
+            KeyCode::Char('f') if self.explain_data.is_some() => {
+                let current = self.explain_filters.get(&self.explain_selected_col);
+                let text = current.map(|f| f.text.clone()).unwrap_or_default();
+                self.enter_mode(Mode::Command(Command::new(CommandKind::ExplainFilter, text)));
+            }
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => self.copy_explain_sql(),
+            KeyCode::Char('y') => self.copy_explain_row(false),
+            KeyCode::Char('Y') => self.copy_explain_row(true),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.set_explain_size(self.explain_size.grow()),
+            KeyCode::Char('-') => self.set_explain_size(self.explain_size.shrink()),
+            KeyCode::Char('z') => self.toggle_explain_fullscreen(),
+            _ => {}
+        }
+    }
+
+    fn handle_plan_key(&mut self, key: KeyEvent) {
+        let max_scroll = self.plan_line_count().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.close_plan(),
+            KeyCode::Up => self.plan_scroll = self.plan_scroll.saturating_sub(1),
+            KeyCode::Down => self.plan_scroll = (self.plan_scroll + 1).min(max_scroll),
+            KeyCode::PageUp => self.plan_scroll = self.plan_scroll.saturating_sub(10),
+            KeyCode::PageDown => self.plan_scroll = (self.plan_scroll + 10).min(max_scroll),
+            KeyCode::Home => self.plan_scroll = 0,
+            KeyCode::End => self.plan_scroll = max_scroll,
+            KeyCode::Left => self.plan_hscroll = self.plan_hscroll.saturating_sub(8),
+            KeyCode::Right => self.plan_hscroll += 8,
+            _ => {}
+        }
+    }
+
+    fn handle_compare_key(&mut self, key: KeyEvent) {
+        let Some(ref mut comparison) = self.comparison else {
+            self.leave_mode(|m| *m == Mode::Compare);
+            return;
+        };
+        let max = comparison.query_diff.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('C') => {
+                self.comparison = None;
+                self.leave_mode(|m| *m == Mode::Compare);
+            }
+            KeyCode::Tab if comparison.overlay.is_some() => {
+                comparison.show_overlay = !comparison.show_overlay;
+            }
+            KeyCode::Up => comparison.diff_scroll = comparison.diff_scroll.saturating_sub(1),
+            KeyCode::Down => comparison.diff_scroll = (comparison.diff_scroll + 1).min(max),
+            KeyCode::Home => comparison.diff_scroll = 0,
+            KeyCode::End => comparison.diff_scroll = max,
+            _ => {}
+        }
+    }
+
+    fn handle_query_log_key(&mut self, key: KeyEvent) {
+        let max = self.query_log.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => self.leave_mode(|m| *m == Mode::QueryLog),
+            KeyCode::Up => self.query_log_selected = self.query_log_selected.saturating_sub(1),
+            KeyCode::Down => self.query_log_selected = (self.query_log_selected + 1).min(max),
+            KeyCode::Home => self.query_log_selected = 0,
+            KeyCode::End => self.query_log_selected = max,
+            _ => {}
+        }
+    }
+
+    fn handle_app_log_key(&mut self, key: KeyEvent) {
+        let max = self.app_log_visible_lines().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('D') => self.leave_mode(|m| *m == Mode::AppLog),
+            KeyCode::Up => self.app_log_scroll = (self.app_log_scroll + 1).min(max),
+            KeyCode::Down => self.app_log_scroll = self.app_log_scroll.saturating_sub(1),
+            KeyCode::PageUp => self.app_log_scroll = (self.app_log_scroll + 10).min(max),
+            KeyCode::PageDown => self.app_log_scroll = self.app_log_scroll.saturating_sub(10),
+            KeyCode::Home => self.app_log_scroll = max,
+            KeyCode::End => self.app_log_scroll = 0,
+            KeyCode::Char('w') => {
+                self.app_log_warnings_only = !self.app_log_warnings_only;
+                self.app_log_scroll = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_profile_picker_key(&mut self, selected: usize, key: KeyEvent) {
+        let max = self.config.profiles.len();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('P') => {
+                self.modes.pop();
+            }
+            KeyCode::Up => self.replace_mode(Mode::ProfilePicker(selected.saturating_sub(1))),
+            KeyCode::Down => self.replace_mode(Mode::ProfilePicker((selected + 1).min(max))),
+            KeyCode::Enter => {
+                self.modes.pop();
+                let name = selected.checked_sub(1).and_then(|i| self.profile_names().get(i).map(|n| n.to_string()));
+                if name != self.active_profile {
+                    self.select_profile(name);
                 }
-                KeyCode::Home => {
-                    self.explain_scroll = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_chart_picker_key(&mut self, selected: usize, key: KeyEvent) {
+        let last = self.workspace.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Tab => {
+                self.modes.pop();
+            }
+            KeyCode::Up => self.replace_mode(Mode::ChartPicker(selected.saturating_sub(1))),
+            KeyCode::Down => self.replace_mode(Mode::ChartPicker((selected + 1).min(last))),
+            KeyCode::Enter => {
+                self.modes.pop();
+                self.switch_chart(selected);
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index < self.workspace.len() {
+                    self.modes.pop();
+                    self.switch_chart(index);
                 }
-                KeyCode::End => {
-                    let max_scroll = self.explain_sorted_indices.len().saturating_sub(1);
-                    self.explain_scroll = max_scroll;
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                self.close_chart(selected);
+                if self.workspace.is_empty() {
+                    self.modes.pop();
+                } else {
+                    self.replace_mode(Mode::ChartPicker(selected.min(self.workspace.len() - 1)));
                 }
-                KeyCode::Left => {
-                    if let Some(ref data) = self.explain_data {
-                        let cols = data.columns.len();
-                        if cols > 0 {
-                            self.explain_selected_col = (self.explain_selected_col + cols - 1) % cols;
-                        }
-                    }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_tasks_key(&mut self, selected: usize, key: KeyEvent) {
+        let last = self.tasks.list().count().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('T') => {
+                self.modes.pop();
+            }
+            KeyCode::Up => self.replace_mode(Mode::Tasks(selected.saturating_sub(1))),
+            KeyCode::Down => self.replace_mode(Mode::Tasks((selected + 1).min(last))),
+            KeyCode::Char('c') | KeyCode::Delete => {
+                let id = self.tasks.list().nth(selected).map(|t| t.id);
+                if let Some(id) = id {
+                    self.cancel_task(id);
                 }
-                KeyCode::Right => {
-                    if let Some(ref data) = self.explain_data {
-                        let cols = data.columns.len();
-                        if cols > 0 {
-                            self.explain_selected_col = (self.explain_selected_col + 1) % cols;
-                        }
-                    }
+            }
+            _ => {}
+        }
+    }
+
+    /// Interrupt a task from the task panel, and stop whatever was waiting on it
+    fn cancel_task(&mut self, id: u64) {
+        let Some(kind) = self.tasks.cancel(id) else { return };
+        info!("Cancelled {} task {} from the task panel", kind.name(), id);
+        match kind {
+            // Its rows won't come, and any already sent are dropped
+            TaskKind::DrillDown if self.explain_loading => {
+                self.drill_down_generation += 1;
+                self.explain_loading = false;
+                self.explain_page_offset = None;
+                self.explain_error = Some("Query cancelled".to_string());
+            }
+            TaskKind::Plan if self.plan_loading => {
+                self.plan_loading = false;
+                self.plan_error = Some("Query cancelled".to_string());
+            }
+            // A cancelled re-run or SQL tab query still reports back, as an error
+            _ => {}
+        }
+    }
+
+    /// Typing a search: it applies as it's typed, Enter keeps it, Esc clears it
+    fn handle_search_key(&mut self, target: SearchTarget, key: KeyEvent) {
+        let text = match target {
+            SearchTarget::Data => &mut self.search_query,
+            SearchTarget::History => &mut self.history_filter,
+            SearchTarget::Query => &mut self.query_search,
+            SearchTarget::Chart => &mut self.chart_search,
+        };
+        match (edit_text(text, key), target) {
+            (Edit::Cancel, SearchTarget::Data) => self.clear_search(),
+            (Edit::Cancel, SearchTarget::History) => self.clear_history_filter(),
+            (Edit::Cancel, SearchTarget::Query) => self.clear_query_search(),
+            (Edit::Cancel, SearchTarget::Chart) => self.clear_chart_search(),
+            (Edit::Submit, _) => {
+                self.modes.pop();
+            }
+            (Edit::Changed, SearchTarget::Data) => self.select_first_match(),
+            (Edit::Changed, SearchTarget::History) => self.select_visible_history(),
+            (Edit::Changed, SearchTarget::Query) => self.scroll_to_query_match(),
+            (Edit::Changed, SearchTarget::Chart) => self.select_first_chart_match(),
+            (Edit::Ignored, _) => {}
+        }
+    }
+
+    /// Typing into a prompt: Enter acts on it, Esc drops it
+    fn handle_command_key(&mut self, key: KeyEvent) {
+        let Some(Mode::Command(command)) = self.modes.last_mut() else { return };
+        match command.edit(key) {
+            Edit::Cancel => {
+                self.modes.pop();
+            }
+            Edit::Submit => {
+                if let Some(Mode::Command(command)) = self.modes.pop() {
+                    self.run_command(command);
                 }
-                KeyCode::Enter => {
-                    self.toggle_explain_sort();
+            }
+            Edit::Changed | Edit::Ignored => {}
+        }
+    }
+
+    fn run_command(&mut self, command: Command) {
+        let text = command.text.trim();
+        match command.kind {
+            CommandKind::GotoRow => {
+                if let Ok(row) = text.parse::<usize>() {
+                    self.goto_row(row);
                 }
-                _ => {}
             }
+            CommandKind::Tags => self.set_history_tags(parse_tags(text)),
+            CommandKind::Rename if !text.is_empty() => self.rename_history_entry(text.to_string()),
+            CommandKind::Export if !text.is_empty() => self.export_chart(text),
+            CommandKind::Rename | CommandKind::Export => {}
+            CommandKind::ExplainFilter => self.apply_explain_filter(command),
+        }
+    }
+
+    /// Filter the selected explain column by the prompt's text (empty clears
+    /// the filter). A comparison that isn't against a number reopens the prompt.
+    fn apply_explain_filter(&mut self, command: Command) {
+        let col = self.explain_selected_col;
+        if command.text.trim().is_empty() {
+            self.explain_filters.remove(&col);
+        } else {
+            let Some(filter) = ColumnFilter::parse(&command.text) else {
+                self.enter_mode(Mode::Command(command));
+                return;
+            };
+            self.explain_filters.insert(col, filter);
+        }
+        self.explain_scroll = 0;
+        self.apply_explain_sort();
+    }
+
+    /// Keys with no overlay or prompt open: the tab-level bindings
+    fn handle_normal_key(&mut self, key: KeyEvent) {
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        if !shift
+            && matches!(
+                key.code,
+                KeyCode::Up | KeyCode::Down | KeyCode::Home | KeyCode::End | KeyCode::PageUp | KeyCode::PageDown
+            )
+        {
+            self.range_anchor = None;
+        }
+
+        if std::mem::take(&mut self.fold_pending) && self.active_tab == Tab::Query {
+            self.handle_fold_key(key);
             return;
         }
 
         match key.code {
             KeyCode::Char('q') => self.running = false,
-            KeyCode::Char('c') => self.clear_data(),
-            KeyCode::Char('?') => self.show_help = true,
+            KeyCode::Char('c') => self.confirm(Confirm::ClearData),
+            KeyCode::Char('?') => self.enter_mode(Mode::Help),
+            KeyCode::Tab if !self.workspace.is_empty() => {
+                let current = if self.data.is_some() { self.workspace_current } else { 0 };
+                self.enter_mode(Mode::ChartPicker(current));
+            }
+            KeyCode::Char('[') => self.cycle_chart(false),
+            KeyCode::Char(']') => self.cycle_chart(true),
+            KeyCode::Char('!') if self.file_error.is_some() => self.enter_mode(Mode::FileProblems),
+            KeyCode::Char('L') => {
+                self.enter_mode(Mode::QueryLog);
+                self.query_log_selected = 0;
+            }
+            KeyCode::Char(c @ ('E' | 'M')) if self.data.is_some() => {
+                let extension = if c == 'M' { "md" } else { "png" };
+                let name = self.data.as_ref().map(|d| format!("{}.{}", file_stem(&d.title), extension)).unwrap_or_default();
+                self.enter_mode(Mode::Command(Command::new(CommandKind::Export, name)));
+            }
+            KeyCode::Char('T') => self.enter_mode(Mode::Tasks(0)),
+            KeyCode::Char('D') => {
+                self.enter_mode(Mode::AppLog);
+                self.app_log_scroll = 0;
+                self.refresh_app_log();
+            }
+            KeyCode::Char('P') => {
+                let active = self.profile_names().iter().position(|n| Some(*n) == self.active_profile.as_ref());
+                self.enter_mode(Mode::ProfilePicker(active.map_or(0, |i| i + 1)));
+            }
             KeyCode::Left => self.active_tab = self.active_tab.prev(),
             KeyCode::Right => self.active_tab = self.active_tab.next(),
-            // Explain selected point / load history entry
+            // Explain selected point / load history entry / edit SQL
+            KeyCode::Char('x') | KeyCode::Enter
+                if self.active_tab == Tab::Chart && self.selected_range().is_some() =>
+            {
+                self.trigger_range_explain();
+            }
             KeyCode::Char('x') => {
                 if matches!(self.active_tab, Tab::Chart | Tab::Data) {
                     self.trigger_explain();
@@ -177,21 +1242,382 @@ impl App {
                     self.trigger_explain();
                 } else if self.active_tab == Tab::Home {
                     self.load_history_entry();
+                } else if self.active_tab == Tab::Sql {
+                    self.start_sql_edit();
                 }
             }
-            KeyCode::Char('d') | KeyCode::Delete => {
-                if self.active_tab == Tab::Home {
-                    self.delete_history_entry();
+            KeyCode::Char('p') if self.active_tab == Tab::Query => self.trigger_plan(),
+            KeyCode::Char('p') if self.active_tab == Tab::Home => self.toggle_history_pin(),
+            KeyCode::Char('t') if self.active_tab == Tab::Home && get_data_dir().is_none() => {
+                if let Some(entry) = self.selected_history_entry() {
+                    let tags = entry.meta.tags.join(", ");
+                    self.enter_mode(Mode::Command(Command::new(CommandKind::Tags, tags)));
                 }
             }
-            KeyCode::Up => self.handle_up(),
-            KeyCode::Down => self.handle_down(),
-            KeyCode::Home => self.handle_home(),
-            KeyCode::End => self.handle_end(),
-            KeyCode::PageUp => self.handle_page_up(),
-            KeyCode::PageDown => self.handle_page_down(),
-            _ => {}
-        }
+            KeyCode::Char('m') if self.active_tab == Tab::Home => self.toggle_history_mark(),
+            KeyCode::Char('C') if self.active_tab == Tab::Home => self.open_comparison(),
+            KeyCode::Char('r') if self.active_tab == Tab::Home && get_data_dir().is_none() => {
+                if let Some(entry) = self.selected_history_entry() {
+                    let title = entry.title.clone();
+                    self.enter_mode(Mode::Command(Command::new(CommandKind::Rename, title)));
+                }
+            }
+            KeyCode::Char('r') if self.active_tab == Tab::Query => self.trigger_rerun(),
+            KeyCode::Char('e') if self.active_tab == Tab::Query => self.start_external_edit(),
+            KeyCode::Char('d') if self.active_tab == Tab::Query => self.toggle_query_diff(),
+            KeyCode::Char('z') if self.active_tab == Tab::Query && !self.query_diff => {
+                self.fold_pending = self.data.is_some();
+            }
+            KeyCode::Char('y') if self.active_tab == Tab::Query => self.copy_query(true),
+            KeyCode::Char('Y') if self.active_tab == Tab::Query => self.copy_query(false),
+            KeyCode::Char('R') => self.cycle_auto_refresh(),
+            KeyCode::Char('i') if self.active_tab == Tab::Sql => self.start_sql_edit(),
+            KeyCode::Char('r') if self.active_tab == Tab::Sql => self.trigger_sql(),
+            KeyCode::Esc if self.sql_running => {
+                info!("Cancelling in-flight SQL tab query");
+                self.sql_running = false;
+                self.tasks.cancel_kind(TaskKind::Sql);
+            }
+            KeyCode::Esc if self.rerun_loading => {
+                info!("Cancelling in-flight re-run");
+                self.rerun_loading = false;
+                self.tasks.cancel_kind(TaskKind::Rerun);
+            }
+            KeyCode::Char('A') if matches!(self.active_tab, Tab::Chart | Tab::Data) => self.show_all_rows(),
+            KeyCode::Char('b') if self.active_tab == Tab::Chart => self.toggle_bar_mode(),
+            KeyCode::Char('v') if self.active_tab == Tab::Chart => self.toggle_bar_orientation(),
+            KeyCode::Char('o') if self.active_tab == Tab::Chart => self.cycle_chart_sort(),
+            KeyCode::Char('d') | KeyCode::Delete if self.active_tab == Tab::Home => {
+                self.delete_history_entry();
+            }
+            KeyCode::Char('d') if self.active_tab == Tab::Mask => self.trigger_source_explain("DESCRIBE"),
+            KeyCode::Char('s') if self.active_tab == Tab::Mask => self.trigger_source_explain("SUMMARIZE"),
+            KeyCode::Char('X') if self.active_tab == Tab::Mask => self.assign_mask_column(true),
+            KeyCode::Char('Y') if self.active_tab == Tab::Mask => self.assign_mask_column(false),
+            KeyCode::Char(':') if matches!(self.active_tab, Tab::Data | Tab::Chart) && self.data.is_some() => {
+                self.enter_mode(Mode::Command(Command::new(CommandKind::GotoRow, "")));
+            }
+            KeyCode::Char('/') if self.active_tab == Tab::Data && self.data.is_some() => {
+                self.enter_mode(Mode::Search(SearchTarget::Data));
+            }
+            KeyCode::Char('/') if self.active_tab == Tab::Home && !self.history.is_empty() => {
+                self.enter_mode(Mode::Search(SearchTarget::History));
+            }
+            KeyCode::Char('/') if self.active_tab == Tab::Query && self.data.is_some() => {
+                self.enter_mode(Mode::Search(SearchTarget::Query));
+            }
+            KeyCode::Char('/') if self.active_tab == Tab::Chart && self.data.is_some() => {
+                self.enter_mode(Mode::Search(SearchTarget::Chart));
+            }
+            KeyCode::Esc if self.active_tab == Tab::Home && !self.history_filter.is_empty() => {
+                self.clear_history_filter()
+            }
+            KeyCode::Char('n') if self.active_tab == Tab::Data => self.step_match(1),
+            KeyCode::Char('N') if self.active_tab == Tab::Data => self.step_match(-1),
+            KeyCode::Char('n') if self.active_tab == Tab::Query => self.step_query_match(1),
+            KeyCode::Char('N') if self.active_tab == Tab::Query => self.step_query_match(-1),
+            KeyCode::Char('n') if self.active_tab == Tab::Chart => self.step_chart_match(1),
+            KeyCode::Char('N') if self.active_tab == Tab::Chart => self.step_chart_match(-1),
+            KeyCode::Esc if self.active_tab == Tab::Query && !self.query_search.is_empty() => {
+                self.clear_query_search()
+            }
+            KeyCode::Up if self.active_tab == Tab::Data && !self.search_query.is_empty() => {
+                self.step_match(-1)
+            }
+            KeyCode::Down if self.active_tab == Tab::Data && !self.search_query.is_empty() => {
+                self.step_match(1)
+            }
+            KeyCode::Esc if self.active_tab == Tab::Data && !self.search_query.is_empty() => {
+                self.clear_search()
+            }
+            KeyCode::Esc if self.range_anchor.is_some() => self.range_anchor = None,
+            KeyCode::Esc if self.active_tab == Tab::Chart && !self.chart_search.is_empty() => {
+                self.clear_chart_search()
+            }
+            KeyCode::Up if shift && self.active_tab == Tab::Chart => self.extend_range(-1),
+            KeyCode::Down if shift && self.active_tab == Tab::Chart => self.extend_range(1),
+            KeyCode::Up => self.handle_up(),
+            KeyCode::Down => self.handle_down(),
+            KeyCode::Home => self.handle_home(),
+            KeyCode::End => self.handle_end(),
+            KeyCode::PageUp => self.handle_page_up(),
+            KeyCode::PageDown => self.handle_page_down(),
+            _ => {}
+        }
+    }
+
+    /// Chart the column highlighted on the Mask tab as x (`as_x`) or y. Picking the
+    /// column already on the other axis swaps the two.
+    fn assign_mask_column(&mut self, as_x: bool) {
+        let Some(ref mut data) = self.data else { return };
+        let Some(column) = data.columns.get(self.mask_selected).cloned() else { return };
+        let (target, other) = if as_x {
+            (&mut data.x_field, &mut data.y_field)
+        } else {
+            (&mut data.y_field, &mut data.x_field)
+        };
+        if *other == column {
+            *other = target.clone();
+        }
+        // A column moved onto an axis stops splitting or doubling the series
+        if data.series_field.as_ref() == Some(&column) {
+            data.series_field = None;
+        }
+        if data.y2_field.as_ref() == Some(&column) {
+            data.y2_field = None;
+        }
+        *target = column;
+        // A data file's generated drill-down filters on whichever column is x
+        if self.data_path.as_deref().and_then(file_query).is_some() {
+            data.drill_down = Some(file_drill_down(&data.query, &data.x_field));
+        }
+        self.selected_point = 0;
+        self.range_anchor = None;
+    }
+
+    /// Switch multi-series bar charts between grouped and stacked layouts
+    fn toggle_bar_mode(&mut self) {
+        let Some(ref data) = self.data else { return };
+        let current = self.bar_mode.unwrap_or_else(|| data.default_bar_mode());
+        self.bar_mode = Some(current.toggle());
+    }
+
+    /// Switch single-series bar charts between vertical and horizontal bars
+    fn toggle_bar_orientation(&mut self) {
+        let Some(ref data) = self.data else { return };
+        let current = self
+            .bar_orientation
+            .unwrap_or_else(|| data.default_bar_orientation());
+        self.bar_orientation = Some(current.toggle());
+    }
+
+    /// Step single-series bar and line charts through x order, ascending y, and
+    /// descending y; the selection stays on the same row
+    fn cycle_chart_sort(&mut self) {
+        let Some(ref data) = self.data else { return };
+        if !data.is_sortable() {
+            self.toast(ToastKind::Info, "Sorting applies to single-series bar, line, area, and scatter charts");
+            return;
+        }
+        self.chart_sort = self.chart_sort.cycle();
+    }
+
+    /// Move the Chart tab selection `delta` places along the sorted order, wrapping
+    fn step_sorted(&mut self, delta: isize) {
+        let Some(ref data) = self.data else { return };
+        let order = data.display_order(self.chart_sort);
+        if order.is_empty() {
+            return;
+        }
+        let pos = order.iter().position(|&i| i == self.selected_point).unwrap_or(0);
+        self.selected_point = order[(pos as isize + delta).rem_euclid(order.len() as isize) as usize];
+    }
+
+    /// Select a 1-based row number, clamped to the loaded rows
+    fn goto_row(&mut self, row: usize) {
+        let Some(ref data) = self.data else { return };
+        if data.rows.is_empty() || row == 0 {
+            return;
+        }
+        self.selected_point = row.min(data.rows.len()) - 1;
+        self.range_anchor = None;
+        // Drop a filter that would hide the target row
+        if self
+            .search_matches()
+            .is_some_and(|m| !m.contains(&self.selected_point))
+        {
+            self.clear_search();
+        }
+    }
+
+    /// Rows matching the Data tab filter, or None when no filter is set
+    pub fn search_matches(&self) -> Option<Vec<usize>> {
+        let data = self.data.as_ref()?;
+        if self.search_query.is_empty() {
+            return None;
+        }
+        Some(data.matching_rows(&self.search_query))
+    }
+
+    fn clear_search(&mut self) {
+        self.search_query.clear();
+        self.leave_mode(|m| *m == Mode::Search(SearchTarget::Data));
+    }
+
+    /// Keep the selection on a visible row as the filter narrows
+    fn select_first_match(&mut self) {
+        if let Some(matches) = self.search_matches() {
+            if !matches.contains(&self.selected_point) {
+                if let Some(&first) = matches.first() {
+                    self.selected_point = first;
+                }
+            }
+        }
+    }
+
+    /// Move the selection to the next (`delta` > 0) or previous matching row, wrapping
+    fn step_match(&mut self, delta: isize) {
+        let Some(matches) = self.search_matches() else { return };
+        if let Some(next) = step_among(&matches, self.selected_point, delta) {
+            self.selected_point = next;
+        }
+    }
+
+    /// Formatted query lines containing the Query tab search, ignoring case
+    pub fn query_matches(&self) -> Vec<usize> {
+        let Some(ref data) = self.data else { return Vec::new() };
+        if self.query_search.is_empty() {
+            return Vec::new();
+        }
+        let needle = self.query_search.to_lowercase();
+        format_sql(&data.query)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.to_lowercase().contains(&needle))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn clear_query_search(&mut self) {
+        self.query_search.clear();
+        self.leave_mode(|m| *m == Mode::Search(SearchTarget::Query));
+    }
+
+    /// Scroll to the first matching line at or below the top one, wrapping
+    fn scroll_to_query_match(&mut self) {
+        let matches = self.query_matches();
+        let top = self.query_top_line();
+        if let Some(&line) = matches.iter().find(|&&i| i >= top).or(matches.first()) {
+            self.scroll_to_query_line(line, true);
+        }
+    }
+
+    /// Scroll to the next (`delta` > 0) or previous matching line, wrapping
+    fn step_query_match(&mut self, delta: isize) {
+        if let Some(line) = step_among(&self.query_matches(), self.query_top_line(), delta) {
+            self.scroll_to_query_line(line, true);
+        }
+    }
+
+    /// Formatted query lines shown on the Query tab, with folded regions hidden
+    fn query_visible_lines(&self) -> Vec<usize> {
+        let Some(ref data) = self.data else { return Vec::new() };
+        let formatted = format_sql(&data.query);
+        visible_lines(&fold_regions(&formatted), &self.query_folds, formatted.lines().count())
+    }
+
+    /// Rows the Query tab scrolls through: the diff's lines, or the query's with
+    /// folded regions counted once
+    fn query_rows(&self) -> usize {
+        match self.data {
+            Some(ref data) if self.query_diff => {
+                let original = data.original_query.as_deref().unwrap_or_default();
+                diff_lines(&format_sql(original.trim()), &format_sql(data.query.trim())).len()
+            }
+            Some(_) => self.query_visible_lines().len(),
+            None => 0,
+        }
+    }
+
+    /// The formatted query line at the top of the Query tab
+    fn query_top_line(&self) -> usize {
+        let visible = self.query_visible_lines();
+        visible.get(self.scroll_offset).or(visible.last()).copied().unwrap_or(0)
+    }
+
+    /// Put `line` at the top of the Query tab, unfolding the regions hiding it when
+    /// `reveal`; otherwise a hidden line's fold goes at the top instead
+    fn scroll_to_query_line(&mut self, line: usize, reveal: bool) {
+        if reveal {
+            if let Some(ref data) = self.data {
+                let regions = fold_regions(&format_sql(&data.query));
+                self.query_folds.retain(|&start| !regions.iter().any(|r| r.start == start && r.start < line && r.end >= line));
+            }
+        }
+        let visible = self.query_visible_lines();
+        self.scroll_offset = visible.iter().rposition(|&i| i <= line).unwrap_or(0);
+    }
+
+    /// The key after `z` on the Query tab: `a` toggles the fold around the top
+    /// line, `c`/`o` close or open it, `M`/`R` close or open them all
+    fn handle_fold_key(&mut self, key: KeyEvent) {
+        let Some(ref data) = self.data else { return };
+        let regions = fold_regions(&format_sql(&data.query));
+        let top = self.query_top_line();
+        let around = innermost_fold(&regions, top).map(|r| r.start);
+        match (key.code, around) {
+            (KeyCode::Char('a'), Some(start)) => {
+                if !self.query_folds.remove(&start) {
+                    self.query_folds.insert(start);
+                }
+            }
+            (KeyCode::Char('c'), Some(start)) => {
+                self.query_folds.insert(start);
+            }
+            (KeyCode::Char('o'), Some(start)) => {
+                self.query_folds.remove(&start);
+            }
+            (KeyCode::Char('M'), _) => self.query_folds = regions.iter().map(|r| r.start).collect(),
+            (KeyCode::Char('R'), _) => self.query_folds.clear(),
+            _ => return,
+        }
+        self.scroll_to_query_line(top, false);
+    }
+
+    /// Rows whose x value contains the Chart tab search, or None when none is set
+    fn chart_matches(&self) -> Option<Vec<usize>> {
+        let data = self.data.as_ref()?;
+        if self.chart_search.is_empty() {
+            return None;
+        }
+        Some(data.matching_x_rows(&self.chart_search))
+    }
+
+    fn clear_chart_search(&mut self) {
+        self.chart_search.clear();
+        self.leave_mode(|m| *m == Mode::Search(SearchTarget::Chart));
+    }
+
+    /// Move the selection onto a matching point as the search is typed
+    fn select_first_chart_match(&mut self) {
+        if let Some(matches) = self.chart_matches() {
+            if !matches.contains(&self.selected_point) {
+                if let Some(&first) = matches.first() {
+                    self.selected_point = first;
+                }
+            }
+        }
+    }
+
+    /// Move the selection to the next (`delta` > 0) or previous matching point, wrapping
+    fn step_chart_match(&mut self, delta: isize) {
+        let Some(matches) = self.chart_matches() else { return };
+        if let Some(next) = step_among(&matches, self.selected_point, delta) {
+            self.selected_point = next;
+        }
+    }
+
+    /// Brushed span between the range anchor and the selected point, if they differ
+    pub fn selected_range(&self) -> Option<XRange> {
+        let data = self.data.as_ref()?;
+        let anchor = self.range_anchor?;
+        if anchor == self.selected_point || anchor >= data.rows.len() || self.selected_point >= data.rows.len() {
+            return None;
+        }
+        Some(data.x_range(anchor, self.selected_point))
+    }
+
+    /// Move the selected point by `delta` rows, anchoring a range brush at the current point
+    fn extend_range(&mut self, delta: isize) {
+        let Some(ref data) = self.data else { return };
+        if data.rows.is_empty() {
+            return;
+        }
+        self.range_anchor.get_or_insert(self.selected_point);
+        self.selected_point = self
+            .selected_point
+            .saturating_add_signed(delta)
+            .min(data.rows.len() - 1);
     }
 
     /// Trigger explain mode for the currently selected data point
@@ -225,53 +1651,25 @@ impl App {
 
         // Check if we have a drill-down query template
         let drill_down_query = if let Some(ref drill_down) = data.drill_down {
-            // Substitute placeholders in the template
-            let mut query = drill_down.query_template.clone();
-
-            // Replace {{database}} placeholder if database is configured
-            if let Some(ref db) = data.database {
-                query = query.replace("{{database}}", db);
-            }
-
-            // Replace {{x}} placeholder (template controls quoting)
-            let x_str = match &x_value {
-                serde_json::Value::String(s) => s.replace('\'', "''"),
-                serde_json::Value::Number(n) => n.to_string(),
-                serde_json::Value::Null => "NULL".to_string(),
-                _ => x_value.to_string().trim_matches('"').to_string(),
-            };
-            query = query.replace("{{x}}", &x_str);
-
-            // Replace {{y}} placeholder (template controls quoting)
-            let y_str = match &y_value {
-                serde_json::Value::String(s) => s.replace('\'', "''"),
-                serde_json::Value::Number(n) => n.to_string(),
-                serde_json::Value::Null => "NULL".to_string(),
-                _ => y_value.to_string().trim_matches('"').to_string(),
-            };
-            query = query.replace("{{y}}", &y_str);
-
-            // Replace any custom param mappings (template controls quoting)
+            // Custom param mappings, then {{x}} and {{y}}, are bound as query parameters
+            let mut values = HashMap::new();
             for (placeholder, field_name) in &drill_down.param_mapping {
                 if let Some(col_idx) = data.columns.iter().position(|c| c == field_name) {
                     if let Some(val) = row.get(col_idx) {
-                        let val_str = match val {
-                            serde_json::Value::String(s) => s.replace('\'', "''"),
-                            serde_json::Value::Number(n) => n.to_string(),
-                            serde_json::Value::Null => "NULL".to_string(),
-                            _ => val.to_string().trim_matches('"').to_string(),
-                        };
-                        query = query.replace(&format!("{{{{{}}}}}", placeholder), &val_str);
+                        values.insert(placeholder.clone(), val.clone());
                     }
                 }
             }
+            values.insert("x".to_string(), x_value);
+            values.insert("y".to_string(), y_value);
 
-            info!("Final drill-down query: {}", query);
+            let query = bind_placeholders(&with_database(&drill_down.query_template, data), &values);
+            info!("Final drill-down query: {} with {:?}", query.sql, query.params);
             query
         } else {
             // No drill-down template - show error
             info!("No drill-down template configured");
-            self.show_explain = true;
+            self.enter_mode(Mode::Explain);
             self.explain_error = Some(
                 "No drill-down query configured. Claude can provide drill-down \
                  metadata when generating charts."
@@ -280,86 +1678,904 @@ impl App {
             return;
         };
 
-        // Set up UI state for loading
-        self.show_explain = true;
-        self.explain_loading = true;
+        self.start_drill_down("Drill-Down Results", drill_down_query);
+    }
+
+    /// Trigger explain mode for the brushed x range
+    fn trigger_range_explain(&mut self) {
+        let Some(range) = self.selected_range() else { return };
+        let Some(ref data) = self.data else { return };
+        info!("trigger_range_explain called for rows {}..={}", range.start, range.end);
+
+        // A dedicated range template wins; otherwise the point template may itself be range-aware
+        let template = data.drill_down.as_ref().and_then(|d| {
+            d.range_query_template
+                .clone()
+                .or_else(|| Some(d.query_template.clone()).filter(|t| t.contains("{{x_start}}")))
+        });
+        let Some(template) = template else {
+            info!("No range drill-down template configured");
+            self.enter_mode(Mode::Explain);
+            self.explain_error = Some(
+                "No range drill-down query configured. Add a range_query_template using \
+                 {{x_start}} and {{x_end}} to the chart's drillDown metadata."
+                    .to_string(),
+            );
+            return;
+        };
+
+        let x_idx = data.get_x_index();
+        let x_at = |row: usize| data.rows[row].get(x_idx).cloned().unwrap_or(serde_json::Value::Null);
+        let values = HashMap::from([
+            ("x_start".to_string(), x_at(range.start)),
+            ("x_end".to_string(), x_at(range.end)),
+        ]);
+        let query = bind_placeholders(&with_database(&template, data), &values);
+        info!("Final range drill-down query: {} with {:?}", query.sql, query.params);
+        self.start_drill_down("Drill-Down Results", query);
+    }
+
+    /// Run `DESCRIBE` or `SUMMARIZE` against the chart's lineage source table
+    fn trigger_source_explain(&mut self, command: &str) {
+        let Some(ref data) = self.data else { return };
+        let source_table = data.lineage.as_ref().and_then(|l| l.source_table.clone());
+        let Some(table) = source_table.filter(|t| !t.trim().is_empty()) else {
+            self.enter_mode(Mode::Explain);
+            self.explain_error = Some(
+                "No source table in this chart's lineage. Add lineage.sourceTable to the \
+                 chart to describe it."
+                    .to_string(),
+            );
+            return;
+        };
+
+        // Unqualified tables live in the chart's database, as in drill-down templates
+        let table = match data.database {
+            Some(ref db) if !table.contains('.') => format!("{}.{}", db, table),
+            _ => table,
+        };
+        let query = BoundQuery { sql: format!("{} {}", command, table), params: Vec::new() };
+        info!("Source table query: {}", query.sql);
+        self.start_drill_down(&format!("{} {}", command, table), query);
+    }
+
+    /// Open the explain overlay for `query`, answering from the cache when the same
+    /// SQL has already run against the current target
+    fn start_drill_down(&mut self, title: &str, query: BoundQuery) {
+        self.drill_down_generation += 1;
+        self.enter_mode(Mode::Explain);
+        self.explain_title = title.to_string();
         self.explain_error = None;
         self.explain_data = None;
         self.explain_scroll = 0;
 
-        // Queue the query for execution by main loop
-        self.pending_drill_down_query = Some(drill_down_query);
+        let key = self.drill_down_cache_key(&query);
+        self.explain_query = Some(query.clone());
+        if let Some(cached) = self.drill_down_cache.get(&key) {
+            info!("Drill-down served from cache");
+            let data = cached.data.clone();
+            self.explain_cached_at = Some(cached.fetched_at);
+            self.explain_loading = false;
+            self.append_drill_down_rows(data);
+            return;
+        }
+
+        // Queue the query for execution by main loop
+        self.explain_cached_at = None;
+        self.explain_loading = true;
+        self.pending_drill_down_query = Some(query);
+    }
+
+    /// Run the overlay's drill-down again, replacing its cached result
+    fn refresh_drill_down(&mut self) {
+        if self.explain_loading {
+            return;
+        }
+        let Some(query) = self.explain_query.clone() else { return };
+        info!("Refreshing cached drill-down");
+        let key = self.drill_down_cache_key(&query);
+        self.drill_down_cache.remove(&key);
+        let title = self.explain_title.clone();
+        self.start_drill_down(&title, query);
+    }
+
+    /// Whether the overlay's drill-down stopped at its `LIMIT` with rows left to fetch
+    pub fn explain_has_more(&self) -> bool {
+        let (Some(query), Some(data)) = (&self.explain_query, &self.explain_data) else { return false };
+        let Some(count) = count_query(&query.sql) else { return false };
+        let fetched = data.rows.len();
+        match data.total_count {
+            Some(total) => fetched < total,
+            None => fetched > 0 && fetched % count.limit == 0,
+        }
+    }
+
+    /// Fetch the next page of the overlay's drill-down, appending it to the rows shown
+    fn fetch_next_drill_down_page(&mut self) {
+        if self.explain_loading || !self.explain_has_more() {
+            return;
+        }
+        let Some(ref query) = self.explain_query else { return };
+        let offset = self.explain_data.as_ref().map_or(0, |d| d.rows.len());
+        let Some(sql) = page_query(&query.sql, offset) else { return };
+        info!("Fetching drill-down rows from offset {}", offset);
+        let params = query.params.clone();
+        self.explain_page_offset = Some(offset);
+        self.explain_cached_at = None;
+        self.explain_loading = true;
+        self.drill_down_generation += 1;
+        self.pending_drill_down_query = Some(BoundQuery { sql, params });
+    }
+
+    fn drill_down_cache_key(&self, query: &BoundQuery) -> String {
+        let params = serde_json::to_string(&query.params).unwrap_or_default();
+        format!("{}\n{}\n{}", self.database_target(), query.sql, params)
+    }
+
+    /// Database to run drill-downs against: the chart's `database_path`, then the
+    /// active profile's, then the configured one (`--db` or config.toml), then MotherDuck
+    pub fn database_target(&self) -> String {
+        self.data
+            .as_ref()
+            .and_then(|d| d.database_path.clone())
+            .or_else(|| self.profile().and_then(|p| p.database_path.clone()))
+            .or_else(|| self.config.database_path.clone())
+            .unwrap_or_else(|| MOTHERDUCK.to_string())
+    }
+
+    /// Databases to attach before drill-downs: the config's, the active profile's,
+    /// then the chart's
+    pub fn attachments(&self) -> Vec<Attachment> {
+        let mut attachments = self.config.attach.clone();
+        let extra = self.profile().map(|p| p.attach.as_slice()).unwrap_or_default();
+        let chart = self.data.as_ref().map(|d| d.attach.as_slice()).unwrap_or_default();
+        for attachment in extra.iter().chain(chart) {
+            if !attachments.contains(attachment) {
+                attachments.push(attachment.clone());
+            }
+        }
+        attachments
+    }
+
+    /// Database that re-runs, plans, and SQL tab queries run in: the chart's, else
+    /// the active profile's default
+    fn query_database(&self) -> Option<String> {
+        self.data
+            .as_ref()
+            .and_then(|d| d.database.clone())
+            .or_else(|| self.profile().and_then(|p| p.database.clone()))
+    }
+
+    /// The active connection profile's settings
+    pub fn profile(&self) -> Option<&Profile> {
+        self.config.profiles.get(self.active_profile.as_ref()?)
+    }
+
+    /// Switch to the profile `name` (None for the config's own settings) once the
+    /// main loop has read its token; until then the current profile stays in use
+    pub fn select_profile(&mut self, name: Option<String>) {
+        let profile = name.as_ref().and_then(|n| self.config.profiles.get(n)).cloned();
+        if name.is_some() && profile.is_none() {
+            warn!("No profile named {:?} in the config", name);
+            self.profile_error = Some(format!("no profile named {}", name.unwrap_or_default()));
+            return;
+        }
+        info!("Switching to profile {:?}", name);
+        self.switching_profile = Some(name.clone());
+        self.pending_profile = Some((name, profile));
+    }
+
+    /// Take a pending profile switch: the name and settings to read a token for
+    /// (called by main loop)
+    pub fn take_pending_profile(&mut self) -> Option<(Option<String>, Option<Profile>)> {
+        self.pending_profile.take()
+    }
+
+    /// The token for switching to profile `name` was read, or couldn't be.
+    /// Returns whether the main loop should reconnect with it. Open connections
+    /// and cached drill-downs belong to the old account, so a switch drops them;
+    /// a failed one keeps the current profile and its token. A switch that a
+    /// later pick superseded is ignored.
+    pub fn on_profile_token(&mut self, name: Option<String>, result: Result<(), String>) -> bool {
+        if self.switching_profile.as_ref() != Some(&name) {
+            return false;
+        }
+        self.switching_profile = None;
+        match result {
+            Ok(()) => {
+                info!("Using profile {:?}", name);
+                self.active_profile = name;
+                self.profile_error = None;
+                self.connection = None;
+                self.drill_down_cache.clear();
+                self.schema_source = None;
+                true
+            }
+            Err(error) => {
+                warn!("Not switching to profile {:?}: {}", name, error);
+                let name = name.as_deref().unwrap_or("no profile");
+                self.profile_error = Some(format!("couldn't switch to {}: {}", name, error));
+                false
+            }
+        }
+    }
+
+    /// Names in the profile picker, after the "no profile" row
+    pub fn profile_names(&self) -> Vec<&String> {
+        self.config.profiles.keys().collect()
+    }
+
+    /// Draw the open chart to `path` (a .png or .svg, `~` expanded), with the
+    /// palette and bar layout the Chart tab is showing, or write it up as a .md report
+    fn export_chart(&mut self, path: &str) {
+        let Some(ref data) = self.data else { return };
+        let path = PathBuf::from(crate::db::expand_home(path));
+        let bar_mode = self.bar_mode.unwrap_or_else(|| data.default_bar_mode());
+        self.pending_export = Some(ExportJob { data: data.clone(), palette: self.palette.clone(), bar_mode, path });
+    }
+
+    /// Take the chart export to draw (called by main loop)
+    pub fn take_pending_export(&mut self) -> Option<ExportJob> {
+        self.pending_export.take()
+    }
+
+    /// Report how an export to `path` went
+    pub fn on_export_result(&mut self, path: PathBuf, result: Result<(), String>) {
+        match result {
+            Ok(()) => {
+                info!("Exported chart to {}", path.display());
+                self.toast(ToastKind::Success, format!("Exported to {}", path.display()));
+            }
+            Err(e) => {
+                warn!("Chart export failed: {}", e);
+                self.toast(ToastKind::Error, format!("Export failed: {}", e));
+            }
+        }
+    }
+
+    /// Re-read the end of the log file for the log tail overlay
+    pub fn refresh_app_log(&mut self) {
+        let Some(path) = logging::log_path() else {
+            self.app_log_error = Some("Logging is off: the log file couldn't be created".to_string());
+            return;
+        };
+        match logging::tail(path, APP_LOG_LINES) {
+            Ok(lines) => {
+                // Keep a scrolled-back view on the same lines as new ones arrive
+                if self.app_log_scroll > 0 {
+                    let added = lines.len().saturating_sub(self.app_log_lines.len());
+                    self.app_log_scroll += added;
+                }
+                self.app_log_lines = lines;
+                self.app_log_error = None;
+            }
+            Err(e) => self.app_log_error = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Log lines the overlay shows: all of them, or only warnings and errors
+    pub fn app_log_visible_lines(&self) -> Vec<&str> {
+        self.app_log_lines
+            .iter()
+            .map(String::as_str)
+            .filter(|line| !self.app_log_warnings_only || line.contains("[WARN]") || line.contains("[ERROR]"))
+            .collect()
+    }
+
+    /// Take pending drill-down query and the generation its results are tagged
+    /// with (called by main loop)
+    pub fn take_pending_drill_down(&mut self) -> Option<(u64, BoundQuery)> {
+        self.pending_drill_down_query.take().map(|query| (self.drill_down_generation, query))
+    }
+
+    /// Whether results tagged with `generation` are for the drill-down the
+    /// overlay is waiting on
+    fn is_current_drill_down(&self, generation: u64) -> bool {
+        let current = generation == self.drill_down_generation && self.explain_loading;
+        if !current {
+            debug!("Dropping results of superseded drill-down {}", generation);
+        }
+        current
+    }
+
+    /// Handle successful drill-down result
+    pub fn on_drill_down_success(&mut self, generation: u64, data: ExplainData) {
+        if !self.is_current_drill_down(generation) {
+            return;
+        }
+        self.append_drill_down_rows(data);
+        self.explain_loading = false;
+        self.explain_error = None;
+
+        // A short page is the last one, whether or not the count was known
+        if let (Some(offset), Some(query), Some(data)) =
+            (self.explain_page_offset.take(), &self.explain_query, &mut self.explain_data)
+        {
+            let limit = count_query(&query.sql).map_or(0, |c| c.limit);
+            if data.rows.len() - offset < limit {
+                data.total_count = Some(data.rows.len());
+            }
+        }
+
+        if let (Some(query), Some(data)) = (&self.explain_query, &self.explain_data) {
+            let key = self.drill_down_cache_key(query);
+            let entry = CachedDrillDown { data: data.clone(), fetched_at: Instant::now() };
+            self.drill_down_cache.insert(key, entry);
+            if self.drill_down_cache.len() > MAX_CACHED_DRILL_DOWNS {
+                let oldest = self
+                    .drill_down_cache
+                    .iter()
+                    .min_by_key(|(_, c)| c.fetched_at)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    self.drill_down_cache.remove(&oldest);
+                }
+            }
+        }
+        self.drill_down_hook();
+    }
+
+    /// Handle a batch of rows from a drill-down that is still running
+    pub fn on_drill_down_batch(&mut self, generation: u64, data: ExplainData) {
+        if self.is_current_drill_down(generation) {
+            self.append_drill_down_rows(data);
+        }
+    }
+
+    /// Add streamed rows to the overlay, keeping the current sort
+    fn append_drill_down_rows(&mut self, mut data: ExplainData) {
+        let Some(ref mut existing) = self.explain_data else {
+            let row_count = data.rows.len();
+            data.title = self.explain_title.clone();
+            self.explain_data = Some(data);
+            self.explain_selected_col = 0;
+            self.explain_sort_column = None;
+            self.explain_sort_asc = true;
+            self.explain_filters.clear();
+            self.explain_sorted_indices = (0..row_count).collect();
+            return;
+        };
+        let start = existing.rows.len();
+        existing.rows.extend(data.rows);
+        let end = existing.rows.len();
+        if existing.columns.is_empty() {
+            existing.columns = data.columns;
+        }
+        if data.total_count.is_some() {
+            existing.total_count = data.total_count;
+        }
+        if self.explain_sort_column.is_some() {
+            self.apply_explain_sort();
+        } else {
+            let passing: Vec<usize> = (start..end).filter(|&i| self.explain_row_passes(i)).collect();
+            self.explain_sorted_indices.extend(passing);
+        }
+    }
+
+    /// Whether an explain row passes every column filter
+    fn explain_row_passes(&self, row: usize) -> bool {
+        let Some(ref data) = self.explain_data else { return false };
+        self.explain_filters.iter().all(|(&col, filter)| {
+            data.rows[row].get(col).is_some_and(|value| filter.matches(value))
+        })
+    }
+
+    fn toggle_explain_sort(&mut self) {
+        let col = self.explain_selected_col;
+        if let Some(current) = self.explain_sort_column {
+            if current == col {
+                if self.explain_sort_asc {
+                    // Was ascending, flip to descending
+                    self.explain_sort_asc = false;
+                } else {
+                    // Was descending, clear sort
+                    self.explain_sort_column = None;
+                    self.apply_explain_sort();
+                    self.explain_scroll = 0;
+                    return;
+                }
+            } else {
+                self.explain_sort_column = Some(col);
+                self.explain_sort_asc = true;
+            }
+        } else {
+            self.explain_sort_column = Some(col);
+            self.explain_sort_asc = true;
+        }
+        self.apply_explain_sort();
+        self.explain_scroll = 0;
+    }
+
+    /// Order the explain rows passing the filters by the sort column, if any
+    fn apply_explain_sort(&mut self) {
+        let Some(ref data) = self.explain_data else { return };
+        let mut indices: Vec<usize> = (0..data.rows.len()).filter(|&i| self.explain_row_passes(i)).collect();
+        let Some(col) = self.explain_sort_column else {
+            self.explain_sorted_indices = indices;
+            return;
+        };
+        let asc = self.explain_sort_asc;
+
+        indices.sort_by(|&a, &b| {
+            let va = data.rows[a].get(col);
+            let vb = data.rows[b].get(col);
+            let ord = cmp_json_values(va, vb);
+            if asc { ord } else { ord.reverse() }
+        });
+        self.explain_sorted_indices = indices;
+    }
+
+    /// Handle drill-down error
+    pub fn on_drill_down_error(&mut self, generation: u64, error: String) {
+        if !self.is_current_drill_down(generation) {
+            return;
+        }
+        self.explain_error = Some(error);
+        self.explain_loading = false;
+        self.explain_page_offset = None;
+        self.drill_down_hook();
+    }
+
+    /// Run EXPLAIN ANALYZE on the chart's query and show the plan overlay
+    fn trigger_plan(&mut self) {
+        let Some(query) = self.data.as_ref().map(|d| d.query.clone()) else { return };
+        info!("trigger_plan called");
+        self.enter_mode(Mode::Plan);
+        self.plan_loading = true;
+        self.plan_text = None;
+        self.plan_error = None;
+        self.plan_scroll = 0;
+        self.plan_hscroll = 0;
+        self.pending_plan_query = Some((query, self.query_database()));
+    }
+
+    /// Take pending plan query and its database (called by main loop)
+    pub fn take_pending_plan(&mut self) -> Option<(String, Option<String>)> {
+        self.pending_plan_query.take()
+    }
+
+    /// Handle the EXPLAIN ANALYZE outcome
+    pub fn on_plan_result(&mut self, result: Result<String, String>) {
+        if !self.plan_loading {
+            return;
+        }
+        self.plan_loading = false;
+        match result {
+            Ok(plan) => self.plan_text = Some(plan),
+            Err(e) => self.plan_error = Some(e),
+        }
+    }
+
+    fn plan_line_count(&self) -> usize {
+        self.plan_text.as_ref().map(|p| p.lines().count()).unwrap_or(0)
+    }
+
+    /// Close the plan overlay, cancelling a query that is still running
+    fn close_plan(&mut self) {
+        if self.plan_loading {
+            info!("Cancelling in-flight EXPLAIN ANALYZE");
+            self.tasks.cancel_kind(TaskKind::Plan);
+        }
+        self.leave_mode(|m| *m == Mode::Plan);
+        self.plan_text = None;
+        self.plan_loading = false;
+        self.plan_error = None;
+        self.pending_plan_query = None;
+    }
+
+    /// Run the chart's query again to refresh its rows in place
+    fn trigger_rerun(&mut self) {
+        let Some(ref data) = self.data else { return };
+        if self.rerun_loading {
+            return;
+        }
+        info!("trigger_rerun called");
+        self.rerun_loading = true;
+        self.rerun_error = None;
+        self.edited_query = None;
+        self.pending_rerun_query = Some((data.query.clone(), self.query_database()));
+    }
+
+    /// Open the chart's query (or a failed edit of it) in `$EDITOR`
+    fn start_external_edit(&mut self) {
+        let Some(ref data) = self.data else { return };
+        if self.rerun_loading {
+            return;
+        }
+        let text = self.edited_query.clone().unwrap_or_else(|| format_sql(data.query.trim()));
+        self.pending_external_edit = Some(text);
+    }
+
+    /// Take query text to edit externally (called by main loop)
+    pub fn take_pending_external_edit(&mut self) -> Option<String> {
+        self.pending_external_edit.take()
+    }
+
+    /// Run the query saved from `$EDITOR` in place of the chart's, or report why not
+    pub fn on_external_edit(&mut self, result: anyhow::Result<String>) {
+        let Some(ref data) = self.data else { return };
+        let sql = match result {
+            Ok(sql) => sql.trim().to_string(),
+            Err(e) => {
+                warn!("Editing the query failed: {:#}", e);
+                self.toast(ToastKind::Error, format!("Couldn't edit the query: {:#}", e));
+                return;
+            }
+        };
+        if sql.is_empty() || sql == data.query.trim() || sql == format_sql(data.query.trim()) {
+            self.edited_query = None;
+            self.copy_notice = Some("Query unchanged".to_string());
+            return;
+        }
+        info!("Running the edited query");
+        self.rerun_loading = true;
+        self.rerun_error = None;
+        self.edited_query = Some(sql.clone());
+        self.pending_rerun_query = Some((sql, self.query_database()));
+    }
+
+    /// Switch the Query tab between the query and its diff from the original
+    fn toggle_query_diff(&mut self) {
+        if self.data.as_ref().is_some_and(|d| d.original_query.is_some()) {
+            self.query_diff = !self.query_diff;
+            self.scroll_offset = 0;
+        }
+    }
+
+    /// Lift the row limit on a truncated chart. Rows cut before the chart arrived
+    /// aren't here to restore, so its query is re-run for the full set.
+    fn show_all_rows(&mut self) {
+        let Some(ref mut data) = self.data else { return };
+        if data.truncated_from.is_none() {
+            return;
+        }
+        if data.show_all_rows() {
+            info!("Showing all {} rows", data.rows.len());
+        } else {
+            info!("Re-running query for the rows cut before the chart arrived");
+            self.trigger_rerun();
+        }
+    }
+
+    /// Take pending re-run query and its database (called by main loop)
+    pub fn take_pending_rerun(&mut self) -> Option<(String, Option<String>)> {
+        self.pending_rerun_query.take()
+    }
+
+    /// Step the auto-refresh interval: off → 30s → 1m → 5m → off
+    fn cycle_auto_refresh(&mut self) {
+        self.auto_refresh = match self.auto_refresh {
+            None => AUTO_REFRESH_INTERVALS.first().copied(),
+            Some(current) => AUTO_REFRESH_INTERVALS
+                .iter()
+                .skip_while(|i| **i != current)
+                .nth(1)
+                .copied(),
+        };
+        info!("Auto-refresh interval: {:?}", self.auto_refresh);
+        self.next_refresh = self.auto_refresh.map(|interval| Instant::now() + interval);
+    }
+
+    /// Replace the chart's rows with the re-run's (columns, rows), or record its error
+    pub fn on_rerun_result(&mut self, result: Result<(Vec<String>, Vec<Vec<serde_json::Value>>), String>) {
+        if !self.rerun_loading {
+            return;
+        }
+        self.rerun_loading = false;
+        let Some(ref mut data) = self.data else { return };
+        match result {
+            Ok((columns, rows)) => {
+                info!("Re-run returned {} rows", rows.len());
+                match self.edited_query.take() {
+                    Some(sql) => {
+                        data.apply_edited_query(&sql, columns, rows);
+                        self.query_folds.clear();
+                        self.scroll_offset = 0;
+                    }
+                    None => data.replace_results(columns, rows),
+                }
+                if data.original_query.is_none() {
+                    self.query_diff = false;
+                }
+                self.selected_point = self.selected_point.min(data.rows.len().saturating_sub(1));
+                self.range_anchor = None;
+            }
+            Err(e) => self.rerun_error = Some(e),
+        }
+    }
+
+    /// Start typing into the SQL editor, seeded with the chart's query when empty
+    fn start_sql_edit(&mut self) {
+        if self.sql_editor.is_empty() {
+            if let Some(ref data) = self.data {
+                self.sql_editor.set_text(&data.query);
+            }
+        }
+        self.enter_mode(Mode::Editor);
+
+        // Fetch names for completion once per target and database
+        let database = self.query_database();
+        let source = (self.database_target(), database.clone());
+        if self.schema_source.as_ref() != Some(&source) {
+            self.schema_source = Some(source);
+            self.schema_names.clear();
+            self.pending_schema = Some(database);
+        }
+    }
+
+    /// Take pending schema-name request (called by main loop)
+    pub fn take_pending_schema(&mut self) -> Option<Option<String>> {
+        self.pending_schema.take()
+    }
+
+    /// Copy the selected explain cell, or with `whole_row` the selected row as
+    /// tab-separated values. The selected row is the top one in view.
+    fn copy_explain_row(&mut self, whole_row: bool) {
+        let Some(ref data) = self.explain_data else { return };
+        let Some(row) = self.explain_sorted_indices.get(self.explain_scroll).and_then(|&i| data.rows.get(i)) else {
+            return;
+        };
+        let (text, what) = if whole_row {
+            let cells: Vec<String> = row.iter().map(value_to_string).collect();
+            (cells.join("\t"), "row".to_string())
+        } else {
+            let Some(value) = row.get(self.explain_selected_col) else { return };
+            let column = data.columns.get(self.explain_selected_col).map_or("", |c| c.as_str());
+            (value_to_string(value), format!("{} value", column))
+        };
+        self.copy_to_clipboard(text, &what);
+    }
+
+    /// Copy the drill-down SQL behind the explain overlay, with its values inlined
+    fn copy_explain_sql(&mut self) {
+        let Some(ref query) = self.explain_query else { return };
+        let sql = query.inline_params();
+        self.copy_to_clipboard(sql, "SQL");
+    }
+
+    /// Copy the chart's query as laid out on the Query tab, or as written
+    fn copy_query(&mut self, formatted: bool) {
+        let Some(ref data) = self.data else { return };
+        let (sql, what) = if formatted {
+            (format_sql(data.query.trim()), "formatted SQL")
+        } else {
+            (data.query.trim().to_string(), "SQL as written")
+        };
+        self.copy_to_clipboard(sql, what);
+        self.copy_notice = Some(format!("Copied {}", what));
+    }
+
+    fn copy_to_clipboard(&mut self, text: String, what: &str) {
+        info!("Copying {} ({} chars) to the clipboard", what, text.len());
+        self.explain_notice = Some(format!("Copied {}", what));
+        self.pending_clipboard = Some(text);
+    }
+
+    /// Resize the explain overlay and save the size for next time
+    fn set_explain_size(&mut self, size: ExplainSize) {
+        if size == self.explain_size {
+            return;
+        }
+        self.explain_size = size;
+        if size != ExplainSize::Fullscreen {
+            self.explain_unzoomed_size = None;
+        }
+        let state = UiState { explain_size: size };
+        if let Err(e) = config::save_ui_state(&config::get_ui_state_path(), &state) {
+            warn!("Failed to save the explain overlay size: {:#}", e);
+        }
+    }
+
+    /// Go fullscreen, or back to the size the overlay had before
+    fn toggle_explain_fullscreen(&mut self) {
+        if self.explain_size == ExplainSize::Fullscreen {
+            let size = self.explain_unzoomed_size.take().unwrap_or_default();
+            self.set_explain_size(size);
+        } else {
+            let previous = self.explain_size;
+            self.set_explain_size(ExplainSize::Fullscreen);
+            self.explain_unzoomed_size = Some(previous);
+        }
+    }
+
+    /// Take pending clipboard text (called by main loop)
+    pub fn take_pending_clipboard(&mut self) -> Option<String> {
+        self.pending_clipboard.take()
+    }
+
+    /// Store the names offered by completion; on failure only keywords are offered
+    pub fn on_schema_names(&mut self, result: Result<Vec<String>, String>) {
+        match result {
+            Ok(names) => self.schema_names = names,
+            Err(e) => {
+                warn!("Schema names unavailable for completion: {}", e);
+                self.schema_source = None;
+            }
+        }
+    }
+
+    /// Drill-down templates to dry-run with `EXPLAIN`, as (label, query) with sample
+    /// values bound, plus the source to hand back with the result (called by main
+    /// loop). Checks start when the Mask tab is shown and are redone only when the
+    /// templates or target change. Problems found without a database are recorded
+    /// here, and a template with unfilled placeholders is not sent to `EXPLAIN`.
+    pub fn take_pending_template_check(&mut self) -> Option<(String, Vec<(String, BoundQuery)>)> {
+        if self.active_tab != Tab::Mask {
+            return None;
+        }
+        let data = self.data.as_ref()?;
+        let drill_down = data.drill_down.as_ref()?;
+        let mut templates = vec![("Drill-down template", drill_down.query_template.as_str())];
+        if let Some(ref range) = drill_down.range_query_template {
+            templates.push(("Range template", range.as_str()));
+        }
+
+        let source = format!(
+            "{}\n{:?}\n{}",
+            self.database_target(),
+            data.database,
+            templates.iter().map(|(_, t)| *t).collect::<Vec<_>>().join("\n")
+        );
+        if self.template_check_source.as_ref() == Some(&source) {
+            return None;
+        }
+
+        let values = sample_values(data);
+        let mut problems = Vec::new();
+        let mut queries = Vec::new();
+        for (label, template) in templates {
+            problems.extend(template_problems(template, data).into_iter().map(|p| format!("{}: {}", label, p)));
+            // Unfilled placeholders would only add a syntax error to the report
+            let query = bind_placeholders(&with_database(template, data), &values);
+            if placeholder_names(&query.sql).is_empty() {
+                queries.push((label.to_string(), query));
+            }
+        }
+        info!("Checking {} drill-down template(s)", queries.len());
+
+        self.template_check_source = Some(source.clone());
+        if queries.is_empty() {
+            self.template_check = Some(TemplateCheck::Done(problems));
+            return None;
+        }
+        // Keep what was found already; `on_template_check` adds the EXPLAIN results
+        self.template_check_problems = problems;
+        self.template_check = Some(TemplateCheck::Running);
+        Some((source, queries))
+    }
+
+    /// Record the `EXPLAIN` errors for the templates checked against `source`
+    pub fn on_template_check(&mut self, source: String, errors: Vec<String>) {
+        if self.template_check_source.as_ref() != Some(&source) {
+            return;
+        }
+        let mut problems = std::mem::take(&mut self.template_check_problems);
+        problems.extend(errors);
+        self.template_check = Some(TemplateCheck::Done(problems));
+    }
+
+    fn handle_sql_key(&mut self, key: KeyEvent) {
+        if key.code != KeyCode::Tab {
+            self.sql_editor.cancel_completion();
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            match key.code {
+                KeyCode::Char('r') => self.trigger_sql(),
+                KeyCode::Char('p') => self.sql_editor.history_prev(),
+                KeyCode::Char('n') => self.sql_editor.history_next(),
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Esc => self.leave_mode(|m| *m == Mode::Editor),
+            KeyCode::F(5) => self.trigger_sql(),
+            KeyCode::Enter => self.sql_editor.insert_newline(),
+            KeyCode::Tab => {
+                let schema_names = &self.schema_names;
+                if !self.sql_editor.complete(|prefix| sql_completions(prefix, schema_names)) {
+                    self.sql_editor.insert_char(' ');
+                    self.sql_editor.insert_char(' ');
+                }
+            }
+            KeyCode::Backspace => self.sql_editor.backspace(),
+            KeyCode::Delete => self.sql_editor.delete(),
+            KeyCode::Left => self.sql_editor.move_left(),
+            KeyCode::Right => self.sql_editor.move_right(),
+            KeyCode::Up => self.sql_editor.move_up(),
+            KeyCode::Down => self.sql_editor.move_down(),
+            KeyCode::Home => self.sql_editor.move_home(),
+            KeyCode::End => self.sql_editor.move_end(),
+            KeyCode::Char(c) => self.sql_editor.insert_char(c),
+            _ => {}
+        }
     }
 
-    /// Take pending drill-down query (called by main loop)
-    pub fn take_pending_drill_down(&mut self) -> Option<String> {
-        self.pending_drill_down_query.take()
+    /// Run the SQL editor's contents against the current database target
+    fn trigger_sql(&mut self) {
+        if self.sql_running || self.sql_editor.is_empty() {
+            return;
+        }
+        let sql = self.sql_editor.text();
+        info!("trigger_sql called");
+        self.sql_editor.record(&sql);
+        self.sql_running = true;
+        self.sql_error = None;
+        let database = self.query_database();
+        self.pending_sql_query = Some((sql, database));
     }
 
-    /// Handle successful drill-down result
-    pub fn on_drill_down_success(&mut self, data: ExplainData) {
-        let row_count = data.rows.len();
-        self.explain_data = Some(data);
-        self.explain_loading = false;
-        self.explain_error = None;
-        self.explain_selected_col = 0;
-        self.explain_sort_column = None;
-        self.explain_sort_asc = true;
-        self.explain_sorted_indices = (0..row_count).collect();
+    /// Take pending SQL tab query and its database (called by main loop)
+    pub fn take_pending_sql(&mut self) -> Option<(String, Option<String>)> {
+        self.pending_sql_query.take()
     }
 
-    fn toggle_explain_sort(&mut self) {
-        let col = self.explain_selected_col;
-        if let Some(current) = self.explain_sort_column {
-            if current == col {
-                if self.explain_sort_asc {
-                    // Was ascending, flip to descending
-                    self.explain_sort_asc = false;
-                } else {
-                    // Was descending, clear sort
-                    self.explain_sort_column = None;
-                    if let Some(ref data) = self.explain_data {
-                        self.explain_sorted_indices = (0..data.rows.len()).collect();
-                    }
-                    self.explain_scroll = 0;
-                    return;
-                }
-            } else {
-                self.explain_sort_column = Some(col);
-                self.explain_sort_asc = true;
+    /// Load the SQL tab's results as the current chart, or record its error
+    pub fn on_sql_result(&mut self, sql: String, result: Result<(Vec<String>, Vec<Vec<serde_json::Value>>), String>) {
+        if !self.sql_running {
+            return;
+        }
+        self.sql_running = false;
+        match result {
+            Ok((columns, rows)) => {
+                info!("SQL tab query returned {} rows", rows.len());
+                let data = ChartData::from_query(&sql, columns, rows, self.data.as_ref());
+                self.show_in_slot(None, true, data);
+                self.leave_mode(|m| *m == Mode::Editor);
+                self.active_tab = Tab::Data;
             }
-        } else {
-            self.explain_sort_column = Some(col);
-            self.explain_sort_asc = true;
+            Err(e) => self.sql_error = Some(e),
         }
-        self.apply_explain_sort();
-        self.explain_scroll = 0;
     }
 
-    fn apply_explain_sort(&mut self) {
-        let Some(ref data) = self.explain_data else { return };
-        let Some(col) = self.explain_sort_column else { return };
-        let asc = self.explain_sort_asc;
+    /// Record an executed drill-down, appending it to disk when enabled in the config
+    pub fn on_query_logged(&mut self, entry: QueryLogEntry) {
+        if entry.duration_ms >= SLOW_QUERY_MS && entry.error.is_none() {
+            let rows = if entry.rows == 1 { "row" } else { "rows" };
+            let text = format!("Drill-down took {:.1}s · {} {}", entry.duration_ms as f64 / 1000.0, entry.rows, rows);
+            self.toast(ToastKind::Info, text);
+        }
+        if self.config.log_queries {
+            if let Err(e) = append_query_log(&entry) {
+                warn!("Failed to write query log: {:#}", e);
+            }
+        }
+        self.query_log.push(entry);
+        if self.query_log.len() > MAX_QUERY_LOG {
+            self.query_log.remove(0);
+        }
+        // Keep the selection on the same entry as new ones arrive above it
+        if self.in_mode(&Mode::QueryLog) && self.query_log_selected > 0 {
+            self.query_log_selected = (self.query_log_selected + 1).min(self.query_log.len() - 1);
+        }
+    }
 
-        let mut indices: Vec<usize> = (0..data.rows.len()).collect();
-        indices.sort_by(|&a, &b| {
-            let va = data.rows[a].get(col);
-            let vb = data.rows[b].get(col);
-            let ord = cmp_json_values(va, vb);
-            if asc { ord } else { ord.reverse() }
-        });
-        self.explain_sorted_indices = indices;
+    /// Record a connection status change (called by main loop)
+    pub fn on_connection_state(&mut self, target: String, state: ConnectionState) {
+        info!("Connection to {}: {:?}", target, state);
+        if let ConnectionState::Failed { ref error } = state {
+            let was_connected = matches!(self.connection, Some((ref t, ConnectionState::Connected)) if *t == target);
+            let what = if was_connected { "lost" } else { "failed" };
+            let reason = error.lines().next().unwrap_or_default();
+            self.toast(ToastKind::Error, format!("Connection to {} {}: {}", target_label(&target), what, reason));
+        }
+        self.connection = Some((target, state));
     }
 
-    /// Handle drill-down error
-    pub fn on_drill_down_error(&mut self, error: String) {
-        self.explain_error = Some(error);
-        self.explain_loading = false;
+    /// Connection status for the current database target, if it has been used
+    pub fn connection_state(&self) -> Option<&ConnectionState> {
+        let (target, state) = self.connection.as_ref()?;
+        (*target == self.database_target()).then_some(state)
     }
 
-    /// Close the explain overlay
+    /// Close the explain overlay, cancelling a query that is still loading
     fn close_explain(&mut self) {
-        self.show_explain = false;
+        if self.explain_loading {
+            info!("Cancelling in-flight drill-down");
+            self.tasks.cancel_kind(TaskKind::DrillDown);
+        }
+        self.drill_down_generation += 1;
+        self.leave_mode(|m| *m == Mode::Explain || matches!(m, Mode::Command(c) if c.kind == CommandKind::ExplainFilter));
         self.explain_data = None;
         self.explain_loading = false;
         self.explain_error = None;
@@ -368,52 +2584,109 @@ impl App {
         self.explain_sort_column = None;
         self.explain_sort_asc = true;
         self.explain_sorted_indices = Vec::new();
+        self.explain_filters.clear();
+        self.explain_notice = None;
+        self.explain_query = None;
+        self.explain_cached_at = None;
+        self.explain_page_offset = None;
         self.pending_drill_down_query = None;
     }
 
     pub fn handle_mouse(&mut self, mouse: MouseEvent) {
-        if self.show_help {
+        if self.in_mode(&Mode::Help) {
             if matches!(mouse.kind, MouseEventKind::Down(_)) {
-                self.show_help = false;
+                self.leave_mode(|m| *m == Mode::Help);
+            }
+            return;
+        }
+
+        if let Some(ref mut comparison) = self.comparison {
+            let max = comparison.query_diff.len().saturating_sub(1);
+            match mouse.kind {
+                MouseEventKind::ScrollUp => comparison.diff_scroll = comparison.diff_scroll.saturating_sub(3),
+                MouseEventKind::ScrollDown => comparison.diff_scroll = (comparison.diff_scroll + 3).min(max),
+                _ => {}
+            }
+            return;
+        }
+
+        if self.in_mode(&Mode::Plan) {
+            let max_scroll = self.plan_line_count().saturating_sub(1);
+            match mouse.kind {
+                MouseEventKind::ScrollUp => self.plan_scroll = self.plan_scroll.saturating_sub(3),
+                MouseEventKind::ScrollDown => self.plan_scroll = (self.plan_scroll + 3).min(max_scroll),
+                _ => {}
             }
             return;
         }
 
+        let selected = self.selected_point;
         match mouse.kind {
-            MouseEventKind::ScrollUp => {
+            // The wheel and chart clicks act on the tab, so not while an overlay covers it
+            MouseEventKind::ScrollUp if self.modes.is_empty() => {
                 self.handle_scroll(-3);
             }
-            MouseEventKind::ScrollDown => {
+            MouseEventKind::ScrollDown if self.modes.is_empty() => {
                 self.handle_scroll(3);
             }
+            // A drill-down row clicked becomes the top one, which `y`/`Y` copy
+            MouseEventKind::Down(MouseButton::Left) if self.in_mode(&Mode::Explain) => {
+                if let Some(pos) = row_at(&self.explain_hits, mouse.column, mouse.row) {
+                    self.explain_scroll = pos;
+                }
+            }
+            // Not under an overlay (a confirm prompt, the task list), which a click would act behind
+            MouseEventKind::Down(MouseButton::Left) if self.active_tab == Tab::Data && self.modes.is_empty() => {
+                if let Some(row) = row_at(&self.data_hits, mouse.column, mouse.row) {
+                    self.selected_point = row;
+                    let double = self
+                        .last_click
+                        .is_some_and(|(last, at)| last == row && at.elapsed() < DOUBLE_CLICK);
+                    if double {
+                        self.last_click = None;
+                        self.trigger_explain();
+                    } else {
+                        self.last_click = Some((row, Instant::now()));
+                    }
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left) if self.active_tab == Tab::Chart && self.modes.is_empty() => {
+                if let Some(row) = hit_test(&self.chart_hits, mouse.column, mouse.row) {
+                    self.selected_point = row;
+                    // Dragging from here brushes a range
+                    self.range_anchor = Some(row);
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left)
+                if self.active_tab == Tab::Chart && self.modes.is_empty() && self.range_anchor.is_some() =>
+            {
+                if let Some(row) = hit_test(&self.chart_hits, mouse.column, mouse.row) {
+                    self.selected_point = row;
+                }
+            }
             _ => {}
         }
+        self.after_selection(selected);
     }
 
     fn handle_scroll(&mut self, delta: i32) {
         match self.active_tab {
             Tab::Home => {
-                let len = self.history.len();
-                if len > 0 {
-                    if delta < 0 {
-                        self.history_selected =
-                            self.history_selected.saturating_sub((-delta) as usize);
-                    } else {
-                        self.history_selected =
-                            (self.history_selected + delta as usize).min(len - 1);
-                    }
+                let visible = self.visible_history();
+                if let Some(pos) = visible.iter().position(|&i| i == self.history_selected) {
+                    let pos = (pos as i64 + delta as i64).clamp(0, visible.len() as i64 - 1);
+                    self.history_selected = visible[pos as usize];
                 }
             }
             Tab::Query => {
-                if let Some(ref data) = self.data {
-                    let max_scroll = get_query_line_count(data).saturating_sub(1);
-                    if delta < 0 {
-                        self.scroll_offset = self.scroll_offset.saturating_sub((-delta) as usize);
-                    } else {
-                        self.scroll_offset = (self.scroll_offset + delta as usize).min(max_scroll);
-                    }
+                let max_scroll = self.query_rows().saturating_sub(1);
+                if delta < 0 {
+                    self.scroll_offset = self.scroll_offset.saturating_sub((-delta) as usize);
+                } else {
+                    self.scroll_offset = (self.scroll_offset + delta as usize).min(max_scroll);
                 }
             }
+            Tab::Data if !self.search_query.is_empty() => self.step_match(delta.signum() as isize),
             Tab::Data | Tab::Chart => {
                 if let Some(ref data) = self.data {
                     let len = data.rows.len();
@@ -428,21 +2701,29 @@ impl App {
                     }
                 }
             }
+            Tab::Mask => {
+                if let Some(ref data) = self.data {
+                    let len = data.columns.len();
+                    if len > 0 {
+                        if delta < 0 {
+                            self.mask_selected = self.mask_selected.saturating_sub((-delta) as usize);
+                        } else {
+                            self.mask_selected = (self.mask_selected + delta as usize).min(len - 1);
+                        }
+                    }
+                }
+            }
             _ => {}
         }
     }
 
     fn handle_up(&mut self) {
         match self.active_tab {
-            Tab::Home => {
-                let len = self.history.len();
-                if len > 0 {
-                    self.history_selected = (self.history_selected + len - 1) % len;
-                }
-            }
+            Tab::Home => self.step_history(-1),
             Tab::Query => {
                 self.scroll_offset = self.scroll_offset.saturating_sub(1);
             }
+            Tab::Chart if self.chart_sort != ChartSort::Original => self.step_sorted(-1),
             Tab::Data | Tab::Chart => {
                 if let Some(ref data) = self.data {
                     let len = data.rows.len();
@@ -451,24 +2732,26 @@ impl App {
                     }
                 }
             }
+            Tab::Mask => {
+                if let Some(ref data) = self.data {
+                    let len = data.columns.len();
+                    if len > 0 {
+                        self.mask_selected = (self.mask_selected + len - 1) % len;
+                    }
+                }
+            }
             _ => {}
         }
     }
 
     fn handle_down(&mut self) {
         match self.active_tab {
-            Tab::Home => {
-                let len = self.history.len();
-                if len > 0 {
-                    self.history_selected = (self.history_selected + 1) % len;
-                }
-            }
+            Tab::Home => self.step_history(1),
             Tab::Query => {
-                if let Some(ref data) = self.data {
-                    let max_scroll = get_query_line_count(data).saturating_sub(1);
-                    self.scroll_offset = (self.scroll_offset + 1).min(max_scroll);
-                }
+                let max_scroll = self.query_rows().saturating_sub(1);
+                self.scroll_offset = (self.scroll_offset + 1).min(max_scroll);
             }
+            Tab::Chart if self.chart_sort != ChartSort::Original => self.step_sorted(1),
             Tab::Data | Tab::Chart => {
                 if let Some(ref data) = self.data {
                     let len = data.rows.len();
@@ -477,6 +2760,14 @@ impl App {
                     }
                 }
             }
+            Tab::Mask => {
+                if let Some(ref data) = self.data {
+                    let len = data.columns.len();
+                    if len > 0 {
+                        self.mask_selected = (self.mask_selected + 1) % len;
+                    }
+                }
+            }
             _ => {}
         }
     }
@@ -484,7 +2775,9 @@ impl App {
     fn handle_home(&mut self) {
         match self.active_tab {
             Tab::Home => {
-                self.history_selected = 0;
+                if let Some(&first) = self.visible_history().first() {
+                    self.history_selected = first;
+                }
             }
             Tab::Query => {
                 self.scroll_offset = 0;
@@ -492,6 +2785,9 @@ impl App {
             Tab::Data | Tab::Chart => {
                 self.selected_point = 0;
             }
+            Tab::Mask => {
+                self.mask_selected = 0;
+            }
             _ => {}
         }
     }
@@ -499,14 +2795,12 @@ impl App {
     fn handle_end(&mut self) {
         match self.active_tab {
             Tab::Home => {
-                if !self.history.is_empty() {
-                    self.history_selected = self.history.len() - 1;
+                if let Some(&last) = self.visible_history().last() {
+                    self.history_selected = last;
                 }
             }
             Tab::Query => {
-                if let Some(ref data) = self.data {
-                    self.scroll_offset = get_query_line_count(data).saturating_sub(1);
-                }
+                self.scroll_offset = self.query_rows().saturating_sub(1);
             }
             Tab::Data | Tab::Chart => {
                 if let Some(ref data) = self.data {
@@ -515,15 +2809,18 @@ impl App {
                     }
                 }
             }
+            Tab::Mask => {
+                if let Some(ref data) = self.data {
+                    self.mask_selected = data.columns.len().saturating_sub(1);
+                }
+            }
             _ => {}
         }
     }
 
     fn handle_page_up(&mut self) {
         match self.active_tab {
-            Tab::Home => {
-                self.history_selected = self.history_selected.saturating_sub(10);
-            }
+            Tab::Home => self.handle_scroll(-(HISTORY_PAGE as i32)),
             Tab::Query => {
                 self.scroll_offset = self.scroll_offset.saturating_sub(10);
             }
@@ -541,17 +2838,10 @@ impl App {
 
     fn handle_page_down(&mut self) {
         match self.active_tab {
-            Tab::Home => {
-                let len = self.history.len();
-                if len > 0 {
-                    self.history_selected = (self.history_selected + 10).min(len - 1);
-                }
-            }
+            Tab::Home => self.handle_scroll(HISTORY_PAGE as i32),
             Tab::Query => {
-                if let Some(ref data) = self.data {
-                    let max_scroll = get_query_line_count(data).saturating_sub(1);
-                    self.scroll_offset = (self.scroll_offset + 10).min(max_scroll);
-                }
+                let max_scroll = self.query_rows().saturating_sub(1);
+                self.scroll_offset = (self.scroll_offset + 10).min(max_scroll);
             }
             Tab::Data | Tab::Chart => {
                 if let Some(ref data) = self.data {
@@ -565,42 +2855,359 @@ impl App {
         }
     }
 
+    /// Reload the Home tab's list: the watched directory's charts in directory mode,
+    /// otherwise the archived history
     pub fn refresh_history(&mut self) {
-        self.history = load_history_entries();
+        self.history = match get_data_dir() {
+            Some(dir) => load_dir_entries(dir),
+            None => load_history_entries(),
+        };
         if !self.history.is_empty() {
             self.history_selected = self.history_selected.min(self.history.len() - 1);
         } else {
             self.history_selected = 0;
         }
+        let history = &self.history;
+        self.history_marked.retain(|path| history.iter().any(|e| &e.path == path));
+        self.select_visible_history();
+    }
+
+    /// Indices of the history entries passing the Home tab filter, all when unset
+    pub fn visible_history(&self) -> Vec<usize> {
+        (0..self.history.len())
+            .filter(|&i| self.history_filter.is_empty() || self.history[i].matches(&self.history_filter))
+            .collect()
+    }
+
+    fn clear_history_filter(&mut self) {
+        self.history_filter.clear();
+        self.leave_mode(|m| *m == Mode::Search(SearchTarget::History));
+    }
+
+    /// Keep the selection on a visible entry as the filter narrows
+    fn select_visible_history(&mut self) {
+        let visible = self.visible_history();
+        if !visible.contains(&self.history_selected) {
+            if let Some(&first) = visible.first() {
+                self.history_selected = first;
+            }
+        }
+    }
+
+    /// Move the selection to the next (`delta` > 0) or previous visible entry, wrapping
+    fn step_history(&mut self, delta: isize) {
+        let visible = self.visible_history();
+        if visible.is_empty() {
+            return;
+        }
+        let len = visible.len() as isize;
+        let pos = visible.iter().position(|&i| i == self.history_selected).unwrap_or(0) as isize;
+        self.history_selected = visible[(pos + delta).rem_euclid(len) as usize];
+    }
+
+    /// The selected history entry, unless the filter hides it
+    fn selected_history_entry(&self) -> Option<&HistoryEntry> {
+        let entry = self.history.get(self.history_selected)?;
+        (self.history_filter.is_empty() || entry.matches(&self.history_filter)).then_some(entry)
     }
 
     fn delete_history_entry(&mut self) {
-        if self.history_selected >= self.history.len() {
+        let Some(entry) = self.selected_history_entry() else { return };
+        let action = Confirm::DeleteHistory { path: entry.path.clone(), title: entry.title.clone() };
+        self.confirm(action);
+    }
+
+    /// Ask before `action`, unless the config turns confirmations off
+    fn confirm(&mut self, action: Confirm) {
+        if self.config.confirm_destructive == Some(false) {
+            self.run_confirmed(action);
+        } else {
+            self.enter_mode(Mode::Confirm(action));
+        }
+    }
+
+    fn run_confirmed(&mut self, action: Confirm) {
+        match action {
+            Confirm::ClearData => self.clear_data(),
+            Confirm::DeleteHistory { path, .. } => {
+                remove_history_entry(&path);
+                self.refresh_history();
+            }
+        }
+    }
+
+    /// `y` or Enter goes ahead, `n` or Esc backs out; anything else is ignored
+    fn handle_confirm_key(&mut self, action: Confirm, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('y' | 'Y') | KeyCode::Enter => {
+                self.modes.pop();
+                self.run_confirmed(action);
+            }
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => {
+                self.modes.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Pin the selected analysis to the top of the list, keeping it out of rotation,
+    /// or unpin it. A directory's charts are the user's own files and aren't pinned.
+    fn toggle_history_pin(&mut self) {
+        if get_data_dir().is_some() {
+            return;
+        }
+        let Some(entry) = self.selected_history_entry() else { return };
+        let path = entry.path.clone();
+        let mut meta = entry.meta.clone();
+        meta.pinned = !meta.pinned;
+        if let Err(e) = save_history_meta(&path, &meta) {
+            warn!("Failed to pin {}: {:#}", path.display(), e);
             return;
         }
-        let entry = &self.history[self.history_selected];
-        let _ = std::fs::remove_file(&entry.path);
         self.refresh_history();
+        // The entry moved with the re-sort; keep it selected
+        if let Some(i) = self.history.iter().position(|e| e.path == path) {
+            self.history_selected = i;
+        }
     }
 
-    fn load_history_entry(&mut self) {
-        if self.history_selected >= self.history.len() {
+    /// Replace the selected analysis's tags with those typed into the prompt
+    fn set_history_tags(&mut self, tags: Vec<String>) {
+        let Some(entry) = self.selected_history_entry() else { return };
+        let path = entry.path.clone();
+        let mut meta = entry.meta.clone();
+        meta.tags = tags;
+        if let Err(e) = save_history_meta(&path, &meta) {
+            warn!("Failed to tag {}: {:#}", path.display(), e);
+            return;
+        }
+        self.refresh_history();
+        if let Some(i) = self.history.iter().position(|e| e.path == path) {
+            self.history_selected = i;
+        }
+    }
+
+    /// Retitle the selected analysis, in its history file and on screen if it's open
+    fn rename_history_entry(&mut self, title: String) {
+        let Some(entry) = self.selected_history_entry() else { return };
+        let path = entry.path.clone();
+        if let Err(e) = rename_history_entry(&path, &title) {
+            warn!("Failed to rename {}: {:#}", path.display(), e);
+            return;
+        }
+        info!("Renamed {} to {}", path.display(), title);
+        if self.data_path.as_ref() == Some(&path) {
+            if let Some(ref mut data) = self.data {
+                data.title = title;
+            }
+        }
+        self.refresh_history();
+        if let Some(i) = self.history.iter().position(|e| e.path == path) {
+            self.history_selected = i;
+        }
+    }
+
+    /// Mark the selected analysis for comparison, or unmark it. Marking a third
+    /// drops the earliest mark.
+    fn toggle_history_mark(&mut self) {
+        let Some(entry) = self.selected_history_entry() else { return };
+        let path = entry.path.clone();
+        if let Some(i) = self.history_marked.iter().position(|p| *p == path) {
+            self.history_marked.remove(i);
+            return;
+        }
+        if self.history_marked.len() == 2 {
+            self.history_marked.remove(0);
+        }
+        self.history_marked.push(path);
+    }
+
+    /// Open the two marked analyses side by side. With one mark, the selected
+    /// analysis is the other half.
+    fn open_comparison(&mut self) {
+        let mut paths = self.history_marked.clone();
+        if paths.len() == 1 {
+            match self.selected_history_entry() {
+                Some(entry) if entry.path != paths[0] => paths.push(entry.path.clone()),
+                _ => return,
+            }
+        }
+        let mut entries: Vec<HistoryEntry> = paths
+            .iter()
+            .filter_map(|path| self.history.iter().find(|e| &e.path == path).cloned())
+            .collect();
+        if entries.len() != 2 {
             return;
         }
-        let entry = &self.history[self.history_selected];
-        if let Ok(data) = load_data(&entry.path) {
-            self.on_data_update(data);
+        entries.sort_by_key(|e| e.timestamp);
+
+        let mut charts = Vec::with_capacity(2);
+        for entry in &entries {
+            match load_data(&entry.path) {
+                Ok(data) => charts.push(data),
+                Err(e) => {
+                    warn!("Failed to load {} for comparison: {:#}", entry.path.display(), e);
+                    return;
+                }
+            }
+        }
+        for (chart, label) in charts.iter_mut().zip(["A", "B"]) {
+            chart.title = format!("{} · {}", label, chart.title);
+        }
+        let overlay = charts[0].overlay("A", &charts[1], "B");
+        let query_diff = diff_lines(&format_sql(&charts[0].query), &format_sql(&charts[1].query));
+        info!("Comparing {} with {}", entries[0].title, entries[1].title);
+
+        let (Ok(entries), Ok(charts)) = (entries.try_into(), charts.try_into()) else { return };
+        let charts: [ChartData; 2] = charts;
+        let palettes = charts.each_ref().map(|chart| Palette::resolve(&self.config.palette, chart.palette.as_ref()));
+        self.comparison = Some(Comparison {
+            entries,
+            charts,
+            show_overlay: overlay.is_some(),
+            overlay,
+            query_diff,
+            diff_scroll: 0,
+            palettes,
+        });
+        self.enter_mode(Mode::Compare);
+    }
+
+    fn load_history_entry(&mut self) {
+        let Some(entry) = self.selected_history_entry() else { return };
+        let path = entry.path.clone();
+        if let Ok(data) = load_data(&path) {
+            self.show_chart(Some(path), data);
         }
     }
 
     pub fn tick(&mut self) {
         self.frame = self.frame.wrapping_add(1);
+        let now = Instant::now();
+        self.toasts.retain(|t| t.expires > now);
+
+        // Follow the log about once a second while it's shown
+        if self.in_mode(&Mode::AppLog) && self.frame.is_multiple_of(10) {
+            self.refresh_app_log();
+        }
+
+        if let (Some(interval), Some(due)) = (self.auto_refresh, self.next_refresh) {
+            if Instant::now() >= due && !self.rerun_loading {
+                self.next_refresh = Some(Instant::now() + interval);
+                self.trigger_rerun();
+            }
+        }
     }
 }
 
 impl Default for App {
     fn default() -> Self {
-        Self::new()
+        Self::new(Config::default())
+    }
+}
+
+/// The match after (`delta` > 0) or before `current` in ascending `matches`,
+/// wrapping; None when there are none
+fn step_among(matches: &[usize], current: usize, delta: isize) -> Option<usize> {
+    if matches.is_empty() {
+        return None;
+    }
+    let len = matches.len();
+    let next = match matches.iter().position(|&i| i == current) {
+        Some(pos) => (pos as isize + delta).rem_euclid(len as isize) as usize,
+        None if delta > 0 => matches.iter().position(|&i| i > current).unwrap_or(0),
+        None => matches.iter().rposition(|&i| i < current).unwrap_or(len - 1),
+    };
+    Some(matches[next])
+}
+
+/// A chart title as a file name: `Revenue by Month` → `revenue-by-month`
+fn file_stem(title: &str) -> String {
+    let stem = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if stem.is_empty() { "chart".to_string() } else { stem }
+}
+
+/// Stand-in values for dry-running drill-down templates: the first row's x, y, and
+/// mapped fields, and the first and last x values as a range
+fn sample_values(data: &ChartData) -> HashMap<String, serde_json::Value> {
+    let x_idx = data.get_x_index();
+    let cell = |row: Option<&Vec<serde_json::Value>>, idx: usize| {
+        row.and_then(|r| r.get(idx)).cloned().unwrap_or(serde_json::Value::Null)
+    };
+    let first = data.rows.first();
+    let mut values = HashMap::new();
+    if let Some(ref drill_down) = data.drill_down {
+        for (placeholder, field_name) in &drill_down.param_mapping {
+            if let Some(col_idx) = data.columns.iter().position(|c| c == field_name) {
+                values.insert(placeholder.clone(), cell(first, col_idx));
+            }
+        }
+    }
+    values.insert("x".to_string(), cell(first, x_idx));
+    values.insert("y".to_string(), cell(first, data.get_y_index()));
+    values.insert("x_start".to_string(), cell(first, x_idx));
+    values.insert("x_end".to_string(), cell(data.rows.last(), x_idx));
+    values
+}
+
+/// Problems in a drill-down template that show without running it: placeholders
+/// nothing fills, and `{{database}}` used without, or missing despite, a chart database
+fn template_problems(template: &str, data: &ChartData) -> Vec<String> {
+    let mapped = |name: &str| {
+        data.drill_down
+            .as_ref()
+            .is_some_and(|d| d.param_mapping.contains_key(name))
+    };
+    let mut problems = Vec::new();
+    let names = placeholder_names(template);
+    for name in &names {
+        let known = matches!(name.as_str(), "x" | "y" | "x_start" | "x_end") || mapped(name);
+        if name == "database" {
+            if data.database.is_none() {
+                problems.push("uses {{database}} but the chart has no database".to_string());
+            }
+        } else if !known {
+            problems.push(format!("{{{{{}}}}} has no value (not x, y, x_start, x_end, or in paramMapping)", name));
+        }
+    }
+    if data.database.is_some() && !names.iter().any(|n| n == "database") {
+        problems.push("doesn't use {{database}}, so tables resolve against the default database".to_string());
+    }
+    problems
+}
+
+/// Completion candidates for `prefix`: schema names, then keywords and functions
+/// in the prefix's case
+fn sql_completions(prefix: &str, schema_names: &[String]) -> Vec<String> {
+    let upper = prefix.to_uppercase();
+    let lowercase = !prefix.chars().any(|c| c.is_uppercase());
+    let mut candidates: Vec<String> = schema_names
+        .iter()
+        .filter(|name| name.to_uppercase().starts_with(&upper))
+        .cloned()
+        .collect();
+    for word in SQL_KEYWORDS.iter().chain(SQL_FUNCTIONS) {
+        if word.starts_with(&upper) {
+            let word = if lowercase { word.to_lowercase() } else { word.to_string() };
+            if !candidates.contains(&word) {
+                candidates.push(word);
+            }
+        }
+    }
+    candidates
+}
+
+/// Substitute `{{database}}` into a template. It names a catalog rather than a value,
+/// so unlike the other placeholders it can't be a bound parameter.
+fn with_database(template: &str, data: &ChartData) -> String {
+    match data.database {
+        Some(ref db) => template.replace("{{database}}", db),
+        None => template.to_string(),
     }
 }
 
@@ -620,9 +3227,9 @@ fn cmp_json_values(
         (Some(serde_json::Value::Null), _) => Ordering::Greater,
         (_, Some(serde_json::Value::Null)) => Ordering::Less,
         (Some(va), Some(vb)) => {
-            // Try numeric comparison first
-            if let (Some(na), Some(nb)) = (as_f64(va), as_f64(vb)) {
-                return na.partial_cmp(&nb).unwrap_or(Ordering::Equal);
+            // Try numeric comparison first, exact past f64 precision
+            if let Some(ordering) = compare_numbers(va, vb) {
+                return ordering;
             }
             // Fall back to string comparison
             let sa = val_to_str(va);
@@ -632,17 +3239,111 @@ fn cmp_json_values(
     }
 }
 
-fn as_f64(v: &serde_json::Value) -> Option<f64> {
-    match v {
-        serde_json::Value::Number(n) => n.as_f64(),
-        serde_json::Value::String(s) => s.parse::<f64>().ok(),
-        _ => None,
-    }
-}
-
 fn val_to_str(v: &serde_json::Value) -> String {
     match v {
         serde_json::Value::String(s) => s.clone(),
         _ => v.to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chart(title: &str) -> ChartData {
+        serde_json::from_value(json!({
+            "title": title, "query": "SELECT month, revenue FROM sales", "x": "month", "y": "revenue",
+            "columns": ["month", "revenue"], "rows": [["2025-01", 10]],
+        }))
+        .unwrap()
+    }
+
+    fn run_sql(app: &mut App, sql: &str) {
+        app.sql_running = true;
+        let rows = vec![vec![json!("a"), json!(1)]];
+        app.on_sql_result(sql.to_string(), Ok((vec!["k".to_string(), "n".to_string()], rows)));
+    }
+
+    #[test]
+    fn sql_results_keep_the_chart_they_ran_from() {
+        let mut app = App::new(Config::default());
+        let path = PathBuf::from("/tmp/ducktrace-test/revenue.json");
+        app.on_file_change(path.clone(), chart("Revenue"));
+        run_sql(&mut app, "SELECT 'a' AS k, 1 AS n");
+
+        assert_eq!(app.workspace.len(), 2);
+        assert_eq!(app.data_path, None);
+        assert_eq!(app.active_tab, Tab::Data);
+
+        // Switching back stashes the results in their own slot, not the chart's
+        app.cycle_chart(true);
+        assert_eq!(app.data.as_ref().map(|d| d.title.as_str()), Some("Revenue"));
+        assert_eq!(app.data_path.as_ref(), Some(&path));
+        assert!(app.workspace[1].sql && app.workspace_chart(1).is_some());
+
+        // Another run replaces the earlier results
+        run_sql(&mut app, "SELECT 'b' AS k, 2 AS n");
+        assert_eq!(app.workspace.len(), 2);
+        assert_eq!(app.workspace_current, 1);
+        assert_eq!(app.workspace_chart(0).map(|d| d.title.as_str()), Some("Revenue"));
+    }
+
+    #[test]
+    fn overlays_keep_the_mouse_off_the_chart() {
+        let mut app = App::new(Config::default());
+        let mut data = chart("Revenue");
+        data.rows = (1..=5).map(|n| vec![json!(format!("2025-0{}", n)), json!(n)]).collect();
+        app.on_file_change(PathBuf::from("/tmp/ducktrace-test/revenue.json"), data);
+        app.active_tab = Tab::Chart;
+        app.enter_mode(Mode::Tasks(0));
+
+        let wheel = MouseEvent { kind: MouseEventKind::ScrollDown, column: 10, row: 10, modifiers: KeyModifiers::NONE };
+        app.handle_mouse(wheel);
+        assert_eq!(app.selected_point, 0);
+
+        app.leave_mode(|m| matches!(m, Mode::Tasks(_)));
+        app.handle_mouse(wheel);
+        assert_eq!(app.selected_point, 3);
+    }
+
+    #[test]
+    fn vim_gt_switches_tabs() {
+        let mut app = App::new(Config::default());
+        let tab = app.active_tab;
+        for c in ['g', 't'] {
+            app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        assert_eq!(app.active_tab, tab.next());
+        for c in ['g', 'T'] {
+            app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        assert_eq!(app.active_tab, tab);
+    }
+
+    #[test]
+    fn failed_profile_switch_keeps_the_current_profile() {
+        let mut config = Config::default();
+        config.profiles.insert("work".to_string(), Profile::default());
+        config.profiles.insert("personal".to_string(), Profile::default());
+        let mut app = App::new(config);
+
+        app.select_profile(Some("work".to_string()));
+        assert_eq!(app.active_profile, None);
+        assert!(app.take_pending_profile().is_some());
+        assert!(app.on_profile_token(Some("work".to_string()), Ok(())));
+        assert_eq!(app.active_profile.as_deref(), Some("work"));
+
+        app.select_profile(Some("personal".to_string()));
+        assert!(!app.on_profile_token(Some("personal".to_string()), Err("`op read` failed".to_string())));
+        assert_eq!(app.active_profile.as_deref(), Some("work"));
+        assert!(app.profile_error.as_deref().is_some_and(|e| e.contains("personal")));
+
+        // A token for a pick that a later one superseded is dropped
+        app.select_profile(Some("personal".to_string()));
+        app.select_profile(None);
+        assert!(!app.on_profile_token(Some("personal".to_string()), Ok(())));
+        assert!(app.on_profile_token(None, Ok(())));
+        assert_eq!(app.active_profile, None);
+    }
+}
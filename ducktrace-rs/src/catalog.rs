@@ -0,0 +1,272 @@
+use anyhow::{Context, Result};
+use duckdb::{params, Connection};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::UNIX_EPOCH;
+
+use crate::data::{HistoryEntry, HistoryMeta};
+use crate::watcher::meta_path;
+
+/// Catalog file, kept alongside the charts in the history directory
+const CATALOG_FILE: &str = "catalog.duckdb";
+
+/// Version of `SCHEMA`, bumped whenever it changes. A catalog written by another
+/// version is rebuilt from the charts, since it only caches them.
+const SCHEMA_VERSION: i64 = 1;
+
+/// The open catalog and the directory it's in, kept between refreshes
+static CATALOG: Mutex<Option<(PathBuf, Connection)>> = Mutex::new(None);
+
+/// One row per history chart. `modified`, `size`, and `meta_modified` record the
+/// chart file and its `.meta` sidecar as they were when the row was written.
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS history (
+    path VARCHAR PRIMARY KEY,
+    modified BIGINT NOT NULL,
+    size BIGINT NOT NULL,
+    meta_modified BIGINT NOT NULL,
+    title VARCHAR NOT NULL,
+    timestamp BIGINT NOT NULL,
+    row_count BIGINT NOT NULL,
+    chart_type VARCHAR,
+    database VARCHAR,
+    x_field VARCHAR NOT NULL,
+    y_field VARCHAR NOT NULL,
+    pinned BOOLEAN NOT NULL,
+    tags VARCHAR NOT NULL
+)";
+
+/// Modification time (ms) and size of a chart file, and modification time of its
+/// sidecar (0 without one)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: i64,
+    size: i64,
+    meta_modified: i64,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: modified_millis(&metadata),
+            size: metadata.len() as i64,
+            meta_modified: std::fs::metadata(meta_path(path)).map(|m| modified_millis(&m)).unwrap_or(0),
+        })
+    }
+}
+
+fn modified_millis(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// History entries for the `*.json` charts in `dir`, through the catalog there.
+/// Charts unchanged since their row was written come from the catalog; new or
+/// changed ones are read with `read` and their rows replaced, and rows for
+/// deleted charts are dropped. The catalog is opened on first use and kept open;
+/// fails if it can't be, e.g. while another instance has it open.
+pub fn load_entries(dir: &Path, read: impl Fn(&Path) -> Option<HistoryEntry>) -> Result<Vec<HistoryEntry>> {
+    let mut catalog = CATALOG.lock().unwrap_or_else(PoisonError::into_inner);
+    let conn = match &mut *catalog {
+        Some((open_dir, conn)) if open_dir == dir => conn,
+        slot => {
+            // Close the old catalog before opening the new one
+            *slot = None;
+            &mut slot.insert((dir.to_path_buf(), open(&dir.join(CATALOG_FILE))?)).1
+        }
+    };
+
+    let mut cached = load_rows(conn)?;
+    let tx = conn.transaction()?;
+    let mut entries = Vec::new();
+    let (mut reused, mut read_count) = (0, 0);
+
+    for file in std::fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let path = file.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(stamp) = Stamp::of(&path) else { continue };
+        match cached.remove(&path) {
+            Some((cached_stamp, entry)) if cached_stamp == stamp => {
+                reused += 1;
+                entries.push(entry);
+            }
+            _ => {
+                read_count += 1;
+                match read(&path) {
+                    Some(entry) => {
+                        save_row(&tx, &entry, stamp)?;
+                        entries.push(entry);
+                    }
+                    None => delete_row(&tx, &path)?,
+                }
+            }
+        }
+    }
+    for path in cached.keys() {
+        delete_row(&tx, path)?;
+    }
+    tx.commit()?;
+
+    log::debug!(
+        "History catalog: {} cached, {} read, {} dropped",
+        reused,
+        read_count,
+        cached.len()
+    );
+    Ok(entries)
+}
+
+/// Open the catalog at `path`, rebuilding it if it's from another schema version
+fn open(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)
+        .with_context(|| format!("Failed to open history catalog {}", path.display()))?;
+    conn.execute_batch("CREATE TABLE IF NOT EXISTS catalog_version (version BIGINT NOT NULL)")?;
+    let version: Option<i64> = conn.query_row("SELECT max(version) FROM catalog_version", [], |row| row.get(0))?;
+    if version != Some(SCHEMA_VERSION) {
+        log::info!("Rebuilding history catalog {} (version {:?}, now {})", path.display(), version, SCHEMA_VERSION);
+        conn.execute_batch(&format!(
+            "BEGIN;
+             DROP TABLE IF EXISTS history;
+             {};
+             DELETE FROM catalog_version;
+             INSERT INTO catalog_version VALUES ({});
+             COMMIT;",
+            SCHEMA, SCHEMA_VERSION
+        ))?;
+    }
+    Ok(conn)
+}
+
+fn load_rows(conn: &Connection) -> Result<HashMap<PathBuf, (Stamp, HistoryEntry)>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, modified, size, meta_modified, title, timestamp, row_count, chart_type,
+                database, x_field, y_field, pinned, tags
+         FROM history",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let path = PathBuf::from(row.get::<_, String>(0)?);
+        let stamp = Stamp { modified: row.get(1)?, size: row.get(2)?, meta_modified: row.get(3)? };
+        let tags: String = row.get(12)?;
+        let entry = HistoryEntry {
+            path: path.clone(),
+            title: row.get(4)?,
+            timestamp: row.get::<_, i64>(5)? as u64,
+            row_count: row.get::<_, i64>(6)? as usize,
+            chart_type: row.get(7)?,
+            database: row.get(8)?,
+            x_field: row.get(9)?,
+            y_field: row.get(10)?,
+            meta: HistoryMeta {
+                pinned: row.get(11)?,
+                tags: serde_json::from_str(&tags).unwrap_or_default(),
+            },
+        };
+        Ok((path, (stamp, entry)))
+    })?;
+    Ok(rows.collect::<duckdb::Result<_>>()?)
+}
+
+fn save_row(conn: &Connection, entry: &HistoryEntry, stamp: Stamp) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO history VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            entry.path.to_string_lossy().into_owned(),
+            stamp.modified,
+            stamp.size,
+            stamp.meta_modified,
+            entry.title,
+            entry.timestamp as i64,
+            entry.row_count as i64,
+            entry.chart_type,
+            entry.database,
+            entry.x_field,
+            entry.y_field,
+            entry.meta.pinned,
+            serde_json::to_string(&entry.meta.tags)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn delete_row(conn: &Connection, path: &Path) -> Result<()> {
+    conn.execute("DELETE FROM history WHERE path = ?", params![path.to_string_lossy().into_owned()])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::ChartData;
+    use std::cell::Cell;
+
+    fn write_chart(dir: &Path, name: &str, title: &str) {
+        let chart = serde_json::json!({
+            "title": title, "query": "SELECT 1", "x": "x", "y": "y",
+            "columns": ["x", "y"], "rows": [["a", 1]],
+        });
+        std::fs::write(dir.join(name), chart.to_string()).unwrap();
+    }
+
+    #[test]
+    fn indexes_a_history_dir() {
+        let dir = std::env::temp_dir().join(format!("ducktrace-catalog-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        write_chart(&dir, "a.json", "First");
+        write_chart(&dir, "b.json", "Second");
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a chart").unwrap();
+
+        let reads = Cell::new(0);
+        let read = |path: &Path| {
+            reads.set(reads.get() + 1);
+            let data: ChartData = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+            Some(HistoryEntry {
+                path: path.to_path_buf(),
+                title: data.title,
+                timestamp: 0,
+                row_count: data.rows.len(),
+                chart_type: None,
+                database: None,
+                x_field: data.x_field,
+                y_field: data.y_field,
+                meta: HistoryMeta::default(),
+            })
+        };
+        let titles = |entries: Vec<HistoryEntry>| {
+            let mut titles: Vec<String> = entries.into_iter().map(|e| e.title).collect();
+            titles.sort();
+            titles
+        };
+
+        assert_eq!(titles(load_entries(&dir, read).unwrap()), vec!["First", "Second"]);
+        assert_eq!(reads.replace(0), 3);
+
+        // Unchanged charts come from the catalog; the broken one has no row, so it's read again
+        assert_eq!(titles(load_entries(&dir, read).unwrap()), vec!["First", "Second"]);
+        assert_eq!(reads.replace(0), 1);
+
+        write_chart(&dir, "b.json", "Second, renamed");
+        std::fs::remove_file(dir.join("a.json")).unwrap();
+        assert_eq!(titles(load_entries(&dir, read).unwrap()), vec!["Second, renamed"]);
+        assert_eq!(reads.replace(0), 2);
+
+        // A catalog from another version is rebuilt
+        *CATALOG.lock().unwrap() = None;
+        let conn = Connection::open(dir.join(CATALOG_FILE)).unwrap();
+        conn.execute_batch("UPDATE catalog_version SET version = 0").unwrap();
+        drop(conn);
+        assert_eq!(titles(load_entries(&dir, read).unwrap()), vec!["Second, renamed"]);
+        assert_eq!(reads.replace(0), 2);
+
+        *CATALOG.lock().unwrap() = None;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+
+use crate::config::{Config, Profile};
+use crate::data::ChartData;
+use crate::db::{self, QueryCancel, QueryExecutor, MOTHERDUCK};
+use crate::watcher;
+
+/// Flags of `ducktrace query` that take a value
+const VALUE_FLAGS: &[&str] = &[
+    "x", "y", "series", "type", "title", "database", "db", "file", "dir", "max-rows", "profile", "export",
+];
+
+pub const QUERY_USAGE: &str = "Usage: ducktrace query \"<sql>\" [--x <column>] [--y <column>] [--series <column>] \
+[--type <chart type>] [--title <title>] [--database <name>] [--db <path>] [--file <path>] [--max-rows <n>] [--profile <name>] [--export <file.png|svg|md>] [--tui]";
+
+/// `ducktrace query "<sql>" --x month --y revenue`: run a query and chart its results
+#[derive(Debug, Default)]
+pub struct QueryCommand {
+    pub sql: String,
+    pub x: Option<String>,
+    pub y: Option<String>,
+    pub series: Option<String>,
+    pub chart_type: Option<String>,
+    pub title: Option<String>,
+    /// Database to `USE` before the query (for MotherDuck, or an attached database)
+    pub database: Option<String>,
+    /// Also draw the chart to this PNG or SVG file, or write a Markdown report (`--export`)
+    pub export: Option<String>,
+    /// Open the TUI on the new chart once it's written (`--tui`)
+    pub open_tui: bool,
+}
+
+impl QueryCommand {
+    /// Parse the arguments after `query`. `--db`, `--file`, `--dir`, and `--profile`
+    /// are left to the usual startup handling.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut command = Self::default();
+        let mut sql = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                if sql.replace(arg.clone()).is_some() {
+                    bail!("Expected a single SQL argument\n{}", QUERY_USAGE);
+                }
+                continue;
+            };
+            if flag == "tui" {
+                command.open_tui = true;
+                continue;
+            }
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name, value.to_string()),
+                None => (flag, args.next().cloned().unwrap_or_default()),
+            };
+            if !VALUE_FLAGS.contains(&name) {
+                bail!("Unknown option --{}\n{}", name, QUERY_USAGE);
+            }
+            if value.is_empty() {
+                bail!("--{} needs a value\n{}", name, QUERY_USAGE);
+            }
+            let slot = match name {
+                "x" => &mut command.x,
+                "y" => &mut command.y,
+                "series" => &mut command.series,
+                "type" => &mut command.chart_type,
+                "title" => &mut command.title,
+                "database" => &mut command.database,
+                "export" => &mut command.export,
+                _ => continue,
+            };
+            *slot = Some(value);
+        }
+        command.sql = sql.filter(|s| !s.trim().is_empty()).with_context(|| QUERY_USAGE.to_string())?;
+        Ok(command)
+    }
+
+    /// Run the query against the configured database (or the profile's) and build
+    /// its chart. Columns not given as flags are picked as for the SQL tab.
+    pub fn execute(&self, config: &Config, profile: Option<&Profile>) -> Result<ChartData> {
+        let database_path = profile
+            .and_then(|p| p.database_path.clone())
+            .or_else(|| config.database_path.clone());
+        let database = self.database.clone().or_else(|| profile.and_then(|p| p.database.clone()));
+        let mut attachments = config.attach.clone();
+        if let Some(profile) = profile {
+            db::set_motherduck_token(profile.token()?);
+            attachments.extend(profile.attach.iter().filter(|a| !config.attach.contains(a)).cloned());
+        }
+
+        let target = database_path.clone().unwrap_or_else(|| MOTHERDUCK.to_string());
+        let exec = QueryExecutor::connect(&target)?;
+        exec.ensure_attached(&attachments)?;
+        let (columns, rows) = exec.run_query(&self.sql, database.as_deref(), &QueryCancel::default())?;
+
+        let mut data = ChartData::from_query(&self.sql, columns, rows, None);
+        for (field, column) in [(&mut data.x_field, &self.x), (&mut data.y_field, &self.y)] {
+            if let Some(column) = column {
+                if !data.columns.contains(column) {
+                    bail!("No column `{}` in the results (columns: {})", column, data.columns.join(", "));
+                }
+                *field = column.clone();
+            }
+        }
+        if let Some(ref series) = self.series {
+            if !data.columns.contains(series) {
+                bail!("No column `{}` in the results (columns: {})", series, data.columns.join(", "));
+            }
+            data.series_field = Some(series.clone());
+        }
+        data.chart_type = self.chart_type.clone();
+        data.title = self.title.clone().unwrap_or_else(|| "Query".to_string());
+        data.database = database;
+        data.database_path = database_path;
+        Ok(data)
+    }
+}
+
+/// Where `ducktrace query` writes its chart: the watched data file, or a new file
+/// in the watched directory
+pub fn chart_output_path(data: &ChartData) -> PathBuf {
+    match watcher::get_data_dir() {
+        Some(dir) => dir.join(format!("query-{}.json", data.timestamp.unwrap_or_default())),
+        None => watcher::get_data_path(),
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::data::{Attachment, ColorOverrides, NumberFormat};
+use crate::hooks::Hooks;
+use crate::keymap::Keymap;
+use crate::timezone::TimeZone;
+
+/// How long a profile's `token_command` may run before it's given up on
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// User configuration loaded from `~/.config/ducktrace/config.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Local DuckDB file (or `:memory:`) for drill-downs when the chart doesn't name one
+    pub database_path: Option<String>,
+    /// Databases attached for every chart's drill-downs (`attach = [...]`)
+    pub attach: Vec<Attachment>,
+    /// Append every drill-down to `~/.claude/ducktrace/queries.ndjson` as well as the in-memory log
+    pub log_queries: bool,
+    /// Chart and highlight colors (`[palette]` table)
+    pub palette: ColorOverrides,
+    /// Charts kept in `~/.claude/ducktrace/history`, 20 when unset
+    pub history_limit: Option<usize>,
+    /// Rows kept per chart, 50 when unset
+    pub max_rows: Option<usize>,
+    /// Show numbers compact (`1.2M`, the default) or exact (`1,234,567`)
+    pub numbers: NumberDisplay,
+    /// Locale for number separators, e.g. `de_DE`; `LC_NUMERIC` or `LANG` when unset
+    pub number_locale: Option<String>,
+    /// Zone times are shown in: `local` (the default), `UTC`, an offset like `+05:30`, or a name like `Europe/Berlin`
+    pub timezone: Option<TimeZone>,
+    /// Ask before `c` and deleting an analysis, true when unset
+    pub confirm_destructive: Option<bool>,
+    /// Re-run the open chart's query every this many seconds from startup (`R` still cycles)
+    pub auto_refresh: Option<u64>,
+    /// Data file to watch instead of `~/.claude/ducktrace/current.json` (`--file` wins)
+    pub data_file: Option<String>,
+    /// Directory of charts to watch instead of a data file (`--dir` wins)
+    pub data_dir: Option<String>,
+    /// Rebound keys (`[keys]` table), action name to key
+    pub keys: Keymap,
+    /// Shell commands run on events (`[hooks]` table), event name to command
+    pub hooks: Hooks,
+    /// Level to log at: off, error, warn, info (default), debug, or trace (`--log-level` wins)
+    pub log_level: Option<String>,
+    /// Log file instead of `~/.claude/ducktrace/ducktrace.log` (`--log-file` wins)
+    pub log_file: Option<String>,
+    /// Connection profile to start with (`--profile` wins)
+    pub profile: Option<String>,
+    /// Named connection profiles (`[profiles.<name>]` tables)
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named set of connection settings, for switching between MotherDuck accounts
+/// or databases. Its settings take the place of the config's top-level ones.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Environment variable holding this account's MotherDuck token
+    pub token_env: Option<String>,
+    /// Command that prints the token, e.g. a keychain lookup
+    pub token_command: Option<String>,
+    /// Local DuckDB file to use instead of MotherDuck
+    pub database_path: Option<String>,
+    /// Database to run in when the chart doesn't name one
+    pub database: Option<String>,
+    /// Databases attached along with the config's
+    pub attach: Vec<Attachment>,
+}
+
+impl Profile {
+    /// The MotherDuck token from `token_env` or `token_command`; None when the
+    /// profile names neither, leaving `MOTHERDUCK_TOKEN` to DuckDB. The command
+    /// gets no stdin and [`TOKEN_TIMEOUT`] to finish; it blocks until then, so
+    /// the main loop reads tokens off the UI thread.
+    pub fn token(&self) -> Result<Option<String>> {
+        if let Some(ref var) = self.token_env {
+            let token = std::env::var(var).with_context(|| format!("{} is not set", var))?;
+            return Ok(Some(token));
+        }
+        let Some(ref command) = self.token_command else { return Ok(None) };
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run `{}`", command))?;
+        let started = Instant::now();
+        while child.try_wait().with_context(|| format!("Failed to wait for `{}`", command))?.is_none() {
+            if started.elapsed() > TOKEN_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                bail!("`{}` took longer than {}s", command, TOKEN_TIMEOUT.as_secs());
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let output = child.wait_with_output().with_context(|| format!("Failed to wait for `{}`", command))?;
+        if !output.status.success() {
+            bail!("`{}` failed: {}", command, String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if token.is_empty() {
+            bail!("`{}` printed no token", command);
+        }
+        Ok(Some(token))
+    }
+}
+
+impl Config {
+    /// The number format from `numbers` and `number_locale`, falling back to
+    /// the environment's locale
+    pub fn number_format(&self) -> NumberFormat {
+        let locale = self.number_locale.clone().or_else(|| {
+            ["LC_ALL", "LC_NUMERIC", "LANG"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .find(|value| !value.is_empty())
+        });
+        NumberFormat::for_locale(self.numbers == NumberDisplay::Exact, locale.as_deref().unwrap_or_default())
+    }
+}
+
+/// How numbers are shown in tables, tooltips, and stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberDisplay {
+    #[default]
+    Compact,
+    Exact,
+}
+
+/// Get the path to the user config file
+pub fn get_config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".config/ducktrace/config.toml")
+}
+
+/// Load the config file; a missing file yields the defaults
+pub fn load_config(path: &Path) -> Result<Config> {
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Size of the drill-down overlay, as a share of the terminal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplainSize {
+    Compact,
+    #[default]
+    Normal,
+    Large,
+    Fullscreen,
+}
+
+impl ExplainSize {
+    const STEPS: [ExplainSize; 4] = [Self::Compact, Self::Normal, Self::Large, Self::Fullscreen];
+
+    /// Width and height percentages for `centered_rect`
+    pub fn percent(self) -> (u16, u16) {
+        match self {
+            Self::Compact => (60, 50),
+            Self::Normal => (80, 70),
+            Self::Large => (95, 90),
+            Self::Fullscreen => (100, 100),
+        }
+    }
+
+    pub fn grow(self) -> Self {
+        let i = Self::STEPS.iter().position(|&s| s == self).unwrap_or(1);
+        Self::STEPS[(i + 1).min(Self::STEPS.len() - 1)]
+    }
+
+    pub fn shrink(self) -> Self {
+        let i = Self::STEPS.iter().position(|&s| s == self).unwrap_or(1);
+        Self::STEPS[i.saturating_sub(1)]
+    }
+}
+
+/// View preferences the TUI remembers between runs, kept in
+/// `~/.claude/ducktrace/ui.json` (not meant for hand editing, unlike the config)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiState {
+    pub explain_size: ExplainSize,
+}
+
+/// Get the path to the saved view preferences
+pub fn get_ui_state_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".claude/ducktrace/ui.json")
+}
+
+/// Load saved view preferences; defaults when the file is missing or unreadable
+pub fn load_ui_state(path: &Path) -> UiState {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_ui_state(path: &Path, state: &UiState) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(state)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,229 @@
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// An exact decimal read from a JSON number or numeric text. BIGINT, HUGEINT, and
+/// DECIMAL values can carry more digits than an f64 holds, so they're compared
+/// and shown from their digits rather than through `as_f64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decimal {
+    negative: bool,
+    /// Significant digits with no leading or trailing zeros; empty for zero
+    digits: Vec<u8>,
+    /// Digits before the decimal point: 3 for 125, 0 for 0.5, -1 for 0.05
+    exponent: i64,
+}
+
+/// Significant digits an f64 always holds exactly
+const F64_DIGITS: usize = 15;
+
+/// Largest power of ten kept exactly, past any f64's; beyond it a number is
+/// left to f64, so a cell like `1e999999999` can't ask for a billion digits
+const MAX_EXPONENT: i64 = 400;
+
+impl Decimal {
+    /// Parse `123`, `-0.50`, `1.5e3`; None for anything else, including `inf`, `NaN`,
+    /// and exponents past [`MAX_EXPONENT`]
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (negative, text) = match text.as_bytes().first()? {
+            b'-' => (true, &text[1..]),
+            b'+' => (false, &text[1..]),
+            _ => (false, text),
+        };
+        let (mantissa, power) = match text.find(['e', 'E']) {
+            Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if whole.len() + fraction.len() == 0 || !all_digits(whole) || !all_digits(fraction) {
+            return None;
+        }
+
+        let mut digits: Vec<u8> = whole.bytes().chain(fraction.bytes()).map(|b| b - b'0').collect();
+        let mut exponent = (whole.len() as i64).checked_add(power)?;
+        let leading = digits.iter().take_while(|&&d| d == 0).count();
+        digits.drain(..leading);
+        exponent = exponent.checked_sub(leading as i64)?;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            return Some(Self { negative: false, digits, exponent: 0 });
+        }
+        if exponent.unsigned_abs() > MAX_EXPONENT as u64 {
+            return None;
+        }
+        Some(Self { negative, digits, exponent })
+    }
+
+    /// A JSON number, or text holding one
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => Self::parse(&n.to_string()),
+            Value::String(s) => Self::parse(s),
+            _ => None,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        if self.digits.is_empty() {
+            return 0.0;
+        }
+        let digits: String = self.digits.iter().map(|d| (b'0' + d) as char).collect();
+        let value: f64 = format!("0.{}e{}", digits, self.exponent).parse().unwrap_or(0.0);
+        if self.negative {
+            -value
+        } else {
+            value
+        }
+    }
+
+    /// Whether `to_f64` keeps every digit
+    pub fn fits_f64(&self) -> bool {
+        self.digits.len() <= F64_DIGITS
+    }
+
+    /// Whether there's nothing after the point
+    pub fn is_whole(&self) -> bool {
+        self.exponent >= self.digits.len() as i64
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The digits before and after the point, rounded half away from zero to
+    /// `decimals` places (or every place when None)
+    pub fn split(&self, decimals: Option<usize>) -> (String, String) {
+        // Every digit, padded so the point falls inside: 0.05 is 0|05, 250 is 250|
+        let lead = (-self.exponent).max(0) as usize;
+        let point = self.exponent.max(0) as usize;
+        let mut all: Vec<u8> = std::iter::repeat_n(0, lead).chain(self.digits.iter().copied()).collect();
+        if all.len() < point {
+            all.resize(point, 0);
+        }
+        let fraction_len = decimals.unwrap_or(all.len() - point);
+        let mut all = match decimals {
+            Some(places) if all.len() > point + places => {
+                let round_up = all[point + places] >= 5;
+                all.truncate(point + places);
+                if round_up {
+                    carry(&mut all);
+                }
+                all
+            }
+            Some(places) => {
+                all.resize(point + places, 0);
+                all
+            }
+            None => all,
+        };
+        // A carry out of the top digit adds a place before the point
+        let point = all.len() - fraction_len;
+        let fraction: String = all.split_off(point).iter().map(|d| (b'0' + d) as char).collect();
+        let whole: String = all.iter().map(|d| (b'0' + d) as char).collect();
+        let whole = whole.trim_start_matches('0');
+        (if whole.is_empty() { "0".to_string() } else { whole.to_string() }, fraction)
+    }
+
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
+        match (self.digits.is_empty(), other.digits.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            // Without trailing zeros, a digit string that's a prefix of another is smaller
+            (false, false) => self.exponent.cmp(&other.exponent).then_with(|| self.digits.cmp(&other.digits)),
+        }
+    }
+}
+
+/// Add one to a digit string, growing it when every digit carries
+fn carry(digits: &mut Vec<u8>) {
+    for digit in digits.iter_mut().rev() {
+        if *digit == 9 {
+            *digit = 0;
+        } else {
+            *digit += 1;
+            return;
+        }
+    }
+    digits.insert(0, 1);
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compare two cells as numbers when both hold one, exactly
+pub fn compare_numbers(a: &Value, b: &Value) -> Option<Ordering> {
+    Some(Decimal::from_value(a)?.cmp(&Decimal::from_value(b)?))
+}
+
+/// Whether a cell holds a number (or numeric text) below zero
+pub fn is_negative_number(value: &Value) -> bool {
+    Decimal::from_value(value).is_some_and(|n| n.is_negative())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decimal(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    #[test]
+    fn compares_past_f64_precision() {
+        let a = decimal("12345678901234567890.01");
+        let b = decimal("12345678901234567890.02");
+        assert_eq!(a.to_f64(), b.to_f64());
+        assert!(a < b);
+        assert!(decimal("-5") < decimal("-4.99"));
+        assert!(decimal("0.05") < decimal("0.5"));
+        assert!(decimal("0") < decimal("0.0001"));
+        assert_eq!(decimal("1.50"), decimal("15e-1"));
+        assert_eq!(decimal("-0.00"), decimal("0"));
+        assert_eq!(compare_numbers(&json!(9007199254740993u64), &json!("9007199254740992")), Some(Ordering::Greater));
+        assert_eq!(compare_numbers(&json!("paid"), &json!(1)), None);
+        assert_eq!(Decimal::parse("inf"), None);
+        assert_eq!(Decimal::parse("."), None);
+    }
+
+    #[test]
+    fn leaves_huge_exponents_to_f64() {
+        assert_eq!(Decimal::parse("1e9223372036854775807"), None);
+        assert_eq!(Decimal::parse("-1e-9223372036854775808"), None);
+        assert_eq!(Decimal::parse("1234567890123456e999999999999"), None);
+        assert_eq!(Decimal::parse("1e-999"), None);
+        assert_eq!(decimal("0e999999"), decimal("0"));
+        assert_eq!(Decimal::parse("0.001e-9223372036854775808"), None);
+        assert_eq!(decimal("1e399").split(Some(0)).0.len(), 400);
+        assert_eq!(compare_numbers(&json!("1e9223372036854775807"), &json!(1)), None);
+    }
+
+    #[test]
+    fn splits_with_rounding() {
+        let split = |text: &str, places| decimal(text).split(places);
+        assert_eq!(split("12345678901234567890.125", Some(2)), ("12345678901234567890".into(), "13".into()));
+        assert_eq!(split("99.995", Some(2)), ("100".into(), "00".into()));
+        assert_eq!(split("0.05", None), ("0".into(), "05".into()));
+        assert_eq!(split("250", Some(2)), ("250".into(), "00".into()));
+        assert_eq!(split("1.5e3", None), ("1500".into(), "".into()));
+        assert_eq!(split("0.004", Some(2)), ("0".into(), "00".into()));
+    }
+}
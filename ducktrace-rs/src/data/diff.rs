@@ -0,0 +1,68 @@
+/// One line of a line-by-line diff
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Line diff turning `old` into `new`, from their longest common subsequence.
+/// Removals come before additions where lines were replaced.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            lines.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    lines.extend(a[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    lines.extend(b[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_marks_changed_lines() {
+        let old = "SELECT month,\n  SUM(revenue)\nFROM sales\nGROUP BY 1";
+        let new = "SELECT month,\n  SUM(profit)\nFROM sales\nGROUP BY 1\nORDER BY 1";
+        assert_eq!(
+            diff_lines(old, new),
+            vec![
+                DiffLine::Same("SELECT month,".into()),
+                DiffLine::Removed("  SUM(revenue)".into()),
+                DiffLine::Added("  SUM(profit)".into()),
+                DiffLine::Same("FROM sales".into()),
+                DiffLine::Same("GROUP BY 1".into()),
+                DiffLine::Added("ORDER BY 1".into()),
+            ]
+        );
+        assert!(diff_lines("SELECT 1", "SELECT 1").iter().all(|l| matches!(l, DiffLine::Same(_))));
+    }
+}
@@ -0,0 +1,63 @@
+/// Indices of at most `threshold` points that keep the shape of a series, by
+/// largest-triangle-three-buckets: the ends always, then from each bucket the
+/// point forming the largest triangle with the one kept before it and the mean of
+/// the next bucket. Points must be in x order. Every index is returned when there
+/// are no more than `threshold` points, or too few buckets to choose from.
+pub fn lttb(points: &[(f64, f64)], threshold: usize) -> Vec<usize> {
+    let n = points.len();
+    if threshold >= n || threshold < 3 {
+        return (0..n).collect();
+    }
+
+    // Buckets between the two ends, `bucket` points wide
+    let bucket = (n - 2) as f64 / (threshold - 2) as f64;
+    let bounds = |i: usize| {
+        let start = (i as f64 * bucket) as usize + 1;
+        let end = (((i + 1) as f64 * bucket) as usize + 1).min(n - 1);
+        (start, end.max(start + 1))
+    };
+
+    let mut kept = Vec::with_capacity(threshold);
+    kept.push(0);
+    let mut previous = 0;
+    for i in 0..threshold - 2 {
+        let (start, end) = bounds(i);
+        // The next bucket's mean, or the last point after the final bucket
+        let (cx, cy) = if i + 3 == threshold {
+            points[n - 1]
+        } else {
+            let (next_start, next_end) = bounds(i + 1);
+            let count = (next_end - next_start) as f64;
+            let (sx, sy) = points[next_start..next_end]
+                .iter()
+                .fold((0.0, 0.0), |(sx, sy), &(x, y)| (sx + x, sy + y));
+            (sx / count, sy / count)
+        };
+        let (ax, ay) = points[previous];
+        let area = |&(bx, by): &(f64, f64)| ((ax - cx) * (by - ay) - (ax - bx) * (cy - ay)).abs();
+        previous = (start..end)
+            .max_by(|&a, &b| area(&points[a]).total_cmp(&area(&points[b])))
+            .unwrap_or(start);
+        kept.push(previous);
+    }
+    kept.push(n - 1);
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_ends_and_spikes() {
+        let mut points: Vec<(f64, f64)> = (0..1000).map(|i| (i as f64, (i % 7) as f64)).collect();
+        points[500].1 = 100.0;
+        points[801].1 = -100.0;
+        let kept = lttb(&points, 50);
+        assert_eq!(kept.len(), 50);
+        assert_eq!((kept[0], kept[49]), (0, 999));
+        assert!(kept.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(kept.contains(&500) && kept.contains(&801));
+        assert_eq!(lttb(&points[..10], 50), (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,102 @@
+use super::decimal::Decimal;
+use super::model::value_to_string;
+
+/// A filter on one column of the explain table, parsed from what was typed:
+/// `>1000`, `<=0.5`, `=paid`, `!=paid`, `contains:refund`, or bare text (contains)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnFilter {
+    /// The expression as typed, shown in the column header
+    pub text: String,
+    test: Test,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Test {
+    Greater(Decimal),
+    GreaterOrEqual(Decimal),
+    Less(Decimal),
+    LessOrEqual(Decimal),
+    Equals(String),
+    NotEquals(String),
+    Contains(String),
+}
+
+impl ColumnFilter {
+    /// Parse a filter expression; None for one that's empty, or a `<`/`>`
+    /// comparison against something other than a number
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let number = Decimal::parse;
+        let test = if let Some(rest) = text.strip_prefix(">=") {
+            Test::GreaterOrEqual(number(rest)?)
+        } else if let Some(rest) = text.strip_prefix("<=") {
+            Test::LessOrEqual(number(rest)?)
+        } else if let Some(rest) = text.strip_prefix('>') {
+            Test::Greater(number(rest)?)
+        } else if let Some(rest) = text.strip_prefix('<') {
+            Test::Less(number(rest)?)
+        } else if let Some(rest) = text.strip_prefix("!=") {
+            Test::NotEquals(rest.trim().to_lowercase())
+        } else if let Some(rest) = text.strip_prefix('=') {
+            Test::Equals(rest.trim().to_lowercase())
+        } else if let Some(rest) = text.strip_prefix("contains:") {
+            Test::Contains(rest.trim().to_lowercase())
+        } else if !text.is_empty() {
+            Test::Contains(text.to_lowercase())
+        } else {
+            return None;
+        };
+        Some(Self { text: text.to_string(), test })
+    }
+
+    /// Whether a cell passes. Comparisons need a number (or numeric text); `=`
+    /// compares numerically when both sides are numbers, else as text ignoring case.
+    pub fn matches(&self, value: &serde_json::Value) -> bool {
+        let number = Decimal::from_value(value);
+        let text = || value_to_string(value).to_lowercase();
+        let equals = |expected: &str| match (&number, Decimal::parse(expected)) {
+            (Some(a), Some(b)) => *a == b,
+            _ => text() == expected,
+        };
+        match self.test {
+            Test::Greater(ref limit) => number.is_some_and(|n| n > *limit),
+            Test::GreaterOrEqual(ref limit) => number.is_some_and(|n| n >= *limit),
+            Test::Less(ref limit) => number.is_some_and(|n| n < *limit),
+            Test::LessOrEqual(ref limit) => number.is_some_and(|n| n <= *limit),
+            Test::Equals(ref expected) => equals(expected),
+            Test::NotEquals(ref expected) => !equals(expected),
+            Test::Contains(ref needle) => text().contains(needle.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn filters_compare_numbers_and_match_text() {
+        let over = ColumnFilter::parse(">1000").unwrap();
+        assert!(over.matches(&json!(1500)));
+        assert!(over.matches(&json!("2000.5")));
+        assert!(!over.matches(&json!(1000)));
+        assert!(!over.matches(&json!(null)));
+
+        let refund = ColumnFilter::parse("contains:Refund").unwrap();
+        assert!(refund.matches(&json!("partial refund")));
+        assert!(ColumnFilter::parse("refund").unwrap().matches(&json!("REFUNDED")));
+
+        assert!(ColumnFilter::parse("= 3").unwrap().matches(&json!(3.0)));
+        assert!(ColumnFilter::parse("!=paid").unwrap().matches(&json!("open")));
+        assert!(!ColumnFilter::parse("!=paid").unwrap().matches(&json!("Paid")));
+
+        let big = ColumnFilter::parse(">9007199254740992").unwrap();
+        assert!(big.matches(&json!(9007199254740993u64)));
+        assert!(ColumnFilter::parse("=12345678901234567890.10").unwrap().matches(&json!("12345678901234567890.1")));
+        assert!(!ColumnFilter::parse("=12345678901234567890.10").unwrap().matches(&json!("12345678901234567890.11")));
+
+        assert_eq!(ColumnFilter::parse("  "), None);
+        assert_eq!(ColumnFilter::parse(">lots"), None);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeSet;
+
+/// Lines of formatted SQL that fold away together: a parenthesized block that
+/// spans lines, like a CTE's body or a subquery. `start` is the line with the
+/// opening paren, which stays shown; `end` is the one with the closing paren.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldRegion {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl FoldRegion {
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+
+    /// Lines hidden while it's folded
+    pub fn hidden(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// Foldable regions of `text`, by start line. Parens inside quotes and comments
+/// don't count; of several regions opening on one line, the outermost is kept.
+pub fn fold_regions(text: &str) -> Vec<FoldRegion> {
+    let mut regions: Vec<FoldRegion> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    let mut line = 0;
+    let mut quote: Option<char> = None;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (_, '\n') => line += 1,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, '-') if chars.peek() == Some(&'-') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            (None, '(') => open.push(line),
+            (None, ')') => {
+                if let Some(start) = open.pop() {
+                    if start < line {
+                        regions.push(FoldRegion { start, end: line });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    // Inner regions close first, so the outermost on a line comes last
+    regions.sort_by_key(|r| (r.start, std::cmp::Reverse(r.end)));
+    regions.dedup_by_key(|r| r.start);
+    regions
+}
+
+/// Lines still shown out of `line_count` with the regions starting on the lines
+/// in `folded` folded
+pub fn visible_lines(regions: &[FoldRegion], folded: &BTreeSet<usize>, line_count: usize) -> Vec<usize> {
+    let mut visible = Vec::with_capacity(line_count);
+    let mut hidden_through = None;
+    for line in 0..line_count {
+        if hidden_through.is_some_and(|end| line <= end) {
+            continue;
+        }
+        visible.push(line);
+        if folded.contains(&line) {
+            hidden_through = regions.iter().find(|r| r.start == line).map(|r| r.end);
+        }
+    }
+    visible
+}
+
+/// The smallest region around `line`
+pub fn innermost_fold(regions: &[FoldRegion], line: usize) -> Option<FoldRegion> {
+    regions.iter().filter(|r| r.contains(line)).min_by_key(|r| r.hidden()).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQL: &str = "WITH a AS (\n  SELECT\n    ')' AS x -- (\n),\nb AS (\n  SELECT\n    *\n  FROM\n    (\n      SELECT\n        1\n    ) t\n)\nSELECT\n  count(*)\nFROM\n  b";
+
+    #[test]
+    fn folds_ctes_and_subqueries() {
+        let regions = fold_regions(SQL);
+        let region = |start, end| FoldRegion { start, end };
+        assert_eq!(regions, vec![region(0, 3), region(4, 12), region(8, 11)]);
+        assert_eq!(innermost_fold(&regions, 10), Some(region(8, 11)));
+        assert_eq!(innermost_fold(&regions, 4), Some(region(4, 12)));
+        assert_eq!(innermost_fold(&regions, 14), None);
+
+        let lines = SQL.lines().count();
+        let folded: BTreeSet<usize> = [0, 8].into();
+        assert_eq!(visible_lines(&regions, &folded, lines), vec![0, 4, 5, 6, 7, 8, 12, 13, 14, 15, 16]);
+        let folded: BTreeSet<usize> = [4, 8].into();
+        assert_eq!(visible_lines(&regions, &folded, lines), vec![0, 1, 2, 3, 4, 13, 14, 15, 16]);
+    }
+}
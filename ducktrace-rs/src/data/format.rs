@@ -1,6 +1,69 @@
-/// Format a numeric value for display
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use super::decimal::Decimal;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use crate::timezone::{self, TimeZone};
+
+/// How numbers are shown: compact (`1.2M`) or exact (`1,234,567`), and the
+/// separators for the locale
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat {
+    pub exact: bool,
+    /// Between groups of three digits
+    pub group: char,
+    /// Before the fraction
+    pub decimal: char,
+}
+
+impl NumberFormat {
+    pub const DEFAULT: NumberFormat = NumberFormat { exact: false, group: ',', decimal: '.' };
+
+    /// The separators used in `locale`, e.g. `de_DE.UTF-8`, `fr`, or `en-US`;
+    /// English ones for a locale that isn't known
+    pub fn for_locale(exact: bool, locale: &str) -> Self {
+        let locale = locale.split(['.', '@']).next().unwrap_or_default().replace('-', "_");
+        let language = locale.split('_').next().unwrap_or_default().to_ascii_lowercase();
+        let (group, decimal) = match language.as_str() {
+            _ if locale.eq_ignore_ascii_case("de_CH") => ('\'', '.'),
+            "de" | "nl" | "it" | "es" | "pt" | "id" | "da" | "tr" | "el" | "ro" | "hr" | "sl" => ('.', ','),
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "nb" | "no" | "fi" | "uk" | "hu" | "bg" | "lt" | "lv" | "et" => {
+                ('\u{a0}', ',')
+            }
+            _ => (',', '.'),
+        };
+        Self { exact, group, decimal }
+    }
+}
+
+/// The number format from the config (`numbers`, `number_locale`)
+static NUMBER_FORMAT: RwLock<NumberFormat> = RwLock::new(NumberFormat::DEFAULT);
+
+pub fn set_number_format(format: NumberFormat) {
+    *NUMBER_FORMAT.write().unwrap_or_else(|e| e.into_inner()) = format;
+}
+
+fn number_format() -> NumberFormat {
+    *NUMBER_FORMAT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Format a numeric value for display: compact or exact, as the config says
 pub fn format_number(value: f64) -> String {
-    if value.abs() >= 1_000_000_000.0 {
+    let format = number_format();
+    if format.exact {
+        let decimals = if value.fract() == 0.0 { 0 } else { 2 };
+        format_exact(value, decimals, format)
+    } else {
+        format_compact(value)
+    }
+}
+
+/// Format a number short enough for an axis, e.g. `1.2M`, whatever the config says
+pub fn format_compact(value: f64) -> String {
+    let text = if value.abs() >= 1_000_000_000.0 {
         format!("{:.1}B", value / 1_000_000_000.0)
     } else if value.abs() >= 1_000_000.0 {
         format!("{:.1}M", value / 1_000_000.0)
@@ -10,39 +73,563 @@ pub fn format_number(value: f64) -> String {
         format!("{:.0}", value)
     } else {
         format!("{:.2}", value)
+    };
+    localize(text, number_format())
+}
+
+/// Format `value` in full with `decimals` places and grouped digits, e.g. `-1,234,567.89`
+fn format_exact(value: f64, decimals: usize, format: NumberFormat) -> String {
+    let text = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    join_digits(value < 0.0, whole, fraction, format)
+}
+
+/// Format a decimal in full from its digits, rounded to `decimals` places (or
+/// with every place when None), e.g. `12,345,678,901,234,567,890.13`
+fn format_decimal(value: &Decimal, decimals: Option<usize>, format: NumberFormat) -> String {
+    let (whole, fraction) = value.split(decimals);
+    join_digits(value.is_negative(), &whole, &fraction, format)
+}
+
+/// Sign, grouped whole digits, and fraction; no sign when everything rounded to zero
+fn join_digits(negative: bool, whole: &str, fraction: &str, format: NumberFormat) -> String {
+    let mut out = String::new();
+    if negative && whole.chars().chain(fraction.chars()).any(|c| c != '0') {
+        out.push('-');
+    }
+    out.push_str(&group_digits(whole, format.group));
+    if !fraction.is_empty() {
+        out.push(format.decimal);
+        out.push_str(fraction);
     }
+    out
 }
 
-/// Format a value as currency
+/// Put `group` between every three digits from the right
+fn group_digits(digits: &str, group: char) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(group);
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Swap the decimal point of Rust's formatting for the locale's
+fn localize(text: String, format: NumberFormat) -> String {
+    if format.decimal == '.' {
+        text
+    } else {
+        text.replace('.', &format.decimal.to_string())
+    }
+}
+
+/// Format a row count with thousands separators, e.g. 5,432
+pub fn format_count(n: usize) -> String {
+    group_digits(&n.to_string(), number_format().group)
+}
+
+/// Format a value as currency, negatives in parentheses, e.g. `($1.5K)`
 pub fn format_currency(value: f64) -> String {
-    if value.abs() >= 1_000_000_000.0 {
-        format!("${:.1}B", value / 1_000_000_000.0)
-    } else if value.abs() >= 1_000_000.0 {
-        format!("${:.1}M", value / 1_000_000.0)
-    } else if value.abs() >= 1_000.0 {
-        format!("${:.1}K", value / 1_000.0)
+    let format = number_format();
+    let amount = value.abs();
+    if format.exact {
+        return parenthesize(value < 0.0, format!("${}", format_exact(amount, 2, format)));
+    }
+    let text = if amount >= 1_000_000_000.0 {
+        format!("${:.1}B", amount / 1_000_000_000.0)
+    } else if amount >= 1_000_000.0 {
+        format!("${:.1}M", amount / 1_000_000.0)
+    } else if amount >= 1_000.0 {
+        format!("${:.1}K", amount / 1_000.0)
     } else {
-        format!("${:.2}", value)
+        format!("${:.2}", amount)
+    };
+    parenthesize(value < 0.0, localize(text, format))
+}
+
+/// Wrap a negative amount in parentheses, unless it rounded to zero
+fn parenthesize(negative: bool, text: String) -> String {
+    if negative && text.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        format!("({})", text)
+    } else {
+        text
     }
 }
 
 /// Format a value as percentage
 pub fn format_percent(value: f64) -> String {
-    format!("{:.1}%", value * 100.0)
+    localize(format!("{:.1}%", value * 100.0), number_format())
 }
 
 /// Detect and format a value based on field name hints
 pub fn format_value(value: f64, field_name: &str) -> String {
+    guess_format(field_name).number(value)
+}
+
+/// The format a column without a hint gets from its name
+fn guess_format(field_name: &str) -> ColumnFormat {
     let lower = field_name.to_lowercase();
 
     if lower.contains("percent") || lower.contains("pct") || lower.contains("rate") {
-        format_percent(value)
+        ColumnFormat::Percent
     } else if lower.contains("price") || lower.contains("cost") || lower.contains("revenue")
         || lower.contains("amount") || lower.contains("$") {
-        format_currency(value)
+        ColumnFormat::Currency
+    } else if looks_like_bytes(&lower) {
+        ColumnFormat::Bytes
+    } else {
+        ColumnFormat::Number
+    }
+}
+
+/// How a chart says to show one of its columns (`formats` in the chart spec),
+/// e.g. `"formats": {"exchange_rate": "number", "took": "duration"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ColumnFormat {
+    /// Compact or exact by the config, whatever the column is called
+    Number,
+    /// Whole numbers with grouped digits
+    Integer,
+    Currency,
+    /// Fractions shown as percentages (0.25 is 25.0%)
+    Percent,
+    /// Seconds, e.g. `2m 05s`
+    Duration,
+    /// Byte counts in powers of 1024, e.g. `1.2 GiB`
+    Bytes,
+    /// Byte counts in powers of 1000 (`bytes:si`), e.g. `430 kB`
+    DecimalBytes,
+    /// Dates or timestamps (text, or seconds since the epoch) shown with a
+    /// strftime-style pattern: `%Y %y %m %d %e %H %M %S %b %B %a %%`
+    Date(String),
+}
+
+const FORMAT_NAMES: &str = "number, integer, currency, percent, duration, bytes, bytes:si, or date:<pattern>";
+
+impl FromStr for ColumnFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if let Some(pattern) = text.strip_prefix("date:") {
+            return Ok(ColumnFormat::Date(pattern.to_string()));
+        }
+        match text {
+            "number" => Ok(ColumnFormat::Number),
+            "integer" => Ok(ColumnFormat::Integer),
+            "currency" => Ok(ColumnFormat::Currency),
+            "percent" => Ok(ColumnFormat::Percent),
+            "duration" => Ok(ColumnFormat::Duration),
+            "bytes" => Ok(ColumnFormat::Bytes),
+            "bytes:si" => Ok(ColumnFormat::DecimalBytes),
+            "date" => Ok(ColumnFormat::Date("%Y-%m-%d".to_string())),
+            _ => Err(format!("unknown format `{}` (expected {})", text, FORMAT_NAMES)),
+        }
+    }
+}
+
+impl TryFrom<String> for ColumnFormat {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl fmt::Display for ColumnFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnFormat::Number => f.write_str("number"),
+            ColumnFormat::Integer => f.write_str("integer"),
+            ColumnFormat::Currency => f.write_str("currency"),
+            ColumnFormat::Percent => f.write_str("percent"),
+            ColumnFormat::Duration => f.write_str("duration"),
+            ColumnFormat::Bytes => f.write_str("bytes"),
+            ColumnFormat::DecimalBytes => f.write_str("bytes:si"),
+            ColumnFormat::Date(pattern) => write!(f, "date:{}", pattern),
+        }
+    }
+}
+
+impl From<ColumnFormat> for String {
+    fn from(format: ColumnFormat) -> Self {
+        format.to_string()
+    }
+}
+
+impl ColumnFormat {
+    /// Format a number for tables, tooltips, and stats
+    pub fn number(&self, value: f64) -> String {
+        match self {
+            ColumnFormat::Number => format_number(value),
+            ColumnFormat::Integer => format_exact(value.round(), 0, number_format()),
+            ColumnFormat::Currency => format_currency(value),
+            ColumnFormat::Percent => format_percent(value),
+            ColumnFormat::Duration => format_duration(value),
+            ColumnFormat::Bytes => format_bytes(value),
+            ColumnFormat::DecimalBytes => format_decimal_bytes(value),
+            ColumnFormat::Date(pattern) => format_epoch(value, pattern),
+        }
+    }
+
+    /// Format an exact decimal as `number` does, keeping every digit an f64
+    /// would lose where the format shows them all (integers, exact numbers and
+    /// currency)
+    pub fn decimal(&self, value: &Decimal) -> String {
+        let format = number_format();
+        if value.fits_f64() {
+            return self.number(value.to_f64());
+        }
+        match self {
+            ColumnFormat::Integer => format_decimal(value, Some(0), format),
+            ColumnFormat::Number if format.exact => {
+                format_decimal(value, Some(if value.is_whole() { 0 } else { 2 }), format)
+            }
+            ColumnFormat::Currency if format.exact => {
+                let (whole, fraction) = value.split(Some(2));
+                parenthesize(value.is_negative(), format!("${}", join_digits(false, &whole, &fraction, format)))
+            }
+            _ => self.number(value.to_f64()),
+        }
+    }
+
+    /// Format a number short enough for an axis tick
+    pub fn axis(&self, value: f64) -> String {
+        match self {
+            ColumnFormat::Number => format_compact(value),
+            ColumnFormat::Integer => format_compact(value.round()),
+            ColumnFormat::Currency if value < 0.0 => format!("-${}", format_compact(-value)),
+            ColumnFormat::Currency => format!("${}", format_compact(value)),
+            _ => self.number(value),
+        }
+    }
+
+    /// Format a cell: numbers (or text holding one) as `number` does, and
+    /// dates reformatted. None for a value the format doesn't apply to.
+    pub fn cell(&self, value: &Value) -> Option<String> {
+        match (self, Decimal::from_value(value), value.as_str()) {
+            (ColumnFormat::Date(pattern), _, Some(text)) => parse_datetime(text).map(|dt| dt.format(pattern)),
+            (_, Some(number), _) => Some(self.decimal(&number)),
+            _ => None,
+        }
+    }
+}
+
+/// Format hints by column name, from the chart spec's `formats`. Hinted columns
+/// are shown as the chart says; the rest by guessing from their names.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ColumnFormats(BTreeMap<String, ColumnFormat>);
+
+impl ColumnFormats {
+    pub const fn new() -> Self {
+        ColumnFormats(BTreeMap::new())
+    }
+
+    pub fn get(&self, column: &str) -> Option<&ColumnFormat> {
+        self.0.get(column)
+    }
+
+    /// Format a number from `column` for tables, tooltips, and stats
+    pub fn value(&self, value: f64, column: &str) -> String {
+        match self.get(column) {
+            Some(format) => format.number(value),
+            None => format_value(value, column),
+        }
+    }
+
+    /// Format a cell holding a number (or numeric text) from `column` exactly
+    /// where its format allows; None for one that isn't a number
+    pub fn number(&self, value: &Value, column: &str) -> Option<String> {
+        let number = Decimal::from_value(value)?;
+        Some(match self.get(column) {
+            Some(format) => format.decimal(&number),
+            None => guess_format(column).decimal(&number),
+        })
+    }
+
+    /// Format a number from `column` for an axis tick
+    pub fn axis(&self, value: f64, column: &str) -> String {
+        match self.get(column) {
+            Some(format) => format.axis(value),
+            None => format_compact(value),
+        }
+    }
+
+    /// Format a cell of `column` by its hint; None when it has none that applies
+    pub fn cell(&self, value: &Value, column: &str) -> Option<String> {
+        self.get(column)?.cell(value)
+    }
+
+    /// Text from `column` (an x value or label) with its hint applied
+    pub fn text(&self, text: &str, column: &str) -> String {
+        self.cell(&Value::String(text.to_string()), column).unwrap_or_else(|| text.to_string())
+    }
+
+    /// Axis labels for values of `column`: by its hint, or with dates shortened
+    /// to what tells them apart when it has none
+    pub fn axis_texts(&self, texts: &[String], column: &str) -> Vec<String> {
+        if self.get(column).is_some() {
+            texts.iter().map(|text| self.text(text, column)).collect()
+        } else {
+            abbreviate_dates(texts).unwrap_or_else(|| texts.to_vec())
+        }
+    }
+}
+
+/// Format seconds as the two largest units, e.g. `1h 05m`, `42.5s`, `120ms`
+pub fn format_duration(seconds: f64) -> String {
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    let whole = seconds.round() as u64;
+    let text = if seconds < 1.0 {
+        format!("{:.0}ms", seconds * 1000.0)
+    } else if seconds < 60.0 {
+        localize(format!("{:.1}s", seconds), number_format())
+    } else if whole < 3600 {
+        format!("{}m {:02}s", whole / 60, whole % 60)
+    } else if whole < 86_400 {
+        format!("{}h {:02}m", whole / 3600, whole % 3600 / 60)
     } else {
-        format_number(value)
+        format!("{}d {:02}h", whole / 86_400, whole % 86_400 / 3600)
+    };
+    format!("{}{}", sign, text)
+}
+
+/// Format a byte count in powers of 1024, e.g. `1.2 GiB`, `430 B`
+pub fn format_bytes(bytes: f64) -> String {
+    scale_bytes(bytes, 1024.0, &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"])
+}
+
+/// Format a byte count in powers of 1000, e.g. `1.3 GB`, `430 kB`
+pub fn format_decimal_bytes(bytes: f64) -> String {
+    scale_bytes(bytes, 1000.0, &["kB", "MB", "GB", "TB", "PB", "EB"])
+}
+
+fn scale_bytes(bytes: f64, step: f64, units: &[&str]) -> String {
+    if bytes.abs() < step {
+        return format!("{:.0} B", bytes);
+    }
+    let mut value = bytes / step;
+    let mut unit = 0;
+    while value.abs() >= step && unit < units.len() - 1 {
+        value /= step;
+        unit += 1;
+    }
+    // Whole numbers of the unit read better without the decimal, e.g. `430 kB`
+    let decimals = if value.abs() >= 100.0 { 0 } else { 1 };
+    localize(format!("{:.*} {}", decimals, value, units[unit]), number_format())
+}
+
+/// Whether a column name reads as a byte count: `bytes`, `*_bytes`, `size`, or
+/// `*_size` without a unit of its own (`size_mb`) or a count meaning (`batch_size`)
+fn looks_like_bytes(lower: &str) -> bool {
+    const NOT_BYTES: [&str; 12] =
+        ["kb", "mb", "gb", "tb", "kib", "mib", "gib", "tib", "batch", "sample", "page", "step"];
+    let words: Vec<&str> = lower.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    let sized = words.iter().any(|w| *w == "size" || w.ends_with("bytes"));
+    sized && !words.iter().any(|w| NOT_BYTES.contains(w))
+}
+
+/// A date and time of day read from a cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+const MONTHS: [&str; 12] =
+    ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+impl DateTime {
+    /// Render with a strftime-style pattern; unknown directives are kept as written
+    fn format(&self, pattern: &str) -> String {
+        let mut out = String::new();
+        let mut chars = pattern.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                out.push(ch);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => out.push_str(&self.year.to_string()),
+                Some('y') => out.push_str(&format!("{:02}", self.year.rem_euclid(100))),
+                Some('m') => out.push_str(&format!("{:02}", self.month)),
+                Some('d') => out.push_str(&format!("{:02}", self.day)),
+                Some('e') => out.push_str(&self.day.to_string()),
+                Some('H') => out.push_str(&format!("{:02}", self.hour)),
+                Some('M') => out.push_str(&format!("{:02}", self.minute)),
+                Some('S') => out.push_str(&format!("{:02}", self.second)),
+                Some('B') => out.push_str(MONTHS[self.month as usize - 1]),
+                Some('b') => out.push_str(&MONTHS[self.month as usize - 1][..3]),
+                Some('a') => {
+                    let days = days_from_civil(self.year, self.month, self.day);
+                    out.push_str(WEEKDAYS[(days + 4).rem_euclid(7) as usize]);
+                }
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                }
+                None => out.push('%'),
+            }
+        }
+        out
+    }
+}
+
+/// Read `YYYY-MM-DD`, optionally followed by `HH:MM[:SS[.fff]]` after a `T` or
+/// space; a trailing zone is ignored
+fn parse_datetime(text: &str) -> Option<DateTime> {
+    let text = text.trim();
+    let (date, time) = match text.find(['T', ' ']) {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok().filter(|m| (1..=12).contains(m))?;
+    let day = parts.next()?.parse().ok().filter(|d| (1..=31).contains(d))?;
+    let mut datetime = DateTime { year, month, day, hour: 0, minute: 0, second: 0 };
+    if let Some(time) = time {
+        let time = time.split(['Z', '+', '-', '.']).next().unwrap_or_default();
+        let mut parts = time.split(':').map(|part| part.parse::<u32>().ok());
+        datetime.hour = parts.next().flatten()?;
+        datetime.minute = parts.next().flatten()?;
+        datetime.second = parts.next().flatten().unwrap_or(0);
+    }
+    Some(datetime)
+}
+
+/// Shorten date or timestamp labels when every one parses, dropping what they
+/// share: `10:30` within a day, `Mar 5` within a year, `Mar 2024` for months
+fn abbreviate_dates(texts: &[String]) -> Option<Vec<String>> {
+    let dates: Vec<DateTime> = texts.iter().map(|text| parse_datetime(text)).collect::<Option<_>>()?;
+    let first = dates.first()?;
+    let timed = dates.iter().any(|d| (d.hour, d.minute, d.second) != (0, 0, 0));
+    let seconds = dates.iter().any(|d| d.second != 0);
+    let one_year = dates.iter().all(|d| d.year == first.year);
+    let one_day = one_year && dates.iter().all(|d| (d.month, d.day) == (first.month, first.day));
+    let monthly = dates.iter().all(|d| d.day == 1);
+    let pattern = match (timed, one_day, one_year, monthly) {
+        (true, true, _, _) if seconds => "%H:%M:%S",
+        (true, true, _, _) => "%H:%M",
+        (true, false, _, _) => "%b %e %H:%M",
+        (false, _, true, true) => "%b",
+        (false, _, false, true) => "%b %Y",
+        (false, _, true, false) => "%b %e",
+        (false, _, false, false) => "%Y-%m-%d",
+    };
+    Some(dates.iter().map(|d| d.format(pattern)).collect())
+}
+
+/// Where each of `texts` falls between the first and last in time, from 0 to 1,
+/// when every one is a date or timestamp and they run forward; None otherwise,
+/// including when they're all the same instant
+pub fn time_fractions(texts: &[String]) -> Option<Vec<f64>> {
+    let seconds: Vec<i64> = texts
+        .iter()
+        .map(|text| {
+            let dt = parse_datetime(text)?;
+            let days = days_from_civil(dt.year, dt.month, dt.day);
+            Some(days * 86_400 + (dt.hour * 3600 + dt.minute * 60 + dt.second) as i64)
+        })
+        .collect::<Option<_>>()?;
+    if seconds.windows(2).any(|pair| pair[1] < pair[0]) {
+        return None;
+    }
+    let (first, last) = (*seconds.first()?, *seconds.last()?);
+    if last == first {
+        return None;
     }
+    Some(seconds.iter().map(|&s| (s - first) as f64 / (last - first) as f64).collect())
+}
+
+/// Format seconds since the Unix epoch (or milliseconds, past the year 5138) in
+/// the display time zone
+fn format_epoch(value: f64, pattern: &str) -> String {
+    format_epoch_in(value, pattern, &timezone::time_zone())
+}
+
+fn format_epoch_in(value: f64, pattern: &str, zone: &TimeZone) -> String {
+    let seconds = if value.abs() >= 1e11 { value / 1000.0 } else { value }.floor() as i64;
+    let local = zone.local_time(seconds);
+    let datetime = DateTime {
+        year: local.year,
+        month: local.month,
+        day: local.day,
+        hour: local.hour,
+        minute: local.minute,
+        second: local.second,
+    };
+    datetime.format(pattern)
+}
+
+/// Days since 1970-01-01 of a calendar date
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Calendar date of a day counted from 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Render numbers as a one-line block sparkline, averaging into at most `max_width` buckets
+pub fn sparkline(values: &[f64], max_width: usize) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    if values.is_empty() || max_width == 0 {
+        return String::new();
+    }
+
+    let width = values.len().min(max_width);
+    let buckets: Vec<f64> = (0..width)
+        .map(|i| {
+            let start = i * values.len() / width;
+            let end = ((i + 1) * values.len() / width).max(start + 1);
+            let slice = &values[start..end];
+            slice.iter().sum::<f64>() / slice.len() as f64
+        })
+        .collect();
+
+    let min = buckets.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = buckets.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    buckets
+        .iter()
+        .map(|&v| {
+            if range > 0.0 {
+                LEVELS[(((v - min) / range) * 7.0).round() as usize]
+            } else {
+                LEVELS[3]
+            }
+        })
+        .collect()
 }
 
 /// Truncate a string to fit within max_width, adding ellipsis if needed
@@ -61,6 +648,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_format_number() {
         assert_eq!(format_number(1_500_000_000.0), "1.5B");
         assert_eq!(format_number(2_500_000.0), "2.5M");
@@ -69,10 +657,118 @@ mod tests {
         assert_eq!(format_number(3.14159), "3.14");
     }
 
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(5_432), "5,432");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_exact_and_locale() {
+        let exact = NumberFormat { exact: true, ..NumberFormat::DEFAULT };
+        assert_eq!(format_exact(1_234_567.0, 0, exact), "1,234,567");
+        assert_eq!(format_exact(-1_234.567, 2, exact), "-1,234.57");
+        assert_eq!(format_exact(-0.001, 2, exact), "0.00");
+
+        let german = NumberFormat::for_locale(true, "de_DE.UTF-8");
+        assert_eq!((german.group, german.decimal), ('.', ','));
+        assert_eq!(format_exact(1_234_567.5, 2, german), "1.234.567,50");
+        assert_eq!(NumberFormat::for_locale(false, "fr-FR").decimal, ',');
+        assert_eq!(NumberFormat::for_locale(false, "de_CH").group, '\'');
+        assert_eq!(NumberFormat::for_locale(false, "C"), NumberFormat::DEFAULT);
+        assert_eq!(localize("1.5K".to_string(), german), "1,5K");
+
+        let big = Decimal::parse("-12345678901234567890.125").unwrap();
+        assert_eq!(format_decimal(&big, Some(2), german), "-12.345.678.901.234.567.890,13");
+        assert_eq!(format_decimal(&Decimal::parse("-0.001").unwrap(), Some(2), exact), "0.00");
+    }
+
+    #[test]
+    fn test_column_formats() {
+        let formats: ColumnFormats = serde_json::from_value(serde_json::json!({
+            "exchange_rate": "number",
+            "took": "duration",
+            "size": "bytes",
+            "day": "date:%a %e %b %Y",
+            "orders": "integer",
+        }))
+        .unwrap();
+        assert_eq!(formats.value(1.0842, "exchange_rate"), "1.08");
+        assert_eq!(formats.value(0.25, "conversion_rate"), "25.0%");
+        assert_eq!(formats.value(125.0, "took"), "2m 05s");
+        assert_eq!(formats.value(0.25, "took"), "250ms");
+        assert_eq!(formats.value(1_288_490_189.0, "size"), "1.2 GiB");
+        assert_eq!(formats.value(430.0, "size"), "430 B");
+        assert_eq!(ColumnFormat::DecimalBytes.number(430_000.0), "430 kB");
+        assert_eq!(ColumnFormat::DecimalBytes.number(1_260_000_000.0), "1.3 GB");
+        assert_eq!(formats.value(1234.4, "orders"), "1,234");
+        assert_eq!(formats.axis(1234.4, "orders"), "1.2K");
+        assert_eq!(formats.text("2024-03-05T10:30:00Z", "day"), "Tue 5 Mar 2024");
+        assert_eq!(formats.text("March", "day"), "March");
+        assert_eq!(formats.cell(&serde_json::json!(0), "day").as_deref(), Some("Thu 1 Jan 1970"));
+        assert_eq!(formats.cell(&serde_json::json!("12"), "orders").as_deref(), Some("12"));
+        assert_eq!(formats.cell(&serde_json::json!(5), "other"), None);
+        let huge = serde_json::json!("170141183460469231731687303715884105727");
+        assert_eq!(formats.number(&huge, "orders").as_deref(), Some("170,141,183,460,469,231,731,687,303,715,884,105,727"));
+        assert_eq!(formats.number(&serde_json::json!(1_500), "other").as_deref(), Some("1.5K"));
+        assert_eq!(formats.number(&serde_json::json!("n/a"), "other"), None);
+
+        let texts = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let none = ColumnFormats::new();
+        assert_eq!(none.axis_texts(&texts(&["2024-03-05 10:30:00", "2024-03-05 11:00:00"]), "ts"), texts(&["10:30", "11:00"]));
+        assert_eq!(none.axis_texts(&texts(&["2024-03-05", "2024-03-06"]), "day"), texts(&["Mar 5", "Mar 6"]));
+        assert_eq!(none.axis_texts(&texts(&["2023-12-01", "2024-01-01"]), "month"), texts(&["Dec 2023", "Jan 2024"]));
+        assert_eq!(none.axis_texts(&texts(&["2024-03-05", "east"]), "x"), texts(&["2024-03-05", "east"]));
+        assert_eq!(formats.axis_texts(&texts(&["2024-03-05"]), "day"), texts(&["Tue 5 Mar 2024"]));
+
+        assert!(serde_json::from_value::<ColumnFormats>(serde_json::json!({"x": "money"})).is_err());
+        assert_eq!("date".parse::<ColumnFormat>().unwrap().to_string(), "date:%Y-%m-%d");
+        assert_eq!(civil_from_days(days_from_civil(2000, 2, 29)), (2000, 2, 29));
+        assert_eq!(format_epoch_in(1_700_000_000_000.0, "%Y-%m-%d %H:%M", &TimeZone::utc()), "2023-11-14 22:13");
+        let kolkata = TimeZone::load("+05:30").unwrap();
+        assert_eq!(format_epoch_in(1_700_000_000.0, "%Y-%m-%d %H:%M", &kolkata), "2023-11-15 03:43");
+    }
+
+    #[test]
+    fn time_fractions_follow_the_calendar() {
+        let texts = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        // Friday, Monday, Tuesday: the weekend takes up most of the axis
+        let fractions = time_fractions(&texts(&["2025-01-03", "2025-01-06", "2025-01-07"])).unwrap();
+        assert_eq!(fractions, vec![0.0, 0.75, 1.0]);
+        let fractions = time_fractions(&texts(&["2025-01-01 00:00", "2025-01-01 06:00", "2025-01-02 00:00"])).unwrap();
+        assert_eq!(fractions, vec![0.0, 0.25, 1.0]);
+        assert_eq!(time_fractions(&texts(&["2025-01-07", "2025-01-03"])), None);
+        assert_eq!(time_fractions(&texts(&["north", "south"])), None);
+        assert_eq!(time_fractions(&texts(&["2025-01-03"])), None);
+    }
+
+    #[test]
+    fn test_bytes_heuristics() {
+        assert_eq!(format_value(1_288_490_189.0, "table_size"), "1.2 GiB");
+        assert_eq!(format_value(440_320.0, "bytes_scanned"), "430 KiB");
+        assert_eq!(format_value(2048.0, "rx_bytes"), "2.0 KiB");
+        assert_eq!(format_value(2048.0, "size_mb"), "2.0K");
+        assert_eq!(format_value(64.0, "batch_size"), "64");
+        assert_eq!(format_value(64.0, "sizes_seen"), "64");
+    }
+
     #[test]
     fn test_format_currency() {
         assert_eq!(format_currency(1_000_000.0), "$1.0M");
         assert_eq!(format_currency(2_500.0), "$2.5K");
+        assert_eq!(format_currency(-2_500.0), "($2.5K)");
+        assert_eq!(format_currency(-12.5), "($12.50)");
+        assert_eq!(format_currency(-0.001), "$0.00");
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0], 10), "▁▂▃▄▅▆▇█");
+        assert_eq!(sparkline(&[5.0, 5.0], 10), "▄▄");
+        assert_eq!(sparkline(&[0.0, 0.0, 10.0, 10.0], 2), "▁█");
+        assert_eq!(sparkline(&[], 10), "");
     }
 
     #[test]
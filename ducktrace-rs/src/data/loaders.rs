@@ -0,0 +1,161 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::model::DrillDown;
+
+/// The DuckDB query that reads a CSV, TSV, or Parquet file as a table, or `None`
+/// for other files (chart specs are parsed directly, by [`parse_spec`])
+pub fn file_query(path: &Path) -> Option<String> {
+    let reader = match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "csv" | "tsv" => "read_csv_auto",
+        "parquet" => "read_parquet",
+        _ => return None,
+    };
+    let literal = path.to_string_lossy().replace('\'', "''");
+    Some(format!("SELECT * FROM {}('{}')", reader, literal))
+}
+
+/// Rows a data file's generated drill-down returns per page
+const DRILL_DOWN_LIMIT: usize = 100;
+
+/// A drill-down over the same file as `query` (from [`file_query`]): the rows
+/// whose x column equals the selected value, or falls in the brushed range
+pub fn file_drill_down(query: &str, x_field: &str) -> DrillDown {
+    let column = format!("\"{}\"", x_field.replace('"', "\"\""));
+    DrillDown {
+        description: format!("Rows with this {}", x_field),
+        query_template: format!("{} WHERE {} = {{{{x}}}} LIMIT {}", query, column, DRILL_DOWN_LIMIT),
+        range_query_template: Some(format!(
+            "{} WHERE {} BETWEEN {{{{x_start}}}} AND {{{{x_end}}}} ORDER BY {} LIMIT {}",
+            query, column, column, DRILL_DOWN_LIMIT
+        )),
+        param_mapping: HashMap::new(),
+    }
+}
+
+/// Default x and y columns for a data file: the first date-like column (else the
+/// first text one, else the first) on x, and the first numeric column that isn't
+/// an id on y. Unlike an ad-hoc query's, a file's columns weren't picked or
+/// ordered for charting.
+pub fn default_axes(columns: &[String], rows: &[Vec<Value>]) -> Option<(String, String)> {
+    let values = |i: usize| rows.iter().filter_map(move |r| r.get(i)).filter(|v| !v.is_null());
+    let numeric = |i: usize| values(i).next().is_some() && values(i).all(Value::is_number);
+    let date_like = |i: usize| values(i).next().is_some() && values(i).all(|v| v.as_str().is_some_and(looks_like_date));
+    let id = |i: usize| {
+        let name = columns[i].to_ascii_lowercase();
+        name == "id" || name.ends_with("_id")
+    };
+
+    let all = 0..columns.len();
+    let x = all.clone().find(|&i| date_like(i))
+        .or_else(|| all.clone().find(|&i| !numeric(i)))
+        .or_else(|| all.clone().next())?;
+    let y = all.clone().find(|&i| i != x && numeric(i) && !id(i))
+        .or_else(|| all.clone().find(|&i| i != x && numeric(i)))?;
+    Some((columns[x].clone(), columns[y].clone()))
+}
+
+/// `2024-06-01`, `2024-06-01 12:00:00`, `2024-06`: a leading ISO date
+fn looks_like_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let digits = |range: std::ops::Range<usize>| bytes.get(range).is_some_and(|b| b.iter().all(u8::is_ascii_digit));
+    digits(0..4) && bytes.get(4) == Some(&b'-') && digits(5..7) && matches!(bytes.get(7), None | Some(b'-'))
+}
+
+/// A chart spec as a JSON document, read as YAML for `.yaml`/`.yml`, TOML for
+/// `.toml`, and JSON otherwise. YAML block scalars and TOML multi-line strings
+/// spare a hand-written drill-down template JSON's escaping.
+pub fn parse_spec(path: &Path, content: &str) -> anyhow::Result<Value> {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    Ok(match extension.as_deref() {
+        Some("yaml" | "yml") => serde_yaml::from_str(content)?,
+        Some("toml") => toml::from_str(content)?,
+        _ => serde_json::from_str(content)?,
+    })
+}
+
+/// Whether `path` is a file that can be opened as a chart: a JSON, YAML, or TOML
+/// spec, or a data file read through [`file_query`]
+pub fn is_chart_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("json" | "yaml" | "yml" | "toml")) || file_query(path).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn data_files_are_read_through_duckdb() {
+        assert_eq!(
+            file_query(Path::new("/tmp/sales.csv")).as_deref(),
+            Some("SELECT * FROM read_csv_auto('/tmp/sales.csv')")
+        );
+        assert_eq!(
+            file_query(Path::new("/tmp/o'brien.PARQUET")).as_deref(),
+            Some("SELECT * FROM read_parquet('/tmp/o''brien.PARQUET')")
+        );
+        assert_eq!(file_query(Path::new("/tmp/chart.json")), None);
+        assert!(is_chart_file(Path::new("chart.json")));
+        assert!(is_chart_file(Path::new("events.tsv")));
+        assert!(is_chart_file(Path::new("revenue.yaml")));
+        assert!(!is_chart_file(Path::new("notes.txt")));
+    }
+
+    #[test]
+    fn data_files_drill_down_into_the_same_file() {
+        let query = file_query(Path::new("/tmp/events.parquet")).unwrap();
+        let drill_down = file_drill_down(&query, "event \"day\"");
+        assert_eq!(
+            drill_down.query_template,
+            "SELECT * FROM read_parquet('/tmp/events.parquet') WHERE \"event \"\"day\"\"\" = {{x}} LIMIT 100"
+        );
+        assert_eq!(
+            drill_down.range_query_template.as_deref(),
+            Some("SELECT * FROM read_parquet('/tmp/events.parquet') WHERE \"event \"\"day\"\"\" BETWEEN {{x_start}} AND {{x_end}} ORDER BY \"event \"\"day\"\"\" LIMIT 100")
+        );
+    }
+
+    #[test]
+    fn data_files_chart_a_date_against_a_measure() {
+        let columns: Vec<String> = ["order_id", "region", "day", "customer_id", "revenue"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let rows = vec![
+            vec![json!(1), json!("east"), json!("2024-06-01"), json!(7), json!(120.5)],
+            vec![json!(2), json!("west"), json!("2024-06-02"), json!(9), json!(null)],
+        ];
+        assert_eq!(
+            default_axes(&columns, &rows),
+            Some(("day".to_string(), "revenue".to_string()))
+        );
+
+        // No dates: the first text column, against the only number (an id)
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let rows = vec![vec![json!(1), json!("a")], vec![json!(2), json!("b")]];
+        assert_eq!(default_axes(&columns, &rows), Some(("name".to_string(), "id".to_string())));
+
+        assert_eq!(default_axes(&["name".to_string()], &[vec![json!("a")]]), None);
+        assert!(looks_like_date("2024-06"));
+        assert!(!looks_like_date("2024/06/01"));
+    }
+
+    #[test]
+    fn yaml_and_toml_specs_read_as_json() {
+        let yaml = "title: Revenue\nx: month\ncolumns: [month, revenue]\nrows:\n  - [2025-01, 10]\n\
+                    drillDown:\n  queryTemplate: |\n    SELECT * FROM orders\n    WHERE month = '{{x}}'\n";
+        let document = parse_spec(Path::new("chart.yaml"), yaml).unwrap();
+        assert_eq!(document["rows"], json!([["2025-01", 10]]));
+        assert_eq!(document["drillDown"]["queryTemplate"], json!("SELECT * FROM orders\nWHERE month = '{{x}}'\n"));
+
+        let toml = "title = \"Revenue\"\ncolumns = [\"month\", \"revenue\"]\nrows = [[\"2025-01\", 10]]\n\
+                    [drillDown]\nqueryTemplate = '''\nSELECT * FROM orders WHERE month = '{{x}}'\n'''\n";
+        let document = parse_spec(Path::new("chart.toml"), toml).unwrap();
+        assert_eq!(document["rows"], json!([["2025-01", 10]]));
+        assert_eq!(document["drillDown"]["queryTemplate"], json!("SELECT * FROM orders WHERE month = '{{x}}'\n"));
+
+        assert!(parse_spec(Path::new("chart.json"), "{\"title\": ").is_err());
+    }
+}
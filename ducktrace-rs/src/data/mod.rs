@@ -1,6 +1,24 @@
 pub mod model;
 pub mod format;
+pub mod decimal;
+pub mod downsample;
+pub mod template;
+pub mod loaders;
+pub mod diff;
+pub mod filter;
+pub mod folds;
+pub mod validate;
+pub mod window;
 
 #[allow(unused_imports)]
-pub use model::{ChartData, ChartType, DrillDown, ExplainData, HistoryEntry, Lineage, value_to_string};
-pub use format::{format_number, format_value, truncate_string};
+pub use model::{Attachment, BarMode, BarOrientation, ChartData, ChartSort, ChartType, ColorOverrides, ColumnProfile, ColumnStats, DrillDown, ExplainData, HistoryEntry, HistoryMeta, Lineage, QueryLogEntry, Threshold, XRange, DEFAULT_ROW_LIMIT, nice_ticks, parse_tags, set_row_limit, sort_history, value_to_string};
+pub use decimal::{compare_numbers, is_negative_number};
+pub use diff::{diff_lines, DiffLine};
+pub use downsample::lttb;
+pub use filter::ColumnFilter;
+pub use folds::{fold_regions, innermost_fold, visible_lines};
+pub use format::{ColumnFormats, format_compact, format_count, format_number, set_number_format, sparkline, time_fractions, truncate_string, NumberFormat};
+pub use loaders::{default_axes, file_drill_down, file_query, is_chart_file, parse_spec};
+pub use validate::validate_chart;
+pub use window::scroll_window;
+pub use template::{bind_placeholders, count_query, page_query, placeholder_names, BoundQuery};
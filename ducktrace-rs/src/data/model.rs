@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use super::decimal::{compare_numbers, Decimal};
+use super::format::ColumnFormats;
+
 /// Drill-down query template for explaining data points
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DrillDown {
@@ -12,6 +16,9 @@ pub struct DrillDown {
     /// Parameterized SQL query template (use {{x}}, {{y}} placeholders)
     #[serde(alias = "queryTemplate")]
     pub query_template: String,
+    /// Template for a brushed span of x values (use {{x_start}}, {{x_end}} placeholders)
+    #[serde(default, alias = "rangeQueryTemplate")]
+    pub range_query_template: Option<String>,
     /// Maps placeholder names to field names (e.g., {"x": "category"})
     #[serde(default, alias = "paramMapping")]
     pub param_mapping: HashMap<String, String>,
@@ -49,6 +56,80 @@ pub struct ExplainData {
     pub total_count: Option<usize>,
 }
 
+/// Color overrides by role, from the config file or a chart's `palette` field.
+/// Values are color names (`"blue"`, `"lightred"`) or hex (`"#1e66f5"`).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ColorOverrides {
+    /// Main series line, bars, and box plots
+    pub primary: Option<String>,
+    /// Second value column (right-hand axis)
+    pub secondary: Option<String>,
+    /// Area chart fill
+    pub fill: Option<String>,
+    /// Selected point, bar, row, and tab
+    pub selection: Option<String>,
+    /// Negative numbers in the Data tab, explain overlay, and chart info bar
+    pub negative: Option<String>,
+    /// Per-series colors for multi-series and pie charts, cycled in order
+    pub series: Option<Vec<String>>,
+    /// Home tab banner gradient, one color per letter
+    pub banner: Option<Vec<String>>,
+}
+
+/// A short label pinned to an x value (e.g. "pricing change" on a date)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Annotation {
+    /// X value to mark; matched against the x column's displayed value
+    pub x: serde_json::Value,
+    pub label: String,
+}
+
+/// A horizontal reference line (SLA, budget, goal) drawn across the chart
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Threshold {
+    pub value: f64,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Color name or `#rrggbb` hex for the line and the values above it (default: red)
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+/// A database to ATTACH alongside the main one, so drill-downs can join across them.
+/// Either a bare path (`"ref.duckdb"`, `"md:other_db"`) or an object with options.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Attachment {
+    Path(String),
+    Spec {
+        path: String,
+        /// Catalog name to attach as (DuckDB defaults to the file stem)
+        #[serde(default)]
+        alias: Option<String>,
+        #[serde(default, alias = "readOnly")]
+        read_only: bool,
+    },
+}
+
+impl Attachment {
+    /// `ATTACH IF NOT EXISTS` statement for this database
+    pub fn attach_sql(&self) -> String {
+        let (path, alias, read_only) = match self {
+            Attachment::Path(path) => (path, None, false),
+            Attachment::Spec { path, alias, read_only } => (path, alias.as_ref(), *read_only),
+        };
+        let mut sql = format!("ATTACH IF NOT EXISTS '{}'", path.replace('\'', "''"));
+        if let Some(alias) = alias {
+            sql.push_str(&format!(" AS \"{}\"", alias.replace('"', "\"\"")));
+        }
+        if read_only {
+            sql.push_str(" (READ_ONLY)");
+        }
+        sql
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChartData {
@@ -58,6 +139,12 @@ pub struct ChartData {
     pub x_field: String,
     #[serde(alias = "y")]
     pub y_field: String,
+    /// Optional second value column plotted against a right-hand axis
+    #[serde(default, alias = "y2_field", alias = "y2")]
+    pub y2_field: Option<String>,
+    /// Optional grouping column that splits rows into separate series
+    #[serde(default, alias = "series_field", alias = "series")]
+    pub series_field: Option<String>,
     pub columns: Vec<String>,
     pub rows: Vec<Vec<serde_json::Value>>,
     #[serde(alias = "chart_type")]
@@ -66,6 +153,18 @@ pub struct ChartData {
     #[allow(dead_code)]
     pub error_message: Option<String>,
     pub truncated_from: Option<usize>,
+    /// Rows kept for this chart, overriding the config's `max_rows`
+    #[serde(default, alias = "max_rows")]
+    pub max_rows: Option<usize>,
+    /// Rows cut by the row limit, kept so they can be shown on request
+    #[serde(skip)]
+    pub dropped_rows: Vec<Vec<serde_json::Value>>,
+    /// Every row is shown, the row limit having been lifted with `A`
+    #[serde(skip)]
+    pub all_rows: bool,
+    /// The query the chart arrived with, once it's been edited from the Query tab
+    #[serde(skip)]
+    pub original_query: Option<String>,
     /// Drill-down configuration for explaining data points
     #[serde(alias = "drill_down")]
     pub drill_down: Option<DrillDown>,
@@ -75,12 +174,158 @@ pub struct ChartData {
     /// Explain data (populated when responding to drill-down command, legacy)
     #[allow(dead_code)]
     pub explain_data: Option<ExplainData>,
+    /// Number of buckets for histogram charts (default: Sturges' rule)
+    #[serde(default)]
+    pub bins: Option<usize>,
+    /// Labeled markers on specific x values
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// Reference lines; values above one are highlighted in its color
+    #[serde(default)]
+    pub thresholds: Vec<Threshold>,
+    /// Per-chart color overrides, applied on top of the config file's palette
+    #[serde(default)]
+    pub palette: Option<ColorOverrides>,
+    /// How to show particular columns, taking precedence over guesses from their names
+    #[serde(default)]
+    pub formats: ColumnFormats,
     /// Database name for drill-down queries (e.g., "orb_data_export")
     pub database: Option<String>,
+    /// Local DuckDB file (or `:memory:`) to run drill-downs against instead of MotherDuck
+    #[serde(default, alias = "databasePath")]
+    pub database_path: Option<String>,
+    /// Extra databases attached before drill-downs run
+    #[serde(default)]
+    pub attach: Vec<Attachment>,
     /// Timestamp in milliseconds
     pub timestamp: Option<u64>,
 }
 
+/// Rows covered by a brush between two x positions, in axis order
+#[derive(Debug, Clone, PartialEq)]
+pub struct XRange {
+    /// Row holding the earliest x value of the span
+    pub start: usize,
+    /// Row holding the latest x value of the span
+    pub end: usize,
+    pub rows: Vec<usize>,
+}
+
+/// A histogram bucket covering `[start, end)` (the last bucket includes `end`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+impl HistogramBin {
+    pub fn contains(&self, value: f64, is_last: bool) -> bool {
+        value >= self.start && (value < self.end || (is_last && value <= self.end))
+    }
+}
+
+/// Five-number summary for one box in a box plot
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxStats {
+    pub label: String,
+    pub min: f64,
+    pub q1: f64,
+    pub median: f64,
+    pub q3: f64,
+    pub max: f64,
+    /// Number of samples summarized (None when read from pre-aggregated columns)
+    pub count: Option<usize>,
+}
+
+/// Aggregates over one numeric column for a subset of rows
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStats {
+    pub sum: f64,
+    pub avg: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Profile of one drill-down column over the rows shown, for the explain overlay
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    /// Non-null values
+    pub count: usize,
+    pub nulls: usize,
+    pub distinct: usize,
+    /// Smallest and largest values, compared as numbers when both are numbers
+    pub min: Option<serde_json::Value>,
+    pub max: Option<serde_json::Value>,
+    /// Mean, when every non-null value is a number
+    pub mean: Option<f64>,
+}
+
+impl ExplainData {
+    /// Profile column `col` over `rows`
+    pub fn column_profile(&self, col: usize, rows: &[usize]) -> ColumnProfile {
+        let values: Vec<&serde_json::Value> = rows
+            .iter()
+            .filter_map(|&i| self.rows.get(i)?.get(col))
+            .filter(|v| !v.is_null())
+            .collect();
+        let order = |a: &&serde_json::Value, b: &&serde_json::Value| {
+            compare_numbers(a, b).unwrap_or_else(|| value_to_string(a).cmp(&value_to_string(b)))
+        };
+        let distinct: std::collections::HashSet<String> = values.iter().map(|v| value_to_string(v)).collect();
+        let numbers: Vec<f64> = values.iter().filter_map(|v| v.as_f64()).collect();
+        ColumnProfile {
+            count: values.len(),
+            nulls: rows.len() - values.len(),
+            distinct: distinct.len(),
+            min: values.iter().copied().min_by(order).cloned(),
+            max: values.iter().copied().max_by(order).cloned(),
+            mean: (!numbers.is_empty() && numbers.len() == values.len())
+                .then(|| numbers.iter().sum::<f64>() / numbers.len() as f64),
+        }
+    }
+}
+
+/// One executed drill-down, as shown in the query log overlay
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryLogEntry {
+    /// When the query started, in milliseconds
+    pub timestamp: u64,
+    /// Database it ran against (`md:` or a file path)
+    pub target: String,
+    /// SQL after template substitution, with `?` for bound parameters
+    pub sql: String,
+    pub params: Vec<serde_json::Value>,
+    pub duration_ms: u64,
+    /// Rows fetched before the query finished, failed, or was cancelled
+    pub rows: usize,
+    pub error: Option<String>,
+}
+
+/// User metadata kept beside a history file as `<name>.meta`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HistoryMeta {
+    /// Sorted to the top of the Home tab and never rotated out
+    pub pinned: bool,
+    /// Free-form labels for grouping analyses, matched by the Home tab filter
+    pub tags: Vec<String>,
+}
+
+/// Split tags typed into the Home tab prompt on commas and whitespace, dropping a
+/// leading `#`, empties, and repeats
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(|c: char| c == ',' || c.is_whitespace()) {
+        let tag = tag.trim_start_matches('#');
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
 #[derive(Debug, Clone)]
 pub struct HistoryEntry {
     pub path: PathBuf,
@@ -89,6 +334,31 @@ pub struct HistoryEntry {
     pub row_count: usize,
     #[allow(dead_code)]
     pub chart_type: Option<String>,
+    pub database: Option<String>,
+    pub x_field: String,
+    pub y_field: String,
+    pub meta: HistoryMeta,
+}
+
+/// Order history for the Home tab: pinned entries first, then newest first
+pub fn sort_history(history: &mut [HistoryEntry]) {
+    history.sort_by_key(|e| (std::cmp::Reverse(e.meta.pinned), std::cmp::Reverse(e.timestamp)));
+}
+
+impl HistoryEntry {
+    /// Whether the Home tab filter matches the title, database, x/y field names, or
+    /// tags, ignoring case. A `#tag` query matches only tags starting with it.
+    pub fn matches(&self, query: &str) -> bool {
+        let needle = query.to_lowercase();
+        if let Some(tag) = needle.strip_prefix('#') {
+            return self.meta.tags.iter().any(|t| t.to_lowercase().starts_with(tag));
+        }
+        [Some(&self.title), self.database.as_ref(), Some(&self.x_field), Some(&self.y_field)]
+            .into_iter()
+            .flatten()
+            .chain(&self.meta.tags)
+            .any(|field| field.to_lowercase().contains(&needle))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -96,42 +366,323 @@ pub enum ChartType {
     Line,
     Bar,
     Scatter,
+    Pie,
+    Area,
+    Histogram,
+    BoxPlot,
+}
+
+/// How bars for multiple series share a category slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarMode {
+    Grouped,
+    Stacked,
+}
+
+impl BarMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            BarMode::Grouped => BarMode::Stacked,
+            BarMode::Stacked => BarMode::Grouped,
+        }
+    }
+}
+
+/// Whether single-series bars grow upward or rightward
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarOrientation {
+    Vertical,
+    Horizontal,
+}
+
+impl BarOrientation {
+    pub fn toggle(self) -> Self {
+        match self {
+            BarOrientation::Vertical => BarOrientation::Horizontal,
+            BarOrientation::Horizontal => BarOrientation::Vertical,
+        }
+    }
+}
+
+/// Order single-series charts draw their rows in, cycled with `o` on the Chart tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChartSort {
+    /// By x, oldest first
+    #[default]
+    Original,
+    /// By y, smallest first
+    Ascending,
+    /// By y, largest first
+    Descending,
+}
+
+impl ChartSort {
+    pub fn cycle(self) -> Self {
+        match self {
+            ChartSort::Original => ChartSort::Ascending,
+            ChartSort::Ascending => ChartSort::Descending,
+            ChartSort::Descending => ChartSort::Original,
+        }
+    }
+}
+
+/// Rows kept per chart when the config doesn't say
+pub const DEFAULT_ROW_LIMIT: usize = 50;
+
+/// Rows kept per chart, from `max_rows` in the config
+static ROW_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_ROW_LIMIT);
+
+pub fn set_row_limit(limit: usize) {
+    ROW_LIMIT.store(limit.max(1), Ordering::Relaxed);
+}
+
+fn row_limit() -> usize {
+    ROW_LIMIT.load(Ordering::Relaxed)
+}
+
+fn now_millis() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .ok()
 }
 
-const MAX_ROWS: usize = 50;
+/// Axes for an ad-hoc query's results: x is the first column and y the first
+/// column after it holding only numbers (else the second column)
+fn query_axes(columns: &[String], rows: &[Vec<serde_json::Value>]) -> (String, String) {
+    let numeric = |i: usize| {
+        rows.iter().all(|r| r.get(i).is_some_and(|v| v.is_number() || v.is_null()))
+            && rows.iter().any(|r| r.get(i).is_some_and(|v| v.is_number()))
+    };
+    let x_field = columns.first().cloned().unwrap_or_default();
+    let y_field = (1..columns.len())
+        .find(|&i| numeric(i))
+        .or(if columns.len() > 1 { Some(1) } else { None })
+        .map(|i| columns[i].clone())
+        .unwrap_or_else(|| x_field.clone());
+    (x_field, y_field)
+}
 
 impl ChartData {
-    /// Truncate rows to MAX_ROWS, recording original count in `truncated_from`
+    /// Rows this chart keeps: its own `max_rows`, else the configured limit, or
+    /// all of them once the limit is lifted
+    fn row_limit(&self) -> usize {
+        match self.max_rows {
+            _ if self.all_rows => usize::MAX,
+            Some(limit) => limit.max(1),
+            None => row_limit(),
+        }
+    }
+
+    /// Truncate rows to the row limit, recording original count in `truncated_from`.
+    /// The cut rows are kept in `dropped_rows` for [`Self::show_all_rows`].
     pub fn apply_row_limit(&mut self) {
-        if self.rows.len() > MAX_ROWS {
+        let limit = self.row_limit();
+        if self.rows.len() > limit {
             self.truncated_from = Some(self.rows.len());
-            self.rows.truncate(MAX_ROWS);
+            self.dropped_rows = self.rows.split_off(limit);
             self.status = Some("truncated".to_string());
         }
     }
 
+    /// Lift the row limit, restoring the rows it cut. False when the rows were cut
+    /// before the chart arrived (only `truncated_from` is known), so the full set
+    /// has to be queried again.
+    pub fn show_all_rows(&mut self) -> bool {
+        self.all_rows = true;
+        if self.dropped_rows.is_empty() && self.truncated_from.is_some_and(|total| total > self.rows.len()) {
+            return false;
+        }
+        self.rows.append(&mut self.dropped_rows);
+        self.truncated_from = None;
+        if self.status.as_deref() == Some("truncated") {
+            self.status = None;
+        }
+        true
+    }
+
     /// Set timestamp to current time if not already present
     pub fn ensure_timestamp(&mut self) {
         if self.timestamp.is_none() {
-            self.timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_millis() as u64)
-                .ok();
+            self.timestamp = now_millis();
         }
     }
 
+    /// Chart the results of an ad-hoc query: x is the first column and y the first
+    /// column after it holding only numbers. The database settings of `source` (the
+    /// chart being explored) carry over so follow-up queries run in the same place.
+    pub fn from_query(
+        query: &str,
+        columns: Vec<String>,
+        rows: Vec<Vec<serde_json::Value>>,
+        source: Option<&ChartData>,
+    ) -> ChartData {
+        let (x_field, y_field) = query_axes(&columns, &rows);
+
+        let mut data = ChartData {
+            title: "Ad-hoc SQL".to_string(),
+            query: query.to_string(),
+            x_field,
+            y_field,
+            y2_field: None,
+            series_field: None,
+            columns,
+            rows,
+            chart_type: None,
+            status: None,
+            error_message: None,
+            truncated_from: None,
+            max_rows: None,
+            dropped_rows: Vec::new(),
+            all_rows: false,
+            original_query: None,
+            drill_down: None,
+            lineage: None,
+            explain_data: None,
+            bins: None,
+            annotations: Vec::new(),
+            thresholds: Vec::new(),
+            palette: source.and_then(|s| s.palette.clone()),
+            formats: source.map(|s| s.formats.clone()).unwrap_or_default(),
+            database: source.and_then(|s| s.database.clone()),
+            database_path: source.and_then(|s| s.database_path.clone()),
+            attach: source.map(|s| s.attach.clone()).unwrap_or_default(),
+            timestamp: None,
+        };
+        data.apply_row_limit();
+        data.ensure_timestamp();
+        data
+    }
+
+    /// Swap in freshly queried results and stamp the current time. An empty result
+    /// carries no column names, so the existing columns are kept in that case.
+    pub fn replace_results(&mut self, columns: Vec<String>, rows: Vec<Vec<serde_json::Value>>) {
+        if !columns.is_empty() {
+            self.columns = columns;
+        }
+        self.rows = rows;
+        self.dropped_rows.clear();
+        self.truncated_from = None;
+        self.status = None;
+        self.error_message = None;
+        self.apply_row_limit();
+        self.timestamp = now_millis();
+    }
+
+    /// Swap in an edited query and its results, keeping the chart's settings.
+    /// Axes the new columns lack are picked again as for an ad-hoc query, and the
+    /// query the chart arrived with stays in `original_query` until edited back.
+    pub fn apply_edited_query(&mut self, query: &str, columns: Vec<String>, rows: Vec<Vec<serde_json::Value>>) {
+        let lacks = |field: &str| !columns.is_empty() && !columns.iter().any(|c| c == field);
+        if lacks(&self.x_field) || lacks(&self.y_field) {
+            (self.x_field, self.y_field) = query_axes(&columns, &rows);
+        }
+        if self.series_field.as_deref().is_some_and(lacks) {
+            self.series_field = None;
+        }
+        if self.y2_field.as_deref().is_some_and(lacks) {
+            self.y2_field = None;
+        }
+        let previous = std::mem::replace(&mut self.query, query.to_string());
+        let original = self.original_query.take().unwrap_or(previous);
+        self.original_query = (original.trim() != query.trim()).then_some(original);
+        self.replace_results(columns, rows);
+    }
+
+    /// Append rows streamed from the append file: objects are matched to columns by
+    /// name, arrays taken in column order, and anything else skipped. Past the row
+    /// limit the oldest rows are dropped, so a live chart shows the latest window.
+    /// Returns how many rows were added.
+    pub fn append_rows(&mut self, lines: Vec<serde_json::Value>) -> usize {
+        let seen = self.truncated_from.unwrap_or(self.rows.len());
+        let mut added = 0;
+        for line in lines {
+            let row = match line {
+                serde_json::Value::Array(values) => values,
+                serde_json::Value::Object(mut fields) => self
+                    .columns
+                    .iter()
+                    .map(|c| fields.remove(c).unwrap_or(serde_json::Value::Null))
+                    .collect(),
+                _ => continue,
+            };
+            self.rows.push(row);
+            added += 1;
+        }
+        let limit = self.row_limit();
+        if self.rows.len() > limit {
+            self.rows.drain(..self.rows.len() - limit);
+            self.truncated_from = Some(seen + added);
+            self.status = Some("truncated".to_string());
+        }
+        self.timestamp = now_millis();
+        added
+    }
+
+    /// Both charts as two series of one chart, labelled `label` and `other_label`,
+    /// when they plot a single series of the same x values as a line, area,
+    /// scatter, or bar chart. `None` when they have to be compared side by side.
+    pub fn overlay(&self, label: &str, other: &ChartData, other_label: &str) -> Option<ChartData> {
+        let chart_type = match self.infer_chart_type() {
+            ChartType::Line => "line",
+            ChartType::Area => "area",
+            ChartType::Scatter => "scatter",
+            ChartType::Bar => "grouped_bar",
+            _ => return None,
+        };
+        let plain = |d: &ChartData| d.series_field.is_none() && !d.rows.is_empty();
+        if !plain(self) || !plain(other) || self.distinct_x_values() != other.distinct_x_values() {
+            return None;
+        }
+
+        let series = "chart".to_string();
+        let mut rows = Vec::with_capacity(self.rows.len() + other.rows.len());
+        for (data, name) in [(self, label), (other, other_label)] {
+            let (x_idx, y_idx) = (data.get_x_index(), data.get_y_index());
+            for row in &data.rows {
+                let cell = |i: usize| row.get(i).cloned().unwrap_or(serde_json::Value::Null);
+                rows.push(vec![cell(x_idx), serde_json::Value::String(name.to_string()), cell(y_idx)]);
+            }
+        }
+        let columns = vec![self.x_field.clone(), series.clone(), self.y_field.clone()];
+        let mut merged = ChartData::from_query(&self.query, columns, Vec::new(), Some(self));
+        // Both windows fit; the row limit applies to each chart, not the pair
+        merged.rows = rows;
+        merged.title = format!("{} vs {}", self.title, other.title);
+        merged.x_field = self.x_field.clone();
+        merged.y_field = self.y_field.clone();
+        merged.series_field = Some(series);
+        merged.chart_type = Some(chart_type.to_string());
+        Some(merged)
+    }
+
     /// Infer the chart type based on data characteristics
     pub fn infer_chart_type(&self) -> ChartType {
         // Check explicit chart_type first
         if let Some(ref ct) = self.chart_type {
             match ct.to_lowercase().as_str() {
                 "line" => return ChartType::Line,
-                "bar" => return ChartType::Bar,
+                "bar" | "grouped_bar" | "stacked_bar" | "hbar" | "horizontal_bar" => {
+                    return ChartType::Bar
+                }
                 "scatter" => return ChartType::Scatter,
+                "pie" | "donut" => return ChartType::Pie,
+                "area" => return ChartType::Area,
+                "histogram" | "hist" => return ChartType::Histogram,
+                "box" | "boxplot" | "box_plot" => return ChartType::BoxPlot,
                 _ => {}
             }
         }
 
+        // Running totals read best as a filled region
+        let y_lower = self.y_field.to_lowercase();
+        if ["cumulative", "running", "cume", "to_date", "ytd", "mtd"]
+            .iter()
+            .any(|hint| y_lower.contains(hint))
+        {
+            return ChartType::Area;
+        }
+
         // Infer from data
         if self.rows.is_empty() {
             return ChartType::Bar;
@@ -164,6 +715,30 @@ impl ChartData {
         }
     }
 
+    /// Bar layout requested by the chart type, used until the user toggles it
+    pub fn default_bar_mode(&self) -> BarMode {
+        match self.chart_type.as_deref().map(|ct| ct.to_lowercase()) {
+            Some(ct) if ct == "stacked_bar" => BarMode::Stacked,
+            _ => BarMode::Grouped,
+        }
+    }
+
+    /// Bar orientation requested by the chart type, used until the user toggles it
+    pub fn default_bar_orientation(&self) -> BarOrientation {
+        match self.chart_type.as_deref().map(|ct| ct.to_lowercase()) {
+            Some(ct) if ct == "hbar" || ct == "horizontal_bar" => BarOrientation::Horizontal,
+            _ => BarOrientation::Vertical,
+        }
+    }
+
+    /// Whether a pie chart should be drawn with a hollow center
+    pub fn is_donut(&self) -> bool {
+        self.chart_type
+            .as_deref()
+            .map(|ct| ct.eq_ignore_ascii_case("donut"))
+            .unwrap_or(false)
+    }
+
     pub fn get_x_index(&self) -> usize {
         self.columns
             .iter()
@@ -178,6 +753,59 @@ impl ChartData {
             .unwrap_or(1.min(self.columns.len().saturating_sub(1)))
     }
 
+    /// Index of the secondary value column, if one is configured and present
+    pub fn get_y2_index(&self) -> Option<usize> {
+        let field = self.y2_field.as_ref()?;
+        self.columns.iter().position(|c| c == field)
+    }
+
+    pub fn get_y2_value(&self, row: &[serde_json::Value]) -> Option<f64> {
+        let idx = self.get_y2_index()?;
+        Some(row.get(idx).map(value_to_f64).unwrap_or(0.0))
+    }
+
+    /// Index of the series column, if one is configured and present
+    pub fn get_series_index(&self) -> Option<usize> {
+        let field = self.series_field.as_ref()?;
+        self.columns.iter().position(|c| c == field)
+    }
+
+    pub fn get_series_value(&self, row: &[serde_json::Value]) -> Option<String> {
+        let idx = self.get_series_index()?;
+        Some(row.get(idx).map(value_to_string).unwrap_or_default())
+    }
+
+    /// Row indices grouped by series value, in order of first appearance
+    pub fn series_groups(&self) -> Vec<(String, Vec<usize>)> {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        for (i, row) in self.rows.iter().enumerate() {
+            let name = self.get_series_value(row).unwrap_or_default();
+            match groups.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, indices)) => indices.push(i),
+                None => groups.push((name, vec![i])),
+            }
+        }
+        groups
+    }
+
+    /// Distinct x values in ascending order (numeric when every value parses)
+    pub fn distinct_x_values(&self) -> Vec<String> {
+        let mut values: Vec<String> = Vec::new();
+        for row in &self.rows {
+            let x = self.get_x_value(row);
+            if !values.contains(&x) {
+                values.push(x);
+            }
+        }
+        let all_numeric = values.iter().all(|v| Decimal::parse(v).is_some());
+        if all_numeric {
+            values.sort_by_cached_key(|v| Decimal::parse(v));
+        } else {
+            values.sort();
+        }
+        values
+    }
+
     pub fn get_x_value(&self, row: &[serde_json::Value]) -> String {
         let idx = self.get_x_index();
         row.get(idx)
@@ -185,6 +813,148 @@ impl ChartData {
             .unwrap_or_default()
     }
 
+    /// The x value of `row` for display, with the x column's format hint applied
+    pub fn x_label(&self, row: &[serde_json::Value]) -> String {
+        self.formats.text(&self.get_x_value(row), &self.x_field)
+    }
+
+    /// Annotations with the first row whose x value they mark, numbered from 1 in config order.
+    /// Annotations that match no row are dropped.
+    pub fn annotation_rows(&self) -> Vec<(usize, usize, &Annotation)> {
+        self.annotations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, annotation)| {
+                let x = value_to_string(&annotation.x);
+                let row = self.rows.iter().position(|r| self.get_x_value(r) == x)?;
+                Some((i + 1, row, annotation))
+            })
+            .collect()
+    }
+
+    /// Whether rows run newest-first (first x > last x), so charts draw them in reverse
+    pub fn is_reverse_sorted(&self) -> bool {
+        if self.rows.len() < 2 {
+            return false;
+        }
+        let first_x = self.get_x_value(&self.rows[0]);
+        let last_x = self.get_x_value(&self.rows[self.rows.len() - 1]);
+        first_x > last_x
+    }
+
+    /// Whether `o` can reorder the chart: single-series bar, line, area, and scatter
+    /// charts draw one point per row
+    pub fn is_sortable(&self) -> bool {
+        self.get_series_index().is_none()
+            && matches!(
+                self.infer_chart_type(),
+                ChartType::Bar | ChartType::Line | ChartType::Area | ChartType::Scatter
+            )
+    }
+
+    /// Row indices in the order a single-series chart draws them: by x (oldest first,
+    /// even when rows run newest-first), or by y with nulls last and ties kept in x order.
+    /// The rows themselves stay as they are.
+    pub fn display_order(&self, sort: ChartSort) -> Vec<usize> {
+        let mut order: Vec<usize> = if self.is_reverse_sorted() {
+            (0..self.rows.len()).rev().collect()
+        } else {
+            (0..self.rows.len()).collect()
+        };
+        let y = |i: usize| self.y_value(&self.rows[i]);
+        let by_y = |a: usize, b: usize, descending: bool| match (y(a), y(b)) {
+            (Some(a), Some(b)) if descending => b.total_cmp(&a),
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        };
+        match sort {
+            ChartSort::Original => {}
+            ChartSort::Ascending => order.sort_by(|&a, &b| by_y(a, b, false)),
+            ChartSort::Descending => order.sort_by(|&a, &b| by_y(a, b, true)),
+        }
+        order
+    }
+
+    /// Position of each row along the x axis as charts lay it out: row order (flipped for
+    /// descending data) for a single series, or the sorted distinct x values when split by series
+    pub fn x_positions(&self) -> Vec<usize> {
+        if self.get_series_index().is_some() {
+            let x_values = self.distinct_x_values();
+            self.rows
+                .iter()
+                .map(|row| {
+                    let x = self.get_x_value(row);
+                    x_values.iter().position(|v| *v == x).unwrap_or(0)
+                })
+                .collect()
+        } else if self.is_reverse_sorted() {
+            (0..self.rows.len()).rev().collect()
+        } else {
+            (0..self.rows.len()).collect()
+        }
+    }
+
+    /// Rows whose x position lies between those of rows `a` and `b` (inclusive)
+    pub fn x_range(&self, a: usize, b: usize) -> XRange {
+        let positions = self.x_positions();
+        let (start, end) = if positions[a] <= positions[b] { (a, b) } else { (b, a) };
+        let (lo, hi) = (positions[start], positions[end]);
+        let mut rows: Vec<usize> = (0..self.rows.len())
+            .filter(|&i| (lo..=hi).contains(&positions[i]))
+            .collect();
+        rows.sort_by_key(|&i| positions[i]);
+        XRange { start, end, rows }
+    }
+
+    /// Per-column aggregates over `rows`; None for columns with non-numeric or no values.
+    /// Nulls are skipped, so a column of numbers with gaps still aggregates.
+    pub fn column_stats(&self, rows: &[usize]) -> Vec<Option<ColumnStats>> {
+        (0..self.columns.len())
+            .map(|col| {
+                let mut values = Vec::new();
+                for &i in rows {
+                    match self.rows[i].get(col) {
+                        Some(serde_json::Value::Number(n)) => values.push(n.as_f64()?),
+                        Some(serde_json::Value::Null) | None => {}
+                        Some(_) => return None,
+                    }
+                }
+                if values.is_empty() {
+                    return None;
+                }
+                let sum: f64 = values.iter().sum();
+                Some(ColumnStats {
+                    sum,
+                    avg: sum / values.len() as f64,
+                    min: values.iter().cloned().fold(f64::INFINITY, f64::min),
+                    max: values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                })
+            })
+            .collect()
+    }
+
+    /// Indices of rows with any cell containing `query`, ignoring case
+    pub fn matching_rows(&self, query: &str) -> Vec<usize> {
+        let needle = query.to_lowercase();
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(|v| value_to_string(v).to_lowercase().contains(&needle)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Indices of rows whose x value contains `query`, ignoring case
+    pub fn matching_x_rows(&self, query: &str) -> Vec<usize> {
+        let needle = query.to_lowercase();
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| self.get_x_value(row).to_lowercase().contains(&needle))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
     pub fn get_y_value(&self, row: &[serde_json::Value]) -> f64 {
         let idx = self.get_y_index();
         row.get(idx)
@@ -192,21 +962,163 @@ impl ChartData {
             .unwrap_or(0.0)
     }
 
+    /// The y value of `row`, or None when it's null or missing, for charts that
+    /// leave a gap rather than plot a zero
+    pub fn y_value(&self, row: &[serde_json::Value]) -> Option<f64> {
+        match row.get(self.get_y_index())? {
+            serde_json::Value::Null => None,
+            value => Some(value_to_f64(value)),
+        }
+    }
+
+    /// Bin the y column into equal-width buckets, skipping nulls and non-numeric values
+    pub fn histogram(&self) -> Vec<HistogramBin> {
+        let idx = self.get_y_index();
+        let values: Vec<f64> = self
+            .rows
+            .iter()
+            .filter_map(|row| match row.get(idx)? {
+                serde_json::Value::Number(n) => n.as_f64(),
+                serde_json::Value::String(s) => s.parse().ok(),
+                _ => None,
+            })
+            .collect();
+        if values.is_empty() {
+            return Vec::new();
+        }
+
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if min == max {
+            return vec![HistogramBin { start: min, end: max, count: values.len() }];
+        }
+
+        // Sturges' rule keeps small samples from fragmenting into empty buckets
+        let default_bins = (values.len() as f64).log2().ceil() as usize + 1;
+        let bin_count = self.bins.unwrap_or(default_bins).max(1);
+        let width = (max - min) / bin_count as f64;
+
+        let mut bins: Vec<HistogramBin> = (0..bin_count)
+            .map(|i| HistogramBin {
+                start: min + width * i as f64,
+                end: if i + 1 == bin_count { max } else { min + width * (i + 1) as f64 },
+                count: 0,
+            })
+            .collect();
+        for v in values {
+            let i = (((v - min) / width).floor() as usize).min(bin_count - 1);
+            bins[i].count += 1;
+        }
+        bins
+    }
+
+    /// Per-x five-number summaries, read from min/p25/median/p75/max columns when the
+    /// query pre-aggregated them, otherwise computed from the y values of each x group
+    pub fn box_stats(&self) -> Vec<BoxStats> {
+        let find = |names: &[&str]| {
+            self.columns.iter().position(|c| {
+                let lower = c.to_lowercase();
+                names
+                    .iter()
+                    .any(|n| lower == *n || lower.ends_with(&format!("_{}", n)))
+            })
+        };
+        let pre_aggregated = (
+            find(&["min"]),
+            find(&["p25", "q1"]),
+            find(&["median", "p50"]),
+            find(&["p75", "q3"]),
+            find(&["max"]),
+        );
+
+        if let (Some(min), Some(q1), Some(median), Some(q3), Some(max)) = pre_aggregated {
+            let get = |row: &[serde_json::Value], idx: usize| {
+                row.get(idx).map(value_to_f64).unwrap_or(0.0)
+            };
+            return self
+                .rows
+                .iter()
+                .map(|row| BoxStats {
+                    label: self.get_x_value(row),
+                    min: get(row, min),
+                    q1: get(row, q1),
+                    median: get(row, median),
+                    q3: get(row, q3),
+                    max: get(row, max),
+                    count: None,
+                })
+                .collect();
+        }
+
+        let y_idx = self.get_y_index();
+        self.distinct_x_values()
+            .into_iter()
+            .filter_map(|label| {
+                let mut values: Vec<f64> = self
+                    .rows
+                    .iter()
+                    .filter(|row| self.get_x_value(row) == label)
+                    .filter_map(|row| match row.get(y_idx)? {
+                        serde_json::Value::Number(n) => n.as_f64(),
+                        serde_json::Value::String(s) => s.parse().ok(),
+                        _ => None,
+                    })
+                    .collect();
+                if values.is_empty() {
+                    return None;
+                }
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                Some(BoxStats {
+                    min: values[0],
+                    q1: percentile(&values, 0.25),
+                    median: percentile(&values, 0.5),
+                    q3: percentile(&values, 0.75),
+                    max: values[values.len() - 1],
+                    count: Some(values.len()),
+                    label,
+                })
+            })
+            .collect()
+    }
+
+    /// The highest threshold that a value exceeds, if any
+    pub fn exceeded_threshold(&self, y: f64) -> Option<&Threshold> {
+        self.thresholds
+            .iter()
+            .filter(|t| y > t.value)
+            .max_by(|a, b| a.value.partial_cmp(&b.value).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// Y range covering both the data and every threshold, so reference lines stay on screen
+    pub fn y_range_with_thresholds(&self) -> (f64, f64) {
+        self.thresholds
+            .iter()
+            .fold((self.min_y(), self.max_y()), |(lo, hi), t| (lo.min(t.value), hi.max(t.value)))
+    }
+
     pub fn max_y(&self) -> f64 {
         self.rows
             .iter()
-            .map(|row| self.get_y_value(row))
-            .fold(0.0_f64, |a, b| a.max(b))
+            .filter_map(|row| self.y_value(row))
+            .fold(f64::MIN, |a, b| a.max(b))
     }
 
     pub fn min_y(&self) -> f64 {
         self.rows
             .iter()
-            .map(|row| self.get_y_value(row))
+            .filter_map(|row| self.y_value(row))
             .fold(f64::MAX, |a, b| a.min(b))
     }
 }
 
+/// Linearly interpolated percentile of an ascending, non-empty slice
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
 pub fn value_to_string(v: &serde_json::Value) -> String {
     match v {
         serde_json::Value::String(s) => s.clone(),
@@ -225,6 +1137,44 @@ pub fn value_to_f64(v: &serde_json::Value) -> f64 {
     }
 }
 
+/// Evenly spaced axis ticks at 1, 2, or 5 × 10^n covering `lo..=hi`, at most
+/// `max_count` of them; the first and last are the axis bounds
+pub fn nice_ticks(lo: f64, hi: f64, max_count: usize) -> Vec<f64> {
+    let max_count = max_count.max(2);
+    if !lo.is_finite() || !hi.is_finite() || hi <= lo {
+        return vec![lo, hi];
+    }
+    let mut raw = (hi - lo) / (max_count - 1) as f64;
+    loop {
+        let step = nice_step(raw);
+        let start = (lo / step).floor();
+        let end = (hi / step).ceil();
+        let count = (end - start) as usize + 1;
+        // A span across zero takes three ticks however wide the step
+        if count <= max_count || step >= hi - lo {
+            // Multiplying whole steps keeps 0.1 + 0.2 noise out of the labels
+            return (0..count).map(|i| (start + i as f64) * step).collect();
+        }
+        raw = step * 1.01;
+    }
+}
+
+/// The smallest of 1, 2, 5, or 10 × 10^n that's at least `raw`
+fn nice_step(raw: f64) -> f64 {
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let fraction = raw / magnitude;
+    let nice = if fraction <= 1.0 {
+        1.0
+    } else if fraction <= 2.0 {
+        2.0
+    } else if fraction <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -276,6 +1226,56 @@ mod tests {
         assert!(data.drill_down.is_some());
     }
 
+    #[test]
+    fn append_rows_keeps_the_latest_window() {
+        let json = r#"{
+            "title": "Live",
+            "query": "SELECT 1",
+            "x": "ts",
+            "y": "val",
+            "columns": ["ts", "val"],
+            "rows": [[0, 0]]
+        }"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+        let added = data.append_rows(vec![
+            serde_json::json!({"val": 1, "ts": 1}),
+            serde_json::json!([2, 2]),
+            serde_json::json!("not a row"),
+        ]);
+        assert_eq!(added, 2);
+        assert_eq!(data.rows[1], vec![serde_json::json!(1), serde_json::json!(1)]);
+
+        let more = (3..60).map(|i| serde_json::json!([i, i])).collect();
+        data.append_rows(more);
+        assert_eq!(data.rows.len(), 50);
+        assert_eq!(data.rows[0][0], serde_json::json!(10));
+        assert_eq!(data.truncated_from, Some(60));
+    }
+
+    #[test]
+    fn overlay_needs_matching_x_values() {
+        let chart = |rows: &str| {
+            let json = format!(
+                r#"{{"title": "Sales", "query": "SELECT 1", "x": "month", "y": "revenue",
+                    "columns": ["month", "revenue"], "rows": {}}}"#,
+                rows
+            );
+            serde_json::from_str::<ChartData>(&json).unwrap()
+        };
+        let before = chart(r#"[["Jan", 10], ["Feb", 20]]"#);
+        let after = chart(r#"[["Feb", 25], ["Jan", 12]]"#);
+
+        let merged = before.overlay("A", &after, "B").unwrap();
+        assert_eq!(merged.series_field.as_deref(), Some("chart"));
+        assert_eq!(merged.y_field, "revenue");
+        assert_eq!(merged.rows.len(), 4);
+        assert_eq!(merged.rows[2], vec![serde_json::json!("Feb"), serde_json::json!("B"), serde_json::json!(25)]);
+        assert_eq!(merged.series_groups().len(), 2);
+
+        let other_months = chart(r#"[["Jan", 10], ["Mar", 20]]"#);
+        assert!(before.overlay("A", &other_months, "B").is_none());
+    }
+
     #[test]
     fn apply_row_limit_truncates() {
         let json = r#"{
@@ -299,6 +1299,29 @@ mod tests {
         assert_eq!(data.rows.len(), 50);
         assert_eq!(data.truncated_from, Some(60));
         assert_eq!(data.status.as_deref(), Some("truncated"));
+
+        assert!(data.show_all_rows());
+        assert_eq!(data.rows.len(), 60);
+        assert_eq!(data.rows[59][0], serde_json::json!(59));
+        assert_eq!(data.truncated_from, None);
+        assert_eq!(data.status, None);
+    }
+
+    #[test]
+    fn chart_row_limit_overrides_default() {
+        let json = r#"{"title": "T", "query": "SELECT 1", "x": "id", "y": "val", "columns": ["id", "val"],
+                       "rows": [[1, 1], [2, 2], [3, 3]], "max_rows": 2}"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+        data.apply_row_limit();
+        assert_eq!(data.rows.len(), 2);
+        assert_eq!(data.truncated_from, Some(3));
+
+        // Cut by the writer: the rows aren't here to restore
+        let json = r#"{"title": "T", "query": "SELECT 1", "x": "id", "y": "val", "columns": ["id", "val"],
+                       "rows": [[1, 1]], "truncatedFrom": 500}"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+        assert!(!data.show_all_rows());
+        assert!(data.all_rows);
     }
 
     #[test]
@@ -318,6 +1341,402 @@ mod tests {
         assert!(data.timestamp.unwrap() > 1_000_000_000_000); // millis
     }
 
+    #[test]
+    fn from_query_picks_first_numeric_column_for_y() {
+        use serde_json::json;
+
+        let source: ChartData = serde_json::from_str(
+            r#"{"title": "T", "query": "SELECT 1", "x": "a", "y": "b", "columns": [], "rows": [],
+                "database": "sales", "databasePath": "local.duckdb"}"#,
+        )
+        .unwrap();
+        let data = ChartData::from_query(
+            "SELECT region, name, total FROM t",
+            vec!["region".into(), "name".into(), "total".into()],
+            vec![vec![json!("EU"), json!("a"), json!(3)], vec![json!("US"), json!("b"), json!(null)]],
+            Some(&source),
+        );
+        assert_eq!(data.x_field, "region");
+        assert_eq!(data.y_field, "total");
+        assert_eq!(data.database.as_deref(), Some("sales"));
+        assert_eq!(data.database_path.as_deref(), Some("local.duckdb"));
+        assert!(data.drill_down.is_none());
+
+        let text_only = ChartData::from_query("SELECT 'a', 'b'", vec!["a".into(), "b".into()], vec![], None);
+        assert_eq!(text_only.y_field, "b");
+    }
+
+    #[test]
+    fn replace_results_restamps_and_reapplies_limit() {
+        use serde_json::json;
+
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "id",
+            "y": "val",
+            "columns": ["id", "val"],
+            "rows": [[1, 10]],
+            "status": "truncated",
+            "truncatedFrom": 80,
+            "timestamp": 1234567890000
+        }"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+
+        data.replace_results(vec!["id".into(), "val".into()], vec![vec![json!(1), json!(11)]]);
+        assert_eq!(data.rows, vec![vec![json!(1), json!(11)]]);
+        assert_eq!(data.truncated_from, None);
+        assert_eq!(data.status, None);
+        assert!(data.timestamp.unwrap() > 1234567890000);
+
+        let rows = (0..60).map(|i| vec![json!(i), json!(i)]).collect();
+        data.replace_results(Vec::new(), rows);
+        assert_eq!(data.columns, vec!["id", "val"]);
+        assert_eq!(data.rows.len(), 50);
+        assert_eq!(data.truncated_from, Some(60));
+    }
+
+    #[test]
+    fn infer_explicit_pie_and_donut() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "region",
+            "y": "share",
+            "columns": ["region", "share"],
+            "rows": [["east", 10], ["west", 30]],
+            "chart_type": "donut"
+        }"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.infer_chart_type(), ChartType::Pie);
+        assert!(data.is_donut());
+        data.chart_type = Some("pie".to_string());
+        assert_eq!(data.infer_chart_type(), ChartType::Pie);
+        assert!(!data.is_donut());
+    }
+
+    #[test]
+    fn series_groups_preserve_first_appearance() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "month",
+            "y": "revenue",
+            "series": "region",
+            "columns": ["month", "region", "revenue"],
+            "rows": [
+                ["2025-02", "west", 3],
+                ["2025-01", "east", 1],
+                ["2025-01", "west", 2],
+                ["2025-02", "east", 4]
+            ]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.get_series_index(), Some(1));
+        let groups = data.series_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0], ("west".to_string(), vec![0, 2]));
+        assert_eq!(groups[1], ("east".to_string(), vec![1, 3]));
+        assert_eq!(data.distinct_x_values(), vec!["2025-01", "2025-02"]);
+    }
+
+    #[test]
+    fn infer_area_for_cumulative_metrics() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "day",
+            "y": "cumulative_revenue",
+            "columns": ["day", "cumulative_revenue"],
+            "rows": [["2025-01-01", 10], ["2025-01-02", 30]]
+        }"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.infer_chart_type(), ChartType::Area);
+        data.y_field = "revenue".to_string();
+        assert_eq!(data.infer_chart_type(), ChartType::Line);
+        data.chart_type = Some("area".to_string());
+        assert_eq!(data.infer_chart_type(), ChartType::Area);
+    }
+
+    #[test]
+    fn histogram_bins_values() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "id",
+            "y": "latency",
+            "columns": ["id", "latency"],
+            "rows": [[1, 0], [2, 1], [3, 4], [4, 5], [5, 10], [6, null]],
+            "chart_type": "histogram",
+            "bins": 2
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.infer_chart_type(), ChartType::Histogram);
+        let bins = data.histogram();
+        assert_eq!(bins.len(), 2);
+        assert_eq!((bins[0].start, bins[0].end, bins[0].count), (0.0, 5.0, 3));
+        assert_eq!((bins[1].start, bins[1].end, bins[1].count), (5.0, 10.0, 2));
+        assert!(bins[1].contains(10.0, true));
+        assert!(!bins[0].contains(5.0, false));
+    }
+
+    #[test]
+    fn histogram_single_value_collapses_to_one_bin() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "id",
+            "y": "v",
+            "columns": ["id", "v"],
+            "rows": [[1, 7], [2, 7]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        let bins = data.histogram();
+        assert_eq!(bins, vec![HistogramBin { start: 7.0, end: 7.0, count: 2 }]);
+    }
+
+    #[test]
+    fn box_stats_computed_per_category() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "endpoint",
+            "y": "latency",
+            "columns": ["endpoint", "latency"],
+            "rows": [["/a", 1], ["/a", 2], ["/a", 3], ["/a", 4], ["/a", 5], ["/b", 10]],
+            "chart_type": "box"
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.infer_chart_type(), ChartType::BoxPlot);
+        let stats = data.box_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].label, "/a");
+        assert_eq!(
+            (stats[0].min, stats[0].q1, stats[0].median, stats[0].q3, stats[0].max),
+            (1.0, 2.0, 3.0, 4.0, 5.0)
+        );
+        assert_eq!(stats[0].count, Some(5));
+        assert_eq!((stats[1].min, stats[1].median, stats[1].max), (10.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn box_stats_read_pre_aggregated_columns() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "region",
+            "y": "price_median",
+            "columns": ["region", "price_min", "price_p25", "price_median", "price_p75", "price_max"],
+            "rows": [["east", 1, 2, 3, 4, 5]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        let stats = data.box_stats();
+        assert_eq!(
+            stats,
+            vec![BoxStats {
+                label: "east".to_string(),
+                min: 1.0,
+                q1: 2.0,
+                median: 3.0,
+                q3: 4.0,
+                max: 5.0,
+                count: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn stacked_bar_chart_type_sets_bar_mode() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "month",
+            "y": "revenue",
+            "columns": ["month", "revenue"],
+            "rows": [],
+            "chart_type": "stacked_bar"
+        }"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.infer_chart_type(), ChartType::Bar);
+        assert_eq!(data.default_bar_mode(), BarMode::Stacked);
+        assert_eq!(BarMode::Stacked.toggle(), BarMode::Grouped);
+        data.chart_type = Some("bar".to_string());
+        assert_eq!(data.default_bar_mode(), BarMode::Grouped);
+    }
+
+    #[test]
+    fn y2_field_resolves_secondary_column() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "month",
+            "y": "revenue",
+            "y2": "conversion_rate",
+            "columns": ["month", "revenue", "conversion_rate"],
+            "rows": [["2025-01", 100, 0.25]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.get_y2_index(), Some(2));
+        assert_eq!(data.get_y2_value(&data.rows[0]), Some(0.25));
+
+        let mut data = data;
+        data.y2_field = None;
+        assert_eq!(data.get_y2_value(&data.rows[0]), None);
+    }
+
+    #[test]
+    fn hbar_chart_type_sets_orientation() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "customer",
+            "y": "revenue",
+            "columns": ["customer", "revenue"],
+            "rows": [],
+            "chart_type": "hbar"
+        }"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.infer_chart_type(), ChartType::Bar);
+        assert_eq!(data.default_bar_orientation(), BarOrientation::Horizontal);
+        data.chart_type = None;
+        assert_eq!(data.default_bar_orientation(), BarOrientation::Vertical);
+    }
+
+    #[test]
+    fn annotation_rows_match_x_values() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "month",
+            "y": "revenue",
+            "columns": ["month", "revenue"],
+            "rows": [["2025-01", 10], ["2025-02", 20], ["2025-03", 30]],
+            "annotations": [
+                {"x": "2025-03", "label": "incident"},
+                {"x": "2024-12", "label": "out of range"},
+                {"x": "2025-02", "label": "pricing change"}
+            ]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        let marks: Vec<(usize, usize, &str)> = data
+            .annotation_rows()
+            .into_iter()
+            .map(|(n, row, a)| (n, row, a.label.as_str()))
+            .collect();
+        assert_eq!(marks, vec![(1, 2, "incident"), (3, 1, "pricing change")]);
+    }
+
+    #[test]
+    fn exceeded_threshold_picks_highest_crossed() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "day",
+            "y": "latency_ms",
+            "columns": ["day", "latency_ms"],
+            "rows": [["2025-01-01", 120], ["2025-01-02", 480]],
+            "thresholds": [
+                {"value": 200, "label": "warn", "color": "yellow"},
+                {"value": 400, "label": "SLA"}
+            ]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert!(data.exceeded_threshold(150.0).is_none());
+        assert_eq!(data.exceeded_threshold(250.0).unwrap().label.as_deref(), Some("warn"));
+        assert_eq!(data.exceeded_threshold(480.0).unwrap().label.as_deref(), Some("SLA"));
+        assert!(data.exceeded_threshold(400.0).unwrap().value < 400.0);
+        assert_eq!(data.y_range_with_thresholds(), (120.0, 480.0));
+    }
+
+    #[test]
+    fn null_y_values_stay_out_of_the_range() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "day",
+            "y": "orders",
+            "columns": ["day", "orders"],
+            "rows": [["2025-01-01", 40], ["2025-01-02", null], ["2025-01-03", 25]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.y_value(&data.rows[1]), None);
+        assert_eq!(data.y_value(&data.rows[2]), Some(25.0));
+        assert_eq!(data.y_range_with_thresholds(), (25.0, 40.0));
+    }
+
+    #[test]
+    fn display_order_sorts_without_touching_rows() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "day",
+            "y": "orders",
+            "chart_type": "bar",
+            "columns": ["day", "orders"],
+            "rows": [["2025-01-04", 7], ["2025-01-03", null], ["2025-01-02", 9], ["2025-01-01", 7]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert!(data.is_sortable());
+        assert_eq!(data.display_order(ChartSort::Original), vec![3, 2, 1, 0]);
+        // Ties stay oldest first; the null goes last either way
+        assert_eq!(data.display_order(ChartSort::Ascending), vec![3, 0, 2, 1]);
+        assert_eq!(data.display_order(ChartSort::Descending), vec![2, 3, 0, 1]);
+        assert_eq!(data.get_x_value(&data.rows[0]), "2025-01-04");
+    }
+
+    #[test]
+    fn x_range_follows_axis_order() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "day",
+            "y": "orders",
+            "columns": ["day", "orders"],
+            "rows": [["2025-01-04", 4], ["2025-01-03", 3], ["2025-01-02", 2], ["2025-01-01", 1]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert!(data.is_reverse_sorted());
+        // Newest-first rows: row 0 is the latest day, so the span runs from row 2 to row 0
+        let range = data.x_range(0, 2);
+        assert_eq!((range.start, range.end), (2, 0));
+        assert_eq!(range.rows, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn x_range_spans_all_series() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "month",
+            "y": "revenue",
+            "series": "region",
+            "columns": ["month", "region", "revenue"],
+            "rows": [
+                ["2025-01", "east", 1], ["2025-01", "west", 2],
+                ["2025-02", "east", 3], ["2025-02", "west", 4],
+                ["2025-03", "east", 5], ["2025-03", "west", 6]
+            ]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        let range = data.x_range(3, 0);
+        assert_eq!((range.start, range.end), (0, 3));
+        assert_eq!(range.rows, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn distinct_x_values_sort_numerically() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "bucket",
+            "y": "n",
+            "columns": ["bucket", "n"],
+            "rows": [[10, 1], [9, 2], [100, 3], [9, 4]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.distinct_x_values(), vec!["9", "10", "100"]);
+    }
+
     #[test]
     fn ensure_timestamp_preserves_existing() {
         let json = r#"{
@@ -333,4 +1752,176 @@ mod tests {
         data.ensure_timestamp();
         assert_eq!(data.timestamp, Some(1234567890000));
     }
+
+    #[test]
+    fn edited_query_keeps_axes_it_still_has() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT month, region, revenue FROM sales",
+            "x": "month",
+            "y": "revenue",
+            "series": "region",
+            "columns": ["month", "region", "revenue"],
+            "rows": [["2025-01", "East", 100]]
+        }"#;
+        let mut data: ChartData = serde_json::from_str(json).unwrap();
+        let columns = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        data.apply_edited_query("SELECT month, revenue FROM sales", columns(&["month", "revenue"]), vec![vec!["2025-01".into(), 5.into()]]);
+        assert_eq!((data.x_field.as_str(), data.y_field.as_str()), ("month", "revenue"));
+        assert_eq!(data.series_field, None);
+        assert_eq!(data.original_query.as_deref(), Some("SELECT month, region, revenue FROM sales"));
+
+        data.apply_edited_query("SELECT day, orders FROM sales", columns(&["day", "orders"]), vec![vec!["Mon".into(), 3.into()]]);
+        assert_eq!((data.x_field.as_str(), data.y_field.as_str()), ("day", "orders"));
+        assert_eq!(data.original_query.as_deref(), Some("SELECT month, region, revenue FROM sales"));
+
+        data.apply_edited_query("SELECT month, region, revenue FROM sales", columns(&["month", "region", "revenue"]), Vec::new());
+        assert_eq!(data.original_query, None);
+    }
+
+    #[test]
+    fn matching_rows_searches_every_column() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "month",
+            "y": "revenue",
+            "columns": ["month", "region", "revenue"],
+            "rows": [["2025-01", "East", 100], ["2025-01", "West", 250], ["2025-02", "east", null]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.matching_rows("EAST"), vec![0, 2]);
+        assert_eq!(data.matching_rows("25"), vec![0, 1, 2]);
+        assert_eq!(data.matching_rows("250"), vec![1]);
+        assert!(data.matching_rows("north").is_empty());
+        assert_eq!(data.matching_x_rows("-01"), vec![0, 1]);
+        assert!(data.matching_x_rows("east").is_empty());
+    }
+
+    #[test]
+    fn history_entry_matches_title_database_fields_and_tags() {
+        let entry = HistoryEntry {
+            path: PathBuf::from("1.json"),
+            title: "Revenue by Month".to_string(),
+            timestamp: 0,
+            row_count: 12,
+            chart_type: None,
+            database: Some("shop".to_string()),
+            x_field: "order_month".to_string(),
+            y_field: "total".to_string(),
+            meta: HistoryMeta { pinned: false, tags: vec!["incident-2024-06".to_string()] },
+        };
+        assert!(entry.matches("REVENUE"));
+        assert!(entry.matches("2024-06"));
+        assert!(entry.matches("#Incident"));
+        assert!(!entry.matches("#shop"));
+        assert!(entry.matches("shop"));
+        assert!(entry.matches("order_"));
+        assert!(entry.matches("tot"));
+        assert!(!entry.matches("refunds"));
+    }
+
+    #[test]
+    fn sort_history_puts_pinned_first() {
+        let entry = |timestamp, pinned| HistoryEntry {
+            path: PathBuf::from(format!("{}.json", timestamp)),
+            title: String::new(),
+            timestamp,
+            row_count: 0,
+            chart_type: None,
+            database: None,
+            x_field: String::new(),
+            y_field: String::new(),
+            meta: HistoryMeta { pinned, tags: Vec::new() },
+        };
+        let mut history = vec![entry(1, false), entry(2, true), entry(3, false), entry(0, true)];
+        sort_history(&mut history);
+        let order: Vec<u64> = history.iter().map(|e| e.timestamp).collect();
+        assert_eq!(order, vec![2, 0, 3, 1]);
+    }
+
+    #[test]
+    fn parse_tags_splits_and_dedupes() {
+        assert_eq!(parse_tags("revenue, #churn  revenue,,q3"), vec!["revenue", "churn", "q3"]);
+        assert!(parse_tags(" , # ").is_empty());
+    }
+
+    #[test]
+    fn column_stats_skip_nulls_and_text() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "month",
+            "y": "revenue",
+            "columns": ["month", "region", "revenue"],
+            "rows": [["2025-01", "east", 100], ["2025-02", "west", null], ["2025-03", "east", 50], ["2025-04", "west", 30]]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        let stats = data.column_stats(&[0, 1, 2]);
+        assert_eq!(stats[0], None);
+        assert_eq!(stats[1], None);
+        assert_eq!(
+            stats[2],
+            Some(ColumnStats { sum: 150.0, avg: 75.0, min: 50.0, max: 100.0 })
+        );
+        assert_eq!(data.column_stats(&[1])[2], None);
+    }
+
+    #[test]
+    fn column_profile_counts_nulls_and_distinct_values() {
+        let data = ExplainData {
+            title: "Rows".to_string(),
+            response_to_command: None,
+            columns: vec!["status".to_string(), "amount".to_string()],
+            rows: vec![
+                vec![serde_json::json!("paid"), serde_json::json!(10)],
+                vec![serde_json::json!("open"), serde_json::json!(null)],
+                vec![serde_json::json!("paid"), serde_json::json!(2.5)],
+            ],
+            total_count: None,
+        };
+        let amount = data.column_profile(1, &[0, 1, 2]);
+        assert_eq!((amount.count, amount.nulls, amount.distinct), (2, 1, 2));
+        assert_eq!(amount.min, Some(serde_json::json!(2.5)));
+        assert_eq!(amount.max, Some(serde_json::json!(10)));
+        assert_eq!(amount.mean, Some(6.25));
+
+        let status = data.column_profile(0, &[0, 2]);
+        assert_eq!((status.count, status.distinct), (2, 1));
+        assert_eq!(status.min, Some(serde_json::json!("paid")));
+        assert_eq!(status.mean, None);
+    }
+
+    #[test]
+    fn attachments_accept_paths_and_specs() {
+        let json = r#"{
+            "title": "Test",
+            "query": "SELECT 1",
+            "x": "month",
+            "y": "revenue",
+            "columns": ["month", "revenue"],
+            "rows": [],
+            "attach": ["md:sales", {"path": "~/ref/o'neil.duckdb", "alias": "ref", "read_only": true}]
+        }"#;
+        let data: ChartData = serde_json::from_str(json).unwrap();
+        let sql: Vec<String> = data.attach.iter().map(Attachment::attach_sql).collect();
+        assert_eq!(
+            sql,
+            vec![
+                "ATTACH IF NOT EXISTS 'md:sales'".to_string(),
+                "ATTACH IF NOT EXISTS '~/ref/o''neil.duckdb' AS \"ref\" (READ_ONLY)".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn nice_ticks_land_on_round_numbers() {
+        assert_eq!(nice_ticks(0.0, 97.0, 6), vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0]);
+        assert_eq!(nice_ticks(3.2, 8.9, 4), vec![0.0, 5.0, 10.0]);
+        assert_eq!(nice_ticks(-150.0, 420.0, 8), vec![-200.0, -100.0, 0.0, 100.0, 200.0, 300.0, 400.0, 500.0]);
+        assert_eq!(nice_ticks(5.0, 5.0, 4), vec![5.0, 5.0]);
+        assert_eq!(nice_ticks(-1662.0, 282.0, 2), vec![-2000.0, 0.0, 2000.0]);
+        assert!(nice_ticks(0.0, 1.0, 11).iter().all(|t| (t * 10.0).fract().abs() < 1e-9));
+    }
 }
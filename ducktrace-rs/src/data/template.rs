@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+
+use super::model::value_to_string;
+
+/// A drill-down query with its `{{placeholder}}` values pulled out as bound parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundQuery {
+    /// SQL with a `?` for each bound value
+    pub sql: String,
+    /// Values for the `?` markers, in order
+    pub params: Vec<serde_json::Value>,
+}
+
+impl BoundQuery {
+    /// The SQL with each `?` replaced by its value as a literal, for copying the
+    /// query to run elsewhere. Text is quoted with `'` doubled; `?` inside string
+    /// literals and comments is left alone.
+    pub fn inline_params(&self) -> String {
+        let mut sql = String::with_capacity(self.sql.len());
+        let mut params = self.params.iter();
+        let mut chars = self.sql.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\'' => {
+                    sql.push(ch);
+                    while let Some(c) = chars.next() {
+                        sql.push(c);
+                        if c == '\'' {
+                            if chars.peek() == Some(&'\'') {
+                                sql.push(chars.next().unwrap_or_default());
+                            } else {
+                                break;
+                            }
+                        }
+                    }
+                }
+                '-' if chars.peek() == Some(&'-') => {
+                    sql.push(ch);
+                    for c in chars.by_ref() {
+                        sql.push(c);
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                '?' => match params.next() {
+                    Some(value) => sql.push_str(&sql_literal(value)),
+                    None => sql.push(ch),
+                },
+                _ => sql.push(ch),
+            }
+        }
+        sql
+    }
+}
+
+/// A JSON value as a SQL literal
+fn sql_literal(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "NULL".to_string(),
+        serde_json::Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        other => format!("'{}'", value_to_string(other).replace('\'', "''")),
+    }
+}
+
+/// Turn `{{name}}` placeholders with a value in `values` into `?` parameters.
+///
+/// A placeholder that is the whole of a string literal (`'{{x}}'`) becomes a bare `?`
+/// bound as text; one inside a longer literal (`'%{{x}}%'`) becomes a concatenation
+/// (`('%' || ? || '%')`). Unquoted placeholders bind the value with its JSON type.
+/// Placeholders without a value, and any in `--` comments, are left in the SQL
+/// untouched.
+pub fn bind_placeholders(template: &str, values: &HashMap<String, serde_json::Value>) -> BoundQuery {
+    let mut sql = String::with_capacity(template.len());
+    let mut params = Vec::new();
+    let mut rest = template;
+
+    while !rest.is_empty() {
+        if let Some(after_quote) = rest.strip_prefix('\'') {
+            let (literal, remainder) = split_literal(after_quote);
+            bind_literal(literal, values, &mut sql, &mut params);
+            rest = remainder;
+        } else if rest.starts_with("--") {
+            let end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+            sql.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if let Some((name, remainder)) = placeholder_at(rest) {
+            match values.get(name) {
+                Some(value) => {
+                    sql.push('?');
+                    params.push(value.clone());
+                }
+                None => sql.push_str(&rest[..rest.len() - remainder.len()]),
+            }
+            rest = remainder;
+        } else {
+            let ch = rest.chars().next().unwrap_or_default();
+            sql.push(ch);
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+
+    BoundQuery { sql, params }
+}
+
+/// Names of the `{{placeholder}}`s in `template`, inside literals or not, in order of
+/// first use
+pub fn placeholder_names(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start..];
+        match placeholder_at(rest) {
+            Some((name, remainder)) => {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+                rest = remainder;
+            }
+            None => rest = &rest[1..],
+        }
+    }
+    names
+}
+
+/// A `count(*)` over a drill-down query with its top-level `LIMIT` removed
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountQuery {
+    /// Counting SQL; it takes the same parameters as the original query
+    pub sql: String,
+    /// The original query's row limit
+    pub limit: usize,
+}
+
+/// Build the companion count for a query ending in `LIMIT n`, so the full number of
+/// matching rows can be shown next to the limited result. Returns None when there
+/// is no top-level limit, or when it has an `OFFSET` or a bound parameter.
+pub fn count_query(sql: &str) -> Option<CountQuery> {
+    let start = top_level_limit(sql)?;
+    let clause = sql[start + "LIMIT".len()..].trim().trim_end_matches(';').trim_end();
+    let limit = clause.parse::<usize>().ok()?;
+    let inner = sql[..start].trim_end();
+    Some(CountQuery {
+        sql: format!("SELECT count(*) FROM ({}) AS limited", inner),
+        limit,
+    })
+}
+
+/// The page of a query ending in `LIMIT n` that starts `offset` rows in, for
+/// fetching more of a drill-down than its limit. Returns None for the same queries
+/// [`count_query`] does.
+pub fn page_query(sql: &str, offset: usize) -> Option<String> {
+    let count = count_query(sql)?;
+    let inner = sql[..top_level_limit(sql)?].trim_end();
+    Some(format!("{} LIMIT {} OFFSET {}", inner, count.limit, offset))
+}
+
+/// Byte offset of the last `LIMIT` keyword outside parentheses, literals, and comments
+fn top_level_limit(sql: &str) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    let mut depth = 0usize;
+    let mut found = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' => {
+                let quote = bytes[i];
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b'l' | b'L' if depth == 0 => {
+                let word_start = i == 0 || !is_word_byte(bytes[i - 1]);
+                let word = bytes.get(i..i + 5).is_some_and(|w| w.eq_ignore_ascii_case(b"LIMIT"));
+                let word_end = bytes.get(i + 5).is_none_or(|b| !is_word_byte(*b));
+                if word_start && word && word_end {
+                    found = Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    found
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Split at the closing quote of a literal (`''` is an escaped quote): (contents, after)
+fn split_literal(s: &str) -> (&str, &str) {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\'' {
+            if bytes.get(i + 1) == Some(&b'\'') {
+                i += 2;
+                continue;
+            }
+            return (&s[..i], &s[i + 1..]);
+        }
+        i += 1;
+    }
+    // Unterminated literal: take everything
+    (s, "")
+}
+
+/// The placeholder name at the start of `s`, and the text after it
+fn placeholder_at(s: &str) -> Option<(&str, &str)> {
+    let inner = s.strip_prefix("{{")?;
+    let end = inner.find("}}")?;
+    let name = &inner[..end];
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((name, &inner[end + 2..]))
+}
+
+/// Emit a string literal, binding any placeholders inside it as text
+fn bind_literal(
+    literal: &str,
+    values: &HashMap<String, serde_json::Value>,
+    sql: &mut String,
+    params: &mut Vec<serde_json::Value>,
+) {
+    // Pieces of literal text, with None marking a bound parameter
+    let mut pieces: Vec<Option<String>> = Vec::new();
+    let mut text = String::new();
+    let mut rest = literal;
+    while !rest.is_empty() {
+        if let Some((name, remainder)) = placeholder_at(rest) {
+            if let Some(value) = values.get(name) {
+                pieces.push(Some(std::mem::take(&mut text)));
+                pieces.push(None);
+                params.push(serde_json::Value::String(value_to_string(value)));
+                rest = remainder;
+                continue;
+            }
+        }
+        let ch = rest.chars().next().unwrap_or_default();
+        text.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    if pieces.is_empty() {
+        sql.push('\'');
+        sql.push_str(&text);
+        sql.push('\'');
+        return;
+    }
+    pieces.push(Some(text));
+
+    let parts: Vec<String> = pieces
+        .into_iter()
+        .filter(|p| p.as_deref() != Some(""))
+        .map(|p| match p {
+            Some(text) => format!("'{}'", text),
+            None => "?".to_string(),
+        })
+        .collect();
+    if parts.len() == 1 {
+        sql.push_str(&parts[0]);
+    } else {
+        sql.push('(');
+        sql.push_str(&parts.join(" || "));
+        sql.push(')');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn values(pairs: &[(&str, serde_json::Value)]) -> HashMap<String, serde_json::Value> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[test]
+    fn quoted_placeholder_binds_as_text() {
+        let bound = bind_placeholders(
+            "SELECT * FROM t WHERE month = '{{x}}' AND amount > {{y}}",
+            &values(&[("x", json!("2025-01")), ("y", json!(100))]),
+        );
+        assert_eq!(bound.sql, "SELECT * FROM t WHERE month = ? AND amount > ?");
+        assert_eq!(bound.params, vec![json!("2025-01"), json!(100)]);
+    }
+
+    #[test]
+    fn placeholder_inside_literal_concatenates() {
+        let bound = bind_placeholders(
+            "SELECT * FROM t WHERE name LIKE '%{{x}}%' AND note = 'it''s'",
+            &values(&[("x", json!("O'Brien"))]),
+        );
+        assert_eq!(bound.sql, "SELECT * FROM t WHERE name LIKE ('%' || ? || '%') AND note = 'it''s'");
+        assert_eq!(bound.params, vec![json!("O'Brien")]);
+    }
+
+    #[test]
+    fn injection_stays_a_parameter() {
+        let attack = "x'; DROP TABLE t; --";
+        let bound = bind_placeholders("SELECT * FROM t WHERE c = '{{x}}'", &values(&[("x", json!(attack))]));
+        assert_eq!(bound.sql, "SELECT * FROM t WHERE c = ?");
+        assert_eq!(bound.params, vec![json!(attack)]);
+    }
+
+    #[test]
+    fn inline_params_writes_literals() {
+        let bound = bind_placeholders(
+            "SELECT '?' AS q, * FROM t WHERE name LIKE '%{{x}}%' AND n > {{y}} -- why?\nAND ok = {{z}}",
+            &values(&[("x", json!("O'Brien")), ("y", json!(2.5)), ("z", json!(true))]),
+        );
+        assert_eq!(
+            bound.inline_params(),
+            "SELECT '?' AS q, * FROM t WHERE name LIKE ('%' || 'O''Brien' || '%') AND n > 2.5 -- why?\nAND ok = TRUE"
+        );
+    }
+
+    #[test]
+    fn count_query_strips_top_level_limit() {
+        let count = count_query("SELECT * FROM t WHERE c = ? ORDER BY d LIMIT 100;").unwrap();
+        assert_eq!(count.sql, "SELECT count(*) FROM (SELECT * FROM t WHERE c = ? ORDER BY d) AS limited");
+        assert_eq!(count.limit, 100);
+
+        // Limits inside subqueries and literals don't count
+        assert_eq!(count_query("SELECT * FROM (SELECT * FROM t LIMIT 5)"), None);
+        assert_eq!(count_query("SELECT 'no limit 5' AS note"), None);
+        assert_eq!(count_query("SELECT * FROM t LIMIT 10 OFFSET 20"), None);
+        assert_eq!(count_query("SELECT * FROM t LIMIT ?"), None);
+        assert_eq!(count_query("SELECT rate_limit FROM t"), None);
+    }
+
+    #[test]
+    fn page_query_adds_offset() {
+        assert_eq!(
+            page_query("SELECT * FROM t WHERE c = ? ORDER BY d LIMIT 100;", 200).as_deref(),
+            Some("SELECT * FROM t WHERE c = ? ORDER BY d LIMIT 100 OFFSET 200")
+        );
+        assert_eq!(page_query("SELECT * FROM t LIMIT 10 OFFSET 20", 10), None);
+        assert_eq!(page_query("SELECT * FROM t", 10), None);
+    }
+
+    #[test]
+    fn commented_placeholders_are_not_bound() {
+        let bound = bind_placeholders(
+            "SELECT * FROM t WHERE a = {{x}} -- AND b = {{y}}\nAND note = '--{{y}}'",
+            &values(&[("x", json!(1)), ("y", json!("b"))]),
+        );
+        assert_eq!(bound.sql, "SELECT * FROM t WHERE a = ? -- AND b = {{y}}\nAND note = ('--' || ?)");
+        assert_eq!(bound.params, vec![json!(1), json!("b")]);
+    }
+
+    #[test]
+    fn unknown_placeholders_are_left_alone() {
+        let bound = bind_placeholders("SELECT '{{other}}', {{missing}}", &values(&[]));
+        assert_eq!(bound.sql, "SELECT '{{other}}', {{missing}}");
+        assert!(bound.params.is_empty());
+    }
+
+    #[test]
+    fn placeholder_names_are_unique_and_ordered() {
+        let names = placeholder_names(
+            "SELECT * FROM {{database}}.orders WHERE day = {{x}} AND name LIKE '%{{x}}%' \
+             AND region = '{{region}}' AND note = '{{ not a placeholder }}' AND t = {{{y}}",
+        );
+        assert_eq!(names, vec!["database", "x", "region", "y"]);
+        assert!(placeholder_names("SELECT 1").is_empty());
+    }
+}
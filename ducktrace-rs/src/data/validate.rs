@@ -0,0 +1,285 @@
+use serde_json::Value;
+
+use super::format::ColumnFormat;
+
+/// What a chart field has to hold
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Text,
+    /// A whole number zero or above
+    Count,
+    /// A list of column names
+    Names,
+    /// A list of rows, each a list of values
+    Rows,
+    Object,
+    List,
+}
+
+/// The chart file schema: each field's accepted names (the first is the one
+/// shown in problems), whether it's required, and what it holds
+const FIELDS: &[(&[&str], bool, Kind)] = &[
+    (&["title"], true, Kind::Text),
+    (&["query"], true, Kind::Text),
+    (&["x", "xField"], true, Kind::Text),
+    (&["y", "yField"], true, Kind::Text),
+    (&["y2", "y2Field", "y2_field"], false, Kind::Text),
+    (&["series", "seriesField", "series_field"], false, Kind::Text),
+    (&["columns"], true, Kind::Names),
+    (&["rows"], true, Kind::Rows),
+    (&["chartType", "chart_type"], false, Kind::Text),
+    (&["status"], false, Kind::Text),
+    (&["errorMessage"], false, Kind::Text),
+    (&["truncatedFrom"], false, Kind::Count),
+    (&["maxRows", "max_rows"], false, Kind::Count),
+    (&["drillDown", "drill_down"], false, Kind::Object),
+    (&["lineage"], false, Kind::Object),
+    (&["explainData"], false, Kind::Object),
+    (&["bins"], false, Kind::Count),
+    (&["annotations"], false, Kind::List),
+    (&["thresholds"], false, Kind::List),
+    (&["palette"], false, Kind::Object),
+    (&["formats"], false, Kind::Object),
+    (&["database"], false, Kind::Text),
+    (&["databasePath"], false, Kind::Text),
+    (&["attach"], false, Kind::List),
+    (&["timestamp"], false, Kind::Count),
+];
+
+/// Rows listed individually before the rest are summed up
+const MAX_ROW_PROBLEMS: usize = 3;
+
+/// Check a chart document against the chart file schema, returning a readable
+/// line per problem: missing fields, values of the wrong type, and rows whose
+/// length doesn't match `columns`. Empty when the chart is fine.
+pub fn validate_chart(document: &Value) -> Vec<String> {
+    let Some(fields) = document.as_object() else {
+        return vec![format!("the chart should be a JSON object, not {}", describe(document))];
+    };
+
+    let mut problems = Vec::new();
+    for &(names, required, kind) in FIELDS {
+        let given: Vec<&str> = names.iter().copied().filter(|name| fields.contains_key(*name)).collect();
+        let name = names[0];
+        match given.as_slice() {
+            [] if required => problems.push(format!("missing `{}`", name)),
+            [] => {}
+            [field] => {
+                let value = &fields[*field];
+                // Optional fields other than lists can be null
+                let allowed = holds(value, kind) || (value.is_null() && !required && kind != Kind::List);
+                if !allowed {
+                    problems.push(format!("`{}` should be {}, not {}", field, expected(kind), describe(value)));
+                }
+            }
+            _ => problems.push(format!("`{}` is given more than once (as {})", name, quoted(&given))),
+        }
+    }
+
+    if let (Some(Value::Array(columns)), Some(Value::Array(rows))) = (fields.get("columns"), fields.get("rows")) {
+        let mismatched: Vec<(usize, usize)> = rows
+            .iter()
+            .enumerate()
+            .filter_map(|(i, row)| row.as_array().map(|r| (i + 1, r.len())))
+            .filter(|&(_, len)| len != columns.len())
+            .collect();
+        for &(row, len) in mismatched.iter().take(MAX_ROW_PROBLEMS) {
+            let values = if len == 1 { "value" } else { "values" };
+            problems.push(format!("row {} has {} {} but there are {} columns", row, len, values, columns.len()));
+        }
+        if mismatched.len() > MAX_ROW_PROBLEMS {
+            problems.push(format!("…and {} more rows of the wrong length", mismatched.len() - MAX_ROW_PROBLEMS));
+        }
+    }
+
+    if let Some(drill) = fields.get("drillDown").or_else(|| fields.get("drill_down")).and_then(Value::as_object) {
+        let template = ["queryTemplate", "query_template"].iter().find_map(|name| drill.get(*name));
+        match template {
+            None => problems.push("`drillDown` is missing `queryTemplate`".to_string()),
+            Some(value) if !value.is_string() => {
+                problems.push(format!("`drillDown.queryTemplate` should be text, not {}", describe(value)));
+            }
+            _ => {}
+        }
+        if !drill.get("description").is_some_and(Value::is_string) {
+            problems.push("`drillDown` needs a `description` (text)".to_string());
+        }
+    }
+    for (column, format) in fields.get("formats").and_then(Value::as_object).into_iter().flatten() {
+        match format.as_str().map(str::parse::<ColumnFormat>) {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => problems.push(format!("`formats.{}`: {}", column, e)),
+            None => problems.push(format!("`formats.{}` should be text, not {}", column, describe(format))),
+        }
+    }
+    for (i, threshold) in list_items(fields.get("thresholds")) {
+        if !threshold.get("value").is_some_and(Value::is_number) {
+            problems.push(format!("threshold {} needs a numeric `value`", i));
+        }
+    }
+    for (i, annotation) in list_items(fields.get("annotations")) {
+        if annotation.get("x").is_none() || !annotation.get("label").is_some_and(Value::is_string) {
+            problems.push(format!("annotation {} needs an `x` and a `label`", i));
+        }
+    }
+    problems
+}
+
+/// The items of a list field, numbered from 1
+fn list_items(value: Option<&Value>) -> impl Iterator<Item = (usize, &Value)> {
+    value.and_then(Value::as_array).into_iter().flatten().enumerate().map(|(i, item)| (i + 1, item))
+}
+
+fn holds(value: &Value, kind: Kind) -> bool {
+    match kind {
+        Kind::Text => value.is_string(),
+        Kind::Count => value.is_u64(),
+        Kind::Names => value.as_array().is_some_and(|names| names.iter().all(Value::is_string)),
+        Kind::Rows => value.as_array().is_some_and(|rows| rows.iter().all(Value::is_array)),
+        Kind::Object => value.is_object(),
+        Kind::List => value.is_array(),
+    }
+}
+
+fn expected(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Text => "text",
+        Kind::Count => "a whole number",
+        Kind::Names => "a list of column names",
+        Kind::Rows => "a list of rows, each a list of values",
+        Kind::Object => "an object",
+        Kind::List => "a list",
+    }
+}
+
+/// A value's type, for problems, e.g. "a number (42)"
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => format!("a boolean ({})", b),
+        Value::Number(n) => format!("a number ({})", n),
+        Value::String(s) => format!("text ({:?})", s),
+        Value::Array(items) => format!("a list of {}", items.len()),
+        Value::Object(_) => "an object".to_string(),
+    }
+}
+
+fn quoted(names: &[&str]) -> String {
+    names.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(" and ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::model::{
+        Annotation, Attachment, ChartData, ColorOverrides, DrillDown, ExplainData, Lineage, Threshold,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn valid_chart_has_no_problems() {
+        let chart = json!({
+            "title": "Revenue", "query": "SELECT 1", "x": "month", "yField": "revenue",
+            "columns": ["month", "revenue"], "rows": [["2025-01", 10], ["2025-02", null]],
+            "chartType": null, "thresholds": [{"value": 5}], "formats": {"revenue": "currency"},
+        });
+        assert!(validate_chart(&chart).is_empty());
+    }
+
+    #[test]
+    fn problems_name_the_field_and_row() {
+        let chart = json!({
+            "title": 7, "query": "SELECT 1", "x": "month", "xField": "month",
+            "columns": ["month", "revenue"], "rows": [["2025-01", 10], ["2025-02"], ["a", 1, 2]],
+            "maxRows": -1, "drillDown": {"description": "rows"}, "formats": {"revenue": "money"},
+        });
+        assert_eq!(
+            validate_chart(&chart),
+            vec![
+                "`title` should be text, not a number (7)",
+                "`x` is given more than once (as `x` and `xField`)",
+                "missing `y`",
+                "`maxRows` should be a whole number, not a number (-1)",
+                "row 2 has 1 value but there are 2 columns",
+                "row 3 has 3 values but there are 2 columns",
+                "`drillDown` is missing `queryTemplate`",
+                "`formats.revenue`: unknown format `money` (expected number, integer, currency, percent, duration, bytes, bytes:si, or date:<pattern>)",
+            ]
+        );
+        assert_eq!(validate_chart(&json!([])), vec!["the chart should be a JSON object, not a list of 0"]);
+    }
+
+    /// Every field set, written as a struct literal so a new `ChartData` field
+    /// fails to compile here until `FIELDS` is checked for it
+    fn full_chart() -> ChartData {
+        let text = |s: &str| Some(s.to_string());
+        ChartData {
+            title: "Revenue".into(),
+            query: "SELECT month, revenue, cost, region FROM sales".into(),
+            x_field: "month".into(),
+            y_field: "revenue".into(),
+            y2_field: text("cost"),
+            series_field: text("region"),
+            columns: vec!["month".into(), "revenue".into(), "cost".into(), "region".into()],
+            rows: vec![vec![json!("2025-01"), json!(10.5), json!(4), json!("EU")]],
+            chart_type: text("line"),
+            status: text("success"),
+            error_message: text("none"),
+            truncated_from: Some(5000),
+            max_rows: Some(1000),
+            dropped_rows: Vec::new(),
+            all_rows: false,
+            original_query: None,
+            drill_down: Some(DrillDown {
+                description: "Orders in the month".into(),
+                query_template: "SELECT * FROM orders WHERE month = '{{x}}'".into(),
+                range_query_template: text("SELECT * FROM orders WHERE month BETWEEN '{{x_start}}' AND '{{x_end}}'"),
+                param_mapping: [("x".to_string(), "month".to_string())].into(),
+            }),
+            lineage: Some(Lineage {
+                aggregation: text("SUM"),
+                source_column: text("amount"),
+                source_table: text("orders"),
+                group_by: Some(vec!["month".into()]),
+            }),
+            explain_data: Some(ExplainData {
+                title: "Orders".into(),
+                response_to_command: text("cmd-1"),
+                columns: vec!["id".into()],
+                rows: vec![vec![json!(1)]],
+                total_count: Some(1),
+            }),
+            bins: Some(20),
+            annotations: vec![Annotation { x: json!("2025-01"), label: "launch".into() }],
+            thresholds: vec![Threshold { value: 8.0, label: text("goal"), color: text("#ff0000") }],
+            palette: Some(ColorOverrides {
+                primary: text("blue"),
+                secondary: text("green"),
+                fill: text("cyan"),
+                selection: text("yellow"),
+                negative: text("red"),
+                series: Some(vec!["blue".into(), "magenta".into()]),
+                banner: Some(vec!["#1e66f5".into()]),
+            }),
+            formats: serde_json::from_value(json!({"revenue": "currency", "month": "date:%Y-%m"})).unwrap(),
+            database: text("sales.duckdb"),
+            database_path: text("/data/sales.duckdb"),
+            attach: vec![
+                Attachment::Path("ref.duckdb".into()),
+                Attachment::Spec { path: "md:other".into(), alias: text("other"), read_only: true },
+            ],
+            timestamp: Some(1_735_689_600_000),
+        }
+    }
+
+    #[test]
+    fn serialized_chart_passes() {
+        let document = serde_json::to_value(full_chart()).unwrap();
+        assert!(validate_chart(&document).is_empty(), "{:?}", validate_chart(&document));
+        for key in document.as_object().unwrap().keys() {
+            assert!(FIELDS.iter().any(|(names, _, _)| names.contains(&key.as_str())), "`{}` isn't in FIELDS", key);
+        }
+        let reread: ChartData = serde_json::from_value(document.clone()).unwrap();
+        assert_eq!(serde_json::to_value(reread).unwrap(), document);
+    }
+}
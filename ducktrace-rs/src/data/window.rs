@@ -0,0 +1,27 @@
+use std::ops::Range;
+
+/// The run of at most `capacity` of `len` items to draw, scrolled to keep the one
+/// at `focus` centered where the ends allow
+pub fn scroll_window(len: usize, capacity: usize, focus: usize) -> Range<usize> {
+    let capacity = capacity.max(1);
+    if len <= capacity {
+        return 0..len;
+    }
+    let start = focus.saturating_sub(capacity / 2).min(len - capacity);
+    start..start + capacity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_follows_focus() {
+        assert_eq!(scroll_window(100, 10, 0), 0..10);
+        assert_eq!(scroll_window(100, 10, 50), 45..55);
+        assert_eq!(scroll_window(100, 10, 99), 90..100);
+        assert_eq!(scroll_window(8, 10, 5), 0..8);
+        assert_eq!(scroll_window(10, 10, 9), 0..10);
+        assert_eq!(scroll_window(5, 0, 3), 3..4);
+    }
+}
@@ -1,74 +1,384 @@
-use anyhow::{Context, Result};
-use duckdb::{types::ValueRef, Connection, Row};
-use log::{debug, info};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-/// Query executor that connects to MotherDuck via embedded DuckDB
+use anyhow::{bail, Context, Result};
+use duckdb::{
+    arrow::datatypes::DataType,
+    types::{Value, ValueRef},
+    Connection, InterruptHandle, Row, Statement,
+};
+use log::{debug, info, warn};
+
+use crate::data::{Attachment, BoundQuery};
+use crate::timezone;
+
+/// Connection target for MotherDuck
+pub const MOTHERDUCK: &str = "md:";
+
+/// Rows per batch streamed to the explain overlay while a drill-down runs
+pub const BATCH_ROWS: usize = 200;
+
+/// Connection attempts per request before giving up
+const CONNECT_ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry; doubles on each further attempt
+const CONNECT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Connection status for a database target, surfaced in the status bar
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    Connecting { attempt: u32, max_attempts: u32 },
+    Connected,
+    Failed { error: String },
+}
+
+/// MotherDuck token of the active connection profile, used over `MOTHERDUCK_TOKEN`
+static MOTHERDUCK_TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+/// Connect to MotherDuck with `token` from now on (None for `MOTHERDUCK_TOKEN`).
+/// Connections already open keep their account until [`ConnectionManager::reset`].
+pub fn set_motherduck_token(token: Option<String>) {
+    *MOTHERDUCK_TOKEN.lock().unwrap_or_else(|e| e.into_inner()) = token;
+}
+
+/// Display name for a connection target
+pub fn target_label(target: &str) -> &str {
+    if target == MOTHERDUCK {
+        "MotherDuck"
+    } else {
+        std::path::Path::new(target)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(target)
+    }
+}
+
+/// Executors by database target. Each target connects on first use, retrying with
+/// exponential backoff; a target that failed is retried on its next request.
+#[derive(Default)]
+pub struct ConnectionManager {
+    executors: Mutex<HashMap<String, Arc<QueryExecutor>>>,
+}
+
+impl ConnectionManager {
+    /// The executor for `target`, connecting if needed. `on_state` hears each attempt and
+    /// the outcome; a cancel between attempts abandons the retries.
+    pub fn executor(
+        &self,
+        target: &str,
+        cancel: &QueryCancel,
+        mut on_state: impl FnMut(ConnectionState),
+    ) -> Result<Arc<QueryExecutor>> {
+        if let Some(exec) = self.lock().get(target) {
+            return Ok(exec.clone());
+        }
+
+        let mut delay = CONNECT_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            on_state(ConnectionState::Connecting { attempt, max_attempts: CONNECT_ATTEMPTS });
+            info!("Connecting to {} (attempt {}/{})", target, attempt, CONNECT_ATTEMPTS);
+            match QueryExecutor::connect(target) {
+                Ok(exec) => {
+                    info!("{} connection successful", target);
+                    on_state(ConnectionState::Connected);
+                    let exec = Arc::new(exec);
+                    self.lock().insert(target.to_string(), exec.clone());
+                    return Ok(exec);
+                }
+                Err(e) if attempt < CONNECT_ATTEMPTS && !cancel.is_cancelled() => {
+                    warn!("{} connection failed, retrying in {:?}: {:#}", target, delay, e);
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    on_state(ConnectionState::Failed { error: format!("{:#}", e) });
+                    return Err(e.context(format!("Gave up after {} attempts", attempt)));
+                }
+            }
+        }
+    }
+
+    /// Drop every executor, so the next request for each target connects afresh
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<QueryExecutor>>> {
+        self.executors.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Cancels an in-flight drill-down from another thread via DuckDB's interrupt
+#[derive(Default)]
+pub struct QueryCancel {
+    cancelled: AtomicBool,
+    handle: Mutex<Option<Arc<InterruptHandle>>>,
+}
+
+impl QueryCancel {
+    /// Interrupt the running query, or stop it from starting if it hasn't yet
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            handle.interrupt();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn register(&self, conn: &Connection) -> Result<()> {
+        *self.handle.lock().unwrap_or_else(|e| e.into_inner()) = Some(conn.interrupt_handle());
+        // A cancel that landed before the handle was registered had nothing to interrupt
+        if self.is_cancelled() {
+            bail!("Query cancelled");
+        }
+        Ok(())
+    }
+}
+
+/// Query executor that connects to MotherDuck or a local database via embedded DuckDB.
+/// Opening the database is the slow part, so one connection is kept open and each query
+/// runs on a cheap clone of it.
 pub struct QueryExecutor {
-    _marker: (),
+    target: String,
+    conn: Mutex<Connection>,
 }
 
 impl QueryExecutor {
-    /// Verify MotherDuck connection is possible
-    pub fn connect() -> Result<Self> {
-        debug!("Opening MotherDuck connection for verification");
-        let _conn = Connection::open("md:")
-            .context("Failed to connect to MotherDuck. Ensure MOTHERDUCK_TOKEN is set.")?;
-        debug!("MotherDuck connection verified");
-        Ok(Self { _marker: () })
+    /// Open a connection to `target`: `md:`, a `.duckdb` file path, or `:memory:`
+    pub fn connect(target: &str) -> Result<Self> {
+        let target = expand_home(target);
+        let conn = Self::open(&target)?;
+        Ok(Self { target, conn: Mutex::new(conn) })
     }
 
-    /// Execute a drill-down query and return results as (columns, rows)
+    fn open(target: &str) -> Result<Connection> {
+        debug!("Opening {} connection", target);
+        let token = MOTHERDUCK_TOKEN.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let conn = if let (true, Some(token)) = (target == MOTHERDUCK, token) {
+            let config = duckdb::Config::default().with("motherduck_token", &token)?;
+            Connection::open_with_flags(target, config)
+                .context("Failed to connect to MotherDuck with the profile's token")?
+        } else if target == MOTHERDUCK {
+            Connection::open(target)
+                .context("Failed to connect to MotherDuck. Ensure MOTHERDUCK_TOKEN is set.")?
+        } else {
+            Connection::open(target).with_context(|| format!("Failed to open DuckDB database {}", target))?
+        };
+        debug!("{} connection opened", target);
+        Ok(conn)
+    }
+
+    /// A connection for one query, cloned from the shared one. If the clone fails its
+    /// health check, the shared connection is reopened once before giving up.
+    fn checkout(&self) -> Result<Connection> {
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let healthy = conn
+            .try_clone()
+            .and_then(|c| c.execute_batch("SELECT 1").map(|_| c));
+        match healthy {
+            Ok(c) => Ok(c),
+            Err(e) => {
+                warn!("{} connection unhealthy, reconnecting: {}", self.target, e);
+                *conn = Self::open(&self.target)?;
+                conn.try_clone()
+                    .with_context(|| format!("Failed to connect to {}", self.target))
+            }
+        }
+    }
+
+    /// ATTACH each database not already attached. Attachments live on the shared
+    /// database instance, so every connection checked out afterwards sees them.
+    pub fn ensure_attached(&self, attachments: &[Attachment]) -> Result<()> {
+        if attachments.is_empty() {
+            return Ok(());
+        }
+        let conn = self.checkout()?;
+        for attachment in attachments {
+            let sql = attachment.attach_sql();
+            debug!("{}", sql);
+            conn.execute_batch(&sql).with_context(|| format!("Failed to run {}", sql))?;
+        }
+        Ok(())
+    }
+
+    /// Execute a drill-down query, handing rows to `on_batch` as (columns, rows) every
+    /// [`BATCH_ROWS`] rows; the remainder is returned once the result is exhausted.
+    /// `cancel` can interrupt the query from another thread while it runs.
     pub fn execute_drill_down(
+        &self,
+        query: &BoundQuery,
+        cancel: &QueryCancel,
+        on_batch: impl FnMut(Vec<String>, Vec<Vec<serde_json::Value>>),
+    ) -> Result<(Vec<String>, Vec<Vec<serde_json::Value>>)> {
+        let conn = self.checkout()?;
+        cancel.register(&conn)?;
+        debug!("Connection checked out");
+        Self::stream_rows(&conn, query, cancel, on_batch)
+    }
+
+    /// Run a single-value `count(*)` query, binding the same parameters as its drill-down
+    pub fn count_rows(&self, query: &BoundQuery, cancel: &QueryCancel) -> Result<usize> {
+        let conn = self.checkout()?;
+        cancel.register(&conn)?;
+        debug!("Counting rows: {}", query.sql);
+        let params = query.params.iter().map(json_to_sql);
+        let count: i64 = conn
+            .query_row(&query.sql, duckdb::params_from_iter(params), |row| row.get(0))
+            .context("Failed to count drill-down rows")?;
+        Ok(count.max(0) as usize)
+    }
+
+    /// Plan `query` with `EXPLAIN` without running it, failing on syntax errors and
+    /// unknown tables or columns
+    pub fn validate_query(&self, query: &BoundQuery, cancel: &QueryCancel) -> Result<()> {
+        let conn = self.checkout()?;
+        cancel.register(&conn)?;
+        let sql = format!("EXPLAIN {}", query.sql.trim().trim_end_matches(';'));
+        debug!("Validating: {}", sql);
+        let params = query.params.iter().map(json_to_sql);
+        let mut stmt = conn.prepare(&sql).context("Failed to prepare EXPLAIN")?;
+        let mut rows = stmt.query(duckdb::params_from_iter(params)).context("Failed to run EXPLAIN")?;
+        while rows.next()?.is_some() {}
+        Ok(())
+    }
+
+    /// Run the chart's own query and return all of its (columns, rows), with
+    /// `database` made the default catalog first as for [`Self::explain_analyze`]
+    pub fn run_query(
         &self,
         query: &str,
+        database: Option<&str>,
+        cancel: &QueryCancel,
     ) -> Result<(Vec<String>, Vec<Vec<serde_json::Value>>)> {
-        debug!("Opening fresh MotherDuck connection for query");
-        let conn = Connection::open("md:")
-            .context("Failed to connect to MotherDuck")?;
-        debug!("Connection opened");
+        let conn = self.checkout()?;
+        cancel.register(&conn)?;
+        Self::use_database(&conn, database)?;
+
+        let query = BoundQuery { sql: query.to_string(), params: Vec::new() };
+        let mut rows = Vec::new();
+        let (columns, rest) = Self::stream_rows(&conn, &query, cancel, |_, batch| rows.extend(batch))?;
+        rows.extend(rest);
+        Ok((columns, rows))
+    }
 
+    /// Fetch the rows of `query` on `conn`, see [`Self::execute_drill_down`]
+    fn stream_rows(
+        conn: &Connection,
+        query: &BoundQuery,
+        cancel: &QueryCancel,
+        mut on_batch: impl FnMut(Vec<String>, Vec<Vec<serde_json::Value>>),
+    ) -> Result<(Vec<String>, Vec<Vec<serde_json::Value>>)> {
         debug!("Preparing query");
-        let mut stmt = conn.prepare(query).context("Failed to prepare query")?;
-        debug!("Calling query()");
-        let mut rows_result = stmt.query([]).context("Failed to execute query")?;
+        let mut stmt = conn.prepare(&query.sql).context("Failed to prepare query")?;
+        debug!("Calling query() with {} parameters", query.params.len());
+        let params = query.params.iter().map(json_to_sql);
+        let mut rows_result = stmt
+            .query(duckdb::params_from_iter(params))
+            .context("Failed to execute query")?;
         debug!("Query started");
 
-        // Collect all rows first, then get column info
-        let mut all_rows: Vec<Vec<serde_json::Value>> = Vec::new();
-        let mut col_count = 0;
+        let mut columns: Vec<String> = Vec::new();
+        let mut batch: Vec<Vec<serde_json::Value>> = Vec::new();
+        let mut fetched = 0;
 
         while let Some(row) = rows_result.next()? {
-            if all_rows.is_empty() {
-                debug!("Got first row");
+            if cancel.is_cancelled() {
+                bail!("Query cancelled");
             }
-            // Determine column count from first row
-            if col_count == 0 {
-                // Try to find column count by probing
-                col_count = Self::probe_column_count(row);
+            // Determine columns from the first row
+            if fetched == 0 {
+                debug!("Got first row");
+                let col_count = Self::probe_column_count(row);
                 debug!("Detected {} columns", col_count);
+                let stmt = row.as_ref();
+                columns = (0..col_count)
+                    .map(|i| {
+                        stmt.column_name(i)
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|_| format!("col_{}", i))
+                    })
+                    .collect();
             }
 
-            let row_values = Self::extract_row(row, col_count)?;
-            all_rows.push(row_values);
+            batch.push(Self::extract_row(row, columns.len())?);
+            fetched += 1;
 
-            if all_rows.len().is_multiple_of(20) {
-                debug!("Fetched {} rows so far", all_rows.len());
+            if batch.len() == BATCH_ROWS {
+                debug!("Fetched {} rows so far", fetched);
+                on_batch(columns.clone(), std::mem::take(&mut batch));
             }
         }
+        info!("Query complete: {} columns, {} rows", columns.len(), fetched);
 
-        // Now get column names (should work after iterating)
-        debug!("Getting column names");
-        let mut columns = Vec::with_capacity(col_count);
-        for i in 0..col_count {
-            let name = stmt
-                .column_name(i)
-                .map(|s| s.to_string())
-                .unwrap_or_else(|_| format!("col_{}", i));
-            columns.push(name);
-        }
-        info!("Query complete: {} columns, {} rows", columns.len(), all_rows.len());
+        Ok((columns, batch))
+    }
+
+    /// Run `EXPLAIN ANALYZE` on `query` and return the rendered plan tree, which
+    /// carries per-operator timings and cardinalities. `database` is made the default
+    /// catalog first so unqualified table names resolve as they did for the chart.
+    pub fn explain_analyze(
+        &self,
+        query: &str,
+        database: Option<&str>,
+        cancel: &QueryCancel,
+    ) -> Result<String> {
+        let conn = self.checkout()?;
+        cancel.register(&conn)?;
+        Self::use_database(&conn, database)?;
 
-        Ok((columns, all_rows))
+        let sql = format!("EXPLAIN ANALYZE {}", query.trim().trim_end_matches(';'));
+        debug!("Running {}", sql);
+        let mut stmt = conn.prepare(&sql).context("Failed to prepare EXPLAIN ANALYZE")?;
+        // Rows are (explain_key, explain_value); the value holds the plan text
+        let plans: Vec<String> = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .context("Failed to run EXPLAIN ANALYZE")?
+            .collect::<duckdb::Result<_>>()?;
+        info!("EXPLAIN ANALYZE complete: {} plan(s)", plans.len());
+        Ok(plans.join("\n"))
+    }
+
+    /// Table and column names in `database` (or the default catalog), plus the names
+    /// of attached databases, for completion in the SQL tab
+    pub fn schema_names(&self, database: Option<&str>, cancel: &QueryCancel) -> Result<Vec<String>> {
+        let conn = self.checkout()?;
+        cancel.register(&conn)?;
+        Self::use_database(&conn, database)?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT DISTINCT name FROM (
+                    SELECT table_name AS name FROM information_schema.tables
+                    WHERE table_catalog = current_database()
+                    UNION ALL
+                    SELECT column_name FROM information_schema.columns
+                    WHERE table_catalog = current_database()
+                    UNION ALL
+                    SELECT database_name FROM duckdb_databases() WHERE NOT internal
+                ) ORDER BY name",
+            )
+            .context("Failed to prepare schema query")?;
+        let names: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .context("Failed to list tables and columns")?
+            .collect::<duckdb::Result<_>>()?;
+        info!("Loaded {} schema names for completion", names.len());
+        Ok(names)
+    }
+
+    /// Make `database` the default catalog so unqualified table names resolve in it
+    fn use_database(conn: &Connection, database: Option<&str>) -> Result<()> {
+        if let Some(db) = database {
+            conn.execute_batch(&format!("USE \"{}\"", db.replace('"', "\"\"")))
+                .with_context(|| format!("Failed to use database {}", db))?;
+        }
+        Ok(())
     }
 
     /// Probe to find column count by trying to access columns
@@ -91,6 +401,13 @@ impl QueryExecutor {
         Ok(values)
     }
 
+    /// Whether column `idx` is a TIMESTAMPTZ, whose values are instants rather
+    /// than wall-clock times
+    fn has_time_zone(row: &Row, idx: usize) -> bool {
+        let statement: &Statement = row.as_ref();
+        matches!(statement.column_type(idx), DataType::Timestamp(_, Some(_)))
+    }
+
     /// Extract a single value from a row
     fn extract_value(row: &Row, idx: usize) -> Result<serde_json::Value> {
         let val = match row.get_ref(idx)? {
@@ -108,7 +425,10 @@ impl QueryExecutor {
             ValueRef::Float(f) => serde_json::json!(f),
             ValueRef::Double(f) => serde_json::json!(f),
             ValueRef::Decimal(d) => serde_json::json!(d.to_string()),
-            ValueRef::Timestamp(_, n) => serde_json::json!(format_timestamp_micros(n)),
+            ValueRef::Timestamp(unit, n) if Self::has_time_zone(row, idx) => {
+                serde_json::json!(format_instant_micros(unit.to_micros(n)))
+            }
+            ValueRef::Timestamp(unit, n) => serde_json::json!(format_timestamp_micros(unit.to_micros(n))),
             ValueRef::Text(s) => serde_json::json!(String::from_utf8_lossy(s).to_string()),
             ValueRef::Blob(b) => serde_json::json!(format!("<blob {} bytes>", b.len())),
             ValueRef::Date32(days) => serde_json::json!(format_date_days(days)),
@@ -127,6 +447,30 @@ impl QueryExecutor {
     }
 }
 
+/// A bound parameter value from a JSON cell, keeping numbers and booleans typed
+fn json_to_sql(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        // Integers past i64 keep every digit as UBIGINT rather than rounding through f64
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => Value::BigInt(i),
+            (None, Some(u)) => Value::UBigInt(u),
+            (None, None) => Value::Double(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path.to_string(),
+    }
+}
+
 fn format_timestamp_micros(micros: i64) -> String {
     use std::time::{Duration, UNIX_EPOCH};
     if micros >= 0 {
@@ -150,6 +494,21 @@ fn format_timestamp_micros(micros: i64) -> String {
     micros.to_string()
 }
 
+/// A TIMESTAMPTZ in the display time zone, with its offset, e.g. `2025-03-01T09:30:00+01:00`
+fn format_instant_micros(micros: i64) -> String {
+    let local = timezone::to_local(micros.div_euclid(1_000_000));
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        local.year,
+        local.month,
+        local.day,
+        local.hour,
+        local.minute,
+        local.second,
+        local.offset_text()
+    )
+}
+
 fn format_date_days(days: i32) -> String {
     let (year, month, day) = days_to_ymd(days as i64 + 719468);
     format!("{:04}-{:02}-{:02}", year, month, day)
@@ -0,0 +1,307 @@
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+/// Most statements kept in the SQL tab's history
+const MAX_HISTORY: usize = 100;
+
+/// Multi-line text buffer behind the SQL tab, with a cursor and a history of the
+/// statements that were run. Columns count characters, not bytes.
+#[derive(Debug, Clone)]
+pub struct SqlEditor {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    history: Vec<String>,
+    /// Index into `history` while browsing it with Ctrl+P/Ctrl+N
+    history_pos: Option<usize>,
+    /// Unsaved text to return to after browsing past the newest entry
+    draft: String,
+    /// Candidates being cycled through by repeated Tab presses
+    completion: Option<Completion>,
+}
+
+/// An in-progress Tab completion of the word ending at the cursor
+#[derive(Debug, Clone)]
+struct Completion {
+    /// Column where the completed word starts
+    start: usize,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl Default for SqlEditor {
+    fn default() -> Self {
+        Self {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+            history: Vec::new(),
+            history_pos: None,
+            draft: String::new(),
+            completion: None,
+        }
+    }
+}
+
+impl SqlEditor {
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Cursor as (line, column)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|l| l.trim().is_empty())
+    }
+
+    /// Replace the buffer and put the cursor at the end
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.lines().map(str::to_string).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.row = self.lines.len() - 1;
+        self.col = self.lines[self.row].chars().count();
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let at = byte_index(&self.lines[self.row], self.col);
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+    }
+
+    /// Split the line at the cursor, carrying its indentation onto the new line
+    pub fn insert_newline(&mut self) {
+        let line = &mut self.lines[self.row];
+        let at = byte_index(line, self.col);
+        let rest = line.split_off(at);
+        let indent: String = line.chars().take_while(|c| *c == ' ').collect();
+        self.col = indent.len();
+        self.row += 1;
+        self.lines.insert(self.row, indent + rest.trim_start_matches(' '));
+    }
+
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let at = byte_index(&self.lines[self.row], self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.lines[self.row].chars().count();
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        let len = self.lines[self.row].chars().count();
+        if self.col < len {
+            let at = byte_index(&self.lines[self.row], self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.lines[self.row].chars().count();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.lines[self.row].chars().count() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.clamp_col();
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.clamp_col();
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.col = self.lines[self.row].chars().count();
+    }
+
+    fn clamp_col(&mut self) {
+        self.col = self.col.min(self.lines[self.row].chars().count());
+    }
+
+    /// The identifier-like word ending at the cursor
+    pub fn word_before_cursor(&self) -> &str {
+        let line = &self.lines[self.row];
+        let end = byte_index(line, self.col);
+        let start = line[..end]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(end);
+        &line[start..end]
+    }
+
+    /// Complete the word at the cursor from `candidates_for(word)`. A unique match is
+    /// inserted, several are first narrowed to their common prefix, and further
+    /// presses cycle through them. Returns false when there is no word to complete.
+    pub fn complete(&mut self, candidates_for: impl FnOnce(&str) -> Vec<String>) -> bool {
+        if let Some(mut completion) = self.completion.take() {
+            completion.index = (completion.index + 1) % completion.candidates.len();
+            let word = completion.candidates[completion.index].clone();
+            self.replace_word(completion.start, &word);
+            self.completion = Some(completion);
+            return true;
+        }
+
+        let prefix = self.word_before_cursor().to_string();
+        if prefix.is_empty() {
+            return false;
+        }
+        let candidates = candidates_for(&prefix);
+        let start = self.col - prefix.chars().count();
+        match candidates.as_slice() {
+            [] => {}
+            [only] => self.replace_word(start, only),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.chars().count(), |len, c| {
+                    len.min(first.chars().zip(c.chars()).take_while(|(a, b)| a == b).count())
+                });
+                if common > prefix.chars().count() {
+                    let shared: String = first.chars().take(common).collect();
+                    self.replace_word(start, &shared);
+                } else {
+                    self.replace_word(start, first);
+                    self.completion = Some(Completion { start, candidates, index: 0 });
+                }
+            }
+        }
+        true
+    }
+
+    /// Stop cycling through completions (any key other than Tab)
+    pub fn cancel_completion(&mut self) {
+        self.completion = None;
+    }
+
+    /// Candidates being cycled through and the one currently inserted
+    pub fn completions(&self) -> Option<(&[String], usize)> {
+        self.completion.as_ref().map(|c| (c.candidates.as_slice(), c.index))
+    }
+
+    /// Replace the text from column `start` to the cursor with `word`
+    fn replace_word(&mut self, start: usize, word: &str) {
+        let line = &mut self.lines[self.row];
+        let from = byte_index(line, start);
+        let to = byte_index(line, self.col);
+        line.replace_range(from..to, word);
+        self.col = start + word.chars().count();
+    }
+
+    /// Remember a statement that was run, skipping repeats of the latest entry
+    pub fn record(&mut self, sql: &str) {
+        self.history_pos = None;
+        if self.history.last().map(String::as_str) != Some(sql) {
+            self.history.push(sql.to_string());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+    }
+
+    /// Load the previous statement from history
+    pub fn history_prev(&mut self) {
+        let pos = match self.history_pos {
+            Some(0) => return,
+            Some(pos) => pos - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.text();
+                self.history.len() - 1
+            }
+        };
+        self.history_pos = Some(pos);
+        let text = self.history[pos].clone();
+        self.set_text(&text);
+    }
+
+    /// Load the next statement from history, or the draft after the newest
+    pub fn history_next(&mut self) {
+        let Some(pos) = self.history_pos else { return };
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            let text = self.history[pos + 1].clone();
+            self.set_text(&text);
+        } else {
+            self.history_pos = None;
+            let draft = std::mem::take(&mut self.draft);
+            self.set_text(&draft);
+        }
+    }
+
+    /// Position in history as (entry, total), while browsing it
+    pub fn history_position(&self) -> Option<(usize, usize)> {
+        self.history_pos.map(|pos| (pos + 1, self.history.len()))
+    }
+}
+
+/// Byte offset of the `col`th character of `line`
+fn byte_index(line: &str, col: usize) -> usize {
+    line.char_indices().nth(col).map(|(i, _)| i).unwrap_or(line.len())
+}
+
+/// Edit `text` in `$VISUAL` or `$EDITOR` (`vi` when neither is set) through a
+/// temporary `.sql` file, returning what was saved. The caller hands the terminal
+/// over first. The command runs through `sh`, so it can carry arguments.
+pub fn edit_externally(text: &str) -> Result<String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let path = std::env::temp_dir().join(format!("ducktrace-query-{}.sql", std::process::id()));
+    std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to run `{}`", editor));
+    let edited = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()));
+    let _ = std::fs::remove_file(&path);
+    let status = status?;
+    if !status.success() {
+        bail!("`{}` exited with {}", editor, status);
+    }
+    edited
+}
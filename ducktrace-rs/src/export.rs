@@ -0,0 +1,539 @@
+use anyhow::{anyhow, bail, Context, Result};
+use plotters::coord::types::RangedCoordf64;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use ratatui::style::Color as TermColor;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::data::{format_compact, value_to_string, BarMode, ChartData, ChartType};
+use crate::ui::query::format_sql;
+use crate::ui::theme::{parse_color, Palette};
+
+/// Size of an exported chart, in pixels
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 700;
+
+/// A chart export from the `:export` command, drawn on the blocking pool
+pub struct ExportJob {
+    pub data: ChartData,
+    pub palette: Palette,
+    pub bar_mode: BarMode,
+    pub path: PathBuf,
+}
+
+type Chart<'a, DB> = ChartContext<'a, DB, Cartesian2d<RangedCoordf64, RangedCoordf64>>;
+
+/// Draw the chart to `path` as a PNG or SVG, or write a Markdown report for a
+/// `.md` path, picked by the extension. The chart type, series, bar layout, and
+/// thresholds match the Chart tab, on a white page.
+pub fn export_chart(data: &ChartData, palette: &Palette, bar_mode: BarMode, path: &Path) -> Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("md" | "markdown") => std::fs::write(path, markdown_report(data))
+            .with_context(|| format!("Failed to write {}", path.display())),
+        Some("svg") => draw(SVGBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area(), data, palette, bar_mode),
+        Some("png") => draw(BitMapBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area(), data, palette, bar_mode),
+        _ => bail!("Can't export to {}: use a .png, .svg, or .md file", path.display()),
+    }
+}
+
+/// The chart as a Markdown document: title, formatted SQL, the rows as a table,
+/// and the lineage and drill-down settings
+pub fn markdown_report(data: &ChartData) -> String {
+    let mut out = format!("# {}\n\n", data.title);
+    let kind = match data.infer_chart_type() {
+        ChartType::Line => "line",
+        ChartType::Bar => "bar",
+        ChartType::Scatter => "scatter",
+        ChartType::Pie => "pie",
+        ChartType::Area => "area",
+        ChartType::Histogram => "histogram",
+        ChartType::BoxPlot => "box plot",
+    };
+    let mut facts = vec![
+        format!("**Chart:** {}", kind),
+        format!("**x:** `{}`", data.x_field),
+        format!("**y:** `{}`", data.y_field),
+    ];
+    if let Some(ref y2) = data.y2_field {
+        facts.push(format!("**y2:** `{}`", y2));
+    }
+    if let Some(ref series) = data.series_field {
+        facts.push(format!("**Series:** `{}`", series));
+    }
+    if let Some(ref database) = data.database {
+        facts.push(format!("**Database:** `{}`", database));
+    }
+    let _ = writeln!(out, "{}\n", facts.join(" · "));
+
+    let _ = writeln!(out, "## Query\n\n```sql\n{}\n```\n", format_sql(data.query.trim()));
+
+    let _ = writeln!(out, "## Data\n");
+    let _ = writeln!(out, "| {} |", data.columns.iter().map(|c| markdown_cell(c)).collect::<Vec<_>>().join(" | "));
+    let _ = writeln!(out, "|{}", data.columns.iter().map(|_| " --- |").collect::<String>());
+    for row in &data.rows {
+        let cells: Vec<String> = data
+            .columns
+            .iter()
+            .zip(row)
+            .map(|(column, value)| markdown_cell(&report_value(data, column, value)))
+            .collect();
+        let _ = writeln!(out, "| {} |", cells.join(" | "));
+    }
+    let shown = format!("{} rows", data.rows.len());
+    match data.truncated_from {
+        Some(total) if !data.all_rows => {
+            let _ = writeln!(out, "\n_{} of {} shown._", shown, total);
+        }
+        _ => {
+            let _ = writeln!(out, "\n_{}._", shown);
+        }
+    }
+
+    if let Some(ref lineage) = data.lineage {
+        let _ = writeln!(out, "\n## Lineage\n");
+        let fields = [
+            ("Source table", lineage.source_table.clone()),
+            ("Source column", lineage.source_column.clone()),
+            ("Aggregation", lineage.aggregation.clone()),
+            ("Grouped by", lineage.group_by.as_ref().map(|g| g.join(", "))),
+        ];
+        for (label, value) in fields {
+            if let Some(value) = value {
+                let _ = writeln!(out, "- **{}:** `{}`", label, value);
+            }
+        }
+    }
+
+    if let Some(ref drill) = data.drill_down {
+        let _ = writeln!(out, "\n## Drill-down\n");
+        if !drill.description.is_empty() {
+            let _ = writeln!(out, "{}\n", drill.description);
+        }
+        let mut params: Vec<_> = drill.param_mapping.iter().collect();
+        params.sort();
+        for (placeholder, field) in &params {
+            let _ = writeln!(out, "- `{{{{{}}}}}` ← `{}`", placeholder, field);
+        }
+        if !params.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "```sql\n{}\n```", format_sql(drill.query_template.trim()));
+        if let Some(ref range) = drill.range_query_template {
+            let _ = writeln!(out, "\nFor a brushed range:\n\n```sql\n{}\n```", format_sql(range.trim()));
+        }
+    }
+    out
+}
+
+/// A value as the Data tab shows it: by the column's hint, else numbers with
+/// every digit kept, and anything else as text
+fn report_value(data: &ChartData, column: &str, value: &serde_json::Value) -> String {
+    let number = || (value.is_number() || *column == data.y_field).then(|| data.formats.number(value, column)).flatten();
+    data.formats.cell(value, column).or_else(number).unwrap_or_else(|| value_to_string(value))
+}
+
+/// Text safe inside a Markdown table cell
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn draw<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, data: &ChartData, palette: &Palette, bar_mode: BarMode) -> Result<()> {
+    if data.rows.is_empty() {
+        bail!("The chart has no rows to export");
+    }
+    root.fill(&WHITE).map_err(plot_error)?;
+    let area = root.titled(&data.title, ("sans-serif", 28)).map_err(plot_error)?;
+    match data.infer_chart_type() {
+        ChartType::Pie => draw_pie(&area, data, palette)?,
+        ChartType::Histogram => draw_histogram(&area, data, palette)?,
+        ChartType::BoxPlot => draw_box_plot(&area, data, palette)?,
+        ChartType::Bar => draw_bars(&area, data, palette, bar_mode)?,
+        kind => draw_xy(&area, data, palette, kind)?,
+    }
+    root.present().map_err(plot_error)?;
+    Ok(())
+}
+
+/// Line, area, and scatter charts: one line (or set of points) per series
+fn draw_xy<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, data: &ChartData, palette: &Palette, kind: ChartType) -> Result<()> {
+    let labels = x_labels(data);
+    let (lo, hi) = y_bounds(data, false);
+    let mut chart = category_chart(area, data, &labels, lo, hi)?;
+
+    let groups = series(data);
+    for (i, (name, points)) in groups.iter().enumerate() {
+        let color = series_color(palette, data, i);
+        let drawn = match kind {
+            ChartType::Scatter => chart
+                .draw_series(points.iter().map(|&p| Circle::new(p, 4, color.filled())))
+                .map_err(plot_error)?,
+            ChartType::Area => chart
+                .draw_series(AreaSeries::new(points.iter().copied(), lo.max(0.0), color.mix(0.25)).border_style(color.stroke_width(2)))
+                .map_err(plot_error)?,
+            _ => chart
+                .draw_series(LineSeries::new(points.iter().copied(), color.stroke_width(2)).point_size(3))
+                .map_err(plot_error)?,
+        };
+        if groups.len() > 1 {
+            drawn.label(name).legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 12, y + 5)], color.filled()));
+        }
+    }
+    finish(chart, data, labels.len(), groups.len() > 1)
+}
+
+/// Bars per x value, grouped or stacked by series
+fn draw_bars<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, data: &ChartData, palette: &Palette, bar_mode: BarMode) -> Result<()> {
+    let labels = x_labels(data);
+    let stacked = bar_mode == BarMode::Stacked;
+    let (lo, hi) = y_bounds(data, stacked);
+    let mut chart = category_chart(area, data, &labels, lo, hi)?;
+
+    let groups = series(data);
+    let width = 0.8 / if stacked { 1.0 } else { groups.len() as f64 };
+    let mut stack = vec![0.0; labels.len()];
+    for (i, (name, points)) in groups.iter().enumerate() {
+        let color = series_color(palette, data, i);
+        let bars: Vec<Rectangle<(f64, f64)>> = points
+            .iter()
+            .map(|&(x, y)| {
+                let slot = x.round() as usize;
+                let (left, base) = if stacked {
+                    let base = stack.get(slot).copied().unwrap_or_default();
+                    if let Some(top) = stack.get_mut(slot) {
+                        *top += y;
+                    }
+                    (x - 0.4, base)
+                } else {
+                    (x - 0.4 + width * i as f64, 0.0)
+                };
+                Rectangle::new([(left, base), (left + width, base + y)], color.filled())
+            })
+            .collect();
+        let drawn = chart.draw_series(bars).map_err(plot_error)?;
+        if groups.len() > 1 {
+            drawn.label(name).legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 12, y + 5)], color.filled()));
+        }
+    }
+    finish(chart, data, labels.len(), groups.len() > 1)
+}
+
+/// Bars over the binned y column, on a numeric axis
+fn draw_histogram<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, data: &ChartData, palette: &Palette) -> Result<()> {
+    let bins = data.histogram();
+    let (Some(first), Some(last)) = (bins.first(), bins.last()) else {
+        bail!("No numeric values in {} to bin", data.y_field);
+    };
+    let end = if last.end > first.start { last.end } else { first.start + 1.0 };
+    let top = bins.iter().map(|b| b.count).max().unwrap_or(1).max(1) as f64 * 1.05;
+    let mut chart = ChartBuilder::on(area)
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(70)
+        .build_cartesian_2d(first.start..end, 0.0..top)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc(data.y_field.as_str())
+        .y_desc("count")
+        .x_label_formatter(&|x| data.formats.axis(*x, &data.y_field))
+        .y_label_formatter(&|y| format_compact(*y))
+        .disable_x_mesh()
+        .max_light_lines(0)
+        .draw()
+        .map_err(plot_error)?;
+    let color = rgb(palette.primary);
+    chart
+        .draw_series(bins.iter().map(|bin| {
+            let end = if bin.end > bin.start { bin.end } else { end };
+            Rectangle::new([(bin.start, 0.0), (end, bin.count as f64)], color.filled())
+        }))
+        .map_err(plot_error)?;
+    chart
+        .draw_series(bins.iter().map(|bin| Rectangle::new([(bin.start, 0.0), (bin.end, bin.count as f64)], WHITE)))
+        .map_err(plot_error)?;
+    Ok(())
+}
+
+/// A box from q1 to q3 with the median marked and whiskers out to min and max, per x value
+fn draw_box_plot<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, data: &ChartData, palette: &Palette) -> Result<()> {
+    let stats = data.box_stats();
+    if stats.is_empty() {
+        bail!("No numeric values in {} to summarize", data.y_field);
+    }
+    let labels: Vec<String> = stats.iter().map(|s| s.label.clone()).collect();
+    let lo = stats.iter().map(|s| s.min).fold(f64::INFINITY, f64::min);
+    let hi = stats.iter().map(|s| s.max).fold(f64::NEG_INFINITY, f64::max);
+    let pad = ((hi - lo) * 0.05).max(1e-9);
+    let mut chart = category_chart(area, data, &labels, lo - pad, hi + pad)?;
+
+    let color = rgb(palette.primary);
+    for (i, s) in stats.iter().enumerate() {
+        let x = i as f64;
+        let lines = [
+            vec![(x, s.min), (x, s.q1)],
+            vec![(x, s.q3), (x, s.max)],
+            vec![(x - 0.15, s.min), (x + 0.15, s.min)],
+            vec![(x - 0.15, s.max), (x + 0.15, s.max)],
+        ];
+        chart
+            .draw_series(lines.into_iter().map(|points| PathElement::new(points, BLACK.stroke_width(1))))
+            .map_err(plot_error)?;
+        chart
+            .draw_series([
+                Rectangle::new([(x - 0.3, s.q1), (x + 0.3, s.q3)], color.mix(0.4).filled()),
+                Rectangle::new([(x - 0.3, s.q1), (x + 0.3, s.q3)], color.stroke_width(2)),
+            ])
+            .map_err(plot_error)?;
+        chart
+            .draw_series(std::iter::once(PathElement::new(vec![(x - 0.3, s.median), (x + 0.3, s.median)], BLACK.stroke_width(3))))
+            .map_err(plot_error)?;
+    }
+    Ok(())
+}
+
+/// Slices by x value, sized by y; zero and negative values have no slice
+fn draw_pie<DB: DrawingBackend>(area: &DrawingArea<DB, Shift>, data: &ChartData, palette: &Palette) -> Result<()> {
+    let slices: Vec<(String, f64)> = data
+        .rows
+        .iter()
+        .map(|row| (data.x_label(row), data.get_y_value(row)))
+        .filter(|(_, y)| *y > 0.0)
+        .collect();
+    if slices.is_empty() {
+        bail!("No positive values in {} to chart", data.y_field);
+    }
+    let (width, height) = area.dim_in_pixel();
+    let center = (width as i32 / 2, height as i32 / 2);
+    let radius = (width.min(height) as f64) * 0.35;
+    let sizes: Vec<f64> = slices.iter().map(|(_, y)| *y).collect();
+    let labels: Vec<&str> = slices.iter().map(|(x, _)| x.as_str()).collect();
+    let colors: Vec<RGBColor> = (0..slices.len()).map(|i| rgb(palette.series_color(i))).collect();
+
+    let mut pie = Pie::new(&center, &radius, &sizes, &colors, &labels);
+    pie.start_angle(-90.0);
+    pie.label_style(("sans-serif", 16).into_font().color(&BLACK));
+    pie.percentages(("sans-serif", 14).into_font().color(&WHITE));
+    if data.is_donut() {
+        pie.donut_hole(radius * 0.5);
+    }
+    area.draw(&pie).map_err(plot_error)?;
+    Ok(())
+}
+
+/// Axes for charts laid out by x value: one slot per label, centered on 0, 1, 2, ...
+fn category_chart<'a, DB: DrawingBackend + 'a>(
+    area: &'a DrawingArea<DB, Shift>,
+    data: &ChartData,
+    labels: &[String],
+    lo: f64,
+    hi: f64,
+) -> Result<Chart<'a, DB>> {
+    let mut chart = ChartBuilder::on(area)
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(70)
+        .build_cartesian_2d(-0.5..(labels.len() as f64 - 0.5), lo..hi)
+        .map_err(plot_error)?;
+    let label_at = |x: &f64| {
+        let slot = x.round();
+        match labels.get(slot as usize) {
+            Some(label) if (x - slot).abs() < 1e-6 && slot >= 0.0 => label.clone(),
+            _ => String::new(),
+        }
+    };
+    chart
+        .configure_mesh()
+        .x_desc(data.x_field.as_str())
+        .y_desc(data.y_field.as_str())
+        .x_labels(labels.len().min(12))
+        .x_label_formatter(&label_at)
+        .y_label_formatter(&|y| data.formats.value(*y, &data.y_field))
+        .disable_x_mesh()
+        .max_light_lines(0)
+        .draw()
+        .map_err(plot_error)?;
+    Ok(chart)
+}
+
+/// Threshold lines, then the series legend when there's more than one series
+fn finish<'a, DB: DrawingBackend + 'a>(mut chart: Chart<'a, DB>, data: &ChartData, slots: usize, legend: bool) -> Result<()> {
+    let right = slots as f64 - 0.5;
+    for threshold in &data.thresholds {
+        let color = rgb(threshold.color.as_deref().and_then(parse_color).unwrap_or(TermColor::Red));
+        let line = DashedLineSeries::new([(-0.5, threshold.value), (right, threshold.value)], 8, 5, color.stroke_width(1));
+        let drawn = chart.draw_series(line).map_err(plot_error)?;
+        if let Some(ref label) = threshold.label {
+            drawn.label(label).legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 12, y)], color));
+        }
+    }
+    if legend || data.thresholds.iter().any(|t| t.label.is_some()) {
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.85))
+            .border_style(BLACK)
+            .position(SeriesLabelPosition::UpperLeft)
+            .draw()
+            .map_err(plot_error)?;
+    }
+    Ok(())
+}
+
+/// X labels in axis order: the sorted distinct values when split by series, else
+/// the rows' values in the order the Chart tab lays them out
+fn x_labels(data: &ChartData) -> Vec<String> {
+    if data.get_series_index().is_some() {
+        return data.distinct_x_values().iter().map(|x| data.formats.text(x, &data.x_field)).collect();
+    }
+    let mut labels = vec![String::new(); data.rows.len()];
+    for (row, position) in data.rows.iter().zip(data.x_positions()) {
+        labels[position] = data.x_label(row);
+    }
+    labels
+}
+
+/// (x slot, y) points per series, in x order
+fn series(data: &ChartData) -> Vec<(String, Vec<(f64, f64)>)> {
+    let positions = data.x_positions();
+    let groups = match data.get_series_index() {
+        Some(_) => data.series_groups(),
+        None => vec![(data.y_field.clone(), (0..data.rows.len()).collect())],
+    };
+    groups
+        .into_iter()
+        .map(|(name, rows)| {
+            let mut points: Vec<(f64, f64)> = rows
+                .into_iter()
+                .map(|i| (positions[i] as f64, data.get_y_value(&data.rows[i])))
+                .collect();
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            (name, points)
+        })
+        .collect()
+}
+
+/// Y axis range covering the data (summed per x when stacked), zero, and thresholds
+fn y_bounds(data: &ChartData, stacked: bool) -> (f64, f64) {
+    let (mut lo, mut hi) = data.y_range_with_thresholds();
+    if stacked {
+        let mut totals = vec![0.0; data.rows.len()];
+        for (row, position) in data.rows.iter().zip(data.x_positions()) {
+            totals[position] += data.get_y_value(row);
+        }
+        hi = totals.into_iter().fold(hi, f64::max);
+    }
+    lo = lo.min(0.0);
+    if hi <= lo {
+        hi = lo + 1.0;
+    }
+    let pad = (hi - lo) * 0.05;
+    (if lo < 0.0 { lo - pad } else { lo }, hi + pad)
+}
+
+/// The primary color for a single series, else the series palette in order
+fn series_color(palette: &Palette, data: &ChartData, i: usize) -> RGBColor {
+    if data.get_series_index().is_some() {
+        rgb(palette.series_color(i))
+    } else {
+        rgb(palette.primary)
+    }
+}
+
+/// A palette color on a white page. Named terminal colors are mapped to darker
+/// tones than a terminal shows, so lines stay legible on paper.
+fn rgb(color: TermColor) -> RGBColor {
+    let (r, g, b) = match color {
+        TermColor::Rgb(r, g, b) => (r, g, b),
+        TermColor::Black => (0, 0, 0),
+        TermColor::Red => (214, 39, 40),
+        TermColor::Green => (44, 160, 44),
+        TermColor::Yellow => (214, 160, 0),
+        TermColor::Blue => (31, 119, 180),
+        TermColor::Magenta => (148, 52, 170),
+        TermColor::Cyan => (0, 150, 170),
+        TermColor::Gray => (127, 127, 127),
+        TermColor::DarkGray => (80, 80, 80),
+        TermColor::LightRed => (240, 110, 100),
+        TermColor::LightGreen => (110, 190, 90),
+        TermColor::LightYellow => (230, 190, 60),
+        TermColor::LightBlue => (100, 160, 230),
+        TermColor::LightMagenta => (200, 110, 200),
+        TermColor::LightCyan => (60, 180, 200),
+        TermColor::White => (190, 190, 190),
+        TermColor::Indexed(_) | TermColor::Reset => (31, 119, 180),
+    };
+    RGBColor(r, g, b)
+}
+
+fn plot_error(e: impl std::fmt::Display) -> anyhow::Error {
+    anyhow!("Failed to draw the chart: {}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chart(spec: serde_json::Value) -> ChartData {
+        let mut chart = json!({"title": "Orders", "query": "select month, orders from sales", "x": "month", "y": "orders"});
+        chart.as_object_mut().unwrap().extend(spec.as_object().unwrap().clone());
+        serde_json::from_value(chart).unwrap()
+    }
+
+    #[test]
+    fn report_keeps_exact_digits_and_escapes_cells() {
+        let data = chart(json!({
+            "columns": ["month", "orders", "id", "note"],
+            "rows": [
+                ["2025-01-01", 1500, 12345678901234567891u64, "a|b"],
+                ["2025-02-01", "2500.5", 7, null],
+            ],
+            "formats": {"id": "integer", "month": "date:%b %Y"},
+            "truncatedFrom": 90,
+        }));
+        let report = markdown_report(&data);
+        assert!(report.starts_with("# Orders\n\n**Chart:** line · **x:** `month` · **y:** `orders`\n"), "{}", report);
+        assert!(report.contains("| month | orders | id | note |\n| --- | --- | --- | --- |\n"), "{}", report);
+        assert!(report.contains("| Jan 2025 | 1.5K | 12,345,678,901,234,567,891 | a\\|b |\n"), "{}", report);
+        assert!(report.contains("| Feb 2025 | 2.5K | 7 | null |\n"), "{}", report);
+        assert!(report.contains("_2 rows of 90 shown._"), "{}", report);
+    }
+
+    #[test]
+    fn x_labels_follow_positions_and_hints() {
+        let data = chart(json!({
+            "columns": ["month", "orders"],
+            "rows": [["2025-02-01", 3], ["2025-01-01", 5]],
+            "formats": {"month": "date:%b"},
+        }));
+        assert_eq!(x_labels(&data), data.x_positions().iter().map(|&p| ["Feb", "Jan"][p]).collect::<Vec<_>>());
+
+        let data = chart(json!({
+            "columns": ["month", "region", "orders"], "series": "region",
+            "rows": [["2025-01", "EU", 1], ["2025-01", "US", 2], ["2025-02", "EU", 3]],
+        }));
+        assert_eq!(x_labels(&data), vec!["2025-01", "2025-02"]);
+    }
+
+    #[test]
+    fn y_bounds_pad_and_include_zero() {
+        let data = chart(json!({"columns": ["month", "orders"], "rows": [["a", 10], ["b", 30]]}));
+        assert_eq!(y_bounds(&data, false), (0.0, 31.5));
+
+        let data = chart(json!({
+            "columns": ["month", "orders"], "rows": [["a", -10], ["b", 10]],
+            "thresholds": [{"value": 30}],
+        }));
+        assert_eq!(y_bounds(&data, false), (-12.0, 32.0));
+
+        let data = chart(json!({
+            "columns": ["month", "region", "orders"], "series": "region",
+            "rows": [["a", "EU", 10], ["a", "US", 30], ["b", "EU", 20]],
+        }));
+        assert_eq!(y_bounds(&data, true), (0.0, 42.0));
+
+        let data = chart(json!({"columns": ["month", "orders"], "rows": [["a", 0]]}));
+        assert_eq!(y_bounds(&data, false), (0.0, 1.05));
+    }
+}
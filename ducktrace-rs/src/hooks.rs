@@ -0,0 +1,86 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Events that can run a shell command from the `[hooks]` config table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hook {
+    /// A chart opened: from the watched file, stdin, history, or another source
+    ChartLoaded,
+    /// The selected row changed on the Chart or Data tab
+    PointSelected,
+    /// A drill-down query finished, with its rows or its error
+    DrilldownCompleted,
+}
+
+const HOOKS: &[(&str, Hook)] = &[
+    ("chart_loaded", Hook::ChartLoaded),
+    ("point_selected", Hook::PointSelected),
+    ("drilldown_completed", Hook::DrilldownCompleted),
+];
+
+impl Hook {
+    pub fn name(self) -> &'static str {
+        HOOKS.iter().find(|(_, hook)| *hook == self).map_or("", |(name, _)| name)
+    }
+}
+
+/// Shell commands run on events (`[hooks]` table), e.g.
+/// `drilldown_completed = "notify-send DuckTrace 'Drill-down done'"`. Each gets
+/// the event as JSON on stdin.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "HashMap<String, String>")]
+pub struct Hooks {
+    commands: HashMap<Hook, String>,
+}
+
+impl TryFrom<HashMap<String, String>> for Hooks {
+    type Error = String;
+
+    fn try_from(table: HashMap<String, String>) -> Result<Self, Self::Error> {
+        let mut commands = HashMap::new();
+        for (event, command) in table {
+            let Some(&(_, hook)) = HOOKS.iter().find(|(name, _)| *name == event) else {
+                let names: Vec<&str> = HOOKS.iter().map(|(name, _)| *name).collect();
+                return Err(format!("unknown event `{}` in [hooks] (expected one of: {})", event, names.join(", ")));
+            };
+            commands.insert(hook, command);
+        }
+        Ok(Self { commands })
+    }
+}
+
+impl Hooks {
+    /// The command configured for `hook`, if any
+    pub fn command(&self, hook: Hook) -> Option<&str> {
+        self.commands.get(&hook).map(String::as_str).filter(|c| !c.trim().is_empty())
+    }
+}
+
+/// Run a hook's command through `sh -c` with `payload` on stdin, waiting for it
+/// to exit. Blocks, so the main loop runs it off the UI thread.
+pub fn run_hook(command: &str, payload: &serde_json::Value) -> Result<()> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run `{}`", command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that exits without reading its input closes the pipe; that's fine
+        let _ = writeln!(stdin, "{}", payload);
+    }
+    let output = child.wait_with_output().with_context(|| format!("Failed to wait for `{}`", command))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.trim() {
+            "" => bail!("`{}` failed ({})", command, output.status),
+            stderr => bail!("`{}` failed ({}): {}", command, output.status, stderr),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Actions whose keys can be rebound in the `[keys]` config table, by name,
+/// with their default keys
+const ACTIONS: &[(&str, KeyCode)] = &[
+    ("quit", KeyCode::Char('q')),
+    ("help", KeyCode::Char('?')),
+    ("clear", KeyCode::Char('c')),
+    ("query_log", KeyCode::Char('L')),
+    ("app_log", KeyCode::Char('D')),
+    ("tasks", KeyCode::Char('T')),
+    ("export", KeyCode::Char('E')),
+    ("report", KeyCode::Char('M')),
+    ("profiles", KeyCode::Char('P')),
+    ("charts", KeyCode::Tab),
+    ("prev_chart", KeyCode::Char('[')),
+    ("next_chart", KeyCode::Char(']')),
+    ("prev_tab", KeyCode::Left),
+    ("next_tab", KeyCode::Right),
+    ("explain", KeyCode::Char('x')),
+    ("auto_refresh", KeyCode::Char('R')),
+    ("goto_row", KeyCode::Char(':')),
+    ("search", KeyCode::Char('/')),
+    ("bar_mode", KeyCode::Char('b')),
+    ("bar_orientation", KeyCode::Char('v')),
+    ("chart_sort", KeyCode::Char('o')),
+];
+
+/// A key with the modifiers that matter for bindings (Ctrl and Alt; Shift is
+/// part of the character)
+type Key = (KeyCode, KeyModifiers);
+
+/// Keys rebound in the `[keys]` config table, e.g. `explain = "e"` or
+/// `quit = "ctrl-c"`. Applies to the tab-level keys, after any overlay or prompt
+/// has had its turn. A rebound action's default key does nothing unless another
+/// action took it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "HashMap<String, String>")]
+pub struct Keymap {
+    /// Pressed key → the default key it stands for, or None for a default key
+    /// that was moved elsewhere
+    remap: HashMap<Key, Option<KeyCode>>,
+}
+
+impl TryFrom<HashMap<String, String>> for Keymap {
+    type Error = String;
+
+    fn try_from(bindings: HashMap<String, String>) -> Result<Self, Self::Error> {
+        let mut remap = HashMap::new();
+        let mut moved = Vec::new();
+        for (action, spec) in &bindings {
+            let Some(&(_, default)) = ACTIONS.iter().find(|(name, _)| name == action) else {
+                let names: Vec<&str> = ACTIONS.iter().map(|(name, _)| *name).collect();
+                return Err(format!("unknown action `{}` in [keys] (expected one of: {})", action, names.join(", ")));
+            };
+            let key = parse_key(spec).ok_or_else(|| format!("can't read key `{}` for `{}`", spec, action))?;
+            if remap.insert(key, Some(default)).is_some() {
+                return Err(format!("key `{}` is bound to more than one action", spec));
+            }
+            moved.push(default);
+        }
+        for default in moved {
+            remap.entry((default, KeyModifiers::NONE)).or_insert(None);
+        }
+        Ok(Self { remap })
+    }
+}
+
+impl Keymap {
+    /// The key event the app should handle for `key`: the action's default key
+    /// for a rebound one, None for a default key that was moved, else `key` itself
+    pub fn translate(&self, key: KeyEvent) -> Option<KeyEvent> {
+        let mut modifiers = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if !matches!(key.code, KeyCode::Char(_)) {
+            modifiers |= key.modifiers & KeyModifiers::SHIFT;
+        }
+        match self.remap.get(&(key.code, modifiers)) {
+            Some(Some(code)) => Some(KeyEvent::new(*code, KeyModifiers::NONE)),
+            Some(None) => None,
+            None => Some(key),
+        }
+    }
+
+    /// The key `action` is on, as shown in key hints: the rebound key, else the default
+    pub fn key_for(&self, action: &str) -> String {
+        let Some(&(_, default)) = ACTIONS.iter().find(|(name, _)| *name == action) else {
+            return String::new();
+        };
+        let key = self
+            .remap
+            .iter()
+            .find(|(_, to)| **to == Some(default))
+            .map_or((default, KeyModifiers::NONE), |(key, _)| *key);
+        key_name(key)
+    }
+}
+
+/// A key as it's written in hints: `x`, `Ctrl+E`, `→`, `Tab`, `F5`
+fn key_name((code, modifiers): Key) -> String {
+    let name = match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) if modifiers.is_empty() => c.to_string(),
+        KeyCode::Char(c) => c.to_ascii_uppercase().to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::BackTab => "Shift+Tab".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        code => format!("{:?}", code),
+    };
+    let mut prefix = String::new();
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        prefix.push_str("Ctrl+");
+    }
+    if modifiers.contains(KeyModifiers::ALT) {
+        prefix.push_str("Alt+");
+    }
+    prefix + &name
+}
+
+/// Parse a key like `e`, `E`, `ctrl-e`, `alt-x`, `tab`, `pagedown`, or `f5`
+fn parse_key(spec: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = spec.trim();
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        if let Some(stripped) = lower.strip_prefix("ctrl-").or_else(|| lower.strip_prefix("ctrl+")) {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = &rest[rest.len() - stripped.len()..];
+        } else if let Some(stripped) = lower.strip_prefix("alt-").or_else(|| lower.strip_prefix("alt+")) {
+            modifiers |= KeyModifiers::ALT;
+            rest = &rest[rest.len() - stripped.len()..];
+        } else {
+            break;
+        }
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            name => KeyCode::F(name.strip_prefix('f')?.parse().ok().filter(|n| (1..=12).contains(n))?),
+        },
+    };
+    // Ctrl combinations arrive as the lowercase letter
+    let code = match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(c.to_ascii_lowercase()),
+        code => code,
+    };
+    Some((code, modifiers))
+}
+
+/// Vim-style stand-ins for the navigation keys: `h`/`j`/`k`/`l` for the arrows,
+/// `gg`/`G` for Home/End, `gt`/`gT` for the next/previous tab (the tab-level
+/// `→`/`←`), and `ctrl-u`/`ctrl-d` for Page Up/Down. Remembers a first `g` until
+/// the next key.
+#[derive(Debug, Default)]
+pub struct VimKeys {
+    pending_g: bool,
+}
+
+impl VimKeys {
+    /// The navigation key `key` stands for, else `key` itself; None for the first
+    /// `g` of `gg`, `gt`, or `gT`
+    pub fn translate(&mut self, key: KeyEvent) -> Option<KeyEvent> {
+        let pending_g = std::mem::take(&mut self.pending_g);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let plain = !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let code = match key.code {
+            KeyCode::Char('h') if plain => KeyCode::Left,
+            KeyCode::Char('j') if plain => KeyCode::Down,
+            KeyCode::Char('k') if plain => KeyCode::Up,
+            KeyCode::Char('l') if plain => KeyCode::Right,
+            KeyCode::Char('G') if plain => KeyCode::End,
+            KeyCode::Char('g') if plain && pending_g => KeyCode::Home,
+            KeyCode::Char('t') if plain && pending_g => KeyCode::Right,
+            KeyCode::Char('T') if plain && pending_g => KeyCode::Left,
+            KeyCode::Char('g') if plain => {
+                self.pending_g = true;
+                return None;
+            }
+            KeyCode::Char('d') if ctrl => KeyCode::PageDown,
+            KeyCode::Char('u') if ctrl => KeyCode::PageUp,
+            _ => return Some(key),
+        };
+        Some(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use log::LevelFilter;
+use simplelog::{Config, WriteLogger};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Level logged when neither `--log-level` nor the config sets one
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// Size at which the log rolls over to `ducktrace.log.1`
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Rolled-over logs kept next to the current one
+const KEPT_LOGS: usize = 3;
+
+/// How far back from the end of the log the tail overlay reads
+const TAIL_BYTES: u64 = 256 * 1024;
+
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Default log location, `~/.claude/ducktrace/ducktrace.log`
+pub fn default_log_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".claude/ducktrace/ducktrace.log")
+}
+
+/// The file being logged to, once [`init`] has succeeded
+pub fn log_path() -> Option<&'static Path> {
+    LOG_PATH.get().map(PathBuf::as_path)
+}
+
+/// Parse a level name: `off`, `error`, `warn`, `info`, `debug`, or `trace`
+pub fn parse_level(name: &str) -> Result<LevelFilter> {
+    name.trim()
+        .parse()
+        .ok()
+        .with_context(|| format!("Unknown log level `{}` (expected off, error, warn, info, debug, or trace)", name))
+}
+
+/// Start logging to `path` at `level`, appending to what's there. The file
+/// rolls over to `<path>.1` whenever it passes 5 MB, and only then, so another
+/// instance logging to the same file keeps its lines.
+pub fn init(path: &Path, level: LevelFilter) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = RotatingFile::open(path)?;
+    // Everything reaches the logger; `set_level` moves the cut-off without re-initializing
+    WriteLogger::init(LevelFilter::Trace, Config::default(), file)?;
+    log::set_max_level(level);
+    let _ = LOG_PATH.set(path.to_path_buf());
+    Ok(())
+}
+
+/// Change the level records are logged at, e.g. after the config is edited
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+/// The last `count` lines of the log
+pub fn tail(path: &Path, count: usize) -> Result<Vec<String>> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let text = String::from_utf8_lossy(&bytes);
+    let mut lines: Vec<&str> = text.lines().collect();
+    // Reading from the middle of the file starts partway through a line
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines[skip..].iter().map(|l| l.to_string()).collect())
+}
+
+/// A log file that rolls over to `.1`, `.2`, ... when it grows past [`MAX_LOG_SIZE`]
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    /// Size of the file as of the last check, plus what's been written since
+    written: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path) -> Result<Self> {
+        if fs::metadata(path).is_ok_and(|m| m.len() >= MAX_LOG_SIZE) {
+            rotate(path)?;
+        }
+        let file = append(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let written = file.metadata()?.len();
+        Ok(Self { path: path.to_path_buf(), file, written })
+    }
+}
+
+fn append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > MAX_LOG_SIZE {
+            self.file.flush()?;
+            // Another instance logging here may have rolled the file over already
+            let size = fs::metadata(&self.path).map_or(0, |m| m.len());
+            if size > 0 && size + buf.len() as u64 > MAX_LOG_SIZE {
+                rotate(&self.path).map_err(std::io::Error::other)?;
+            }
+            self.file = append(&self.path)?;
+            self.written = self.file.metadata()?.len();
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Shift `<path>.1` to `.2` and so on, dropping the oldest, then move `path` to `.1`
+fn rotate(path: &Path) -> Result<()> {
+    let numbered = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    for n in (1..KEPT_LOGS).rev() {
+        let from = numbered(n);
+        if from.exists() {
+            fs::rename(&from, numbered(n + 1))?;
+        }
+    }
+    fs::rename(path, numbered(1))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_over_by_size_only() {
+        let dir = std::env::temp_dir().join(format!("ducktrace-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ducktrace.log");
+        let rolled = dir.join("ducktrace.log.1");
+
+        // A second instance appends to the log the first is still writing
+        let mut first = RotatingFile::open(&path).unwrap();
+        first.write_all(b"first\n").unwrap();
+        let mut second = RotatingFile::open(&path).unwrap();
+        second.write_all(b"second\n").unwrap();
+        first.write_all(b"first again\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\nfirst again\n");
+        assert!(!rolled.exists());
+
+        // A write that would take it past the limit goes to a fresh file
+        drop(second);
+        let half = vec![b'x'; MAX_LOG_SIZE as usize / 2];
+        first.write_all(&half).unwrap();
+        first.write_all(&half).unwrap();
+        assert!(fs::read_to_string(&rolled).unwrap().starts_with("first\nsecond\nfirst again\nxx"));
+        assert_eq!(fs::metadata(&path).unwrap().len(), half.len() as u64);
+        assert!(!dir.join("ducktrace.log.2").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
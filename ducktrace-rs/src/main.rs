@@ -1,54 +1,269 @@
 mod app;
+mod catalog;
+mod cli;
+mod config;
 mod data;
 mod db;
+mod editor;
+mod export;
+mod hooks;
+mod keymap;
+mod logging;
+mod mode;
+mod remote;
+mod server;
+mod tasks;
+mod timezone;
 mod ui;
 mod watcher;
 
-use std::fs::File;
-use std::io;
-use std::sync::OnceLock;
-use std::time::Duration;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use base64::Engine;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use log::{info, error, debug};
+use log::{info, error, debug, warn, LevelFilter};
 use ratatui::prelude::*;
-use simplelog::{Config, LevelFilter, WriteLogger};
 use tokio::sync::mpsc;
 
 use app::App;
-use data::{ChartData, ExplainData};
-use db::QueryExecutor;
+use data::{count_query, is_chart_file, BoundQuery, ChartData, ExplainData, QueryLogEntry};
+use db::{ConnectionManager, ConnectionState, QueryCancel, QueryExecutor};
+use tasks::{TaskKind, TaskState};
 
-/// Lazy-initialized MotherDuck executor (connects on first drill-down)
-static EXECUTOR: OnceLock<Option<QueryExecutor>> = OnceLock::new();
+/// Database connections by target (each connects on its first drill-down)
+static CONNECTIONS: LazyLock<ConnectionManager> = LazyLock::new(ConnectionManager::default);
 
 enum AppEvent {
     Key(crossterm::event::KeyEvent),
     Mouse(crossterm::event::MouseEvent),
-    FileChange(Box<ChartData>),
-    DrillDownResult(Result<ExplainData, String>),
+    /// A watched chart file changed: its path and contents
+    FileChange(PathBuf, Box<ChartData>),
+    /// A watched chart file changed but couldn't be parsed, even after retrying
+    FileError(PathBuf, String),
+    /// The user config file changed: the reloaded config, or why it didn't parse
+    ConfigChange(Result<Box<config::Config>, String>),
+    /// Rows appended to the append file, one JSON value per line
+    AppendRows(Vec<serde_json::Value>),
+    /// A chart read from stdin
+    StdinChart(Box<ChartData>),
+    /// Rows streamed from a drill-down that is still running, tagged with its generation
+    DrillDownBatch(u64, ExplainData),
+    /// Final rows of a drill-down, or its error, tagged with its generation
+    DrillDownResult(u64, Result<ExplainData, String>),
+    /// Rendered `EXPLAIN ANALYZE` plan of the chart's query, or its error
+    PlanResult(Result<String, String>),
+    /// Fresh (columns, rows) from re-running the chart's query, or its error
+    RerunResult(Result<(Vec<String>, Vec<Vec<serde_json::Value>>), String>),
+    /// Results of a statement run from the SQL tab, or its error
+    SqlResult(String, Result<(Vec<String>, Vec<Vec<serde_json::Value>>), String>),
+    /// A drill-down finished, failed, or was cancelled; recorded in the query log
+    QueryLogged(QueryLogEntry),
+    /// Table, column, and database names for SQL tab completion, or the error
+    SchemaNames(Result<Vec<String>, String>),
+    /// `EXPLAIN` errors from dry-running drill-down templates, for the given source
+    TemplateCheck(String, Vec<String>),
+    /// Connection status change for a database target
+    Connection(String, ConnectionState),
+    /// A chart export finished: where to, and its error if it failed
+    ExportResult(PathBuf, Result<(), String>),
+    /// The token for switching to a profile (by name), or why it couldn't be read
+    ProfileToken(Option<String>, Result<Option<String>, String>),
+    /// A background task ended, by its id in `App::tasks`
+    TaskFinished(u64, TaskState),
     Tick,
 }
 
+/// Value of `--<name> <value>` (or `--<name>=<value>`) among the command-line arguments
+fn flag_value(mut args: impl Iterator<Item = String>, name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    let prefix = format!("--{}=", name);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Socket to accept charts on: the default for `--socket`, or `--socket=<path>`
+fn socket_arg(args: impl Iterator<Item = String>) -> Option<PathBuf> {
+    let mut socket = None;
+    for arg in args {
+        if arg == "--socket" {
+            socket = Some(watcher::get_socket_path());
+        } else if let Some(path) = arg.strip_prefix("--socket=") {
+            socket = Some(PathBuf::from(path));
+        }
+    }
+    socket
+}
+
+/// Log level from the config's `log_level`; an unknown one is logged and the
+/// default used
+fn config_log_level(config: Option<&config::Config>) -> LevelFilter {
+    match config.and_then(|c| c.log_level.as_deref()).map(logging::parse_level) {
+        Some(Ok(level)) => level,
+        Some(Err(e)) => {
+            warn!("{:#}", e);
+            logging::DEFAULT_LOG_LEVEL
+        }
+        None => logging::DEFAULT_LOG_LEVEL,
+    }
+}
+
+/// Finish switching to profile `name` with its token: connections reopen under
+/// the new account, unless the token couldn't be read or a later pick won
+fn apply_profile_token(app: &mut App, name: Option<String>, token: Result<Option<String>, String>) {
+    let result = token.as_ref().map(|_| ()).map_err(String::clone);
+    if app.on_profile_token(name, result) {
+        db::set_motherduck_token(token.ok().flatten());
+        CONNECTIONS.reset();
+    }
+}
+
+/// Wrap drill-down rows for the explain overlay
+fn drill_down_data(columns: Vec<String>, rows: Vec<Vec<serde_json::Value>>) -> ExplainData {
+    ExplainData {
+        title: "Drill-Down Results".to_string(),
+        response_to_command: None,
+        columns,
+        rows,
+        total_count: None,
+    }
+}
+
+/// Total rows a drill-down would return without its `LIMIT`, when the limit was hit.
+/// A failed count only loses the total, so it is logged rather than reported.
+fn total_count(exec: &QueryExecutor, query: &BoundQuery, fetched: usize, cancel: &QueryCancel) -> Option<usize> {
+    let count = count_query(&query.sql)?;
+    if fetched < count.limit {
+        return Some(fetched);
+    }
+    let count_query = BoundQuery { sql: count.sql, params: query.params.clone() };
+    match exec.count_rows(&count_query, cancel) {
+        Ok(total) => Some(total),
+        Err(e) => {
+            warn!("Drill-down row count failed: {:#}", e);
+            None
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Load .env file (from current dir or parent dirs)
     let _ = dotenvy::dotenv();
 
-    // Initialize file logger
-    let log_path = dirs::home_dir()
-        .unwrap_or_default()
-        .join(".claude/ducktrace/ducktrace.log");
-    if let Ok(log_file) = File::create(&log_path) {
-        let _ = WriteLogger::init(LevelFilter::Debug, Config::default(), log_file);
-        info!("DuckTrace TUI started");
+    // Read the config first: it can say where and how much to log
+    let config_result = config::load_config(&config::get_config_path());
+
+    // `--log-level <level>` and `--log-file <path>` win over `log_level` and
+    // `log_file` in the config; a bad level on the command line is an error
+    let log_level_arg = flag_value(std::env::args().skip(1), "log-level")
+        .map(|level| logging::parse_level(&level))
+        .transpose()?;
+    let log_config = config_result.as_ref().ok();
+    let log_path = flag_value(std::env::args().skip(1), "log-file")
+        .or_else(|| log_config.and_then(|c| c.log_file.clone()))
+        .map_or_else(logging::default_log_path, |path| db::expand_home(&path).into());
+    let log_level = log_level_arg.unwrap_or_else(|| config_log_level(log_config));
+    if let Err(e) = logging::init(&log_path, log_level) {
+        eprintln!("Not logging to {}: {:#}", log_path.display(), e);
+    }
+    info!("DuckTrace TUI started");
+
+    // Create app state
+    let mut config = config_result.unwrap_or_else(|e| {
+        warn!("Using default config: {:#}", e);
+        config::Config::default()
+    });
+    // `--db <path>`: a local DuckDB file or `:memory:` for drill-downs
+    let db_arg = flag_value(std::env::args().skip(1), "db");
+    if let Some(ref path) = db_arg {
+        config.database_path = Some(path.clone());
+    }
+    // `--file <path>` (or DUCKTRACE_FILE) watches another data file, so instances
+    // for different projects don't share current.json; `ducktrace sales.csv` is
+    // short for `--file sales.csv`
+    let file_arg = std::env::args().nth(1).filter(|arg| is_chart_file(Path::new(arg)) && !arg.starts_with('-'));
+    let data_file = flag_value(std::env::args().skip(1), "file")
+        .or(file_arg)
+        .or_else(|| std::env::var("DUCKTRACE_FILE").ok().filter(|p| !p.is_empty()))
+        .or_else(|| config.data_file.as_deref().map(db::expand_home));
+    if let Some(path) = data_file {
+        watcher::set_data_path(path.into());
+    }
+    // `--stdin` reads piped charts (one JSON document or NDJSON) instead of any file
+    if std::env::args().skip(1).any(|arg| arg == "--stdin") {
+        watcher::set_read_stdin();
+    }
+    // `--dir <path>` (or DUCKTRACE_DIR) watches every chart in a directory instead
+    let data_dir = flag_value(std::env::args().skip(1), "dir")
+        .or_else(|| std::env::var("DUCKTRACE_DIR").ok().filter(|p| !p.is_empty()))
+        .or_else(|| config.data_dir.as_deref().map(db::expand_home))
+        .filter(|_| !watcher::reads_stdin());
+    if let Some(dir) = data_dir {
+        watcher::set_data_dir(dir.into());
+    }
+    match watcher::get_data_dir() {
+        _ if watcher::reads_stdin() => info!("Reading charts from stdin"),
+        Some(dir) => info!("Watching charts in {}", dir.display()),
+        None => info!("Watching {}", watcher::get_data_path().display()),
+    }
+    // `--max-rows <n>`: rows kept per chart, over the config's `max_rows`
+    let max_rows_arg = flag_value(std::env::args().skip(1), "max-rows").and_then(|n| n.parse::<usize>().ok());
+    if let Some(limit) = max_rows_arg {
+        config.max_rows = Some(limit);
+    }
+    watcher::set_history_limit(config.history_limit.unwrap_or(watcher::DEFAULT_HISTORY_LIMIT));
+    data::set_row_limit(config.max_rows.unwrap_or(data::DEFAULT_ROW_LIMIT));
+    data::set_number_format(config.number_format());
+    timezone::set_time_zone(config.timezone.clone().unwrap_or_else(timezone::TimeZone::local));
+
+    // `--profile <name>` (or `profile` in the config) picks a connection profile
+    let profile = flag_value(std::env::args().skip(1), "profile").or_else(|| config.profile.clone());
+
+    // `ducktrace query "<sql>" ...` charts a query without Claude: the chart is
+    // written to the watched file (and history), then the TUI opens with `--tui`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("query") {
+        let command = cli::QueryCommand::parse(&args[1..])?;
+        let query_profile = match profile {
+            Some(ref name) => Some(config.profiles.get(name).with_context(|| format!("No profile named {} in the config", name))?),
+            None => None,
+        };
+        info!("Running query subcommand against {}", config.database_path.as_deref().unwrap_or("MotherDuck"));
+        let data = command.execute(&config, query_profile)?;
+        let path = cli::chart_output_path(&data);
+        watcher::save_chart(&path, &data)?;
+        println!("Wrote {} rows ({} by {}) to {}", data.rows.len(), data.y_field, data.x_field, path.display());
+        if let Some(ref export_path) = command.export {
+            let export_path = PathBuf::from(db::expand_home(export_path));
+            let palette = ui::theme::Palette::resolve(&config.palette, data.palette.as_ref());
+            export::export_chart(&data, &palette, data.default_bar_mode(), &export_path)?;
+            println!("Exported the chart to {}", export_path.display());
+        }
+        if !command.open_tui {
+            return Ok(());
+        }
     }
 
+    // A startup profile's token is read before the terminal is set up, so the
+    // first queries already run under its account
+    let startup_token = profile.as_ref().and_then(|name| config.profiles.get(name)).map(|p| p.token());
+
     // Set up terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -56,28 +271,67 @@ async fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app state
-    let mut app = App::new();
+    let mut app = App::new(config);
+    if profile.is_some() {
+        app.select_profile(profile.clone());
+        if let Some(token) = startup_token {
+            app.take_pending_profile();
+            apply_profile_token(&mut app, profile, token.map_err(|e| format!("{:#}", e)));
+        }
+    }
+    app.explain_size = config::load_ui_state(&config::get_ui_state_path()).explain_size;
     app.refresh_history();
 
     // Set up event channel
     let (tx, mut rx) = mpsc::channel::<AppEvent>(32);
 
+    // With --stdin, charts are piped in instead of watched on disk
+    if watcher::reads_stdin() {
+        let stdin_tx = tx.clone();
+        tokio::spawn(async move {
+            let (data_tx, mut data_rx) = mpsc::channel::<ChartData>(16);
+            let reader = tokio::task::spawn_blocking(move || {
+                if let Err(e) = watcher::read_stdin(data_tx) {
+                    error!("Reading charts from stdin failed: {:#}", e);
+                }
+                info!("Stdin closed");
+            });
+
+            while let Some(data) = data_rx.recv().await {
+                if stdin_tx.send(AppEvent::StdinChart(Box::new(data))).await.is_err() {
+                    break;
+                }
+            }
+            let _ = reader.await;
+        });
+    }
+
     // Spawn file watcher with adapter channel
     let watcher_tx = tx.clone();
     tokio::spawn(async move {
-        let (data_tx, mut data_rx) = mpsc::channel::<ChartData>(16);
+        if watcher::reads_stdin() {
+            return;
+        }
+        let (data_tx, mut data_rx) = mpsc::channel::<watcher::FileUpdate>(16);
 
         // Spawn the watcher
         let watcher_handle = tokio::spawn(async move {
-            if let Err(e) = watcher::watch_file(data_tx).await {
+            let result = match watcher::get_data_dir() {
+                Some(dir) => watcher::watch_dir(dir.clone(), data_tx).await,
+                None => watcher::watch_file(data_tx).await,
+            };
+            if let Err(e) = result {
                 eprintln!("File watcher error: {}", e);
             }
         });
 
         // Forward data events to main channel
-        while let Some(data) = data_rx.recv().await {
-            if watcher_tx.send(AppEvent::FileChange(Box::new(data))).await.is_err() {
+        while let Some((path, result)) = data_rx.recv().await {
+            let event = match result {
+                Ok(data) => AppEvent::FileChange(path, Box::new(data)),
+                Err(e) => AppEvent::FileError(path, e),
+            };
+            if watcher_tx.send(event).await.is_err() {
                 break;
             }
         }
@@ -85,23 +339,149 @@ async fn main() -> Result<()> {
         let _ = watcher_handle.await;
     });
 
-    // Spawn input event handler (keyboard + mouse)
+    // Tail current.ndjson (beside the data file) for rows to add to the open chart
+    if !watcher::reads_stdin() && watcher::get_data_dir().is_none() {
+        let append_tx = tx.clone();
+        tokio::spawn(async move {
+            let (rows_tx, mut rows_rx) = mpsc::channel::<Vec<serde_json::Value>>(16);
+            let tail = tokio::spawn(async move {
+                if let Err(e) = watcher::watch_append(rows_tx).await {
+                    error!("Append file watcher failed: {:#}", e);
+                }
+            });
+
+            while let Some(rows) = rows_rx.recv().await {
+                if append_tx.send(AppEvent::AppendRows(rows)).await.is_err() {
+                    break;
+                }
+            }
+            let _ = tail.await;
+        });
+    }
+
+    // Reload the user config whenever it's edited
+    let config_tx = tx.clone();
+    tokio::spawn(async move {
+        let (result_tx, mut result_rx) = mpsc::channel::<Result<config::Config, String>>(4);
+        let config_watcher = tokio::spawn(async move {
+            if let Err(e) = watcher::watch_config(config::get_config_path(), result_tx).await {
+                error!("Config watcher failed: {:#}", e);
+            }
+        });
+
+        while let Some(mut result) = result_rx.recv().await {
+            if let Ok(config) = &mut result {
+                // `--db`, `--max-rows`, and `--log-level` still win over the file
+                if let Some(path) = &db_arg {
+                    config.database_path = Some(path.clone());
+                }
+                if let Some(limit) = max_rows_arg {
+                    config.max_rows = Some(limit);
+                }
+                watcher::set_history_limit(config.history_limit.unwrap_or(watcher::DEFAULT_HISTORY_LIMIT));
+                data::set_row_limit(config.max_rows.unwrap_or(data::DEFAULT_ROW_LIMIT));
+                data::set_number_format(config.number_format());
+                timezone::set_time_zone(config.timezone.clone().unwrap_or_else(timezone::TimeZone::local));
+                if log_level_arg.is_none() {
+                    logging::set_level(config_log_level(Some(config)));
+                }
+            }
+            if config_tx.send(AppEvent::ConfigChange(result.map(Box::new))).await.is_err() {
+                break;
+            }
+        }
+        let _ = config_watcher.await;
+    });
+
+    // With --socket, also accept charts pushed to a unix socket
+    #[cfg(unix)]
+    if let Some(socket_path) = socket_arg(std::env::args().skip(1)) {
+        info!("Listening for charts on {}", socket_path.display());
+        let socket_tx = tx.clone();
+        tokio::spawn(async move {
+            let (data_tx, mut data_rx) = mpsc::channel::<(PathBuf, ChartData)>(16);
+            let listener = tokio::spawn(async move {
+                if let Err(e) = watcher::listen_socket(socket_path, data_tx).await {
+                    error!("Chart socket failed: {:#}", e);
+                }
+            });
+
+            while let Some((path, data)) = data_rx.recv().await {
+                if socket_tx.send(AppEvent::FileChange(path, Box::new(data))).await.is_err() {
+                    break;
+                }
+            }
+            let _ = listener.await;
+        });
+    }
+
+    // With --serve <addr>, also accept charts on `POST /chart`
+    if let Some(addr) = flag_value(std::env::args().skip(1), "serve") {
+        let http_tx = tx.clone();
+        tokio::spawn(async move {
+            let (data_tx, mut data_rx) = mpsc::channel::<(PathBuf, ChartData)>(16);
+            let server = tokio::spawn(async move {
+                if let Err(e) = server::serve(addr, data_tx).await {
+                    error!("Chart server failed: {:#}", e);
+                }
+            });
+
+            while let Some((path, data)) = data_rx.recv().await {
+                if http_tx.send(AppEvent::FileChange(path, Box::new(data))).await.is_err() {
+                    break;
+                }
+            }
+            let _ = server.await;
+        });
+    }
+
+    // With --connect <ws://...>, also render charts pushed over a WebSocket channel
+    if let Some(url) = flag_value(std::env::args().skip(1), "connect") {
+        let remote_tx = tx.clone();
+        tokio::spawn(async move {
+            let (data_tx, mut data_rx) = mpsc::channel::<watcher::FileUpdate>(16);
+            let client = tokio::spawn(async move {
+                if let Err(e) = remote::connect(url, data_tx).await {
+                    error!("Chart channel failed: {:#}", e);
+                }
+            });
+
+            while let Some((path, result)) = data_rx.recv().await {
+                let event = match result {
+                    Ok(data) => AppEvent::FileChange(path, Box::new(data)),
+                    Err(e) => AppEvent::FileError(path, e),
+                };
+                if remote_tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+            let _ = client.await;
+        });
+    }
+
+    // Spawn input event handler (keyboard + mouse), which stands aside while an
+    // external editor has the terminal
+    let input_paused = Arc::new(AtomicBool::new(false));
     let input_tx = tx.clone();
+    let paused = input_paused.clone();
     tokio::spawn(async move {
         loop {
+            if paused.load(Ordering::Relaxed) {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                continue;
+            }
             if event::poll(Duration::from_millis(50)).unwrap_or(false) {
                 match event::read() {
-                    Ok(Event::Key(key)) => {
+                    Ok(Event::Key(key))
                         if key.kind == KeyEventKind::Press
-                            && input_tx.send(AppEvent::Key(key)).await.is_err()
-                        {
-                            break;
-                        }
+                            && input_tx.send(AppEvent::Key(key)).await.is_err() =>
+                    {
+                        break;
                     }
-                    Ok(Event::Mouse(mouse)) => {
-                        if input_tx.send(AppEvent::Mouse(mouse)).await.is_err() {
-                            break;
-                        }
+                    Ok(Event::Mouse(mouse))
+                        if input_tx.send(AppEvent::Mouse(mouse)).await.is_err() =>
+                    {
+                        break;
                     }
                     _ => {}
                 }
@@ -129,71 +509,310 @@ async fn main() -> Result<()> {
         // Draw UI
         terminal.draw(|f| ui::render(f, &mut app))?;
 
-        // Check for drill-down request
-        if let Some(query) = app.take_pending_drill_down() {
-            info!("Drill-down query requested");
-            debug!("Query: {}", query);
+        // Copy to the system clipboard through the terminal (OSC 52), which also
+        // works over SSH
+        if let Some(text) = app.take_pending_clipboard() {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+            let backend = terminal.backend_mut();
+            if let Err(e) = write!(backend, "\x1b]52;c;{}\x07", encoded).and_then(|_| Write::flush(backend)) {
+                warn!("Failed to write to the clipboard: {}", e);
+            }
+        }
+
+        // Hand the terminal to $EDITOR for the Query tab's `e`, once the input
+        // task has finished its last poll
+        if let Some(text) = app.take_pending_external_edit() {
+            input_paused.store(true, Ordering::Relaxed);
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            disable_raw_mode()?;
+            execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+            let result = editor::edit_externally(&text);
+            enable_raw_mode()?;
+            execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture)?;
+            terminal.clear()?;
+            input_paused.store(false, Ordering::Relaxed);
+            app.on_external_edit(result);
+        }
+
+        // Read a newly picked profile's token off the UI thread, since it may run a
+        // command; the switch happens when it's back
+        if let Some((name, profile)) = app.take_pending_profile() {
             let tx_clone = drilldown_tx.clone();
             tokio::task::spawn_blocking(move || {
-                // Lazy-initialize executor on first drill-down
-                let executor = EXECUTOR.get_or_init(|| {
-                    info!("Initializing MotherDuck connection");
-                    match QueryExecutor::connect() {
-                        Ok(exec) => {
-                            info!("MotherDuck connection successful");
-                            Some(exec)
-                        }
-                        Err(e) => {
-                            error!("MotherDuck connection failed: {}", e);
-                            None
-                        }
-                    }
-                });
+                let token = profile.map_or(Ok(None), |p| p.token()).map_err(|e| format!("{:#}", e));
+                let _ = tx_clone.blocking_send(AppEvent::ProfileToken(name, token));
+            });
+        }
 
-                let event = match executor {
-                    Some(exec) => {
+        // Check for drill-down request
+        if let Some((generation, query)) = app.take_pending_drill_down() {
+            let target = app.database_target();
+            let attachments = app.attachments();
+            info!("Drill-down query requested against {}", target);
+            debug!("Query: {} with {:?}", query.sql, query.params);
+            let tx_clone = drilldown_tx.clone();
+            // A new drill-down supersedes any still running, but not other kinds of query
+            app.tasks.cancel_kind(TaskKind::DrillDown);
+            let (task, cancel) = app.tasks.start_query(TaskKind::DrillDown, &query.sql);
+            tokio::task::spawn_blocking(move || {
+                let on_state = |state| {
+                    let _ = tx_clone.blocking_send(AppEvent::Connection(target.clone(), state));
+                };
+                let started = SystemTime::now();
+                let mut fetched = 0;
+                let outcome = CONNECTIONS
+                    .executor(&target, &cancel, on_state)
+                    .and_then(|exec| {
+                        exec.ensure_attached(&attachments)?;
                         info!("Executing drill-down query");
-                        match exec.execute_drill_down(&query) {
-                            Ok((columns, rows)) => {
-                                info!("Drill-down success: {} columns, {} rows", columns.len(), rows.len());
-                                let explain_data = ExplainData {
-                                    title: "Drill-Down Results".to_string(),
-                                    response_to_command: None,
-                                    columns,
-                                    rows,
-                                    total_count: None,
-                                };
-                                AppEvent::DrillDownResult(Ok(explain_data))
-                            }
-                            Err(e) => {
-                                error!("Drill-down query failed: {:#}", e);
-                                AppEvent::DrillDownResult(Err(format!("{:#}", e)))
+                        let on_batch = |columns, rows: Vec<Vec<serde_json::Value>>| {
+                            fetched += rows.len();
+                            if !cancel.is_cancelled() {
+                                let _ = tx_clone.blocking_send(AppEvent::DrillDownBatch(generation, drill_down_data(columns, rows)));
                             }
-                        }
+                        };
+                        let (columns, rows) = exec.execute_drill_down(&query, &cancel, on_batch)?;
+                        let total_count = total_count(&exec, &query, fetched + rows.len(), &cancel);
+                        Ok((columns, rows, total_count))
+                    });
+
+                let cancelled = cancel.is_cancelled();
+                let _ = tx_clone.blocking_send(AppEvent::TaskFinished(task, TaskState::finished(&outcome, cancelled)));
+                let entry = QueryLogEntry {
+                    timestamp: started.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+                    target: target.clone(),
+                    sql: query.sql,
+                    params: query.params,
+                    duration_ms: started.elapsed().map(|d| d.as_millis() as u64).unwrap_or(0),
+                    rows: fetched + outcome.as_ref().map(|(_, rows, _)| rows.len()).unwrap_or(0),
+                    error: match outcome {
+                        Err(ref e) => Some(format!("{:#}", e)),
+                        Ok(_) if cancelled => Some("Query cancelled".to_string()),
+                        Ok(_) => None,
+                    },
+                };
+                let _ = tx_clone.blocking_send(AppEvent::QueryLogged(entry));
+
+                // Sent even when cancelled, so an overlay still waiting on it stops loading;
+                // one that moved on ignores it by its generation
+                let event = match outcome {
+                    _ if cancelled => {
+                        info!("Drill-down cancelled");
+                        AppEvent::DrillDownResult(generation, Err("Query cancelled".to_string()))
                     }
-                    None => {
-                        error!("No MotherDuck executor available");
-                        AppEvent::DrillDownResult(Err(
-                            "MotherDuck not connected. Set MOTHERDUCK_TOKEN environment variable.".to_string()
-                        ))
+                    Ok((columns, rows, total_count)) => {
+                        info!("Drill-down success: {} columns, {} trailing rows", columns.len(), rows.len());
+                        let mut data = drill_down_data(columns, rows);
+                        data.total_count = total_count;
+                        AppEvent::DrillDownResult(generation, Ok(data))
+                    }
+                    Err(e) => {
+                        error!("Drill-down against {} failed: {:#}", target, e);
+                        AppEvent::DrillDownResult(generation, Err(format!("{:#}", e)))
                     }
                 };
                 let _ = tx_clone.blocking_send(event);
             });
         }
 
+        // Check for EXPLAIN ANALYZE request
+        if let Some((query, database)) = app.take_pending_plan() {
+            let target = app.database_target();
+            let attachments = app.attachments();
+            info!("Query plan requested against {}", target);
+            let tx_clone = drilldown_tx.clone();
+            app.tasks.cancel_kind(TaskKind::Plan);
+            let (task, cancel) = app.tasks.start_query(TaskKind::Plan, &query);
+            tokio::task::spawn_blocking(move || {
+                let on_state = |state| {
+                    let _ = tx_clone.blocking_send(AppEvent::Connection(target.clone(), state));
+                };
+                let result = CONNECTIONS
+                    .executor(&target, &cancel, on_state)
+                    .and_then(|exec| {
+                        exec.ensure_attached(&attachments)?;
+                        exec.explain_analyze(&query, database.as_deref(), &cancel)
+                    })
+                    .map_err(|e| {
+                        error!("EXPLAIN ANALYZE failed: {:#}", e);
+                        format!("{:#}", e)
+                    });
+                let cancelled = cancel.is_cancelled();
+                let _ = tx_clone.blocking_send(AppEvent::TaskFinished(task, TaskState::finished(&result, cancelled)));
+                if !cancelled {
+                    let _ = tx_clone.blocking_send(AppEvent::PlanResult(result));
+                }
+            });
+        }
+
+        // Check for a re-run of the chart's query
+        if let Some((query, database)) = app.take_pending_rerun() {
+            let target = app.database_target();
+            let attachments = app.attachments();
+            info!("Re-run requested against {}", target);
+            let tx_clone = drilldown_tx.clone();
+            app.tasks.cancel_kind(TaskKind::Rerun);
+            let (task, cancel) = app.tasks.start_query(TaskKind::Rerun, &query);
+            tokio::task::spawn_blocking(move || {
+                let on_state = |state| {
+                    let _ = tx_clone.blocking_send(AppEvent::Connection(target.clone(), state));
+                };
+                let result = CONNECTIONS
+                    .executor(&target, &cancel, on_state)
+                    .and_then(|exec| {
+                        exec.ensure_attached(&attachments)?;
+                        exec.run_query(&query, database.as_deref(), &cancel)
+                    })
+                    .map_err(|e| {
+                        error!("Re-run failed: {:#}", e);
+                        format!("{:#}", e)
+                    });
+                let state = TaskState::finished(&result, cancel.is_cancelled());
+                let _ = tx_clone.blocking_send(AppEvent::TaskFinished(task, state));
+                // Sent even when superseded, so the Query tab stops waiting on it
+                let _ = tx_clone.blocking_send(AppEvent::RerunResult(result));
+            });
+        }
+
+        // Check for a statement run from the SQL tab
+        if let Some((sql, database)) = app.take_pending_sql() {
+            let target = app.database_target();
+            let attachments = app.attachments();
+            info!("SQL tab query requested against {}", target);
+            debug!("Query: {}", sql);
+            let tx_clone = drilldown_tx.clone();
+            app.tasks.cancel_kind(TaskKind::Sql);
+            let (task, cancel) = app.tasks.start_query(TaskKind::Sql, &sql);
+            tokio::task::spawn_blocking(move || {
+                let on_state = |state| {
+                    let _ = tx_clone.blocking_send(AppEvent::Connection(target.clone(), state));
+                };
+                let result = CONNECTIONS
+                    .executor(&target, &cancel, on_state)
+                    .and_then(|exec| {
+                        exec.ensure_attached(&attachments)?;
+                        exec.run_query(&sql, database.as_deref(), &cancel)
+                    })
+                    .map_err(|e| {
+                        error!("SQL tab query failed: {:#}", e);
+                        format!("{:#}", e)
+                    });
+                let state = TaskState::finished(&result, cancel.is_cancelled());
+                let _ = tx_clone.blocking_send(AppEvent::TaskFinished(task, state));
+                // Sent even when superseded, so the SQL tab stops waiting on it
+                let _ = tx_clone.blocking_send(AppEvent::SqlResult(sql, result));
+            });
+        }
+
+        // Load names for SQL tab completion; a new load doesn't cancel one still running
+        if let Some(database) = app.take_pending_schema() {
+            let target = app.database_target();
+            let attachments = app.attachments();
+            info!("Schema names requested from {}", target);
+            let tx_clone = drilldown_tx.clone();
+            let (task, cancel) = app.tasks.start_query(TaskKind::Schema, "Table and column names for completion");
+            tokio::task::spawn_blocking(move || {
+                let on_state = |state| {
+                    let _ = tx_clone.blocking_send(AppEvent::Connection(target.clone(), state));
+                };
+                let result = CONNECTIONS
+                    .executor(&target, &cancel, on_state)
+                    .and_then(|exec| {
+                        exec.ensure_attached(&attachments)?;
+                        exec.schema_names(database.as_deref(), &cancel)
+                    })
+                    .map_err(|e| format!("{:#}", e));
+                let state = TaskState::finished(&result, cancel.is_cancelled());
+                let _ = tx_clone.blocking_send(AppEvent::TaskFinished(task, state));
+                let _ = tx_clone.blocking_send(AppEvent::SchemaNames(result));
+            });
+        }
+
+        // Run [hooks] commands; a failing one only shows up in the log
+        for (command, payload) in app.take_pending_hooks() {
+            let task = app.tasks.start(TaskKind::Hook, &command);
+            let tx_clone = drilldown_tx.clone();
+            tokio::task::spawn_blocking(move || {
+                let result = hooks::run_hook(&command, &payload);
+                if let Err(ref e) = result {
+                    warn!("Hook failed: {:#}", e);
+                }
+                let _ = tx_clone.blocking_send(AppEvent::TaskFinished(task, TaskState::finished(&result, false)));
+            });
+        }
+
+        // Draw `:export` charts off the UI thread; a large PNG takes a while
+        if let Some(job) = app.take_pending_export() {
+            let task = app.tasks.start(TaskKind::Export, &job.path.display().to_string());
+            let tx_clone = drilldown_tx.clone();
+            tokio::task::spawn_blocking(move || {
+                let result = export::export_chart(&job.data, &job.palette, job.bar_mode, &job.path)
+                    .map_err(|e| format!("{:#}", e));
+                let _ = tx_clone.blocking_send(AppEvent::TaskFinished(task, TaskState::finished(&result, false)));
+                let _ = tx_clone.blocking_send(AppEvent::ExportResult(job.path, result));
+            });
+        }
+
+        // Dry-run drill-down templates for the Mask tab
+        if let Some((source, queries)) = app.take_pending_template_check() {
+            let target = app.database_target();
+            let attachments = app.attachments();
+            let tx_clone = drilldown_tx.clone();
+            let label = format!("Drill-down templates of {}", source);
+            let (task, cancel) = app.tasks.start_query(TaskKind::TemplateCheck, &label);
+            tokio::task::spawn_blocking(move || {
+                let on_state = |state| {
+                    let _ = tx_clone.blocking_send(AppEvent::Connection(target.clone(), state));
+                };
+                let errors = CONNECTIONS
+                    .executor(&target, &cancel, on_state)
+                    .and_then(|exec| {
+                        exec.ensure_attached(&attachments)?;
+                        Ok(queries
+                            .iter()
+                            .filter_map(|(label, query)| {
+                                let result = exec.validate_query(query, &cancel);
+                                result.err().map(|e| format!("{}: {:#}", label, e))
+                            })
+                            .collect())
+                    })
+                    .unwrap_or_else(|e| vec![format!("Could not check against {}: {:#}", target, e)]);
+                let state = if cancel.is_cancelled() { TaskState::Cancelled } else { TaskState::Done };
+                let _ = tx_clone.blocking_send(AppEvent::TaskFinished(task, state));
+                let _ = tx_clone.blocking_send(AppEvent::TemplateCheck(source, errors));
+            });
+        }
+
         // Handle events
         if let Some(event) = rx.recv().await {
             match event {
                 AppEvent::Key(key) => app.handle_key(key),
                 AppEvent::Mouse(mouse) => app.handle_mouse(mouse),
-                AppEvent::FileChange(data) => {
-                    app.on_data_update(*data);
+                AppEvent::FileChange(path, data) => {
+                    app.on_file_change(path, *data);
+                    app.refresh_history();
+                }
+                AppEvent::FileError(path, error) => app.on_file_error(path, error),
+                AppEvent::ConfigChange(result) => app.on_config_change(result.map(|config| *config)),
+                AppEvent::AppendRows(rows) => app.on_append_rows(rows),
+                AppEvent::StdinChart(data) => {
+                    app.on_stdin_chart(*data);
                     app.refresh_history();
                 }
-                AppEvent::DrillDownResult(result) => match result {
-                    Ok(data) => app.on_drill_down_success(data),
-                    Err(e) => app.on_drill_down_error(e),
+                AppEvent::DrillDownBatch(generation, data) => app.on_drill_down_batch(generation, data),
+                AppEvent::PlanResult(result) => app.on_plan_result(result),
+                AppEvent::RerunResult(result) => app.on_rerun_result(result),
+                AppEvent::QueryLogged(entry) => app.on_query_logged(entry),
+                AppEvent::SchemaNames(result) => app.on_schema_names(result),
+                AppEvent::TemplateCheck(source, errors) => app.on_template_check(source, errors),
+                AppEvent::SqlResult(sql, result) => app.on_sql_result(sql, result),
+                AppEvent::ExportResult(path, result) => app.on_export_result(path, result),
+                AppEvent::Connection(target, state) => app.on_connection_state(target, state),
+                AppEvent::ProfileToken(name, token) => apply_profile_token(&mut app, name, token),
+                AppEvent::TaskFinished(task, state) => app.tasks.finish(task, state),
+                AppEvent::DrillDownResult(generation, result) => match result {
+                    Ok(data) => app.on_drill_down_success(generation, data),
+                    Err(e) => app.on_drill_down_error(generation, e),
                 },
                 AppEvent::Tick => app.tick(),
             }
@@ -0,0 +1,136 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use std::path::PathBuf;
+
+/// What keys go to. `App::modes` is a stack: the mode on top handles every key,
+/// and leaving it hands keys back to the one below. With the stack empty, keys
+/// go to the tabs (normal mode).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    /// The help overlay; any key closes it
+    Help,
+    /// Why a chart file didn't load; any key closes it
+    FileProblems,
+    /// Drill-down results
+    Explain,
+    /// EXPLAIN ANALYZE plan
+    Plan,
+    /// Two history charts side by side (`App::comparison`)
+    Compare,
+    /// This session's drill-down queries
+    QueryLog,
+    /// Tail of the log file
+    AppLog,
+    /// Connection profile picker, with the selected row (0 is "no profile")
+    ProfilePicker(usize),
+    /// Chart switcher, with the selected workspace slot
+    ChartPicker(usize),
+    /// Background task panel, with the selected row (newest first)
+    Tasks(usize),
+    /// Typing a filter that applies as it's typed and stays after Enter
+    Search(SearchTarget),
+    /// Typing a value that's acted on when Enter is pressed
+    Command(Command),
+    /// Editing the SQL tab's query
+    Editor,
+    /// Asking before something that can't be undone; `y` goes ahead
+    Confirm(Confirm),
+}
+
+impl Mode {
+    /// Whether keys are typed as text here rather than acted on
+    pub fn takes_text(&self) -> bool {
+        matches!(self, Mode::Search(_) | Mode::Command(_) | Mode::Editor)
+    }
+}
+
+/// An action waiting on a yes or no
+#[derive(Debug, Clone, PartialEq)]
+pub enum Confirm {
+    /// Close the chart (`c`), deleting the data file when it's ours
+    ClearData,
+    /// Delete an analysis from history (`d` on the Home tab)
+    DeleteHistory { path: PathBuf, title: String },
+}
+
+/// What a search applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchTarget {
+    /// Rows on the Data tab (`/`)
+    Data,
+    /// Analyses on the Home tab (`/`)
+    History,
+    /// Lines of the Query tab (`/`), scrolled to rather than filtered
+    Query,
+    /// X labels on the Chart tab (`/`), highlighted rather than filtered
+    Chart,
+}
+
+/// A one-line prompt and what's been typed into it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub kind: CommandKind,
+    pub text: String,
+}
+
+/// What a prompt's text is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandKind {
+    /// Row number to jump to (`:`)
+    GotoRow,
+    /// Tags for the selected analysis (`t`)
+    Tags,
+    /// New title for the selected analysis (`r`)
+    Rename,
+    /// File to export the chart to (`E`, `M`)
+    Export,
+    /// Filter for the selected drill-down column (`f`)
+    ExplainFilter,
+}
+
+impl Command {
+    pub fn new(kind: CommandKind, text: impl Into<String>) -> Self {
+        Self { kind, text: text.into() }
+    }
+
+    /// Apply `key` to the prompt's text, dropping characters it doesn't take
+    pub fn edit(&mut self, key: KeyEvent) -> Edit {
+        match key.code {
+            KeyCode::Char(c) if !self.kind.accepts(c) => Edit::Ignored,
+            _ => edit_text(&mut self.text, key),
+        }
+    }
+}
+
+impl CommandKind {
+    /// Whether `c` can be typed into this prompt
+    fn accepts(self, c: char) -> bool {
+        self != CommandKind::GotoRow || c.is_ascii_digit()
+    }
+}
+
+/// What a key did to a line of text being typed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Cancel,
+    Submit,
+    Changed,
+    Ignored,
+}
+
+/// Apply `key` to text being typed: Esc cancels, Enter submits, and Backspace
+/// and characters edit it
+pub fn edit_text(text: &mut String, key: KeyEvent) -> Edit {
+    match key.code {
+        KeyCode::Esc => Edit::Cancel,
+        KeyCode::Enter => Edit::Submit,
+        KeyCode::Backspace => {
+            text.pop();
+            Edit::Changed
+        }
+        KeyCode::Char(c) => {
+            text.push(c);
+            Edit::Changed
+        }
+        _ => Edit::Ignored,
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::watcher::{accept_chart, FileUpdate};
+
+/// Delay before reconnecting after the connection drops; doubles up to [`MAX_BACKOFF`]
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait between reconnect attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Subscribe to the WebSocket channel at `url` for `--connect` and send each chart
+/// pushed on it, with the URL as its path. A dropped or refused connection is
+/// retried with backoff, so the remote end (often an SSH tunnel) can come and go.
+pub async fn connect(url: String, tx: mpsc::Sender<FileUpdate>) -> Result<()> {
+    let source = PathBuf::from(&url);
+    let mut delay = RECONNECT_BACKOFF;
+    loop {
+        match subscribe(&url, &source, &tx).await {
+            Ok(()) if tx.is_closed() => return Ok(()),
+            Ok(()) => {
+                info!("Chart channel {} closed, reconnecting", url);
+                delay = RECONNECT_BACKOFF;
+            }
+            Err(e) => warn!("Chart channel {} failed, retrying in {:?}: {:#}", url, delay, e),
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Read charts from one connection until it closes. Each text or binary message is
+/// one chart document, answered with `{"ok":true}` or `{"error":...}`.
+async fn subscribe(url: &str, source: &Path, tx: &mpsc::Sender<FileUpdate>) -> Result<()> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+    info!("Subscribed to chart channel {}", url);
+
+    while let Some(message) = socket.next().await {
+        let body = match message? {
+            Message::Text(text) => text.into_bytes(),
+            Message::Binary(bytes) => bytes,
+            Message::Close(_) => break,
+            // Pings are answered by the library
+            _ => continue,
+        };
+        let chart = serde_json::from_slice::<serde_json::Value>(&body)
+            .map_err(anyhow::Error::from)
+            .and_then(accept_chart);
+        let reply = match &chart {
+            Ok(data) => {
+                info!("Chart received over WebSocket: {}", data.title);
+                serde_json::json!({ "ok": true })
+            }
+            Err(e) => serde_json::json!({ "error": format!("{:#}", e) }),
+        };
+        if tx.send((source.to_path_buf(), chart.map_err(|e| format!("{:#}", e)))).await.is_err() {
+            break;
+        }
+        socket.send(Message::Text(reply.to_string())).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use crate::data::ChartData;
+use crate::watcher::accept_chart;
+
+/// Largest request body accepted, in bytes
+const MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// Most header lines read before a request is rejected
+const MAX_HEADERS: usize = 100;
+
+/// Longest request or header line read, in bytes
+const MAX_LINE: u64 = 8192;
+
+/// Serve `POST /chart` on `addr` for `--serve`, sending each chart through the
+/// channel with a `http://<addr>/chart` path. Only enough HTTP/1.1 is spoken for a
+/// local endpoint: one request per connection, with a `Content-Length` body.
+pub async fn serve(addr: String, tx: mpsc::Sender<(PathBuf, ChartData)>) -> Result<()> {
+    let listener = TcpListener::bind(&addr)
+        .await
+        .with_context(|| format!("Failed to listen on {}", addr))?;
+    let source = PathBuf::from(format!("http://{}/chart", addr));
+    info!("Serving charts on {}", source.display());
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let source = source.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, source, tx).await {
+                warn!("Chart request from {} failed: {:#}", peer, e);
+            }
+        });
+    }
+}
+
+/// One line of the request head, empty at the end of input; None past `MAX_LINE` bytes
+async fn read_head_line(reader: &mut BufReader<TcpStream>) -> Result<Option<String>> {
+    let mut line = String::new();
+    let read = reader.take(MAX_LINE).read_line(&mut line).await?;
+    Ok(((read as u64) < MAX_LINE || line.ends_with('\n')).then_some(line))
+}
+
+/// Answer one request: 200 with the chart accepted, 400 for a bad chart, and
+/// 404 or 405 for anything other than `POST /chart`. Requests from a browser (with
+/// an `Origin` header) get 403, and bodies not sent as JSON 415, so a web page
+/// can't post a chart to the local endpoint behind the user's back.
+async fn handle(stream: TcpStream, source: PathBuf, tx: mpsc::Sender<(PathBuf, ChartData)>) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let Some(request_line) = read_head_line(&mut reader).await? else {
+        return respond(reader.get_mut(), 414, "request line too long").await;
+    };
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let mut content_length = None;
+    let mut content_type = None;
+    let mut has_origin = false;
+    let mut expect_continue = false;
+    let mut head_ended = false;
+    for _ in 0..MAX_HEADERS {
+        let Some(line) = read_head_line(&mut reader).await? else {
+            return respond(reader.get_mut(), 431, "header line too long").await;
+        };
+        let line = line.trim_end();
+        if line.is_empty() {
+            head_ended = true;
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse::<usize>().ok();
+        } else if name.eq_ignore_ascii_case("content-type") {
+            content_type = Some(value.to_ascii_lowercase());
+        } else if name.eq_ignore_ascii_case("origin") {
+            has_origin = true;
+        } else if name.eq_ignore_ascii_case("expect") {
+            expect_continue = value.eq_ignore_ascii_case("100-continue");
+        }
+    }
+    if !head_ended {
+        return respond(reader.get_mut(), 431, "too many headers").await;
+    }
+
+    let path = target.split('?').next().unwrap_or("");
+    if path != "/chart" {
+        return respond(reader.get_mut(), 404, "not found").await;
+    }
+    if method != "POST" {
+        return respond(reader.get_mut(), 405, "use POST /chart").await;
+    }
+    if has_origin {
+        return respond(reader.get_mut(), 403, "requests from web pages aren't accepted").await;
+    }
+    let is_json = content_type.as_deref().and_then(|t| t.split(';').next()).is_some_and(|t| t.trim() == "application/json");
+    if !is_json {
+        return respond(reader.get_mut(), 415, "send the chart with Content-Type: application/json").await;
+    }
+    let Some(length) = content_length else {
+        return respond(reader.get_mut(), 411, "Content-Length required").await;
+    };
+    if length > MAX_BODY {
+        return respond(reader.get_mut(), 413, "chart too large").await;
+    }
+
+    // curl waits for this before sending larger bodies
+    if expect_continue {
+        reader.get_mut().write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    let chart = serde_json::from_slice::<serde_json::Value>(&body)
+        .map_err(anyhow::Error::from)
+        .and_then(accept_chart);
+    match chart {
+        Ok(data) => {
+            info!("Chart received over HTTP: {}", data.title);
+            if tx.send((source, data)).await.is_err() {
+                bail!("TUI is shutting down");
+            }
+            respond(reader.get_mut(), 200, "ok").await
+        }
+        Err(e) => respond(reader.get_mut(), 400, &format!("{:#}", e)).await,
+    }
+}
+
+/// Write a JSON response, `{"ok":true}` for success or `{"error":...}`, and close
+async fn respond(stream: &mut TcpStream, status: u16, message: &str) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    };
+    let body = if status == 200 {
+        serde_json::json!({ "ok": true })
+    } else {
+        serde_json::json!({ "error": message })
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
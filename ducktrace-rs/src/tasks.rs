@@ -0,0 +1,175 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::db::QueryCancel;
+
+/// Finished tasks kept for the task panel; running ones are always kept
+const FINISHED_KEPT: usize = 20;
+
+/// What a background task is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskKind {
+    DrillDown,
+    Plan,
+    Rerun,
+    Sql,
+    Schema,
+    TemplateCheck,
+    Hook,
+    Export,
+}
+
+impl TaskKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TaskKind::DrillDown => "Drill-down",
+            TaskKind::Plan => "Plan",
+            TaskKind::Rerun => "Re-run",
+            TaskKind::Sql => "SQL tab",
+            TaskKind::Schema => "Schema",
+            TaskKind::TemplateCheck => "Template check",
+            TaskKind::Hook => "Hook",
+            TaskKind::Export => "Export",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskState {
+    Running,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+impl TaskState {
+    /// How a task that produced `result` ended
+    pub fn finished<T, E: Display>(result: &Result<T, E>, cancelled: bool) -> Self {
+        match result {
+            _ if cancelled => TaskState::Cancelled,
+            Ok(_) => TaskState::Done,
+            Err(e) => TaskState::Failed(format!("{:#}", e)),
+        }
+    }
+}
+
+/// A piece of work running (or run) off the UI thread
+pub struct Task {
+    pub id: u64,
+    pub kind: TaskKind,
+    /// What it's working on: the SQL, hook command, or file
+    pub label: String,
+    pub state: TaskState,
+    started: Instant,
+    finished: Option<Instant>,
+    /// Interrupts the task's query; None for work that can't be stopped
+    cancel: Option<Arc<QueryCancel>>,
+}
+
+impl Task {
+    /// How long it ran, or has been running
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state == TaskState::Running
+    }
+
+    pub fn can_cancel(&self) -> bool {
+        self.is_running() && self.cancel.is_some()
+    }
+}
+
+/// Every task the main loop spawned, for the task panel (`T`). Tasks report back
+/// by id when they end.
+#[derive(Default)]
+pub struct TaskRegistry {
+    /// Oldest first
+    tasks: Vec<Task>,
+    next_id: u64,
+}
+
+impl TaskRegistry {
+    /// Record a database task, returning its id and the cancel handle to run it with
+    pub fn start_query(&mut self, kind: TaskKind, label: &str) -> (u64, Arc<QueryCancel>) {
+        let cancel = Arc::new(QueryCancel::default());
+        let id = self.push(kind, label, Some(cancel.clone()));
+        (id, cancel)
+    }
+
+    /// Record a task that can't be interrupted
+    pub fn start(&mut self, kind: TaskKind, label: &str) -> u64 {
+        self.push(kind, label, None)
+    }
+
+    fn push(&mut self, kind: TaskKind, label: &str, cancel: Option<Arc<QueryCancel>>) -> u64 {
+        self.next_id += 1;
+        let label = label.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.tasks.push(Task {
+            id: self.next_id,
+            kind,
+            label,
+            state: TaskState::Running,
+            started: Instant::now(),
+            finished: None,
+            cancel,
+        });
+        self.prune();
+        self.next_id
+    }
+
+    /// Mark task `id` as ended. A task cancelled from the panel stays cancelled.
+    pub fn finish(&mut self, id: u64, state: TaskState) {
+        let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) else { return };
+        if task.is_running() {
+            task.state = state;
+            task.finished = Some(Instant::now());
+        }
+        task.cancel = None;
+        self.prune();
+    }
+
+    /// Interrupt task `id`'s query, returning its kind if it was running
+    pub fn cancel(&mut self, id: u64) -> Option<TaskKind> {
+        let task = self.tasks.iter_mut().find(|t| t.id == id && t.can_cancel())?;
+        if let Some(cancel) = task.cancel.take() {
+            cancel.cancel();
+        }
+        task.state = TaskState::Cancelled;
+        task.finished = Some(Instant::now());
+        Some(task.kind)
+    }
+
+    /// Interrupt every running `kind` task: the query a closed overlay was waiting
+    /// on, or ones a new query of the same kind supersedes
+    pub fn cancel_kind(&mut self, kind: TaskKind) {
+        let ids: Vec<u64> = self.tasks.iter().filter(|t| t.kind == kind && t.can_cancel()).map(|t| t.id).collect();
+        for id in ids {
+            self.cancel(id);
+        }
+    }
+
+    /// Tasks newest first
+    pub fn list(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().rev()
+    }
+
+    pub fn running(&self) -> usize {
+        self.tasks.iter().filter(|t| t.is_running()).count()
+    }
+
+    /// Drop the oldest finished tasks past the limit
+    fn prune(&mut self) {
+        let finished = self.tasks.iter().filter(|t| !t.is_running()).count();
+        let mut excess = finished.saturating_sub(FINISHED_KEPT);
+        self.tasks.retain(|t| {
+            let drop = excess > 0 && !t.is_running();
+            if drop {
+                excess -= 1;
+            }
+            !drop
+        });
+    }
+}
@@ -0,0 +1,452 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::data::format::{civil_from_days, days_from_civil};
+
+/// Where the tz database lives on Linux and macOS
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+
+/// Local time of one kind in a zone, e.g. CEST at +02:00
+#[derive(Debug, Clone, PartialEq)]
+struct LocalKind {
+    /// Seconds east of UTC
+    offset: i32,
+    abbr: String,
+}
+
+/// The `Mm.w.d/time` part of a POSIX TZ rule: day `d` (0 is Sunday) of week `w`
+/// (5 is the last) of month `m`, at `time` seconds past local midnight
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    month: u32,
+    week: u32,
+    weekday: u32,
+    time: i64,
+}
+
+impl Change {
+    /// Seconds since the epoch, in local time, of the change in `year`
+    fn local_seconds(&self, year: i64) -> i64 {
+        let first = days_from_civil(year, self.month, 1);
+        let next = if self.month == 12 { days_from_civil(year + 1, 1, 1) } else { days_from_civil(year, self.month + 1, 1) };
+        let first_weekday = (first + 4).rem_euclid(7);
+        let mut day = first + (self.weekday as i64 - first_weekday).rem_euclid(7) + (self.week as i64 - 1) * 7;
+        while day >= next {
+            day -= 7;
+        }
+        day * 86_400 + self.time
+    }
+}
+
+/// Local time from a POSIX TZ string such as `CET-1CEST,M3.5.0,M10.5.0/3`
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    standard: LocalKind,
+    /// Daylight time and when it starts and ends
+    daylight: Option<(LocalKind, Change, Change)>,
+}
+
+impl Rule {
+    fn kind_at(&self, utc: i64) -> &LocalKind {
+        let Some((ref daylight, ref start, ref end)) = self.daylight else { return &self.standard };
+        let (year, _, _) = civil_from_days((utc + self.standard.offset as i64).div_euclid(86_400));
+        let start = start.local_seconds(year) - self.standard.offset as i64;
+        let end = end.local_seconds(year) - daylight.offset as i64;
+        let in_daylight = if start < end { start <= utc && utc < end } else { utc < end || utc >= start };
+        if in_daylight {
+            daylight
+        } else {
+            &self.standard
+        }
+    }
+}
+
+/// Zone that times are shown in (`timezone` in the config): UTC, a fixed offset,
+/// or a zone from the tz database, read from its TZif file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TimeZone {
+    /// When the local time changes (UTC seconds) and the kind it changes to, oldest first
+    transitions: Vec<(i64, usize)>,
+    kinds: Vec<LocalKind>,
+    /// Local time after the last transition
+    rule: Option<Rule>,
+}
+
+/// A moment in the display time zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// Seconds east of UTC
+    pub offset: i32,
+    pub abbr: String,
+}
+
+impl LocalTime {
+    /// The offset as ISO 8601 writes it, e.g. `+05:30`
+    pub fn offset_text(&self) -> String {
+        format_offset(self.offset)
+    }
+}
+
+impl TryFrom<String> for TimeZone {
+    type Error = String;
+
+    fn try_from(spec: String) -> Result<Self, Self::Error> {
+        TimeZone::load(&spec).map_err(|e| format!("{:#}", e))
+    }
+}
+
+impl TimeZone {
+    pub fn utc() -> Self {
+        Self::fixed(0, "UTC")
+    }
+
+    fn fixed(offset: i32, abbr: &str) -> Self {
+        let rule = Rule { standard: LocalKind { offset, abbr: abbr.to_string() }, daylight: None };
+        Self { transitions: Vec::new(), kinds: Vec::new(), rule: Some(rule) }
+    }
+
+    /// Read a zone: `local`, `UTC`, an offset such as `+05:30`, or a tz
+    /// database name such as `Europe/Berlin`
+    pub fn load(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if spec.eq_ignore_ascii_case("local") {
+            return Ok(Self::local());
+        }
+        if spec.eq_ignore_ascii_case("utc") || spec.eq_ignore_ascii_case("z") {
+            return Ok(Self::utc());
+        }
+        if let Some(offset) = parse_offset(spec.strip_prefix("UTC").unwrap_or(spec)) {
+            return Ok(Self::fixed(offset, &format!("UTC{}", format_offset(offset))));
+        }
+        if spec.is_empty() || spec.starts_with('/') || spec.split('/').any(|part| part == "..") {
+            bail!("`{}` isn't a time zone (expected local, UTC, an offset like +05:30, or a name like Europe/Berlin)", spec);
+        }
+        let path = format!("{}/{}", ZONEINFO, spec);
+        let bytes = std::fs::read(&path).with_context(|| format!("Unknown time zone `{}` (no {})", spec, path))?;
+        Self::from_tzif(&bytes).with_context(|| format!("Failed to read time zone {}", path))
+    }
+
+    /// The system's zone: from `TZ` (a name, a file, or a POSIX rule), then
+    /// `/etc/localtime`, then UTC
+    pub fn local() -> Self {
+        if let Ok(tz) = std::env::var("TZ") {
+            let tz = tz.strip_prefix(':').unwrap_or(&tz);
+            let path = if tz.starts_with('/') { tz.to_string() } else { format!("{}/{}", ZONEINFO, tz) };
+            let zone = std::fs::read(path)
+                .ok()
+                .and_then(|bytes| Self::from_tzif(&bytes).ok())
+                .or_else(|| parse_rule(tz).map(|rule| Self { transitions: Vec::new(), kinds: Vec::new(), rule: Some(rule) }));
+            if let Some(zone) = zone {
+                return zone;
+            }
+        }
+        std::fs::read("/etc/localtime")
+            .ok()
+            .and_then(|bytes| Self::from_tzif(&bytes).ok())
+            .unwrap_or_else(Self::utc)
+    }
+
+    /// Parse a TZif file (RFC 8536), preferring the 64-bit data of version 2 and later
+    fn from_tzif(bytes: &[u8]) -> Result<Self> {
+        let header = |at: usize| -> Result<[usize; 6]> {
+            if bytes.get(at..at + 4) != Some(b"TZif") || bytes.len() < at + 44 {
+                bail!("not a TZif file");
+            }
+            let count = |i: usize| u32::from_be_bytes(bytes[at + 20 + i * 4..at + 24 + i * 4].try_into().unwrap()) as usize;
+            Ok([count(0), count(1), count(2), count(3), count(4), count(5)])
+        };
+        let block_len = |counts: [usize; 6], time_size: usize| {
+            let [isut, isstd, leap, time, kinds, chars] = counts;
+            time * time_size + time + kinds * 6 + chars + leap * (time_size + 4) + isstd + isut
+        };
+
+        let counts = header(0)?;
+        let (start, counts, time_size) = if bytes[4] >= b'2' {
+            let second = 44 + block_len(counts, 4);
+            (second + 44, header(second)?, 8)
+        } else {
+            (44, counts, 4)
+        };
+        let [_, _, _, time_count, kind_count, char_count] = counts;
+        let end = start + block_len(counts, time_size);
+        if bytes.len() < end || kind_count == 0 {
+            bail!("truncated TZif file");
+        }
+
+        let times = &bytes[start..];
+        let indices = &times[time_count * time_size..];
+        let kinds_at = &indices[time_count..];
+        let abbrs = &kinds_at[kind_count * 6..kind_count * 6 + char_count];
+        let transitions = (0..time_count)
+            .map(|i| {
+                let raw = &times[i * time_size..(i + 1) * time_size];
+                let time = match time_size {
+                    8 => i64::from_be_bytes(raw.try_into().unwrap()),
+                    _ => i32::from_be_bytes(raw.try_into().unwrap()) as i64,
+                };
+                (time, (indices[i] as usize).min(kind_count - 1))
+            })
+            .collect();
+        let kinds = (0..kind_count)
+            .map(|i| {
+                let kind = &kinds_at[i * 6..i * 6 + 6];
+                let abbr = abbrs.get(kind[5] as usize..).unwrap_or_default();
+                let abbr = abbr.split(|&b| b == 0).next().unwrap_or_default();
+                LocalKind {
+                    offset: i32::from_be_bytes(kind[..4].try_into().unwrap()),
+                    abbr: String::from_utf8_lossy(abbr).into_owned(),
+                }
+            })
+            .collect();
+        // Version 2+ ends with a POSIX TZ string for times after the last transition
+        let footer = (time_size == 8).then(|| String::from_utf8_lossy(&bytes[end..]).trim().to_string());
+        let rule = footer.as_deref().and_then(parse_rule);
+        Ok(Self { transitions, kinds, rule })
+    }
+
+    fn kind_at(&self, utc: i64) -> Option<&LocalKind> {
+        let past_last = self.transitions.last().is_none_or(|&(last, _)| utc >= last);
+        if let (true, Some(rule)) = (past_last, &self.rule) {
+            return Some(rule.kind_at(utc));
+        }
+        match self.transitions.partition_point(|&(time, _)| time <= utc) {
+            0 => self.kinds.first(),
+            i => self.kinds.get(self.transitions[i - 1].1),
+        }
+    }
+
+    /// The local date and time of `utc` (seconds since the epoch)
+    pub fn local_time(&self, utc: i64) -> LocalTime {
+        let (offset, abbr) = match self.kind_at(utc) {
+            Some(kind) => (kind.offset, kind.abbr.clone()),
+            None => (0, "UTC".to_string()),
+        };
+        let local = utc + offset as i64;
+        let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+        let time = local.rem_euclid(86_400) as u32;
+        LocalTime { year, month, day, hour: time / 3600, minute: time % 3600 / 60, second: time % 60, offset, abbr }
+    }
+}
+
+/// `+05:30`, `-08`, `+0100` as seconds east of UTC (ISO 8601 signs)
+fn parse_offset(text: &str) -> Option<i32> {
+    let (sign, rest) = match text.as_bytes().first()? {
+        b'+' => (1, &text[1..]),
+        b'-' => (-1, &text[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|&c| c != ':').collect();
+    if !(1..=4).contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = if digits.len() > 2 { digits.split_at(digits.len() - 2) } else { (digits.as_str(), "0") };
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours <= 14 && minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn format_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!("{}{:02}:{:02}", sign, offset / 3600, offset % 3600 / 60)
+}
+
+/// Parse a POSIX TZ string; only `Mm.w.d` change dates are understood, and a
+/// rule with other kinds stays on standard time
+fn parse_rule(text: &str) -> Option<Rule> {
+    let mut rest = text;
+    let standard_abbr = take_abbr(&mut rest)?;
+    // POSIX offsets are hours west of UTC
+    let standard = LocalKind { offset: -take_time(&mut rest)? as i32, abbr: standard_abbr };
+    if rest.is_empty() {
+        return Some(Rule { standard, daylight: None });
+    }
+    let daylight_abbr = take_abbr(&mut rest)?;
+    let daylight_offset = if rest.starts_with(',') || rest.is_empty() {
+        standard.offset + 3600
+    } else {
+        -take_time(&mut rest)? as i32
+    };
+    let daylight = LocalKind { offset: daylight_offset, abbr: daylight_abbr };
+    let rules = rest.strip_prefix(',').unwrap_or("M3.2.0,M11.1.0");
+    let mut changes = rules.split(',').map(parse_change);
+    let (start, end) = match (changes.next().flatten(), changes.next().flatten()) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return Some(Rule { standard, daylight: None }),
+    };
+    Some(Rule { standard, daylight: Some((daylight, start, end)) })
+}
+
+/// A zone abbreviation, bare (`CET`) or quoted (`<+0530>`)
+fn take_abbr(rest: &mut &str) -> Option<String> {
+    let (abbr, tail) = match rest.strip_prefix('<') {
+        Some(quoted) => {
+            let close = quoted.find('>')?;
+            (&quoted[..close], &quoted[close + 1..])
+        }
+        None => {
+            let len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+            rest.split_at(len)
+        }
+    };
+    *rest = tail;
+    (abbr.len() >= 3).then(|| abbr.to_string())
+}
+
+/// `[+-]hh[:mm[:ss]]` as seconds, up to the 167 hours POSIX allows
+fn take_time(rest: &mut &str) -> Option<i64> {
+    let len = rest.find(|c: char| !(c.is_ascii_digit() || matches!(c, ':' | '+' | '-'))).unwrap_or(rest.len());
+    let (text, tail) = rest.split_at(len);
+    *rest = tail;
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => (-1, text),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let mut seconds = 0;
+    for (i, part) in text.split(':').enumerate().take(3) {
+        seconds += part.parse::<u8>().ok()? as i64 * [3600, 60, 1][i];
+    }
+    (seconds < 168 * 3600).then_some(sign * seconds)
+}
+
+/// `Mm.w.d[/time]`, time defaulting to 02:00
+fn parse_change(text: &str) -> Option<Change> {
+    let (date, time) = text.split_once('/').unwrap_or((text, "2"));
+    let mut parts = date.strip_prefix('M')?.split('.').map(|part| part.parse::<u32>().ok());
+    let (month, week, weekday) = (parts.next()??, parts.next()??, parts.next()??);
+    let mut time = time;
+    let time = take_time(&mut time)?;
+    ((1..=12).contains(&month) && (1..=5).contains(&week) && weekday <= 6).then_some(Change { month, week, weekday, time })
+}
+
+/// Zone times are shown in, from the config; the system's until it's read
+static TIME_ZONE: RwLock<Option<Arc<TimeZone>>> = RwLock::new(None);
+
+pub fn set_time_zone(zone: TimeZone) {
+    *TIME_ZONE.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(zone));
+}
+
+pub fn time_zone() -> Arc<TimeZone> {
+    if let Some(zone) = TIME_ZONE.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return zone.clone();
+    }
+    let zone = Arc::new(TimeZone::local());
+    *TIME_ZONE.write().unwrap_or_else(|e| e.into_inner()) = Some(zone.clone());
+    zone
+}
+
+/// `utc` (seconds since the epoch) in the display time zone
+pub fn to_local(utc: i64) -> LocalTime {
+    time_zone().local_time(utc)
+}
+
+/// The display zone's current abbreviation, e.g. `CEST`, for labeling times
+pub fn zone_label() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    to_local(now).abbr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i64, month: u32, day: u32, hour: i64) -> i64 {
+        days_from_civil(year, month, day) * 86_400 + hour * 3600
+    }
+
+    fn abbr_at(rule: &Rule, utc: i64) -> &str {
+        &rule.kind_at(utc).abbr
+    }
+
+    /// A version 2 TZif file: an empty 32-bit block, then the 64-bit data and footer
+    fn tzif(transitions: &[(i64, u8)], kinds: &[(i32, u8)], chars: &[u8], footer: &str) -> Vec<u8> {
+        let header = |counts: [u32; 6]| {
+            let mut bytes = b"TZif2".to_vec();
+            bytes.extend([0; 15]);
+            counts.iter().for_each(|c| bytes.extend(c.to_be_bytes()));
+            bytes
+        };
+        let mut bytes = header([0, 0, 0, 0, 1, 1]);
+        bytes.extend([0; 7]);
+        bytes.extend(header([0, 0, 0, transitions.len() as u32, kinds.len() as u32, chars.len() as u32]));
+        transitions.iter().for_each(|(time, _)| bytes.extend(time.to_be_bytes()));
+        bytes.extend(transitions.iter().map(|&(_, kind)| kind));
+        for &(offset, abbr) in kinds {
+            bytes.extend(offset.to_be_bytes());
+            bytes.extend([0, abbr]);
+        }
+        bytes.extend(chars);
+        bytes.extend(format!("\n{}\n", footer).bytes());
+        bytes
+    }
+
+    #[test]
+    fn fixed_offsets() {
+        let zone = TimeZone::load("+05:30").unwrap();
+        let local = zone.local_time(0);
+        assert_eq!((local.year, local.month, local.day, local.hour, local.minute), (1970, 1, 1, 5, 30));
+        assert_eq!((local.offset_text(), local.abbr), ("+05:30".to_string(), "UTC+05:30".to_string()));
+        assert_eq!(TimeZone::load("UTC-08").unwrap().local_time(0).offset, -8 * 3600);
+        assert_eq!(parse_offset("+0100"), Some(3600));
+        assert_eq!(parse_offset("+15"), None);
+        assert_eq!(parse_offset("05:30"), None);
+        assert!(TimeZone::load("../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn daylight_time_changes_at_the_right_instants() {
+        let us = parse_rule("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        assert_eq!(abbr_at(&us, utc(2024, 3, 10, 7) - 1), "EST");
+        assert_eq!(abbr_at(&us, utc(2024, 3, 10, 7)), "EDT");
+        assert_eq!(abbr_at(&us, utc(2024, 11, 3, 6) - 1), "EDT");
+        assert_eq!(abbr_at(&us, utc(2024, 11, 3, 6)), "EST");
+
+        let eu = parse_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(eu.daylight.as_ref().unwrap().1.local_seconds(2024), utc(2024, 3, 31, 2));
+        assert_eq!(abbr_at(&eu, utc(2024, 3, 31, 1) - 1), "CET");
+        assert_eq!(abbr_at(&eu, utc(2024, 3, 31, 1)), "CEST");
+        assert_eq!(abbr_at(&eu, utc(2024, 10, 27, 1) - 1), "CEST");
+        assert_eq!(abbr_at(&eu, utc(2024, 10, 27, 1)), "CET");
+
+        // Southern summers span the new year
+        let sydney = parse_rule("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(abbr_at(&sydney, utc(2024, 1, 15, 0)), "AEDT");
+        assert_eq!(abbr_at(&sydney, utc(2024, 4, 6, 16) - 1), "AEDT");
+        assert_eq!(abbr_at(&sydney, utc(2024, 4, 6, 16)), "AEST");
+        assert_eq!(abbr_at(&sydney, utc(2024, 7, 1, 0)), "AEST");
+        assert_eq!(abbr_at(&sydney, utc(2024, 10, 5, 16) - 1), "AEST");
+        assert_eq!(abbr_at(&sydney, utc(2024, 10, 5, 16)), "AEDT");
+        assert_eq!(abbr_at(&sydney, utc(2024, 12, 31, 23)), "AEDT");
+
+        assert_eq!(parse_rule("EST5"), Some(Rule { standard: LocalKind { offset: -5 * 3600, abbr: "EST".into() }, daylight: None }));
+        assert_eq!(parse_rule("EST99999999999999999999"), None);
+        assert_eq!(parse_rule("X5"), None);
+    }
+
+    #[test]
+    fn reads_tzif_files_and_rejects_broken_ones() {
+        let bytes = tzif(&[(-2_422_054_408, 1)], &[(3208, 0), (3600, 4)], b"LMT\0CET\0", "CET-1CEST,M3.5.0,M10.5.0/3");
+        let zone = TimeZone::from_tzif(&bytes).unwrap();
+        assert_eq!(zone.local_time(-2_500_000_000).abbr, "LMT");
+        assert_eq!(zone.local_time(utc(2024, 1, 1, 0)).abbr, "CET");
+        assert_eq!(zone.local_time(utc(2024, 7, 1, 0)).offset, 7200);
+
+        let data_end = bytes.len() - "CET-1CEST,M3.5.0,M10.5.0/3\n\n".len();
+        for len in 0..bytes.len() {
+            let parsed = TimeZone::from_tzif(&bytes[..len]);
+            assert_eq!(parsed.is_ok(), len >= data_end, "{} bytes", len);
+        }
+        assert!(TimeZone::from_tzif(b"not a zone file at all, just some text here").is_err());
+        let mut huge = bytes.clone();
+        huge[44 + 7 + 32..44 + 7 + 36].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(TimeZone::from_tzif(&huge).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::app::App;
+use crate::logging;
+use super::centered_rect;
+
+/// Render the tail of the log file, newest lines at the bottom
+pub fn render_app_log(f: &mut Frame, app: &App) {
+    let area = centered_rect(90, 80, f.area());
+    f.render_widget(Clear, area);
+
+    let path = logging::log_path().map_or_else(|| "no log file".to_string(), |p| p.display().to_string());
+    let filter = if app.app_log_warnings_only { " · warnings only" } else { "" };
+    let position = if app.app_log_scroll == 0 {
+        "following".to_string()
+    } else {
+        format!("{} lines up", app.app_log_scroll)
+    };
+    let block = Block::default()
+        .title(format!(" Log · {} · {}{} ", path, log::max_level(), filter))
+        .title_bottom(Line::from(format!(" {} ", position)).right_aligned())
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    let lines = app.app_log_visible_lines();
+    if let Some(ref error) = app.app_log_error {
+        let message = Paragraph::new(format!("\n  {}", error)).style(Style::default().fg(Color::Red));
+        f.render_widget(message, chunks[0]);
+    } else if lines.is_empty() {
+        let empty = Paragraph::new("\n  Nothing logged at this level yet")
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(empty, chunks[0]);
+    } else {
+        let height = chunks[0].height as usize;
+        let end = lines.len().saturating_sub(app.app_log_scroll.min(lines.len() - 1));
+        let start = end.saturating_sub(height);
+        let shown: Vec<Line> = lines[start..end].iter().map(|line| log_line(line)).collect();
+        f.render_widget(Paragraph::new(shown), chunks[0]);
+    }
+
+    let help = Paragraph::new("↑↓ PgUp/PgDn scroll | End follow | w warnings only | Esc close")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[1]);
+}
+
+/// A log line colored by its level
+fn log_line(line: &str) -> Line<'_> {
+    let color = if line.contains("[ERROR]") {
+        Color::Red
+    } else if line.contains("[WARN]") {
+        Color::Yellow
+    } else if line.contains("[DEBUG]") || line.contains("[TRACE]") {
+        Color::DarkGray
+    } else {
+        Color::White
+    };
+    Line::styled(line, Style::default().fg(color))
+}
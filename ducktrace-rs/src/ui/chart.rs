@@ -1,84 +1,1434 @@
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use std::ops::Range;
+
 use ratatui::{
     prelude::*,
     symbols::Marker,
-    widgets::{Axis, Bar, BarChart, BarGroup, Block, Borders, Chart, Dataset, GraphType, Paragraph},
+    widgets::{
+        canvas::{Canvas, Points},
+        Axis, Bar, BarChart, BarGroup, Block, Borders, Chart, Clear, Dataset, GraphType, Paragraph,
+    },
+};
+
+use super::theme::{parse_color, Palette};
+use crate::data::{
+    lttb, nice_ticks, scroll_window, time_fractions, truncate_string, value_to_string, BarMode, BarOrientation,
+    ChartData, ChartSort, ChartType, Threshold, XRange,
 };
 
-use crate::data::{format_number, format_value, truncate_string, ChartData, ChartType};
+/// Chart tab view state for one frame, resolved from `App`
+pub struct ChartView {
+    pub selected: usize,
+    /// Brushed span of x values, if any
+    pub range: Option<XRange>,
+    pub bar_mode: BarMode,
+    pub orientation: BarOrientation,
+    /// Drawing order of single-series bar and line charts
+    pub sort: ChartSort,
+    /// Text typed after `/`; x labels containing it are highlighted
+    pub search: String,
+}
+
+impl ChartView {
+    /// Whether the search matches an x value, ignoring case
+    fn matches_x(&self, x: &str) -> bool {
+        !self.search.is_empty() && x.to_lowercase().contains(&self.search.to_lowercase())
+    }
+}
+
+/// Screen region drawn for one data row, so mouse clicks can be mapped back to rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChartHit {
+    pub area: Rect,
+    pub row: usize,
+}
+
+/// Row drawn at a cell, for tables, where a click between rows selects nothing
+pub fn row_at(hits: &[ChartHit], column: u16, row: u16) -> Option<usize> {
+    let position = Position::new(column, row);
+    hits.iter().find(|h| h.area.contains(position)).map(|h| h.row)
+}
+
+/// Row under a click: an exact hit, else the vertically nearest hit in the clicked column
+pub fn hit_test(hits: &[ChartHit], column: u16, row: u16) -> Option<usize> {
+    let position = Position::new(column, row);
+    if let Some(hit) = hits.iter().find(|h| h.area.contains(position)) {
+        return Some(hit.row);
+    }
+    hits.iter()
+        .filter(|h| column >= h.area.x && column < h.area.x + h.area.width)
+        .min_by_key(|h| {
+            if row < h.area.y {
+                h.area.y - row
+            } else {
+                row - (h.area.y + h.area.height - 1)
+            }
+        })
+        .map(|h| h.row)
+}
+
+/// Title note for a chart sorted by value, e.g. ", by revenue \u{2193}"
+fn sort_note(data: &ChartData, sort: ChartSort) -> String {
+    match sort {
+        ChartSort::Original => String::new(),
+        ChartSort::Ascending => format!(", by {} \u{2191}", data.y_field),
+        ChartSort::Descending => format!(", by {} \u{2193}", data.y_field),
+    }
+}
+
+/// Fractions of the plot width for `n` evenly spaced x positions
+fn evenly_spaced(n: usize) -> Vec<f64> {
+    let last = n.saturating_sub(1).max(1) as f64;
+    (0..n).map(|pos| pos as f64 / last).collect()
+}
+
+/// Fractions of the plot width for x values: by time when they're dates or
+/// timestamps, so irregular sampling keeps its spacing, else evenly by order
+fn x_fractions(texts: &[String]) -> Vec<f64> {
+    time_fractions(texts).unwrap_or_else(|| evenly_spaced(texts.len()))
+}
+
+/// Column span of each x position (a fraction of the plot width) across `plot`,
+/// split at the midpoints between neighbors
+fn column_bands(plot: Rect, positions: &[f64]) -> Vec<(u16, u16)> {
+    let column = |frac: f64| plot.x as f64 + frac * plot.width.saturating_sub(1) as f64;
+    let n = positions.len();
+    (0..n)
+        .map(|pos| {
+            let start = if pos == 0 {
+                plot.x
+            } else {
+                (column((positions[pos - 1] + positions[pos]) / 2.0).floor() as u16) + 1
+            };
+            let end = if pos + 1 == n {
+                plot.x + plot.width
+            } else {
+                (column((positions[pos] + positions[pos + 1]) / 2.0).floor() as u16) + 1
+            };
+            (start, end.max(start + 1))
+        })
+        .collect()
+}
+
+/// Plot region of a ratatui `Chart` drawn in `area`, mirroring its axis label layout
+fn chart_plot_area(area: Rect, x_labels: &[Span], y_labels: &[Span]) -> Rect {
+    let y_label_width = y_labels.iter().map(|l| l.width() as u16).max().unwrap_or(0);
+    let first_x_width = x_labels
+        .first()
+        .map(|l| (l.width() as u16).saturating_sub(1))
+        .unwrap_or(0);
+    let left = y_label_width.max(first_x_width).min(area.width / 3) + 1;
+    Rect {
+        x: area.x + left,
+        y: area.y,
+        width: area.width.saturating_sub(left),
+        height: area.height.saturating_sub(2),
+    }
+}
+
+/// Labels along the x axis, one per position
+struct XLabels<'a> {
+    texts: &'a [String],
+    /// Fractions of the plot width
+    positions: &'a [f64],
+    /// Whether the chart search matches each position's x value
+    matched: &'a [bool],
+    selected: usize,
+}
+
+/// Pick the x labels that fit under `plot`: the selected position's always, then
+/// any the search matches, then the two ends, then halfway points between those
+/// already placed, skipping any that would touch a neighbor.
+/// Returns each label's position, start column, and text.
+fn layout_x_labels(labels: &XLabels, plot: Rect, area: Rect) -> Vec<(usize, u16, String)> {
+    let XLabels { texts, positions, matched, selected } = *labels;
+    let n = texts.len();
+    if n == 0 || plot.width == 0 {
+        return Vec::new();
+    }
+    let max_len = (plot.width as usize / 4).clamp(4, 12);
+    let last = n - 1;
+    let mut order = vec![selected.min(last)];
+    order.extend((0..n).filter(|&pos| matched[pos]));
+    order.extend([0, last]);
+    let mut parts = 2;
+    while parts < 2 * n {
+        order.extend((1..parts).step_by(2).map(|k| (k as f64 * last as f64 / parts as f64).round() as usize));
+        parts *= 2;
+    }
+
+    let mut placed: Vec<(usize, u16, String)> = Vec::new();
+    for pos in order {
+        if placed.iter().any(|&(p, _, _)| p == pos) {
+            continue;
+        }
+        let text = truncate_string(&texts[pos], max_len);
+        let width = text.chars().count() as u16;
+        let center = plot_cell(plot, positions[pos], 0.0).0;
+        let start = center
+            .saturating_sub(width / 2)
+            .min(area.right().saturating_sub(width))
+            .max(area.x);
+        let crowded = placed
+            .iter()
+            .any(|(_, s, t)| start <= s + t.chars().count() as u16 && *s <= start + width);
+        if !crowded {
+            placed.push((pos, start, text));
+        }
+    }
+    placed
+}
+
+/// Draw x labels on the row beneath the axis line, the selected one highlighted
+fn render_x_labels(f: &mut Frame, plot: Rect, area: Rect, labels: &XLabels, palette: &Palette) {
+    let y = plot.y + plot.height + 1;
+    if y >= area.bottom() {
+        return;
+    }
+    for (pos, x, text) in layout_x_labels(labels, plot, area) {
+        let style = if pos == labels.selected {
+            Style::default().fg(palette.selection)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        f.buffer_mut().set_string(x, y, text, mark_match(style, labels.matched[pos]));
+    }
+}
+
+/// Reverse-video a label the chart search matches
+fn mark_match(style: Style, matched: bool) -> Style {
+    if matched {
+        style.add_modifier(Modifier::REVERSED)
+    } else {
+        style
+    }
+}
+
+/// One legend row: a colored marker, the series name, and its value at the selection
+struct LegendEntry {
+    marker: &'static str,
+    color: Color,
+    name: String,
+    value: Option<String>,
+}
+
+/// Legend marker matching how a dataset is drawn
+fn legend_marker(graph_type: GraphType, filled: bool) -> &'static str {
+    match graph_type {
+        GraphType::Scatter => "\u{2022}",
+        _ if filled => "\u{2588}",
+        _ => "\u{2500}",
+    }
+}
+
+/// Draw a bordered legend in a top corner of the plot, away from where the data ends.
+/// Skipped when it would cover more than half of the plot in either direction.
+fn render_legend(f: &mut Frame, plot: Rect, entries: &[LegendEntry], rising: bool) -> Option<Rect> {
+    let name_width = entries.iter().map(|e| e.name.chars().count()).max().unwrap_or(0);
+    let lines: Vec<Line> = entries
+        .iter()
+        .map(|e| {
+            let mut spans = vec![
+                Span::styled(format!("{} ", e.marker), Style::default().fg(e.color)),
+                Span::styled(
+                    format!("{:<width$}", e.name, width = name_width),
+                    Style::default().fg(Color::White),
+                ),
+            ];
+            if let Some(ref value) = e.value {
+                spans.push(Span::styled(format!("  {}", value), Style::default().fg(e.color)));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 2;
+    let height = lines.len() as u16 + 2;
+    if width > plot.width / 2 || height > plot.height / 2 {
+        return None;
+    }
+
+    // Rising data ends top-right, so the legend goes top-left (below the y-axis title)
+    let x = if rising { plot.x + 1 } else { plot.x + plot.width - width };
+    let area = Rect::new(x, plot.y + 1, width, height);
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        ),
+        area,
+    );
+    Some(area)
+}
+
+/// Terminal cell of a point given its position within the plot as fractions (0..1)
+fn plot_cell(plot: Rect, x_frac: f64, y_frac: f64) -> (u16, u16) {
+    let column = plot.x + (x_frac.clamp(0.0, 1.0) * plot.width.saturating_sub(1) as f64).round() as u16;
+    let row = plot.y
+        + ((1.0 - y_frac.clamp(0.0, 1.0)) * plot.height.saturating_sub(1) as f64).round() as u16;
+    (column, row)
+}
+
+/// Lower y bound `padding` below the data, not dipping below zero unless the data does
+fn padded_floor(min: f64, padding: f64) -> f64 {
+    if min >= 0.0 {
+        (min - padding).max(0.0)
+    } else {
+        min - padding
+    }
+}
+
+/// Round y tick values padding out `bounds`, about one every three rows of a chart
+/// `height` tall (borders and the x axis included)
+fn y_ticks(bounds: [f64; 2], height: u16) -> Vec<f64> {
+    let rows = height.saturating_sub(4) as usize;
+    nice_ticks(bounds[0], bounds[1], (rows / 3 + 1).clamp(2, 11))
+}
+
+/// Dotted lines across the plot at the rows ratatui puts `count` y labels on,
+/// over empty cells only; the lowest sits on the data's floor and is left out
+fn render_grid(f: &mut Frame, plot: Rect, count: usize) {
+    if count < 2 || plot.height < 2 {
+        return;
+    }
+    let buf = f.buffer_mut();
+    let style = Style::default().fg(Color::DarkGray);
+    for i in 1..count as u16 {
+        let y = plot.bottom() - 1 - i * (plot.height - 1) / (count as u16 - 1);
+        for x in (plot.x..plot.right()).step_by(2) {
+            if let Some(cell) = buf.cell_mut((x, y)) {
+                if matches!(cell.symbol(), " " | "\u{2800}") {
+                    cell.set_char('\u{00b7}').set_style(style);
+                }
+            }
+        }
+    }
+}
+
+/// Dim guide lines through the selected cell, drawn only over empty cells so data stays visible
+fn render_crosshair(f: &mut Frame, plot: Rect, (column, row): (u16, u16)) {
+    let buf = f.buffer_mut();
+    let style = Style::default().fg(Color::DarkGray);
+    let mut draw = |x: u16, y: u16, ch: char| {
+        if let Some(cell) = buf.cell_mut((x, y)) {
+            if matches!(cell.symbol(), " " | "\u{2800}") {
+                cell.set_char(ch).set_style(style);
+            }
+        }
+    };
+    for y in plot.y..plot.y + plot.height {
+        draw(column, y, '\u{2502}');
+    }
+    for x in plot.x..plot.x + plot.width {
+        draw(x, row, '\u{2500}');
+    }
+}
+
+/// Small bordered box beside the selected cell, preferring a corner that stays clear of the
+/// legend
+fn render_tooltip(
+    f: &mut Frame,
+    plot: Rect,
+    (column, row): (u16, u16),
+    lines: Vec<Line>,
+    legend: Option<Rect>,
+    palette: &Palette,
+) {
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 2;
+    let height = lines.len() as u16 + 2;
+    if width > plot.width || height > plot.height {
+        return;
+    }
+
+    let right = (column + 2 + width <= plot.right()).then_some(column + 2);
+    let left = column.checked_sub(width + 1).filter(|&x| x >= plot.x);
+    let above = row.checked_sub(height).filter(|&y| y >= plot.y);
+    let below = (row + 1 + height <= plot.bottom()).then_some(row + 1);
+    let candidates: Vec<Rect> = [(right, above), (right, below), (left, above), (left, below)]
+        .iter()
+        .filter_map(|&(x, y)| Some(Rect::new(x?, y?, width, height)))
+        .collect();
+    // The selection matters more than the legend, so cover it when nothing else fits
+    let Some(&area) = candidates
+        .iter()
+        .find(|area| legend.is_none_or(|l| !l.intersects(**area)))
+        .or(candidates.first())
+    else {
+        return;
+    };
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(palette.selection)),
+        ),
+        area,
+    );
+}
+
+/// Tooltip text for a row: x value, then y (and y2) formatted for their columns
+fn tooltip_lines(data: &ChartData, row: &[serde_json::Value]) -> Vec<Line<'static>> {
+    let label = Style::default().fg(Color::Gray);
+    let value = Style::default().fg(Color::White).add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(format!("{}: ", data.x_field), label),
+            Span::styled(data.x_label(row), value),
+        ]),
+        Line::from(vec![
+            Span::styled(format!("{}: ", data.y_field), label),
+            Span::styled(
+                data.y_value(row).map_or("null".to_string(), |y| data.formats.value(y, &data.y_field)),
+                value,
+            ),
+        ]),
+    ];
+    if let (Some(field), Some(v)) = (&data.y2_field, data.get_y2_value(row)) {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{}: ", field), label),
+            Span::styled(data.formats.value(v, field), value),
+        ]));
+    }
+    lines
+}
+
+/// Numbered annotation markers: the number on the plot's top row and a dashed guide down the
+/// column, drawn only over empty cells
+fn render_annotation_markers(f: &mut Frame, plot: Rect, marks: &[(usize, u16)]) {
+    let buf = f.buffer_mut();
+    let style = Style::default().fg(Color::LightRed);
+    for &(number, column) in marks {
+        if column < plot.x || column >= plot.right() {
+            continue;
+        }
+        for y in plot.y + 1..plot.bottom() {
+            if let Some(cell) = buf.cell_mut((column, y)) {
+                if matches!(cell.symbol(), " " | "\u{2800}") {
+                    cell.set_char('\u{2506}').set_style(style);
+                }
+            }
+        }
+        buf.set_stringn(
+            column,
+            plot.y,
+            number.to_string(),
+            (plot.right() - column) as usize,
+            style.add_modifier(Modifier::BOLD),
+        );
+    }
+}
+
+/// Footnote line listing each annotation's number and label
+fn render_annotation_notes(f: &mut Frame, area: Rect, data: &ChartData) {
+    if area.height == 0 {
+        return;
+    }
+    let mut spans = Vec::new();
+    for (number, _, annotation) in data.annotation_rows() {
+        if !spans.is_empty() {
+            spans.push(Span::styled("  \u{00b7}  ", Style::default().fg(Color::DarkGray)));
+        }
+        spans.push(Span::styled(
+            format!("{} ", number),
+            Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::styled(
+            format!("{} ({})", annotation.label, value_to_string(&annotation.x)),
+            Style::default().fg(Color::Gray),
+        ));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)).alignment(Alignment::Center), area);
+}
+
+/// Thin line dataset for a threshold
+fn rule_dataset(line: &[(f64, f64)], color: Color) -> Dataset<'_> {
+    Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(color))
+        .data(line)
+}
+
+/// Markers on the points above a threshold (never joined, since they need not be adjacent)
+fn exceedance_dataset(points: &[(f64, f64)], color: Color) -> Dataset<'_> {
+    Dataset::default()
+        .marker(Marker::Dot)
+        .graph_type(GraphType::Scatter)
+        .style(Style::default().fg(color))
+        .data(points)
+}
+
+/// Width of the widest truncated bar label, as ratatui lays it out
+fn bars_label_width(data: &ChartData, indices: &[usize], max_width: usize) -> u16 {
+    indices
+        .iter()
+        .map(|&i| truncate_string(&data.x_label(&data.rows[i]), max_width).chars().count())
+        .max()
+        .unwrap_or(0) as u16
+}
+
+/// Line color for a threshold (red unless the chart sets one)
+fn threshold_color(threshold: &Threshold) -> Color {
+    threshold.color.as_deref().and_then(parse_color).unwrap_or(Color::Red)
+}
+
+/// Endpoints of each threshold's reference line across the x range, with its color
+fn threshold_lines(data: &ChartData, x_bounds: [f64; 2]) -> Vec<(Vec<(f64, f64)>, Color)> {
+    data.thresholds
+        .iter()
+        .map(|t| (vec![(x_bounds[0], t.value), (x_bounds[1], t.value)], threshold_color(t)))
+        .collect()
+}
+
+/// Points above a threshold, grouped under the color of the highest one they cross
+fn threshold_exceedances(data: &ChartData, points: &[(f64, f64)]) -> Vec<(Vec<(f64, f64)>, Color)> {
+    let mut groups: Vec<(Vec<(f64, f64)>, Color)> = data
+        .thresholds
+        .iter()
+        .map(|t| (Vec::new(), threshold_color(t)))
+        .collect();
+    for &point in points {
+        let crossed = data
+            .exceeded_threshold(point.1)
+            .and_then(|t| data.thresholds.iter().position(|other| std::ptr::eq(other, t)));
+        if let Some(i) = crossed {
+            groups[i].0.push(point);
+        }
+    }
+    groups
+}
+
+/// Threshold text, e.g. "SLA 400ms" or just the formatted value when unlabeled
+fn threshold_label(data: &ChartData, threshold: &Threshold) -> String {
+    let value = data.formats.value(threshold.value, &data.y_field);
+    match threshold.label {
+        Some(ref label) => format!("{} {}", label, value),
+        None => value,
+    }
+}
+
+/// Threshold labels at the right end of their reference lines
+fn render_threshold_labels(f: &mut Frame, plot: Rect, data: &ChartData, y_bounds: [f64; 2]) {
+    let span = (y_bounds[1] - y_bounds[0]).max(f64::EPSILON);
+    for t in &data.thresholds {
+        let label = threshold_label(data, t);
+        let (_, row) = plot_cell(plot, 1.0, (t.value - y_bounds[0]) / span);
+        let x = plot.right().saturating_sub(label.chars().count() as u16).max(plot.x);
+        f.buffer_mut().set_stringn(
+            x,
+            row,
+            label,
+            plot.width as usize,
+            Style::default().fg(threshold_color(t)),
+        );
+    }
+}
+
+/// Dashed threshold rules across a bar chart: horizontal rows for vertical bars, columns for
+/// horizontal ones. `offsets` gives each threshold's distance from the bars' baseline in cells.
+fn render_threshold_rules(f: &mut Frame, bars: Rect, data: &ChartData, offsets: &[u16], horizontal: bool) {
+    let buf = f.buffer_mut();
+    for (t, &offset) in data.thresholds.iter().zip(offsets) {
+        let style = Style::default().fg(threshold_color(t));
+        let label = threshold_label(data, t);
+        if horizontal {
+            let x = bars.x + offset.min(bars.width.saturating_sub(1));
+            for y in bars.y..bars.bottom() {
+                if let Some(cell) = buf.cell_mut((x, y)) {
+                    if cell.symbol() == " " {
+                        cell.set_char('\u{2506}').set_style(style);
+                    }
+                }
+            }
+            let label_x = x.min(bars.right().saturating_sub(label.chars().count() as u16)).max(bars.x);
+            buf.set_stringn(label_x, bars.bottom().saturating_sub(1), label, bars.width as usize, style);
+        } else {
+            let y = bars.bottom().saturating_sub(1 + offset.min(bars.height.saturating_sub(1)));
+            for x in bars.x..bars.right() {
+                if let Some(cell) = buf.cell_mut((x, y)) {
+                    if cell.symbol() == " " {
+                        cell.set_char('\u{2504}').set_style(style);
+                    }
+                }
+            }
+            let x = bars.right().saturating_sub(label.chars().count() as u16).max(bars.x);
+            buf.set_stringn(x, y, label, bars.width as usize, style);
+        }
+    }
+}
+
+/// First row matching the given x value (and series, when one is given)
+fn find_row(data: &ChartData, x: &str, series: Option<&str>) -> Option<usize> {
+    data.rows.iter().position(|row| {
+        data.get_x_value(row) == x
+            && series.is_none_or(|s| data.get_series_value(row).as_deref() == Some(s))
+    })
+}
+
+pub fn render_chart(
+    f: &mut Frame,
+    area: Rect,
+    data: &ChartData,
+    view: &ChartView,
+    palette: &Palette,
+) -> Vec<ChartHit> {
+    let chart_type = data.infer_chart_type();
+    let selected = view.selected;
+
+    // Split area for chart, annotation footnotes (if any), and selection info
+    let notes_height = if data.annotation_rows().is_empty() { 0 } else { 1 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(5),
+            Constraint::Length(notes_height),
+            Constraint::Length(3),
+        ])
+        .split(area);
+
+    let chart_area = chunks[0];
+    let info_area = chunks[2];
+    render_annotation_notes(f, chunks[1], data);
+
+    let multi_series = data.get_series_index().is_some();
+
+    let hits = match chart_type {
+        ChartType::Bar if multi_series => match view.bar_mode {
+            BarMode::Grouped => render_grouped_bar_chart(f, chart_area, data, view, palette),
+            BarMode::Stacked => render_stacked_bar_chart(f, chart_area, data, view, palette),
+        },
+        ChartType::Bar => render_bar_chart(f, chart_area, data, view, palette),
+        ChartType::Line if multi_series => {
+            render_multi_series_chart(f, chart_area, data, view, GraphType::Line, palette)
+        }
+        ChartType::Scatter if multi_series => {
+            render_multi_series_chart(f, chart_area, data, view, GraphType::Scatter, palette)
+        }
+        ChartType::Area if multi_series => {
+            render_multi_series_chart(f, chart_area, data, view, GraphType::Line, palette)
+        }
+        ChartType::Line => render_line_chart(f, chart_area, data, view, GraphType::Line, false, palette),
+        ChartType::Area => render_line_chart(f, chart_area, data, view, GraphType::Line, true, palette),
+        ChartType::Scatter => {
+            render_line_chart(f, chart_area, data, view, GraphType::Scatter, false, palette)
+        }
+        ChartType::Pie => render_pie_chart(f, chart_area, data, selected, palette),
+        ChartType::Histogram => render_histogram(f, chart_area, data, selected, palette),
+        ChartType::BoxPlot => render_box_plot(f, chart_area, data, selected, palette),
+    };
+
+    // Shade the brushed span behind the data it covers
+    if let Some(ref range) = view.range {
+        if matches!(
+            chart_type,
+            ChartType::Line | ChartType::Area | ChartType::Scatter | ChartType::Bar
+        ) {
+            render_brush(f, chart_area.inner(Margin::new(1, 1)), &hits, range);
+        }
+    }
+
+    // Render selection info
+    match view.range {
+        Some(ref range) => render_range_info(f, info_area, data, range, palette),
+        None => render_selection_info(f, info_area, data, selected, palette),
+    }
+    hits
+}
+
+/// Dim background across the columns of every row in the brushed span
+fn render_brush(f: &mut Frame, area: Rect, hits: &[ChartHit], range: &XRange) {
+    let buf = f.buffer_mut();
+    for hit in hits.iter().filter(|h| range.rows.contains(&h.row)) {
+        for x in hit.area.x..hit.area.right().min(area.right()) {
+            for y in area.y..area.bottom() {
+                if let Some(cell) = buf.cell_mut((x, y)) {
+                    cell.set_bg(Color::Indexed(236));
+                }
+            }
+        }
+    }
+}
+
+fn render_bar_chart(f: &mut Frame, area: Rect, data: &ChartData, view: &ChartView, palette: &Palette) -> Vec<ChartHit> {
+    if data.rows.is_empty() {
+        render_empty(f, area);
+        return Vec::new();
+    }
+
+    let (selected, orientation, sort) = (view.selected, view.orientation, view.sort);
+
+    let len = data.rows.len();
+    let (min_y, max_y) = data.y_range_with_thresholds();
+    let scale = if max_y > 0.0 { 100.0 / max_y } else { 1.0 };
+    // ratatui's bars only grow up from zero, so data below it is drawn by hand
+    let diverging = min_y < 0.0;
+
+    // Build bars in chronological order (reverse if data is DESC), or by value
+    let indices = data.display_order(sort);
+
+    // Horizontal bars have room for long category names (capped at a third of the width)
+    let horizontal = orientation == BarOrientation::Horizontal;
+    // Columns narrow to the default when their values don't all fit
+    let fits = |width: u16| len * (width as usize + 1) <= area.width.saturating_sub(2) as usize;
+    let bar_width = match column_width(data, &indices) {
+        _ if horizontal => 1,
+        width if fits(width) => width,
+        _ => 5,
+    };
+    let stride = bar_width + 1;
+
+    // Past that, only a window of bars is drawn, scrolled to keep the selection
+    // centered, unless too few columns fit for a window to show much
+    let columns = (area.width.saturating_sub(2) / stride) as usize;
+    if !horizontal && len > columns && columns < MIN_COLUMN_BARS {
+        return render_dense_bars(f, area, data, &indices, view, palette);
+    }
+    let capacity = if horizontal { area.height.saturating_sub(2) as usize } else { columns };
+    let selected_pos = indices.iter().position(|&i| i == selected).unwrap_or(0);
+    let shown = scroll_window(len, capacity, selected_pos);
+    let window = window_note(&shown, len);
+    let indices = &indices[shown];
+    let label_width = if horizontal {
+        indices
+            .iter()
+            .map(|&i| data.x_label(&data.rows[i]).chars().count())
+            .max()
+            .unwrap_or(8)
+            .min(area.width as usize / 3)
+    } else {
+        8
+    };
+
+    let bars: Vec<Bar> = indices
+        .iter()
+        .map(|&i| {
+            let row = &data.rows[i];
+            let label = data.x_label(row);
+            let value = data.get_y_value(row);
+            // Only the height is scaled; the printed value is the real one
+            let scaled_value = (value * scale) as u64;
+
+            let is_selected = i == selected;
+            let style = if is_selected {
+                Style::default().fg(palette.selection)
+            } else if let Some(t) = data.exceeded_threshold(value) {
+                Style::default().fg(threshold_color(t))
+            } else {
+                Style::default().fg(palette.primary)
+            };
+
+            Bar::default()
+                .value(scaled_value)
+                .text_value(data.formats.value(value, &data.y_field))
+                .label(Line::styled(
+                    truncate_string(&label, label_width),
+                    mark_match(Style::default(), view.matches_x(&data.get_x_value(row))),
+                ))
+                .style(style)
+                .value_style(if is_selected {
+                    Style::default().fg(palette.selection).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                })
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(format!(
+            " {} ({}{}{}) ",
+            data.title,
+            if horizontal { "Horizontal Bar" } else { "Bar" },
+            sort_note(data, sort),
+            window
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Blue));
+    if diverging {
+        f.render_widget(block, area);
+    } else {
+        let bar_chart = BarChart::default()
+            .block(block)
+            .data(BarGroup::default().bars(&bars))
+            .max(100);
+        let bar_chart = if horizontal {
+            bar_chart
+                .direction(Direction::Horizontal)
+                .bar_width(1)
+                .bar_gap(0)
+        } else {
+            bar_chart.bar_width(bar_width).bar_gap(1)
+        };
+        f.render_widget(bar_chart, area);
+    }
+
+    // One row per bar when horizontal, a 5-wide column plus gap otherwise
+    let inner = area.inner(Margin::new(1, 1));
+
+    // Bars start after the longest label (plus a space) or above the label row
+    let drawn_label_width = bars_label_width(data, indices, label_width);
+    let bars_area = if horizontal {
+        Rect {
+            x: inner.x + drawn_label_width + 1,
+            width: inner.width.saturating_sub(drawn_label_width + 1),
+            ..inner
+        }
+    } else {
+        Rect {
+            height: inner.height.saturating_sub(1),
+            ..inner
+        }
+    };
+    if diverging {
+        render_diverging_bars(f, bars_area, data, indices, selected, horizontal, palette);
+        for (k, &i) in indices.iter().enumerate() {
+            let label = truncate_string(&data.x_label(&data.rows[i]), if horizontal { label_width } else { bar_width as usize });
+            let (x, y) = if horizontal {
+                (inner.x, inner.y + k as u16)
+            } else {
+                (inner.x + k as u16 * stride, inner.bottom().saturating_sub(1))
+            };
+            if x < inner.right() && y < inner.bottom() {
+                let style = if i == selected { Style::default().fg(palette.selection) } else { Style::default() };
+                let style = mark_match(style, view.matches_x(&data.get_x_value(&data.rows[i])));
+                f.buffer_mut().set_stringn(x, y, label, (inner.right() - x) as usize, style);
+            }
+        }
+    }
+    let extent = if horizontal { bars_area.width } else { bars_area.height };
+    let level = |value: f64| {
+        if diverging {
+            (value - min_y.min(0.0)) / (max_y.max(0.0) - min_y.min(0.0))
+        } else {
+            value * scale / 100.0
+        }
+    };
+    let offsets: Vec<u16> = data
+        .thresholds
+        .iter()
+        .map(|t| (level(t.value).clamp(0.0, 1.0) * extent as f64).round() as u16)
+        .collect();
+    render_threshold_rules(f, bars_area, data, &offsets, horizontal);
+    if !horizontal {
+        let marks: Vec<(usize, u16)> = data
+            .annotation_rows()
+            .into_iter()
+            .filter_map(|(number, row, _)| {
+                let k = indices.iter().position(|&i| i == row)? as u16;
+                Some((number, inner.x + k * stride + bar_width / 2))
+            })
+            .collect();
+        render_annotation_markers(f, inner, &marks);
+    }
+    indices
+        .iter()
+        .enumerate()
+        .map(|(k, &row)| {
+            let k = k as u16;
+            let bar = if horizontal {
+                Rect::new(inner.x, inner.y + k, inner.width, 1)
+            } else {
+                Rect::new(inner.x + k * stride, inner.y, bar_width, inner.height)
+            };
+            ChartHit { area: bar, row }
+        })
+        .filter(|hit| hit.area.right() <= inner.right() && hit.area.bottom() <= inner.bottom())
+        .collect()
+}
+
+/// Fewest bar columns worth scrolling through; a chart narrower than that draws
+/// its bars in braille instead
+const MIN_COLUMN_BARS: usize = 8;
+
+/// Title note for bars cut to a window, e.g. ", bars 12\u{2013}28 of 50"
+fn window_note(shown: &Range<usize>, len: usize) -> String {
+    if shown.len() < len {
+        format!(", bars {}\u{2013}{} of {}", shown.start + 1, shown.end, len)
+    } else {
+        String::new()
+    }
+}
+
+/// Bars too many for columns in a narrow chart, as braille strokes from zero (two
+/// per cell) on a chart with y ticks, like a line chart's. Past two per cell, a
+/// window of them follows the selection.
+fn render_dense_bars(
+    f: &mut Frame,
+    area: Rect,
+    data: &ChartData,
+    indices: &[usize],
+    view: &ChartView,
+    palette: &Palette,
+) -> Vec<ChartHit> {
+    let (min_y, max_y) = data.y_range_with_thresholds();
+    let y_ticks = y_ticks([min_y.min(0.0), max_y.max(0.0)], area.height);
+    let y_bounds = [y_ticks[0], y_ticks[y_ticks.len() - 1]];
+    let x_labels = vec![Span::raw(""); 2];
+    let y_labels: Vec<Span> = y_ticks.iter().map(|&t| Span::raw(data.formats.axis(t, &data.y_field))).collect();
+    let inner = area.inner(Margin::new(1, 1));
+    let plot = chart_plot_area(inner, &x_labels, &y_labels);
+
+    let len = indices.len();
+    let selected_pos = indices.iter().position(|&i| i == view.selected).unwrap_or(0);
+    let shown = scroll_window(len, plot.width as usize * 2, selected_pos);
+    let window = window_note(&shown, len);
+    let selected_pos = selected_pos - shown.start;
+    let indices = &indices[shown];
+    let n = indices.len();
+    let x_bounds = [0.0, (n - 1).max(1) as f64];
+
+    // One dataset per bar color: threshold, negative, or primary
+    let mut strokes: Vec<(Color, Vec<(f64, f64)>)> = Vec::new();
+    for (pos, &i) in indices.iter().enumerate() {
+        let value = data.get_y_value(&data.rows[i]);
+        let color = if pos == selected_pos {
+            palette.selection
+        } else if let Some(t) = data.exceeded_threshold(value) {
+            threshold_color(t)
+        } else if value < 0.0 {
+            palette.negative
+        } else {
+            palette.primary
+        };
+        match strokes.iter_mut().find(|(c, _)| *c == color) {
+            Some((_, points)) => points.push((pos as f64, value)),
+            None => strokes.push((color, vec![(pos as f64, value)])),
+        }
+    }
+    let rules = threshold_lines(data, x_bounds);
+    let mut datasets: Vec<Dataset> = rules.iter().map(|(line, color)| rule_dataset(line, *color)).collect();
+    datasets.extend(strokes.iter().map(|(color, points)| {
+        Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Bar)
+            .style(Style::default().fg(*color))
+            .data(points)
+    }));
+
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(format!(" {} (Bar, {} points{}{}) ", data.title, len, sort_note(data, view.sort), window))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Blue)),
+        )
+        .x_axis(
+            Axis::default()
+                .title(data.x_field.clone())
+                .style(Style::default().fg(Color::Gray))
+                .bounds(x_bounds)
+                .labels(x_labels),
+        )
+        .y_axis(
+            Axis::default()
+                .title(data.y_field.clone())
+                .style(Style::default().fg(Color::Gray))
+                .bounds(y_bounds)
+                .labels(y_labels),
+        );
+    f.render_widget(chart, area);
+
+    let x_values: Vec<String> = indices.iter().map(|&i| data.get_x_value(&data.rows[i])).collect();
+    let x_texts = data.formats.axis_texts(&x_values, &data.x_field);
+    let positions = evenly_spaced(n);
+    let matched: Vec<bool> = x_values.iter().map(|x| view.matches_x(x)).collect();
+    let labels = XLabels { texts: &x_texts, positions: &positions, matched: &matched, selected: selected_pos };
+    render_x_labels(f, plot, inner, &labels, palette);
+    render_grid(f, plot, y_ticks.len());
+
+    let marks: Vec<(usize, u16)> = data
+        .annotation_rows()
+        .into_iter()
+        .filter_map(|(number, row, _)| {
+            let pos = indices.iter().position(|&i| i == row)?;
+            Some((number, plot_cell(plot, positions[pos], 0.0).0))
+        })
+        .collect();
+    render_annotation_markers(f, plot, &marks);
+    render_threshold_labels(f, plot, data, y_bounds);
+
+    column_bands(plot, &positions)
+        .into_iter()
+        .zip(indices)
+        .map(|((start, end), &row)| ChartHit {
+            area: Rect::new(start, plot.y, end - start, plot.height),
+            row,
+        })
+        .collect()
+}
+
+/// Width of vertical bars: the widest printed value plus a space, so every
+/// value fits on its bar
+fn column_width(data: &ChartData, indices: &[usize]) -> u16 {
+    let widest = indices
+        .iter()
+        .map(|&i| data.formats.value(data.get_y_value(&data.rows[i]), &data.y_field).chars().count())
+        .max()
+        .unwrap_or(0);
+    (widest as u16 + 1).clamp(5, 12)
+}
+
+/// Bars for data that dips below zero, growing from a zero baseline: up (or right)
+/// for positive values, down (or left) for negative ones in the negative color,
+/// each with its value past the end when there's room
+fn render_diverging_bars(
+    f: &mut Frame,
+    bars: Rect,
+    data: &ChartData,
+    indices: &[usize],
+    selected: usize,
+    horizontal: bool,
+    palette: &Palette,
+) {
+    let (min_y, max_y) = data.y_range_with_thresholds();
+    let (lo, hi) = (min_y.min(0.0), max_y.max(0.0));
+    let width = column_width(data, indices);
+    let extent = if horizontal { bars.width } else { bars.height };
+    if extent < 3 || hi <= lo {
+        return;
+    }
+    // One cell for the baseline, the rest split between the two sides (a cell at
+    // least for each side with data)
+    let room = extent - 1;
+    let below = ((-lo / (hi - lo)) * room as f64)
+        .round()
+        .clamp(u16::from(lo < 0.0) as f64, (room - u16::from(hi > 0.0)) as f64) as u16;
+    let above = room - below;
+    let cells = |value: f64| {
+        let (side, limit) = if value < 0.0 { (below, lo) } else { (above, hi) };
+        let n = (value / limit * side as f64).round() as u16;
+        if value != 0.0 { n.clamp(1, side.max(1)) } else { 0 }
+    };
+
+    let buf = f.buffer_mut();
+    let axis = Style::default().fg(Color::DarkGray);
+    let base = if horizontal { bars.x + below } else { bars.y + above };
+    if horizontal {
+        for y in bars.y..bars.bottom() {
+            buf.set_string(base, y, "\u{2502}", axis);
+        }
+    } else {
+        buf.set_string(bars.x, base, "\u{2500}".repeat(bars.width as usize), axis);
+    }
+
+    for (k, &i) in indices.iter().enumerate() {
+        let value = data.get_y_value(&data.rows[i]);
+        let style = if i == selected {
+            Style::default().fg(palette.selection)
+        } else if let Some(t) = data.exceeded_threshold(value) {
+            Style::default().fg(threshold_color(t))
+        } else if value < 0.0 {
+            Style::default().fg(palette.negative)
+        } else {
+            Style::default().fg(palette.primary)
+        };
+        let value_style = if i == selected {
+            Style::default().fg(palette.selection).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let n = cells(value);
+        let text = truncate_string(&data.formats.value(value, &data.y_field), if horizontal { 16 } else { width as usize });
+        let text_width = text.chars().count() as u16;
+        let k = k as u16;
+
+        if horizontal {
+            let y = bars.y + k;
+            if y >= bars.bottom() {
+                break;
+            }
+            let (start, text_x) = if value < 0.0 {
+                (base - n, (base - n).checked_sub(text_width + 1).filter(|&x| x >= bars.x))
+            } else {
+                (base + 1, Some(base + n + 2).filter(|&x| x + text_width <= bars.right()))
+            };
+            buf.set_string(start, y, "\u{2588}".repeat(n as usize), style);
+            if let Some(x) = text_x {
+                buf.set_string(x, y, &text, value_style);
+            }
+        } else {
+            let x = bars.x + k * (width + 1);
+            if x + width > bars.right() {
+                break;
+            }
+            let (rows, text_y) = if value < 0.0 {
+                (base + 1..base + 1 + n, Some(base + 1 + n).filter(|&y| y < bars.bottom()))
+            } else {
+                (base - n..base, (base - n).checked_sub(1).filter(|&y| y >= bars.y))
+            };
+            for y in rows {
+                buf.set_string(x, y, "\u{2588}".repeat(width as usize), style);
+            }
+            if let Some(y) = text_y {
+                buf.set_string(x + (width - text_width.min(width)) / 2, y, &text, value_style);
+            }
+        }
+    }
+}
+
+/// Resample a polyline at evenly spaced x positions so vertical fill strokes leave no gaps
+fn interpolate_fill(points: &[(f64, f64)], samples: usize) -> Vec<(f64, f64)> {
+    if points.len() < 2 || samples < 2 {
+        return points.to_vec();
+    }
+    let x_min = points[0].0;
+    let x_max = points[points.len() - 1].0;
+    let mut segment = 0;
+    (0..samples)
+        .map(|i| {
+            let x = x_min + (x_max - x_min) * i as f64 / (samples - 1) as f64;
+            while segment + 2 < points.len() && points[segment + 1].0 < x {
+                segment += 1;
+            }
+            let (x0, y0) = points[segment];
+            let (x1, y1) = points[segment + 1];
+            let t = if x1 > x0 { (x - x0) / (x1 - x0) } else { 0.0 };
+            (x, y0 + (y1 - y0) * t)
+        })
+        .collect()
+}
+
+/// Split positioned values into runs of consecutive non-null points, so a null
+/// leaves a gap in the line instead of a dip to zero
+fn split_at_gaps(values: impl IntoIterator<Item = (f64, Option<f64>)>) -> Vec<Vec<(f64, f64)>> {
+    let mut runs: Vec<Vec<(f64, f64)>> = vec![Vec::new()];
+    for (x, y) in values {
+        match y {
+            Some(y) => runs.last_mut().unwrap().push((x, y)),
+            None if !runs.last().unwrap().is_empty() => runs.push(Vec::new()),
+            None => {}
+        }
+    }
+    runs.retain(|run| !run.is_empty());
+    runs
+}
+
+/// Runs thinned with LTTB to about two points per braille column of the x span
+/// each covers, so long series keep their shape and draw quickly. Only what's
+/// drawn is thinned; hit bands still map every row.
+fn thin_runs(runs: &[Vec<(f64, f64)>], columns: u16) -> Vec<Vec<(f64, f64)>> {
+    let dots = columns as f64 * 2.0;
+    runs.iter()
+        .map(|run| {
+            let span = run[run.len() - 1].0 - run[0].0;
+            lttb(run, (span * dots) as usize + 2).into_iter().map(|i| run[i]).collect()
+        })
+        .collect()
+}
+
+/// One dataset per run; a lone point between two gaps has no segment to draw, so it
+/// gets a dot
+fn run_datasets<'a>(runs: &'a [Vec<(f64, f64)>], graph_type: GraphType, color: Color) -> Vec<Dataset<'a>> {
+    runs.iter()
+        .map(|run| {
+            Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(if run.len() == 1 { GraphType::Scatter } else { graph_type })
+                .style(Style::default().fg(color))
+                .data(run)
+        })
+        .collect()
+}
+
+fn render_line_chart(
+    f: &mut Frame,
+    area: Rect,
+    data: &ChartData,
+    view: &ChartView,
+    graph_type: GraphType,
+    filled: bool,
+    palette: &Palette,
+) -> Vec<ChartHit> {
+    if data.rows.is_empty() {
+        render_empty(f, area);
+        return Vec::new();
+    }
+
+    let (selected, sort) = (view.selected, view.sort);
+
+    // Build indices in chronological order, or by value
+    let indices = data.display_order(sort);
+    let chart_pos = |row: usize| indices.iter().position(|&i| i == row).unwrap_or(0);
+
+    // Points sit at fractions of the x axis, spaced by time for dates
+    let x_values: Vec<String> = indices.iter().map(|&i| data.get_x_value(&data.rows[i])).collect();
+    let xs = x_fractions(&x_values);
+
+    let runs = split_at_gaps(
+        indices
+            .iter()
+            .zip(&xs)
+            .map(|(&row_idx, &x)| (x, data.y_value(&data.rows[row_idx]))),
+    );
+    if runs.is_empty() {
+        render_empty(f, area);
+        return Vec::new();
+    }
+    let points: Vec<(f64, f64)> = runs.concat();
+
+    let (min_y, max_y) = data.y_range_with_thresholds();
+    let y_range = max_y - min_y;
+    let y_padding = y_range * 0.1;
+
+    // Area charts convey magnitude, so anchor them at zero
+    let floor = padded_floor(min_y, y_padding);
+    let y_ticks = y_ticks([if filled { floor.min(0.0) } else { floor }, max_y + y_padding], area.height);
+    let y_bounds = [y_ticks[0], y_ticks[y_ticks.len() - 1]];
+
+    let x_bounds = [0.0, 1.0];
+
+    // Filled region: one vertical stroke per braille column, drawn beneath the line
+    // and stopping at gaps
+    let fill_points: Vec<(f64, f64)> = if filled {
+        let columns = area.width as f64 * 2.0;
+        runs.iter()
+            .flat_map(|run| {
+                let span = run[run.len() - 1].0 - run[0].0;
+                interpolate_fill(run, (span * columns) as usize + 2)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let fill_dataset = Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Bar)
+        .style(Style::default().fg(palette.fill))
+        .data(&fill_points);
+
+    // Secondary series: rescaled into the primary bounds, labeled on a right-hand axis
+    let y2_values: Option<Vec<f64>> = data.get_y2_index().map(|_| {
+        indices
+            .iter()
+            .map(|&i| data.get_y2_value(&data.rows[i]).unwrap_or(0.0))
+            .collect()
+    });
+    let y2_bounds = y2_values.as_ref().map(|values| {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let padding = (max - min) * 0.1;
+        [padded_floor(min, padding), max + padding]
+    });
+    let to_primary = |v: f64| match y2_bounds {
+        Some([lo, hi]) if hi > lo => {
+            y_bounds[0] + (v - lo) / (hi - lo) * (y_bounds[1] - y_bounds[0])
+        }
+        _ => (y_bounds[0] + y_bounds[1]) / 2.0,
+    };
+    let y2_points: Vec<(f64, f64)> = y2_values
+        .iter()
+        .flatten()
+        .zip(&xs)
+        .map(|(&v, &x)| (x, to_primary(v)))
+        .collect();
+
+    // Main dataset, broken at nulls
+    let drawn = thin_runs(&runs, area.width);
+    let primary_datasets = run_datasets(&drawn, graph_type, palette.primary);
+    let y2_dataset = Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(graph_type)
+        .style(Style::default().fg(palette.secondary))
+        .data(&y2_points);
+
+    // Selected point marker — map data index to chart position
+    let selected_chart_pos = chart_pos(selected);
+    let selected_y = data.y_value(&data.rows[selected]);
+    let mut selected_point: Vec<(f64, f64)> =
+        selected_y.map(|y| (xs[selected_chart_pos], y)).into_iter().collect();
+    if let Some(v) = data.get_y2_value(&data.rows[selected]) {
+        selected_point.push((xs[selected_chart_pos], to_primary(v)));
+    }
+    let selected_dataset = Dataset::default()
+        .marker(Marker::Dot)
+        .graph_type(GraphType::Scatter)
+        .style(Style::default().fg(palette.selection))
+        .data(&selected_point);
+
+    // X-axis labels (in chronological order), drawn after the chart once the plot is
+    // known; the axis only reserves their row
+    let x_texts = data.formats.axis_texts(&x_values, &data.x_field);
+    let x_labels = vec![Span::raw(""); 2];
+
+    let y_labels: Vec<Span> = y_ticks.iter().map(|&t| Span::raw(data.formats.axis(t, &data.y_field))).collect();
 
-/// Check if rows are in reverse chronological order (first x > last x)
-fn is_reverse_sorted(data: &ChartData) -> bool {
-    if data.rows.len() < 2 {
-        return false;
+    let chart_type_name = match graph_type {
+        GraphType::Line if filled => "Area",
+        GraphType::Line => "Line",
+        GraphType::Scatter => "Scatter",
+        _ => "Chart",
+    };
+
+    // Reference lines go underneath the data; values above them are redrawn in their color
+    let rules = threshold_lines(data, x_bounds);
+    let exceedances = threshold_exceedances(data, &points);
+    let mut datasets: Vec<Dataset> = rules.iter().map(|(line, color)| rule_dataset(line, *color)).collect();
+    datasets.push(fill_dataset);
+    datasets.extend(primary_datasets);
+    datasets.extend(exceedances.iter().map(|(points, color)| exceedance_dataset(points, *color)));
+    if y2_bounds.is_some() {
+        datasets.push(y2_dataset);
     }
-    let first_x = data.get_x_value(&data.rows[0]);
-    let last_x = data.get_x_value(&data.rows[data.rows.len() - 1]);
-    first_x > last_x
-}
+    datasets.push(selected_dataset);
 
-pub fn render_chart(f: &mut Frame, area: Rect, data: &ChartData, selected: usize) {
-    let chart_type = data.infer_chart_type();
+    let block = Block::default()
+        .title(format!(" {} ({}{}) ", data.title, chart_type_name, sort_note(data, sort)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Blue));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
 
-    // Split area for chart and selection info
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Min(5), Constraint::Length(3)])
-        .split(area);
+    // Reserve a right-hand gutter for the secondary axis labels
+    let y2_field = data.y2_field.as_deref().unwrap_or_default();
+    let y2_labels: Vec<String> = y2_bounds
+        .map(|[lo, hi]| {
+            vec![
+                data.formats.axis(hi, y2_field),
+                data.formats.axis((lo + hi) / 2.0, y2_field),
+                data.formats.axis(lo, y2_field),
+            ]
+        })
+        .unwrap_or_default();
+    let gutter = y2_labels
+        .iter()
+        .map(|l| l.chars().count() as u16 + 1)
+        .max()
+        .unwrap_or(0)
+        .min(inner.width / 4);
+    let chart_area = Rect {
+        width: inner.width - gutter,
+        ..inner
+    };
 
-    let chart_area = chunks[0];
-    let info_area = chunks[1];
+    let plot = chart_plot_area(chart_area, &x_labels, &y_labels);
+
+    let chart = Chart::new(datasets)
+        .x_axis(
+            Axis::default()
+                .title(data.x_field.clone())
+                .style(Style::default().fg(Color::Gray))
+                .bounds(x_bounds)
+                .labels(x_labels),
+        )
+        .y_axis(
+            Axis::default()
+                .title(data.y_field.clone())
+                .style(Style::default().fg(Color::Gray))
+                .bounds(y_bounds)
+                .labels(y_labels),
+        );
+
+    f.render_widget(chart, chart_area);
+    let matched: Vec<bool> = x_values.iter().map(|x| view.matches_x(x)).collect();
+    let labels = XLabels { texts: &x_texts, positions: &xs, matched: &matched, selected: selected_chart_pos };
+    render_x_labels(f, plot, chart_area, &labels, palette);
+
+    let marks: Vec<(usize, u16)> = data
+        .annotation_rows()
+        .into_iter()
+        .map(|(number, row, _)| (number, plot_cell(plot, xs[chart_pos(row)], 0.0).0))
+        .collect();
+    render_annotation_markers(f, plot, &marks);
+    render_threshold_labels(f, plot, data, y_bounds);
+
+    let selected_row = &data.rows[selected];
+    let selected_cell = plot_cell(
+        plot,
+        xs[selected_chart_pos],
+        (selected_y.unwrap_or(y_bounds[0]) - y_bounds[0]) / (y_bounds[1] - y_bounds[0]),
+    );
+    render_crosshair(f, plot, selected_cell);
+    render_grid(f, plot, y_ticks.len());
 
-    match chart_type {
-        ChartType::Bar => render_bar_chart(f, chart_area, data, selected),
-        ChartType::Line => render_line_chart(f, chart_area, data, selected, GraphType::Line),
-        ChartType::Scatter => render_line_chart(f, chart_area, data, selected, GraphType::Scatter),
+    // Legend: each series with its value at the selection, plus the selection marker
+    let marker = legend_marker(graph_type, filled);
+    let mut entries = vec![LegendEntry {
+        marker,
+        color: palette.primary,
+        name: if data.y2_field.is_some() {
+            format!("{} (left)", data.y_field)
+        } else {
+            data.y_field.clone()
+        },
+        value: Some(selected_y.map_or("null".to_string(), |y| data.formats.value(y, &data.y_field))),
+    }];
+    if let Some(ref field) = data.y2_field {
+        entries.push(LegendEntry {
+            marker,
+            color: palette.secondary,
+            name: format!("{} (right)", field),
+            value: data.get_y2_value(selected_row).map(|v| data.formats.value(v, field)),
+        });
     }
+    entries.push(LegendEntry {
+        marker: "\u{2022}",
+        color: palette.selection,
+        name: "selected".to_string(),
+        value: Some(data.x_label(selected_row)),
+    });
+    let rising = points.last().map(|p| p.1) >= points.first().map(|p| p.1);
+    let legend = render_legend(f, plot, &entries, rising);
+    render_tooltip(f, plot, selected_cell, tooltip_lines(data, selected_row), legend, palette);
 
-    // Render selection info
-    render_selection_info(f, info_area, data, selected);
+    // Secondary axis labels line up with the plot rows (x axis line and labels take the last two)
+    if gutter > 0 && inner.height > 2 {
+        let plot_bottom = inner.y + inner.height - 3;
+        let rows = [inner.y, inner.y + (plot_bottom - inner.y) / 2, plot_bottom];
+        let style = Style::default().fg(palette.secondary);
+        for (label, y) in y2_labels.iter().zip(rows) {
+            f.buffer_mut().set_stringn(
+                chart_area.x + chart_area.width + 1,
+                y,
+                label,
+                gutter as usize - 1,
+                style,
+            );
+        }
+    }
+
+    // Each point owns the full-height band of columns nearest to it
+    column_bands(plot, &xs)
+        .into_iter()
+        .zip(indices)
+        .map(|((start, end), row)| ChartHit {
+            area: Rect::new(start, plot.y, end - start, plot.height),
+            row,
+        })
+        .collect()
 }
 
-fn render_bar_chart(f: &mut Frame, area: Rect, data: &ChartData, selected: usize) {
-    if data.rows.is_empty() {
+/// Render bucket counts of the y column; the bucket holding the selected row is highlighted
+fn render_histogram(f: &mut Frame, area: Rect, data: &ChartData, selected: usize, palette: &Palette) -> Vec<ChartHit> {
+    let bins = data.histogram();
+    if bins.is_empty() {
         render_empty(f, area);
-        return;
+        return Vec::new();
     }
 
-    let reversed = is_reverse_sorted(data);
-    let len = data.rows.len();
-    let max_y = data.max_y();
-    let scale = if max_y > 0.0 { 100.0 / max_y } else { 1.0 };
-
-    // Build bars in chronological order (reverse if data is DESC)
-    let indices: Vec<usize> = if reversed {
-        (0..len).rev().collect()
-    } else {
-        (0..len).collect()
-    };
+    let selected_value = data.get_y_value(&data.rows[selected]);
+    let last = bins.len() - 1;
 
-    let bars: Vec<Bar> = indices
+    let bars: Vec<Bar> = bins
         .iter()
-        .map(|&i| {
-            let row = &data.rows[i];
-            let label = data.get_x_value(row);
-            let value = data.get_y_value(row);
-            let scaled_value = (value * scale) as u64;
-
-            let is_selected = i == selected;
+        .enumerate()
+        .map(|(i, bin)| {
+            let is_selected = bin.contains(selected_value, i == last);
             let style = if is_selected {
-                Style::default().fg(Color::Yellow)
+                Style::default().fg(palette.selection)
             } else {
-                Style::default().fg(Color::Cyan)
+                Style::default().fg(palette.primary)
             };
-
             Bar::default()
-                .value(scaled_value)
-                .label(Line::from(truncate_string(&label, 8)))
+                .value(bin.count as u64)
+                .label(Line::from(truncate_string(&data.formats.axis(bin.start, &data.y_field), 7)))
                 .style(style)
                 .value_style(if is_selected {
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    Style::default().fg(Color::Black).bg(palette.selection).add_modifier(Modifier::BOLD)
                 } else {
-                    Style::default().fg(Color::White)
+                    Style::default().fg(Color::Black).bg(palette.primary)
                 })
         })
         .collect();
@@ -86,112 +1436,557 @@ fn render_bar_chart(f: &mut Frame, area: Rect, data: &ChartData, selected: usize
     let bar_chart = BarChart::default()
         .block(
             Block::default()
-                .title(format!(" {} (Bar) ", data.title))
+                .title(format!(
+                    " {} (Histogram of {}, {} bins) ",
+                    data.title,
+                    data.y_field,
+                    bins.len()
+                ))
                 .borders(Borders::ALL)
                 .border_style(Style::default().fg(Color::Blue)),
         )
         .data(BarGroup::default().bars(&bars))
+        .bar_width(7)
+        .bar_gap(1);
+
+    f.render_widget(bar_chart, area);
+
+    // Clicking a bucket selects the first row that falls in it
+    let inner = area.inner(Margin::new(1, 1));
+    bins.iter()
+        .enumerate()
+        .filter_map(|(i, bin)| {
+            let row = data
+                .rows
+                .iter()
+                .position(|row| bin.contains(data.get_y_value(row), i == last))?;
+            let bar = Rect::new(inner.x + i as u16 * 8, inner.y, 7, inner.height);
+            (bar.right() <= inner.right()).then_some(ChartHit { area: bar, row })
+        })
+        .collect()
+}
+
+/// Render a box-and-whisker glyph per x category on a shared vertical scale
+fn render_box_plot(f: &mut Frame, area: Rect, data: &ChartData, selected: usize, palette: &Palette) -> Vec<ChartHit> {
+    let stats = data.box_stats();
+    if stats.is_empty() {
+        render_empty(f, area);
+        return Vec::new();
+    }
+
+    let selected_label = data.get_x_value(&data.rows[selected]);
+
+    let block = Block::default()
+        .title(format!(" {} (Box Plot of {}) ", data.title, data.y_field))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Blue));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Summary of the selected box, then the plot body, then x labels
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1)])
+        .split(inner);
+
+    if let Some(s) = stats.iter().find(|s| s.label == selected_label) {
+        let count = s.count.map(|n| format!("  (n={})", n)).unwrap_or_default();
+        let summary = format!(
+            "{}: min {} · p25 {} · median {} · p75 {} · max {}{}",
+            s.label,
+            data.formats.value(s.min, &data.y_field),
+            data.formats.value(s.q1, &data.y_field),
+            data.formats.value(s.median, &data.y_field),
+            data.formats.value(s.q3, &data.y_field),
+            data.formats.value(s.max, &data.y_field),
+            count
+        );
+        f.render_widget(
+            Paragraph::new(summary)
+                .style(Style::default().fg(palette.selection))
+                .alignment(Alignment::Center),
+            chunks[0],
+        );
+    }
+
+    let lo = stats.iter().map(|s| s.min).fold(f64::INFINITY, f64::min);
+    let hi = stats.iter().map(|s| s.max).fold(f64::NEG_INFINITY, f64::max);
+    let span = if hi > lo { hi - lo } else { 1.0 };
+
+    let gutter: u16 = 9;
+    let body = chunks[1];
+    let plot_x = body.x + gutter.min(body.width);
+    let plot_width = body.width.saturating_sub(gutter);
+    let rows = body.height.max(1);
+    let to_row = |v: f64| -> u16 {
+        let level = ((v - lo) / span * (rows - 1) as f64).round() as u16;
+        body.y + rows - 1 - level.min(rows - 1)
+    };
+
+    let box_width = stats
+        .iter()
+        .map(|s| s.label.chars().count())
+        .max()
+        .unwrap_or(3)
+        .clamp(3, 10) as u16;
+    let slot = box_width + 2;
+
+    let buf = f.buffer_mut();
+    let mut hits = Vec::new();
+
+    // Y axis: max, midpoint, min
+    let axis_style = Style::default().fg(Color::Gray);
+    for (value, y) in [
+        (hi, body.y),
+        ((hi + lo) / 2.0, body.y + rows / 2),
+        (lo, body.y + rows - 1),
+    ] {
+        buf.set_stringn(body.x, y, data.formats.axis(value, &data.y_field), gutter as usize - 1, axis_style);
+    }
+    for y in body.y..body.y + rows {
+        if let Some(cell) = buf.cell_mut((plot_x.saturating_sub(1), y)) {
+            cell.set_char('\u{2502}').set_fg(Color::Gray);
+        }
+    }
+
+    for (i, s) in stats.iter().enumerate() {
+        let x0 = plot_x + i as u16 * slot + 1;
+        if x0 + box_width > plot_x + plot_width {
+            break;
+        }
+        if let Some(row) = find_row(data, &s.label, None) {
+            hits.push(ChartHit {
+                area: Rect::new(x0, body.y, box_width, body.height + 1),
+                row,
+            });
+        }
+        let center = x0 + box_width / 2;
+        let is_selected = s.label == selected_label;
+        let color = if is_selected { palette.selection } else { palette.primary };
+
+        let (y_max, y_q3, y_median, y_q1, y_min) =
+            (to_row(s.max), to_row(s.q3), to_row(s.median), to_row(s.q1), to_row(s.min));
+
+        // Whiskers
+        for y in y_max..=y_min {
+            if let Some(cell) = buf.cell_mut((center, y)) {
+                cell.set_char('\u{2502}').set_fg(color);
+            }
+        }
+        // Caps and box body
+        for x in x0..x0 + box_width {
+            for (y, ch) in [(y_max, '\u{2500}'), (y_min, '\u{2500}')] {
+                if let Some(cell) = buf.cell_mut((x, y)) {
+                    cell.set_char(ch).set_fg(color);
+                }
+            }
+            for y in y_q3..=y_q1 {
+                if let Some(cell) = buf.cell_mut((x, y)) {
+                    cell.set_char('\u{2588}').set_fg(color);
+                }
+            }
+            if let Some(cell) = buf.cell_mut((x, y_median)) {
+                cell.set_char('\u{2501}').set_fg(Color::Black).set_bg(color);
+            }
+        }
+
+        let label_style = if is_selected {
+            Style::default().fg(palette.selection).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        buf.set_stringn(
+            x0,
+            chunks[2].y,
+            truncate_string(&s.label, box_width as usize),
+            box_width as usize,
+            label_style,
+        );
+    }
+    hits
+}
+
+/// Sum of y values for each (x value, series) cell, indexed [x][series]
+fn series_matrix(data: &ChartData, x_values: &[String], series: &[String]) -> Vec<Vec<f64>> {
+    let mut matrix = vec![vec![0.0; series.len()]; x_values.len()];
+    for row in &data.rows {
+        let x = data.get_x_value(row);
+        let name = data.get_series_value(row).unwrap_or_default();
+        if let (Some(xi), Some(si)) = (
+            x_values.iter().position(|v| *v == x),
+            series.iter().position(|v| *v == name),
+        ) {
+            matrix[xi][si] += data.get_y_value(row);
+        }
+    }
+    matrix
+}
+
+/// Legend line of colored swatches, one per series
+fn series_legend(series: &[String], selected_series: Option<&str>, palette: &Palette) -> Line<'static> {
+    let mut spans = Vec::new();
+    for (i, name) in series.iter().enumerate() {
+        let style = if Some(name.as_str()) == selected_series {
+            Style::default().fg(palette.selection).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        spans.push(Span::styled(
+            "\u{25a0} ",
+            Style::default().fg(palette.series_color(i)),
+        ));
+        spans.push(Span::styled(format!("{}  ", name), style));
+    }
+    Line::from(spans)
+}
+
+/// Render side-by-side bars for each series within every x category
+fn render_grouped_bar_chart(f: &mut Frame, area: Rect, data: &ChartData, view: &ChartView, palette: &Palette) -> Vec<ChartHit> {
+    if data.rows.is_empty() {
+        render_empty(f, area);
+        return Vec::new();
+    }
+
+    let selected = view.selected;
+
+    let x_values = data.distinct_x_values();
+    let series: Vec<String> = data.series_groups().into_iter().map(|(name, _)| name).collect();
+    let matrix = series_matrix(data, &x_values, &series);
+
+    let selected_row = &data.rows[selected];
+    let selected_x = data.get_x_value(selected_row);
+    let selected_series = data.get_series_value(selected_row);
+
+    let max_y = matrix.iter().flatten().fold(0.0_f64, |a, &b| a.max(b));
+    let scale = if max_y > 0.0 { 100.0 / max_y } else { 1.0 };
+
+    let groups: Vec<BarGroup> = x_values
+        .iter()
+        .zip(&matrix)
+        .map(|(x, values)| {
+            let bars: Vec<Bar> = values
+                .iter()
+                .enumerate()
+                .map(|(si, &value)| {
+                    let is_selected =
+                        *x == selected_x && selected_series.as_deref() == Some(series[si].as_str());
+                    let color = if is_selected {
+                        palette.selection
+                    } else {
+                        palette.series_color(si)
+                    };
+                    Bar::default()
+                        .value((value.max(0.0) * scale) as u64)
+                        .text_value(data.formats.axis(value, &data.y_field))
+                        .style(Style::default().fg(color))
+                        .value_style(Style::default().fg(Color::Black).bg(color))
+                })
+                .collect();
+            BarGroup::default()
+                .label(Line::styled(truncate_string(x, 10), mark_match(Style::default(), view.matches_x(x))))
+                .bars(&bars)
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(format!(
+            " {} (Grouped Bar by {}) ",
+            data.title,
+            data.series_field.as_deref().unwrap_or_default()
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Blue));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(3)])
+        .split(inner);
+    f.render_widget(
+        Paragraph::new(series_legend(&series, selected_series.as_deref(), palette)),
+        chunks[0],
+    );
+
+    let mut bar_chart = BarChart::default()
         .bar_width(5)
         .bar_gap(1)
+        .group_gap(2)
         .max(100);
+    for group in groups {
+        bar_chart = bar_chart.data(group);
+    }
+    f.render_widget(bar_chart, chunks[1]);
 
-    f.render_widget(bar_chart, area);
+    // Groups are series.len() bars of width 5 with gaps of 1, separated by 2 columns
+    let body = chunks[1];
+    let group_width = series.len() as u16 * 6 - 1 + 2;
+    let mut hits = Vec::new();
+    for (xi, x) in x_values.iter().enumerate() {
+        for (si, name) in series.iter().enumerate() {
+            let bar = Rect::new(
+                body.x + xi as u16 * group_width + si as u16 * 6,
+                body.y,
+                5,
+                body.height,
+            );
+            if bar.right() > body.right() {
+                continue;
+            }
+            if let Some(row) = find_row(data, x, Some(name)) {
+                hits.push(ChartHit { area: bar, row });
+            }
+        }
+    }
+    hits
 }
 
-fn render_line_chart(
+/// Render one bar per x category with series segments stacked bottom-up
+fn render_stacked_bar_chart(f: &mut Frame, area: Rect, data: &ChartData, view: &ChartView, palette: &Palette) -> Vec<ChartHit> {
+    if data.rows.is_empty() {
+        render_empty(f, area);
+        return Vec::new();
+    }
+
+    let selected = view.selected;
+
+    let x_values = data.distinct_x_values();
+    let series: Vec<String> = data.series_groups().into_iter().map(|(name, _)| name).collect();
+    let matrix = series_matrix(data, &x_values, &series);
+
+    let selected_row = &data.rows[selected];
+    let selected_x = data.get_x_value(selected_row);
+    let selected_series = data.get_series_value(selected_row);
+
+    let block = Block::default()
+        .title(format!(
+            " {} (Stacked Bar by {}) ",
+            data.title,
+            data.series_field.as_deref().unwrap_or_default()
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Blue));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Legend on top, totals row, bar body, x labels at the bottom
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+    f.render_widget(
+        Paragraph::new(series_legend(&series, selected_series.as_deref(), palette)),
+        chunks[0],
+    );
+
+    // Stacks only make sense for non-negative segments
+    let totals: Vec<f64> = matrix
+        .iter()
+        .map(|values| values.iter().map(|v| v.max(0.0)).sum())
+        .collect();
+    let max_total = totals.iter().fold(0.0_f64, |a, &b| a.max(b));
+
+    // Size bars to the longest x label so categories stay readable
+    let bar_width = x_values
+        .iter()
+        .map(|x| x.chars().count())
+        .max()
+        .unwrap_or(3)
+        .clamp(3, 10) as u16;
+    let slot = bar_width + 1;
+    let body = chunks[2];
+    let height = body.height as f64;
+    let buf = f.buffer_mut();
+    let mut hits = Vec::new();
+
+    for (xi, values) in matrix.iter().enumerate() {
+        let x0 = body.x + xi as u16 * slot;
+        if x0 + bar_width > body.x + body.width {
+            break;
+        }
+
+        let mut cumulative = 0.0;
+        for (si, &value) in values.iter().enumerate() {
+            let value = value.max(0.0);
+            if value == 0.0 || max_total <= 0.0 {
+                continue;
+            }
+            let from = (cumulative / max_total * height).round() as u16;
+            cumulative += value;
+            let to = (cumulative / max_total * height).round() as u16;
+            if let (true, Some(row)) = (to > from, find_row(data, &x_values[xi], Some(&series[si]))) {
+                hits.push(ChartHit {
+                    area: Rect::new(x0, body.y + body.height - to, bar_width, to - from),
+                    row,
+                });
+            }
+
+            let is_selected = x_values[xi] == selected_x
+                && selected_series.as_deref() == Some(series[si].as_str());
+            let color = if is_selected {
+                palette.selection
+            } else {
+                palette.series_color(si)
+            };
+
+            for level in from..to {
+                let y = body.y + body.height - 1 - level;
+                for x in x0..x0 + bar_width {
+                    if let Some(cell) = buf.cell_mut((x, y)) {
+                        cell.set_char('\u{2588}').set_fg(color);
+                    }
+                }
+            }
+        }
+
+        let is_selected_x = x_values[xi] == selected_x;
+        let label_style = if is_selected_x {
+            Style::default().fg(palette.selection).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        buf.set_stringn(
+            x0,
+            chunks[1].y,
+            data.formats.axis(totals[xi], &data.y_field),
+            bar_width as usize,
+            Style::default().fg(Color::White),
+        );
+        buf.set_stringn(
+            x0,
+            chunks[3].y,
+            truncate_string(&x_values[xi], bar_width as usize),
+            bar_width as usize,
+            mark_match(label_style, view.matches_x(&x_values[xi])),
+        );
+    }
+    hits
+}
+
+/// Render one dataset per series value, positioned on a shared x axis of distinct values
+fn render_multi_series_chart(
     f: &mut Frame,
     area: Rect,
     data: &ChartData,
-    selected: usize,
+    view: &ChartView,
     graph_type: GraphType,
-) {
+    palette: &Palette,
+) -> Vec<ChartHit> {
     if data.rows.is_empty() {
         render_empty(f, area);
-        return;
+        return Vec::new();
     }
 
-    let reversed = is_reverse_sorted(data);
-    let len = data.rows.len();
-
-    // Build indices in chronological order
-    let indices: Vec<usize> = if reversed {
-        (0..len).rev().collect()
-    } else {
-        (0..len).collect()
-    };
+    let selected = view.selected;
 
-    let points: Vec<(f64, f64)> = indices
+    let x_values = data.distinct_x_values();
+    let x_positions: HashMap<&str, usize> = x_values
         .iter()
         .enumerate()
-        .map(|(chart_pos, &row_idx)| (chart_pos as f64, data.get_y_value(&data.rows[row_idx])))
+        .map(|(i, x)| (x.as_str(), i))
         .collect();
+    let x_index = |row: &[serde_json::Value]| {
+        x_positions
+            .get(data.get_x_value(row).as_str())
+            .copied()
+            .unwrap_or(0)
+    };
+    // Fraction of the x axis, spaced by time for dates
+    let xs = x_fractions(&x_values);
+    let x_pos = |row: &[serde_json::Value]| xs[x_index(row)];
 
-    let min_y = data.min_y();
-    let max_y = data.max_y();
-    let y_range = max_y - min_y;
-    let y_padding = y_range * 0.1;
+    let groups = data.series_groups();
+    let series_runs: Vec<_> = groups
+        .into_iter()
+        .map(|(name, indices)| {
+            let mut points: Vec<(f64, Option<f64>)> = indices
+                .iter()
+                .map(|&i| (x_pos(&data.rows[i]), data.y_value(&data.rows[i])))
+                .collect();
+            points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+            (name, split_at_gaps(points))
+        })
+        .collect();
 
-    let y_bounds = [
-        (min_y - y_padding).max(0.0),
-        max_y + y_padding,
-    ];
+    let (min_y, max_y) = data.y_range_with_thresholds();
+    let y_padding = (max_y - min_y) * 0.1;
+    let y_ticks = y_ticks([padded_floor(min_y, y_padding), max_y + y_padding], area.height);
+    let y_bounds = [y_ticks[0], y_ticks[y_ticks.len() - 1]];
+    let x_bounds = [0.0, 1.0];
 
-    let x_bounds = [0.0, (len - 1).max(1) as f64];
+    let rules = threshold_lines(data, x_bounds);
+    let all_points: Vec<(f64, f64)> = series_runs
+        .iter()
+        .flat_map(|(_, runs)| runs.iter().flatten().copied())
+        .collect();
+    let exceedances = threshold_exceedances(data, &all_points);
 
-    // Main dataset
-    let dataset = Dataset::default()
-        .marker(Marker::Braille)
-        .graph_type(graph_type)
-        .style(Style::default().fg(Color::Cyan))
-        .data(&points);
+    let drawn: Vec<Vec<Vec<(f64, f64)>>> = series_runs.iter().map(|(_, runs)| thin_runs(runs, area.width)).collect();
+    let mut datasets: Vec<Dataset> = rules.iter().map(|(line, color)| rule_dataset(line, *color)).collect();
+    datasets.extend(
+        drawn
+            .iter()
+            .enumerate()
+            .flat_map(|(i, runs)| run_datasets(runs, graph_type, palette.series_color(i))),
+    );
+    datasets.extend(exceedances.iter().map(|(points, color)| exceedance_dataset(points, *color)));
 
-    // Selected point marker — map data index to chart position
-    let selected_chart_pos = if reversed {
-        len - 1 - selected
-    } else {
-        selected
-    };
-    let selected_point = vec![(selected_chart_pos as f64, data.get_y_value(&data.rows[selected]))];
-    let selected_dataset = Dataset::default()
-        .marker(Marker::Dot)
-        .graph_type(GraphType::Scatter)
-        .style(Style::default().fg(Color::Yellow))
-        .data(&selected_point);
+    let selected_row = &data.rows[selected];
+    let selected_y = data.y_value(selected_row);
+    let selected_point: Vec<(f64, f64)> = selected_y.map(|y| (x_pos(selected_row), y)).into_iter().collect();
+    datasets.push(
+        Dataset::default()
+            .marker(Marker::Dot)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(palette.selection))
+            .data(&selected_point),
+    );
 
-    // X-axis labels (in chronological order)
-    let first = &data.rows[*indices.first().unwrap()];
-    let last = &data.rows[*indices.last().unwrap()];
-    let x_labels: Vec<Span> = if len <= 5 {
-        indices
-            .iter()
-            .map(|&i| Span::raw(truncate_string(&data.get_x_value(&data.rows[i]), 10)))
-            .collect()
-    } else {
-        let mid = &data.rows[indices[len / 2]];
-        vec![
-            Span::raw(truncate_string(&data.get_x_value(first), 10)),
-            Span::raw(truncate_string(&data.get_x_value(mid), 10)),
-            Span::raw(truncate_string(&data.get_x_value(last), 10)),
-        ]
-    };
+    // Drawn after the chart, like the single-series labels
+    let x_texts = data.formats.axis_texts(&x_values, &data.x_field);
+    let x_labels = vec![Span::raw(""); 2];
 
-    // Y-axis labels
-    let y_labels = vec![
-        Span::raw(format_number(y_bounds[0])),
-        Span::raw(format_number((y_bounds[0] + y_bounds[1]) / 2.0)),
-        Span::raw(format_number(y_bounds[1])),
-    ];
+    let y_labels: Vec<Span> = y_ticks.iter().map(|&t| Span::raw(data.formats.axis(t, &data.y_field))).collect();
 
     let chart_type_name = match graph_type {
-        GraphType::Line => "Line",
         GraphType::Scatter => "Scatter",
-        _ => "Chart",
+        _ => "Line",
     };
 
-    let chart = Chart::new(vec![dataset, selected_dataset])
+    // Every row gets its x band, one line tall at its plotted height
+    let plot = chart_plot_area(area.inner(Margin::new(1, 1)), &x_labels, &y_labels);
+    let bands = column_bands(plot, &xs);
+    let y_span = (y_bounds[1] - y_bounds[0]).max(f64::EPSILON);
+    let hits: Vec<ChartHit> = data
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(row, values)| {
+            let (start, end) = bands[x_index(values)];
+            let level = (data.y_value(values).unwrap_or(y_bounds[0]) - y_bounds[0]) / y_span;
+            let offset = ((1.0 - level.clamp(0.0, 1.0)) * plot.height.saturating_sub(1) as f64)
+                .round() as u16;
+            ChartHit {
+                area: Rect::new(start, plot.y + offset, end - start, 1),
+                row,
+            }
+        })
+        .collect();
+
+    let chart = Chart::new(datasets)
         .block(
             Block::default()
-                .title(format!(" {} ({}) ", data.title, chart_type_name))
+                .title(format!(
+                    " {} ({} by {}) ",
+                    data.title,
+                    chart_type_name,
+                    data.series_field.as_deref().unwrap_or_default()
+                ))
                 .borders(Borders::ALL)
                 .border_style(Style::default().fg(Color::Blue)),
         )
@@ -211,27 +2006,247 @@ fn render_line_chart(
         );
 
     f.render_widget(chart, area);
+    let matched: Vec<bool> = x_values.iter().map(|x| view.matches_x(x)).collect();
+    let labels = XLabels { texts: &x_texts, positions: &xs, matched: &matched, selected: x_index(selected_row) };
+    render_x_labels(f, plot, area.inner(Margin::new(1, 1)), &labels, palette);
+
+    let marks: Vec<(usize, u16)> = data
+        .annotation_rows()
+        .into_iter()
+        .map(|(number, row, _)| {
+            (number, plot_cell(plot, x_pos(&data.rows[row]), 0.0).0)
+        })
+        .collect();
+    render_annotation_markers(f, plot, &marks);
+    render_threshold_labels(f, plot, data, y_bounds);
+
+    let selected_cell = plot_cell(
+        plot,
+        x_pos(selected_row),
+        (selected_y.unwrap_or(y_bounds[0]) - y_bounds[0]) / y_span,
+    );
+    render_crosshair(f, plot, selected_cell);
+    render_grid(f, plot, y_ticks.len());
+
+    // Legend: each series with its value at the selected x
+    let selected_x = data.get_x_value(selected_row);
+    let marker = legend_marker(graph_type, false);
+    let mut entries: Vec<LegendEntry> = series_runs
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| LegendEntry {
+            marker,
+            color: palette.series_color(i),
+            name: name.clone(),
+            value: find_row(data, &selected_x, Some(name))
+                .map(|row| match data.y_value(&data.rows[row]) {
+                    Some(y) => data.formats.value(y, &data.y_field),
+                    None => "null".to_string(),
+                }),
+        })
+        .collect();
+    entries.push(LegendEntry {
+        marker: "\u{2022}",
+        color: palette.selection,
+        name: "selected".to_string(),
+        value: Some(selected_x),
+    });
+    let rising = series_runs.iter().any(|(_, runs)| {
+        runs.last().and_then(|run| run.last()).map(|p| p.1) > runs.first().map(|run| run[0].1)
+    });
+    let legend = render_legend(f, plot, &entries, rising);
+    render_tooltip(f, plot, selected_cell, tooltip_lines(data, selected_row), legend, palette);
+    hits
+}
+
+fn render_pie_chart(f: &mut Frame, area: Rect, data: &ChartData, selected: usize, palette: &Palette) -> Vec<ChartHit> {
+    if data.rows.is_empty() {
+        render_empty(f, area);
+        return Vec::new();
+    }
+
+    let donut = data.is_donut();
+    let block = Block::default()
+        .title(format!(" {} ({}) ", data.title, if donut { "Donut" } else { "Pie" }))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Blue));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(inner);
+
+    // Negative values have no meaningful share, so they contribute nothing
+    let values: Vec<f64> = data
+        .rows
+        .iter()
+        .map(|row| data.get_y_value(row).max(0.0))
+        .collect();
+    let total: f64 = values.iter().sum();
+
+    // Cumulative end fraction of each slice, clockwise from 12 o'clock
+    let mut acc = 0.0;
+    let ends: Vec<f64> = values
+        .iter()
+        .map(|v| {
+            acc += if total > 0.0 { v / total } else { 0.0 };
+            acc
+        })
+        .collect();
+
+    // Braille dots are roughly square, so widen the x bounds to keep the circle round
+    let plot = chunks[0];
+    let aspect = if plot.height > 0 {
+        plot.width as f64 / (plot.height as f64 * 2.0)
+    } else {
+        1.0
+    };
+    let y_bound = 1.05;
+    let x_bound = y_bound * aspect;
+    let inner_radius = if donut { 0.5 } else { 0.0 };
+
+    // Sample one point per braille dot and assign it to a slice by angle
+    let dots_x = plot.width as usize * 2;
+    let dots_y = plot.height as usize * 4;
+    let mut slices: Vec<Vec<(f64, f64)>> = vec![Vec::new(); values.len()];
+    if total > 0.0 {
+        for dy in 0..dots_y {
+            let y = -y_bound + (dy as f64 + 0.5) * (2.0 * y_bound) / dots_y as f64;
+            for dx in 0..dots_x {
+                let x = -x_bound + (dx as f64 + 0.5) * (2.0 * x_bound) / dots_x as f64;
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 || r < inner_radius {
+                    continue;
+                }
+                let angle = x.atan2(y).rem_euclid(TAU) / TAU;
+                let slice = ends.iter().position(|&end| angle < end).unwrap_or(ends.len() - 1);
+                slices[slice].push((x, y));
+            }
+        }
+    }
+
+    let canvas = Canvas::default()
+        .marker(Marker::Braille)
+        .x_bounds([-x_bound, x_bound])
+        .y_bounds([-y_bound, y_bound])
+        .paint(|ctx| {
+            for (i, coords) in slices.iter().enumerate() {
+                let color = if i == selected {
+                    palette.selection
+                } else {
+                    palette.series_color(i)
+                };
+                ctx.draw(&Points { coords, color });
+            }
+        });
+    f.render_widget(canvas, plot);
+
+    // Legend: category, value, and share of total
+    let lines: Vec<Line> = data
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let is_selected = i == selected;
+            let swatch_color = if is_selected {
+                palette.selection
+            } else {
+                palette.series_color(i)
+            };
+            let pct = if total > 0.0 { values[i] / total * 100.0 } else { 0.0 };
+            let text_style = if is_selected {
+                Style::default().fg(palette.selection).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            Line::from(vec![
+                Span::styled(if is_selected { "\u{25b8}" } else { " " }, text_style),
+                Span::styled("\u{25a0} ", Style::default().fg(swatch_color)),
+                Span::styled(truncate_string(&data.get_x_value(row), 16), text_style),
+                Span::styled(
+                    format!("  {}  ", data.formats.value(data.get_y_value(row), &data.y_field)),
+                    Style::default().fg(Color::Gray),
+                ),
+                Span::styled(format!("{:.1}%", pct), Style::default().fg(Color::DarkGray)),
+            ])
+        })
+        .collect();
+
+    // Keep the selected entry visible when the legend is taller than the area
+    let visible = chunks[1].height as usize;
+    let scroll = selected.saturating_sub(visible.saturating_sub(1));
+    let legend = Paragraph::new(lines).scroll((scroll as u16, 0));
+    f.render_widget(legend, chunks[1]);
+
+    // Legend entries are the click targets
+    (scroll..data.rows.len().min(scroll + visible))
+        .map(|row| ChartHit {
+            area: Rect::new(chunks[1].x, chunks[1].y + (row - scroll) as u16, chunks[1].width, 1),
+            row,
+        })
+        .collect()
 }
 
-fn render_selection_info(f: &mut Frame, area: Rect, data: &ChartData, selected: usize) {
+fn render_selection_info(f: &mut Frame, area: Rect, data: &ChartData, selected: usize, palette: &Palette) {
     if data.rows.is_empty() {
         return;
     }
 
     let row = &data.rows[selected];
-    let x_val = data.get_x_value(row);
+    let x_val = data.x_label(row);
     let y_val = data.get_y_value(row);
-    let y_formatted = format_value(y_val, &data.y_field);
-
-    let info = format!(
-        "◆ Point {}/{}: {} = {} → {} = {}",
-        selected + 1,
-        data.rows.len(),
-        data.x_field,
-        x_val,
-        data.y_field,
-        y_formatted
-    );
+    let y_formatted = data.formats.value(y_val, &data.y_field);
+
+    let series_info = match (&data.series_field, data.get_series_value(row)) {
+        (Some(field), Some(value)) => format!(" [{} = {}]", field, value),
+        _ => String::new(),
+    };
+
+    let mut spans = vec![
+        Span::raw(format!(
+            "◆ Point {}/{}: {} = {}{} → {} = ",
+            selected + 1,
+            data.rows.len(),
+            data.x_field,
+            x_val,
+            series_info,
+            data.y_field,
+        )),
+        signed_span(y_formatted, y_val, palette),
+    ];
+    if let (Some(field), Some(value)) = (&data.y2_field, data.get_y2_value(row)) {
+        spans.push(Span::raw(format!(", {} = ", field)));
+        spans.push(signed_span(data.formats.value(value, field), value, palette));
+    }
+
+    let paragraph = Paragraph::new(Line::from(spans))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        )
+        .style(Style::default().fg(palette.selection))
+        .alignment(Alignment::Center);
+
+    f.render_widget(paragraph, area);
+}
+
+fn render_range_info(f: &mut Frame, area: Rect, data: &ChartData, range: &XRange, palette: &Palette) {
+    let total: f64 = range.rows.iter().map(|&i| data.get_y_value(&data.rows[i])).sum();
+    let info = Line::from(vec![
+        Span::raw(format!(
+            "◆ Range: {} {} → {} · {} points · Σ {} = ",
+            data.x_field,
+            data.get_x_value(&data.rows[range.start]),
+            data.get_x_value(&data.rows[range.end]),
+            range.rows.len(),
+            data.y_field,
+        )),
+        signed_span(data.formats.value(total, &data.y_field), total, palette),
+        Span::raw(" · Enter: drill down"),
+    ]);
 
     let paragraph = Paragraph::new(info)
         .block(
@@ -239,12 +2254,21 @@ fn render_selection_info(f: &mut Frame, area: Rect, data: &ChartData, selected:
                 .borders(Borders::ALL)
                 .border_style(Style::default().fg(Color::DarkGray)),
         )
-        .style(Style::default().fg(Color::Yellow))
+        .style(Style::default().fg(palette.selection))
         .alignment(Alignment::Center);
 
     f.render_widget(paragraph, area);
 }
 
+/// A formatted value, in the negative color when below zero
+fn signed_span(text: String, value: f64, palette: &Palette) -> Span<'static> {
+    if value < 0.0 {
+        Span::styled(text, Style::default().fg(palette.negative))
+    } else {
+        Span::raw(text)
+    }
+}
+
 fn render_empty(f: &mut Frame, area: Rect) {
     let paragraph = Paragraph::new("No data to display")
         .block(
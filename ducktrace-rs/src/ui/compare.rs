@@ -0,0 +1,119 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::app::Comparison;
+use crate::data::{ChartData, ChartSort, DiffLine};
+use super::chart::{render_chart, ChartView};
+use super::theme::Palette;
+use super::{centered_rect, format_history_timestamp};
+
+/// Render two history charts for comparison: overlaid when they share x values,
+/// else stacked, above a diff of their queries
+pub fn render_compare(f: &mut Frame, comparison: &Comparison) {
+    let area = centered_rect(95, 95, f.area());
+    f.render_widget(Clear, area);
+
+    let outer_block = Block::default()
+        .title(" Compare Analyses ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    f.render_widget(outer_block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Percentage(65),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .margin(1)
+        .split(area);
+
+    let labels: Vec<Line> = comparison
+        .entries
+        .iter()
+        .zip(["A", "B"])
+        .map(|(entry, label)| {
+            Line::from(vec![
+                Span::styled(format!(" {} ", label), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                Span::styled(entry.title.clone(), Style::default().fg(Color::White)),
+                Span::styled(
+                    format!("  {}  {} rows", format_history_timestamp(entry.timestamp), entry.row_count),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(labels), chunks[0]);
+
+    match comparison.overlay {
+        Some(ref overlay) if comparison.show_overlay => render_one(f, chunks[1], overlay, &comparison.palettes[0]),
+        _ => {
+            let halves = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(chunks[1]);
+            for ((half, chart), palette) in halves.iter().zip(&comparison.charts).zip(&comparison.palettes) {
+                render_one(f, *half, chart, palette);
+            }
+        }
+    }
+
+    render_diff(f, chunks[2], comparison);
+
+    let help = if comparison.overlay.is_some() {
+        "↑↓ scroll diff | Tab overlay/stacked | Esc close"
+    } else {
+        "↑↓ scroll diff | Esc close"
+    };
+    let help = Paragraph::new(help)
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[3]);
+}
+
+fn render_one(f: &mut Frame, area: Rect, data: &ChartData, palette: &Palette) {
+    let view = ChartView {
+        selected: 0,
+        range: None,
+        bar_mode: data.default_bar_mode(),
+        orientation: data.default_bar_orientation(),
+        sort: ChartSort::Original,
+        search: String::new(),
+    };
+    render_chart(f, area, data, &view, palette);
+}
+
+/// Unified diff of the two queries: removed lines in red, added in green
+fn render_diff(f: &mut Frame, area: Rect, comparison: &Comparison) {
+    let changed = comparison
+        .query_diff
+        .iter()
+        .filter(|l| !matches!(l, DiffLine::Same(_)))
+        .count();
+    let title = if changed == 0 {
+        " Query (identical) ".to_string()
+    } else {
+        format!(" Query diff ({} lines changed) ", changed)
+    };
+
+    let lines: Vec<Line> = comparison
+        .query_diff
+        .iter()
+        .skip(comparison.diff_scroll)
+        .map(|line| match line {
+            DiffLine::Same(text) => Line::styled(format!("  {}", text), Style::default().fg(Color::Gray)),
+            DiffLine::Removed(text) => Line::styled(format!("- {}", text), Style::default().fg(Color::Red)),
+            DiffLine::Added(text) => Line::styled(format!("+ {}", text), Style::default().fg(Color::Green)),
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray));
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
@@ -0,0 +1,55 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::app::App;
+use crate::mode::Confirm;
+use crate::watcher::get_data_path;
+
+/// Render the yes/no question asked before `action`
+pub fn render_confirm(f: &mut Frame, app: &App, action: &Confirm) {
+    let question = match action {
+        Confirm::ClearData => {
+            let chart = app.data.as_ref().map_or("the chart".to_string(), |d| format!("“{}”", d.title));
+            if app.clear_deletes_file() {
+                format!("Close {} and delete {}?", chart, get_data_path().display())
+            } else {
+                format!("Close {}?", chart)
+            }
+        }
+        Confirm::DeleteHistory { title, .. } => format!("Delete “{}” from history?", title),
+    };
+
+    let screen = f.area();
+    let width = (question.chars().count() as u16 + 4).clamp(40, screen.width.saturating_sub(4).max(1));
+    let area = Rect::new(
+        screen.x + screen.width.saturating_sub(width) / 2,
+        screen.y + screen.height.saturating_sub(5) / 2,
+        width.min(screen.width),
+        5.min(screen.height),
+    );
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Are you sure? ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    let text = Paragraph::new(Line::styled(question, Style::default().fg(Color::White)))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(text, chunks[0]);
+
+    let help = Paragraph::new("y/Enter: yes | n/Esc: no")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[1]);
+}
@@ -3,9 +3,77 @@ use ratatui::{
     widgets::{Block, Borders, Cell, Row, Table, TableState},
 };
 
-use crate::data::{format_value, truncate_string, value_to_string, ChartData};
+use super::chart::ChartHit;
+use super::theme::Palette;
+use crate::data::{format_number, is_negative_number, sparkline, truncate_string, value_to_string, ChartData};
+
+/// Numbers from a JSON array cell, if every element is numeric
+fn numeric_array(val: &serde_json::Value) -> Option<Vec<f64>> {
+    let items = val.as_array()?;
+    if items.is_empty() {
+        return None;
+    }
+    items
+        .iter()
+        .map(|item| match item {
+            serde_json::Value::Number(n) => n.as_f64(),
+            serde_json::Value::String(s) => s.parse().ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// SUM/AVG/MIN/MAX of each numeric column over the visible rows (x column excluded)
+fn aggregate_footer(data: &ChartData, visible: &[usize], palette: &Palette) -> Option<Row<'static>> {
+    let x_idx = data.get_x_index();
+    let stats = data.column_stats(visible);
+    if stats.iter().enumerate().all(|(i, s)| i == x_idx || s.is_none()) {
+        return None;
+    }
+
+    let label_style = Style::default().fg(Color::DarkGray);
+    let labels = Text::from(
+        ["SUM", "AVG", "MIN", "MAX"]
+            .into_iter()
+            .map(|l| Line::styled(l, label_style))
+            .collect::<Vec<_>>(),
+    );
+    let cells = stats.iter().enumerate().map(|(i, s)| match s {
+        Some(s) if i != x_idx => {
+            let field = &data.columns[i];
+            Cell::from(Text::from(
+                [s.sum, s.avg, s.min, s.max]
+                    .into_iter()
+                    .map(|v| {
+                        let line = Line::from(data.formats.value(v, field));
+                        if v < 0.0 { line.style(Style::default().fg(palette.negative)) } else { line }
+                    })
+                    .collect::<Vec<_>>(),
+            ))
+            .style(Style::default().fg(Color::Green))
+        }
+        _ => Cell::from(""),
+    });
+    Some(
+        Row::new(std::iter::once(Cell::from(labels)).chain(cells))
+            .height(4)
+            .top_margin(1),
+    )
+}
+
+pub fn render_data(
+    f: &mut Frame,
+    area: Rect,
+    data: &ChartData,
+    selected: usize,
+    search: &str,
+    search_input: bool,
+    palette: &Palette,
+) -> Vec<ChartHit> {
+    // Row-number gutter, sized for the largest row number (and the footer's stat labels)
+    let gutter_width = data.rows.len().max(1).to_string().len().max(3) as u16;
+    let gutter_header = Cell::from("#").style(Style::default().fg(Color::DarkGray));
 
-pub fn render_data(f: &mut Frame, area: Rect, data: &ChartData, selected: usize) {
     let header_cells = data
         .columns
         .iter()
@@ -13,7 +81,7 @@ pub fn render_data(f: &mut Frame, area: Rect, data: &ChartData, selected: usize)
         .map(|(i, h)| {
             let style = if i == data.get_x_index() || i == data.get_y_index() {
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(palette.selection)
                     .add_modifier(Modifier::BOLD)
             } else {
                 Style::default()
@@ -22,41 +90,58 @@ pub fn render_data(f: &mut Frame, area: Rect, data: &ChartData, selected: usize)
             };
             Cell::from(h.clone()).style(style)
         });
-    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    let header = Row::new(std::iter::once(gutter_header).chain(header_cells)).height(1).bottom_margin(1);
 
     let y_idx = data.get_y_index();
     let y_field = &data.y_field;
 
-    let rows: Vec<Row> = data
-        .rows
+    // Rows shown in the table: all of them, or only those matching the search
+    let visible: Vec<usize> = if search.is_empty() {
+        (0..data.rows.len()).collect()
+    } else {
+        data.matching_rows(search)
+    };
+
+    let rows: Vec<Row> = visible
         .iter()
-        .enumerate()
-        .map(|(row_idx, row)| {
+        .map(|&row_idx| {
+            let row = &data.rows[row_idx];
+            let number_style = if row_idx == selected {
+                Style::default().fg(Color::Black).bg(palette.selection)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            let number = Cell::from(format!("{:>width$}", row_idx + 1, width = gutter_width as usize))
+                .style(number_style);
             let cells: Vec<Cell> = row
                 .iter()
                 .enumerate()
                 .map(|(col_idx, val)| {
-                    let display = if col_idx == y_idx {
-                        if let Some(n) = val.as_f64() {
-                            format_value(n, y_field)
-                        } else if let Some(s) = val.as_str() {
-                            if let Ok(n) = s.parse::<f64>() {
-                                format_value(n, y_field)
-                            } else {
-                                value_to_string(val)
-                            }
-                        } else {
-                            value_to_string(val)
-                        }
+                    let hinted = data.columns.get(col_idx).and_then(|column| data.formats.cell(val, column));
+                    let display = if val.is_null() {
+                        // Set apart from zero and from the text "null"
+                        "\u{2205}".to_string()
+                    } else if let Some(values) = numeric_array(val) {
+                        // LIST columns of numbers get an inline trend plus the latest value
+                        let last = values.last().map(|v| format_number(*v)).unwrap_or_default();
+                        format!("{} {}", sparkline(&values, 20), last)
+                    } else if let Some(text) = hinted {
+                        text
+                    } else if col_idx == y_idx {
+                        data.formats.number(val, y_field).unwrap_or_else(|| value_to_string(val))
                     } else {
                         let s = value_to_string(val);
                         truncate_string(&s, 30)
                     };
 
                     let style = if row_idx == selected {
-                        Style::default().fg(Color::Black).bg(Color::Yellow)
+                        Style::default().fg(Color::Black).bg(palette.selection)
+                    } else if val.is_null() {
+                        Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
                     } else if col_idx == data.get_x_index() {
-                        Style::default().fg(Color::Cyan)
+                        Style::default().fg(palette.primary)
+                    } else if is_negative_number(val) {
+                        Style::default().fg(palette.negative)
                     } else if col_idx == y_idx {
                         Style::default().fg(Color::Green)
                     } else {
@@ -66,24 +151,31 @@ pub fn render_data(f: &mut Frame, area: Rect, data: &ChartData, selected: usize)
                     Cell::from(display).style(style)
                 })
                 .collect();
-            Row::new(cells)
+            Row::new(std::iter::once(number).chain(cells))
         })
         .collect();
 
     // Calculate column widths based on content
     let num_cols = data.columns.len();
-    let widths: Vec<Constraint> = if num_cols == 0 {
-        vec![]
-    } else {
-        vec![Constraint::Percentage((100 / num_cols) as u16); num_cols]
-    };
+    let mut widths = vec![Constraint::Length(gutter_width)];
+    if let Some(pct) = 100usize.checked_div(num_cols) {
+        widths.extend(vec![Constraint::Percentage(pct as u16); num_cols]);
+    }
 
     let mut title = format!(" Data ({} rows) ", data.rows.len());
     if let Some(truncated) = data.truncated_from {
-        title = format!(" Data ({} rows, truncated from {}) ", data.rows.len(), truncated);
+        title = format!(" Data ({} rows, truncated from {} · A: show all) ", data.rows.len(), truncated);
+    }
+    if search_input || !search.is_empty() {
+        let cursor = if search_input { "█" } else { "" };
+        title.push_str(&format!("/{}{} ({} matches) ", search, cursor, visible.len()));
     }
 
-    let table = Table::new(rows, widths)
+    let footer = aggregate_footer(data, &visible, palette);
+    // Four stat lines below a blank one
+    let footer_height = if footer.is_some() { 5 } else { 0 };
+
+    let mut table = Table::new(rows, widths)
         .header(header)
         .block(
             Block::default()
@@ -92,16 +184,36 @@ pub fn render_data(f: &mut Frame, area: Rect, data: &ChartData, selected: usize)
                 .border_style(Style::default().fg(Color::Blue)),
         )
         .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    if let Some(footer) = footer {
+        table = table.footer(footer);
+    }
 
     // Use stateful rendering for scroll support
     let mut state = TableState::default();
-    state.select(Some(selected));
+    state.select(visible.iter().position(|&i| i == selected));
 
     f.render_stateful_widget(table, area, &mut state);
 
+    // Click targets for the rows on screen, below the border, header, and its margin
+    let body = Rect {
+        x: area.x + 1,
+        y: area.y + 3,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(4 + footer_height),
+    };
+    let hits = visible
+        .iter()
+        .skip(state.offset())
+        .zip(body.rows())
+        .map(|(&row, line)| ChartHit { area: line, row })
+        .collect();
+
     // Show row indicator
     if !data.rows.is_empty() {
-        let indicator = format!(" Row {}/{} ", selected + 1, data.rows.len());
+        let indicator = match visible.iter().position(|&i| i == selected) {
+            Some(pos) if !search.is_empty() => format!(" Match {}/{} ", pos + 1, visible.len()),
+            _ => format!(" Row {}/{} ", selected + 1, data.rows.len()),
+        };
         let indicator_area = Rect::new(
             area.x + area.width - indicator.len() as u16 - 2,
             area.y,
@@ -112,4 +224,5 @@ pub fn render_data(f: &mut Frame, area: Rect, data: &ChartData, selected: usize)
             ratatui::widgets::Paragraph::new(indicator).style(Style::default().fg(Color::DarkGray));
         f.render_widget(indicator_widget, indicator_area);
     }
+    hits
 }
@@ -4,33 +4,43 @@ use ratatui::{
 };
 
 use crate::app::App;
-use crate::data::{format_value, value_to_string, ExplainData};
+use crate::data::{format_count, is_negative_number, value_to_string, ColumnFormats, ColumnProfile, ExplainData};
+use crate::db::MOTHERDUCK;
+use crate::mode::CommandKind;
 use super::centered_rect;
+use super::chart::ChartHit;
+use super::theme::Palette;
+
+/// Drill-down columns are formatted by the chart's hints; without a chart, by name
+static NO_FORMATS: ColumnFormats = ColumnFormats::new();
 
 /// Render the explain overlay panel
-pub fn render_explain(f: &mut Frame, app: &App) {
-    let area = centered_rect(80, 70, f.area());
+/// Draw the drill-down overlay, returning click targets for its table rows (each
+/// row's position in `explain_sorted_indices`)
+pub fn render_explain(f: &mut Frame, app: &App, palette: &Palette) -> Vec<ChartHit> {
+    let (width, height) = app.explain_size.percent();
+    let area = centered_rect(width, height, f.area());
 
     // Clear the background
     f.render_widget(Clear, area);
 
-    // Render based on state
-    if app.explain_loading {
-        render_loading(f, area, app.frame);
-    } else if let Some(ref error) = app.explain_error {
+    // Render based on state; streamed rows show while the query is still running
+    if let Some(ref error) = app.explain_error {
         render_error(f, area, error);
     } else if let Some(ref explain_data) = app.explain_data {
-        render_data(f, area, explain_data, app);
+        return render_data(f, area, explain_data, app, palette);
     } else {
-        render_loading(f, area, app.frame);
+        render_loading(f, area, app.frame, &app.database_target());
     }
+    Vec::new()
 }
 
-fn render_loading(f: &mut Frame, area: Rect, frame: u32) {
+fn render_loading(f: &mut Frame, area: Rect, frame: u32, target: &str) {
     let dots = ".".repeat(((frame / 5) % 4) as usize);
+    let source = if target == MOTHERDUCK { "MotherDuck" } else { target };
     let text = format!(
-        "\n\n\n  Loading drill-down data{}\n\n  Querying MotherDuck...",
-        dots
+        "\n\n\n  Loading drill-down data{}\n\n  Querying {}...\n\n  Press Esc to cancel",
+        dots, source
     );
 
     let paragraph = Paragraph::new(text)
@@ -63,14 +73,16 @@ fn render_error(f: &mut Frame, area: Rect, error: &str) {
     f.render_widget(paragraph, area);
 }
 
-fn render_data(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App) {
-    // Split area: title/info at top, table in middle, help at bottom
+fn render_data(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App, palette: &Palette) -> Vec<ChartHit> {
+    // Split area: title/info at top, column profile, table in middle, help at bottom
+    let stats_height = if app.explain_show_stats { 1 } else { 0 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Title/info
-            Constraint::Min(5),    // Table
-            Constraint::Length(1), // Help hint
+            Constraint::Length(3),            // Title/info
+            Constraint::Length(stats_height), // Column profile
+            Constraint::Min(5),               // Table
+            Constraint::Length(1),            // Help hint
         ])
         .margin(1)
         .split(area);
@@ -83,42 +95,117 @@ fn render_data(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App)
     f.render_widget(outer_block, area);
 
     // Info line
-    let total_info = if let Some(total) = explain_data.total_count {
+    let total_info = if app.explain_loading {
+        let dots = ".".repeat(((app.frame / 5) % 4) as usize);
+        format!("Fetched {} rows{}", explain_data.rows.len(), dots)
+    } else if let Some(total) = explain_data.total_count.filter(|t| *t > explain_data.rows.len()) {
         format!(
             "Showing {} of {} source rows",
-            explain_data.rows.len(),
-            total
+            format_count(explain_data.rows.len()),
+            format_count(total)
         )
     } else {
-        format!("{} source rows", explain_data.rows.len())
+        format!("{} source rows", format_count(explain_data.rows.len()))
     };
 
-    let info = Paragraph::new(total_info)
-        .style(Style::default().fg(Color::Cyan))
-        .alignment(Alignment::Center);
+    let mut info_spans = vec![Span::styled(total_info, Style::default().fg(Color::Cyan))];
+    if !app.explain_filters.is_empty() {
+        info_spans.push(Span::styled(
+            format!(" · {} match the filters", format_count(app.explain_sorted_indices.len())),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if !app.explain_loading && app.explain_has_more() {
+        info_spans.push(Span::styled(" · n for the next page", Style::default().fg(Color::Green)));
+    }
+    if let Some(fetched_at) = app.explain_cached_at {
+        info_spans.push(Span::styled(
+            format!(" · {} (r to refresh)", cached_age(fetched_at.elapsed().as_secs())),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+    let info = Paragraph::new(Line::from(info_spans)).alignment(Alignment::Center);
     f.render_widget(info, chunks[0]);
 
+    if app.explain_show_stats {
+        if let Some(column) = explain_data.columns.get(app.explain_selected_col) {
+            let profile = explain_data.column_profile(app.explain_selected_col, &app.explain_sorted_indices);
+            let formats = app.data.as_ref().map(|d| &d.formats).unwrap_or(&NO_FORMATS);
+            let line = Paragraph::new(profile_line(column, &profile, formats, palette)).alignment(Alignment::Center);
+            f.render_widget(line, chunks[1]);
+        }
+    }
+
     // Table
-    if explain_data.rows.is_empty() {
+    let hits = if explain_data.rows.is_empty() {
         let empty = Paragraph::new("No source data found")
             .style(Style::default().fg(Color::DarkGray))
             .alignment(Alignment::Center);
-        f.render_widget(empty, chunks[1]);
+        f.render_widget(empty, chunks[2]);
+        Vec::new()
     } else {
-        render_table(f, chunks[1], explain_data, app);
-    }
+        render_table(f, chunks[2], explain_data, app, palette)
+    };
 
     // Help hint
-    let help = Paragraph::new("↑↓ scroll | ←→ column | Enter sort | PgUp/PgDn page | Esc close")
+    let prompt;
+    let hint = if let Some(input) = app.command().filter(|c| c.kind == CommandKind::ExplainFilter).map(|c| &c.text) {
+        let column = explain_data.columns.get(app.explain_selected_col).map_or("", |c| c.as_str());
+        prompt = format!("Filter {}: {}█ | >n <=n =x !=x text | Enter: apply | Esc: cancel", column, input);
+        prompt.as_str()
+    } else if let Some(ref notice) = app.explain_notice {
+        prompt = format!("{} | y cell | Y row | Ctrl+Y SQL | Esc close", notice);
+        prompt.as_str()
+    } else if app.explain_loading {
+        "↑↓ scroll | ←→ column | Enter sort | PgUp/PgDn page | Esc cancel"
+    } else if app.explain_has_more() {
+        "↑↓ scroll | ←→ column | Enter sort | f filter | s stats | y/Y copy | n next page | r refresh | +/-/z size | Esc close"
+    } else {
+        "↑↓ scroll | ←→ column | Enter sort | f filter | s stats | y/Y copy | r refresh | +/-/z size | Esc close"
+    };
+    let help = Paragraph::new(hint)
         .style(Style::default().fg(Color::DarkGray))
         .alignment(Alignment::Center);
-    f.render_widget(help, chunks[2]);
+    f.render_widget(help, chunks[3]);
+    hits
+}
+
+/// One-line profile of the selected column: count, nulls, distinct, min, max, mean
+fn profile_line<'a>(column: &'a str, profile: &ColumnProfile, formats: &ColumnFormats, palette: &Palette) -> Line<'a> {
+    let label = Style::default().fg(Color::DarkGray);
+    let value = Style::default().fg(Color::White);
+    let signed = |negative: bool| if negative { value.fg(palette.negative) } else { value };
+    let show = |v: &serde_json::Value| match v.is_number().then(|| formats.number(v, column)).flatten() {
+        Some(text) => text,
+        None => truncate_for_width(&value_to_string(v), 20),
+    };
+    let mut spans = vec![
+        Span::styled(column, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::styled("  count ", label),
+        Span::styled(format_count(profile.count), value),
+    ];
+    if profile.nulls > 0 {
+        spans.push(Span::styled(format!(" (+{} null)", format_count(profile.nulls)), label));
+    }
+    spans.extend([Span::styled("  distinct ", label), Span::styled(format_count(profile.distinct), value)]);
+    if let (Some(min), Some(max)) = (&profile.min, &profile.max) {
+        spans.extend([
+            Span::styled("  min ", label),
+            Span::styled(show(min), signed(is_negative_number(min))),
+            Span::styled("  max ", label),
+            Span::styled(show(max), signed(is_negative_number(max))),
+        ]);
+    }
+    if let Some(mean) = profile.mean {
+        spans.extend([Span::styled("  mean ", label), Span::styled(formats.value(mean, column), signed(mean < 0.0))]);
+    }
+    Line::from(spans)
 }
 
-fn render_table(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App) {
+fn render_table(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App, palette: &Palette) -> Vec<ChartHit> {
     let col_count = explain_data.columns.len();
     if col_count == 0 {
-        return;
+        return Vec::new();
     }
 
     let available_width = area.width.saturating_sub(2);
@@ -130,13 +217,17 @@ fn render_table(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App
         .iter()
         .enumerate()
         .map(|(i, col)| {
-            let indicator = if app.explain_sort_column == Some(i) {
+            let mut indicator = if app.explain_sort_column == Some(i) {
                 if app.explain_sort_asc { " \u{25b2}" } else { " \u{25bc}" }
             } else {
                 ""
-            };
+            }
+            .to_string();
+            if let Some(filter) = app.explain_filters.get(&i) {
+                indicator.push_str(&format!(" [{}]", filter.text));
+            }
             // Reserve space for indicator in truncation
-            let max_name = if indicator.is_empty() { col_width } else { col_width.saturating_sub(2) };
+            let max_name = col_width.saturating_sub(indicator.chars().count()).max(3);
             let label = format!("{}{}", truncate_for_width(col, max_name), indicator);
 
             let style = if i == app.explain_selected_col {
@@ -149,6 +240,8 @@ fn render_table(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App
         .collect();
     let header = Row::new(header_cells).height(1);
 
+    let formats = app.data.as_ref().map(|d| &d.formats).unwrap_or(&NO_FORMATS);
+
     // Use sorted indices for row ordering
     let indices = &app.explain_sorted_indices;
     let visible_height = area.height.saturating_sub(3) as usize;
@@ -158,26 +251,41 @@ fn render_table(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App
 
     let rows: Vec<Row> = indices[start_idx..end_idx]
         .iter()
-        .map(|&row_idx| {
+        .enumerate()
+        .map(|(pos, &row_idx)| {
+            // The top row is the selected one, for `y`/`Y`
+            let selected = pos == 0;
             let row = &explain_data.rows[row_idx];
             let cells: Vec<Cell> = row
                 .iter()
                 .enumerate()
                 .map(|(col_idx, val)| {
                     let text = value_to_string(val);
-                    let formatted = if col_idx < explain_data.columns.len() {
-                        if let Some(num) = val.as_f64() {
-                            format_value(num, &explain_data.columns[col_idx])
-                        } else {
-                            truncate_for_width(&text, col_width)
-                        }
+                    let column = explain_data.columns.get(col_idx);
+                    let formatted = match column {
+                        Some(column) if formats.get(column).is_some() => formats
+                            .cell(val, column)
+                            .unwrap_or_else(|| truncate_for_width(&text, col_width)),
+                        Some(column) if val.is_number() => formats
+                            .number(val, column)
+                            .unwrap_or_else(|| truncate_for_width(&text, col_width)),
+                        _ => truncate_for_width(&text, col_width),
+                    };
+                    let color = if is_negative_number(val) { palette.negative } else { Color::White };
+                    let style = if selected && col_idx == app.explain_selected_col {
+                        Style::default().fg(color).add_modifier(Modifier::REVERSED)
                     } else {
-                        truncate_for_width(&text, col_width)
+                        Style::default().fg(color)
                     };
-                    Cell::from(formatted).style(Style::default().fg(Color::White))
+                    Cell::from(formatted).style(style)
                 })
                 .collect();
-            Row::new(cells)
+            let row = Row::new(cells);
+            if selected {
+                row.style(Style::default().bg(Color::DarkGray))
+            } else {
+                row
+            }
         })
         .collect();
 
@@ -190,6 +298,21 @@ fn render_table(f: &mut Frame, area: Rect, explain_data: &ExplainData, app: &App
         .block(Block::default().borders(Borders::TOP));
 
     f.render_widget(table, area);
+
+    // Rows start below the border line and the header
+    (start_idx..end_idx)
+        .zip(area.y + 2..area.bottom())
+        .map(|(row, y)| ChartHit { area: Rect::new(area.x, y, area.width, 1), row })
+        .collect()
+}
+
+/// Badge text for a cached result fetched `secs` ago
+fn cached_age(secs: u64) -> String {
+    match secs / 60 {
+        0 => "cached just now".to_string(),
+        mins if mins < 60 => format!("cached {} min ago", mins),
+        mins => format!("cached {} h ago", mins / 60),
+    }
 }
 
 fn truncate_for_width(s: &str, max_width: usize) -> String {
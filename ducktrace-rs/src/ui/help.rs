@@ -27,6 +27,14 @@ pub fn render_help(f: &mut Frame) {
             Span::styled("  ↑/↓    ", Style::default().fg(Color::Green)),
             Span::raw("Scroll/select within tab"),
         ]),
+        Line::from(vec![
+            Span::styled("  h/j/k/l", Style::default().fg(Color::Green)),
+            Span::raw(" Same as ←/↓/↑/→; gg/G first/last, ctrl-u/ctrl-d page"),
+        ]),
+        Line::from(vec![
+            Span::styled("  gt/gT  ", Style::default().fg(Color::Green)),
+            Span::raw("Next/previous tab"),
+        ]),
         Line::from(vec![
             Span::styled("  Scroll ", Style::default().fg(Color::Green)),
             Span::raw("Scroll query or change selection"),
@@ -38,13 +46,133 @@ pub fn render_help(f: &mut Frame) {
             Span::styled("  x/Enter", Style::default().fg(Color::Green)),
             Span::raw(" Explain selected point (drill-down)"),
         ]),
+        Line::from(vec![
+            Span::styled("  Shift+↑↓", Style::default().fg(Color::Green)),
+            Span::raw(" Select a range of x values (Chart tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Esc    ", Style::default().fg(Color::Green)),
+            Span::raw("Clear range selection (Chart tab)"),
+        ]),
         Line::from(vec![
             Span::styled("  Enter  ", Style::default().fg(Color::Green)),
             Span::raw("Load selected analysis (Home tab)"),
         ]),
         Line::from(vec![
             Span::styled("  d/Del  ", Style::default().fg(Color::Green)),
-            Span::raw("Delete selected analysis (Home tab)"),
+            Span::raw("Delete selected analysis, asking first (Home tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  p      ", Style::default().fg(Color::Green)),
+            Span::raw("Pin/unpin selected analysis (Home tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  t      ", Style::default().fg(Color::Green)),
+            Span::raw("Tag selected analysis; filter with /#tag (Home tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  /      ", Style::default().fg(Color::Green)),
+            Span::raw("Filter analyses by title, database, field (Home tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  r      ", Style::default().fg(Color::Green)),
+            Span::raw("Rename selected analysis (Home tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  m / C  ", Style::default().fg(Color::Green)),
+            Span::raw("Mark two analyses / compare them (Home tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  p      ", Style::default().fg(Color::Green)),
+            Span::raw("EXPLAIN ANALYZE the chart's query (Query tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  r      ", Style::default().fg(Color::Green)),
+            Span::raw("Re-run the chart's query (Query tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  y / Y  ", Style::default().fg(Color::Green)),
+            Span::raw("Copy the query, formatted / as written (Query tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  e      ", Style::default().fg(Color::Green)),
+            Span::raw("Edit the query in $EDITOR and re-run it (Query tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  d      ", Style::default().fg(Color::Green)),
+            Span::raw("Diff an edited query against the original (Query tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  za     ", Style::default().fg(Color::Green)),
+            Span::raw("Fold CTE/subquery; zc/zo close/open, zM/zR all (Query tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  d/s    ", Style::default().fg(Color::Green)),
+            Span::raw("DESCRIBE/SUMMARIZE source table (Mask tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  X/Y    ", Style::default().fg(Color::Green)),
+            Span::raw("Chart selected column as x/y (Mask tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  R      ", Style::default().fg(Color::Green)),
+            Span::raw("Auto-refresh: off → 30s → 1m → 5m"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Ctrl+R ", Style::default().fg(Color::Green)),
+            Span::raw("Run ad-hoc SQL (SQL tab; Enter to edit)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  L      ", Style::default().fg(Color::Green)),
+            Span::raw("Show the drill-down query log"),
+        ]),
+        Line::from(vec![
+            Span::styled("  E      ", Style::default().fg(Color::Green)),
+            Span::raw("Export the chart to a PNG or SVG file"),
+        ]),
+        Line::from(vec![
+            Span::styled("  M      ", Style::default().fg(Color::Green)),
+            Span::raw("Write a Markdown report: SQL, data, lineage, drill-down"),
+        ]),
+        Line::from(vec![
+            Span::styled("  D      ", Style::default().fg(Color::Green)),
+            Span::raw("Tail the log file"),
+        ]),
+        Line::from(vec![
+            Span::styled("  T      ", Style::default().fg(Color::Green)),
+            Span::raw("Show background tasks; c cancels one"),
+        ]),
+        Line::from(vec![
+            Span::styled("  P      ", Style::default().fg(Color::Green)),
+            Span::raw("Switch connection profile"),
+        ]),
+        Line::from(vec![
+            Span::styled("  :N     ", Style::default().fg(Color::Green)),
+            Span::raw("Go to row N (Data/Chart tabs)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  /      ", Style::default().fg(Color::Green)),
+            Span::raw("Filter rows (Data), find lines (Query), mark x labels (Chart)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  n/N    ", Style::default().fg(Color::Green)),
+            Span::raw("Next/previous match (Data/Query/Chart tabs)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  A      ", Style::default().fg(Color::Green)),
+            Span::raw("Show all rows of a truncated chart"),
+        ]),
+        Line::from(vec![
+            Span::styled("  b      ", Style::default().fg(Color::Green)),
+            Span::raw("Toggle grouped/stacked bars (Chart tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  v      ", Style::default().fg(Color::Green)),
+            Span::raw("Toggle vertical/horizontal bars (Chart tab)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  o      ", Style::default().fg(Color::Green)),
+            Span::raw("Sort by x / ascending y / descending y (Chart tab)"),
         ]),
         Line::from(""),
         Line::from(Span::styled("Drill-Down Overlay", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
@@ -61,16 +189,32 @@ pub fn render_help(f: &mut Frame) {
             Span::styled("  ↑/↓    ", Style::default().fg(Color::Green)),
             Span::raw("Scroll rows"),
         ]),
+        Line::from(vec![
+            Span::styled("  r      ", Style::default().fg(Color::Green)),
+            Span::raw("Refresh a cached result"),
+        ]),
         Line::from(vec![
             Span::styled("  Esc    ", Style::default().fg(Color::Green)),
-            Span::raw("Close overlay"),
+            Span::raw("Close overlay (cancels a running query)"),
         ]),
         Line::from(""),
         Line::from(Span::styled("General", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
         Line::from(""),
         Line::from(vec![
             Span::styled("  c      ", Style::default().fg(Color::Green)),
-            Span::raw("Clear data file"),
+            Span::raw("Clear data file, asking first"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Tab    ", Style::default().fg(Color::Green)),
+            Span::raw("Switch between open charts (Tab then 1-9)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  [ / ]  ", Style::default().fg(Color::Green)),
+            Span::raw("Previous / next open chart"),
+        ]),
+        Line::from(vec![
+            Span::styled("  !      ", Style::default().fg(Color::Green)),
+            Span::raw("Show why the chart file didn't load"),
         ]),
         Line::from(vec![
             Span::styled("  ?      ", Style::default().fg(Color::Green)),
@@ -101,7 +245,7 @@ pub fn render_help(f: &mut Frame) {
         ]),
         Line::from(vec![
             Span::styled("  Chart  ", Style::default().fg(Color::Yellow)),
-            Span::raw("Visualize data (line/bar/scatter)"),
+            Span::raw("Visualize data (line/area/bar/scatter/pie)"),
         ]),
         Line::from(""),
         Line::from(Span::styled("Press any key to close", Style::default().fg(Color::DarkGray))),
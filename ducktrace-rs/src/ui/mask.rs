@@ -1,11 +1,34 @@
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Cell, Row, Table},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap},
 };
 
+use crate::app::TemplateCheck;
 use crate::data::ChartData;
 
-pub fn render_mask(f: &mut Frame, area: Rect, data: &ChartData) {
+pub fn render_mask(
+    f: &mut Frame,
+    area: Rect,
+    data: &ChartData,
+    selected: usize,
+    check: Option<&TemplateCheck>,
+) {
+    // Drill-down template status below the mapping
+    let area = if data.drill_down.is_some() {
+        let height = match check {
+            Some(TemplateCheck::Done(problems)) if !problems.is_empty() => (problems.len() as u16 + 2).min(8),
+            _ => 3,
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(height)])
+            .split(area);
+        render_template_check(f, chunks[1], check);
+        chunks[0]
+    } else {
+        area
+    };
+
     let header_cells = ["Column", "Role", "Sample Value"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
@@ -13,6 +36,8 @@ pub fn render_mask(f: &mut Frame, area: Rect, data: &ChartData) {
 
     let x_idx = data.get_x_index();
     let y_idx = data.get_y_index();
+    let series_idx = data.get_series_index();
+    let y2_idx = data.get_y2_index();
 
     let rows: Vec<Row> = data
         .columns
@@ -23,6 +48,10 @@ pub fn render_mask(f: &mut Frame, area: Rect, data: &ChartData) {
                 "X (Label)"
             } else if i == y_idx {
                 "Y (Value)"
+            } else if Some(i) == y2_idx {
+                "Y2 (Right)"
+            } else if Some(i) == series_idx {
+                "Series"
             } else {
                 "-"
             };
@@ -34,7 +63,11 @@ pub fn render_mask(f: &mut Frame, area: Rect, data: &ChartData) {
                 .map(crate::data::value_to_string)
                 .unwrap_or_else(|| "-".to_string());
 
-            let style = if i == x_idx || i == y_idx {
+            let style = if i == x_idx
+                || i == y_idx
+                || Some(i) == y2_idx
+                || Some(i) == series_idx
+            {
                 Style::default().fg(Color::Green)
             } else {
                 Style::default().fg(Color::White)
@@ -64,11 +97,12 @@ pub fn render_mask(f: &mut Frame, area: Rect, data: &ChartData) {
         )
         .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 
-    f.render_widget(table, area);
+    let mut state = TableState::default().with_selected(Some(selected));
+    f.render_stateful_widget(table, area, &mut state);
 
     // Show mapping summary at bottom
     let summary = format!(
-        " X: {} → Y: {} ",
+        " X: {} → Y: {} · X/Y pick ",
         data.x_field, data.y_field
     );
     let summary_area = Rect::new(
@@ -77,7 +111,44 @@ pub fn render_mask(f: &mut Frame, area: Rect, data: &ChartData) {
         summary.len() as u16,
         1,
     );
-    let summary_widget = ratatui::widgets::Paragraph::new(summary)
+    let summary_widget = Paragraph::new(summary)
         .style(Style::default().fg(Color::Cyan));
     f.render_widget(summary_widget, summary_area);
+
+    // Offer to inspect the source table when lineage names one
+    if let Some(table) = data.lineage.as_ref().and_then(|l| l.source_table.as_deref()) {
+        let hint = format!(" d describe · s summarize {} ", table);
+        let width = (hint.chars().count() as u16).min(area.width.saturating_sub(summary_area.width + 6));
+        let hint_area = Rect::new(area.right().saturating_sub(width + 2), area.y + area.height - 1, width, 1);
+        let hint_widget = Paragraph::new(hint)
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(hint_widget, hint_area);
+    }
+}
+
+fn render_template_check(f: &mut Frame, area: Rect, check: Option<&TemplateCheck>) {
+    let (lines, color) = match check {
+        None | Some(TemplateCheck::Running) => (
+            vec![Line::from("Checking with EXPLAIN against sample values...")],
+            Color::Yellow,
+        ),
+        Some(TemplateCheck::Done(problems)) if problems.is_empty() => (
+            vec![Line::from("✓ Plans cleanly with EXPLAIN against the first row's values")],
+            Color::Green,
+        ),
+        Some(TemplateCheck::Done(problems)) => (
+            problems.iter().map(|p| Line::from(format!("✗ {}", p))).collect(),
+            Color::Red,
+        ),
+    };
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(color))
+        .block(
+            Block::default()
+                .title(" Drill-Down Template ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(color)),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
 }
@@ -2,9 +2,21 @@ pub mod tabs;
 pub mod query;
 pub mod mask;
 pub mod data;
+pub mod app_log;
 pub mod chart;
 pub mod help;
+pub mod compare;
+pub mod confirm;
 pub mod explain;
+pub mod plan;
+pub mod problems;
+pub mod profiles;
+pub mod query_log;
+pub mod sql;
+pub mod tasks;
+pub mod theme;
+pub mod toasts;
+pub mod workspace;
 
 use ratatui::{
     prelude::*,
@@ -32,7 +44,12 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-use crate::app::{App, Tab};
+use crate::app::{App, Tab, HISTORY_PAGE};
+use crate::data::fold_regions;
+use crate::db::{target_label, ConnectionState};
+use crate::mode::{Command, CommandKind, Mode, SearchTarget};
+use crate::timezone;
+use theme::Palette;
 
 /// Main render function that draws the entire UI
 pub fn render(f: &mut Frame, app: &mut App) {
@@ -46,62 +63,144 @@ pub fn render(f: &mut Frame, app: &mut App) {
         ])
         .split(f.area());
 
+    let palette = app.palette.clone();
+
     // Title bar
     render_title(f, chunks[0], app);
 
     // Tabs
-    tabs::render_tabs(f, chunks[1], app.active_tab);
+    tabs::render_tabs(f, chunks[1], app.active_tab, &palette);
 
     // Content area
     match app.active_tab {
-        Tab::Home => render_home(f, chunks[2], app),
+        Tab::Home => render_home(f, chunks[2], app, &palette),
         Tab::Query => {
             if let Some(ref data) = app.data {
-                query::render_query(f, chunks[2], data, app.scroll_offset);
+                let view = query::QueryView {
+                    scroll: app.scroll_offset,
+                    search: &app.query_search,
+                    search_input: app.in_mode(&Mode::Search(SearchTarget::Query)),
+                    show_diff: app.query_diff,
+                    folded: &app.query_folds,
+                };
+                query::render_query(f, chunks[2], data, &view, &app.palette);
             } else {
                 render_no_data(f, chunks[2]);
             }
         }
         Tab::Mask => {
             if let Some(ref data) = app.data {
-                mask::render_mask(f, chunks[2], data);
+                mask::render_mask(f, chunks[2], data, app.mask_selected, app.template_check.as_ref());
             } else {
                 render_no_data(f, chunks[2]);
             }
         }
         Tab::Data => {
             if let Some(ref data) = app.data {
-                self::data::render_data(f, chunks[2], data, app.selected_point);
+                app.data_hits = self::data::render_data(
+                    f,
+                    chunks[2],
+                    data,
+                    app.selected_point,
+                    &app.search_query,
+                    app.in_mode(&Mode::Search(SearchTarget::Data)),
+                    &palette,
+                );
             } else {
                 render_no_data(f, chunks[2]);
             }
         }
         Tab::Chart => {
             if let Some(ref data) = app.data {
-                chart::render_chart(f, chunks[2], data, app.selected_point);
+                let view = chart::ChartView {
+                    selected: app.selected_point,
+                    range: app.selected_range(),
+                    bar_mode: app.bar_mode.unwrap_or_else(|| data.default_bar_mode()),
+                    orientation: app
+                        .bar_orientation
+                        .unwrap_or_else(|| data.default_bar_orientation()),
+                    sort: app.chart_sort,
+                    search: app.chart_search.clone(),
+                };
+                app.chart_hits = chart::render_chart(f, chunks[2], data, &view, &palette);
             } else {
                 render_no_data(f, chunks[2]);
             }
         }
+        Tab::Sql => sql::render_sql(f, chunks[2], app),
     }
 
     // Status bar
     render_status_bar(f, chunks[3], app);
 
     // Explain overlay (on top of content)
-    if app.show_explain {
-        explain::render_explain(f, app);
+    if app.in_mode(&Mode::Explain) {
+        app.explain_hits = explain::render_explain(f, app, &palette);
+    }
+
+    // Query plan overlay
+    if app.in_mode(&Mode::Plan) {
+        plan::render_plan(f, app);
+    }
+
+    // History comparison overlay
+    if let Some(ref comparison) = app.comparison {
+        compare::render_compare(f, comparison);
+    }
+
+    // Drill-down query log overlay
+    if app.in_mode(&Mode::QueryLog) {
+        query_log::render_query_log(f, app);
+    }
+
+    // Log tail overlay
+    if app.in_mode(&Mode::AppLog) {
+        app_log::render_app_log(f, app);
+    }
+
+    // Chart switcher
+    if let Some(selected) = app.chart_picker() {
+        workspace::render_chart_picker(f, app, selected);
+    }
+
+    // Background task panel
+    if let Some(selected) = app.tasks_panel() {
+        tasks::render_tasks(f, app, selected);
     }
 
+    // Connection profile picker
+    if let Some(selected) = app.profile_picker() {
+        profiles::render_profile_picker(f, app, selected);
+    }
+
+    // What's wrong with a chart file that didn't load
+    if app.in_mode(&Mode::FileProblems) {
+        if let Some((ref path, ref error)) = app.file_error {
+            problems::render_file_problems(f, path, error);
+        }
+    }
+
+    // Yes/no before clearing the chart or deleting an analysis
+    if let Some(Mode::Confirm(action)) = app.mode() {
+        confirm::render_confirm(f, app, action);
+    }
+
+    // Background notifications, over overlays but under help
+    toasts::render_toasts(f, app);
+
     // Help overlay (on top of everything)
-    if app.show_help {
+    if app.in_mode(&Mode::Help) {
         help::render_help(f);
     }
 }
 
 fn render_title(f: &mut Frame, area: Rect, app: &App) {
     let title = if let Some(ref data) = app.data {
-        format!("🦆 DuckTrace: {}", data.title)
+        if app.workspace.len() > 1 {
+            format!("🦆 DuckTrace: {}  [{}/{}]", data.title, app.workspace_current + 1, app.workspace.len())
+        } else {
+            format!("🦆 DuckTrace: {}", data.title)
+        }
     } else {
         "🦆 DuckTrace".to_string()
     };
@@ -129,20 +228,7 @@ const DUCKTRACE_BANNER: &[&str] = &[
     " ╚═════╝ | ╚═════╝ | ╚═════╝|╚═╝  ╚═╝|   ╚═╝   |╚═╝  ╚═╝|╚═╝  ╚═╝| ╚═════╝|╚══════╝",
 ];
 
-// Yellow-to-cyan gradient palette (one color per letter: D U C K T R A C E)
-const BANNER_COLORS: &[(u8, u8, u8)] = &[
-    (255, 255, 50),  // D — bright yellow
-    (220, 245, 60),  // U — yellow-lime
-    (180, 235, 80),  // C — lime
-    (130, 220, 110), // K — yellow-green
-    (80, 210, 150),  // T — green-teal
-    (50, 200, 180),  // R — teal
-    (40, 190, 210),  // A — teal-cyan
-    (30, 180, 235),  // C — light cyan
-    (0, 170, 255),   // E — cyan-blue
-];
-
-fn render_banner_lines(lines: &mut Vec<Line>) {
+fn render_banner_lines(lines: &mut Vec<Line>, palette: &Palette) {
     lines.push(Line::from(""));
     for banner_line in DUCKTRACE_BANNER {
         let segments: Vec<&str> = banner_line.split('|').collect();
@@ -150,11 +236,10 @@ fn render_banner_lines(lines: &mut Vec<Line>) {
             .iter()
             .enumerate()
             .map(|(i, seg)| {
-                let (r, g, b) = BANNER_COLORS[i % BANNER_COLORS.len()];
                 Span::styled(
                     *seg,
                     Style::default()
-                        .fg(Color::Rgb(r, g, b))
+                        .fg(palette.banner[i % palette.banner.len()])
                         .add_modifier(Modifier::BOLD),
                 )
             })
@@ -163,49 +248,16 @@ fn render_banner_lines(lines: &mut Vec<Line>) {
     }
 }
 
+/// A millisecond timestamp as month/day and time in the display time zone
 fn format_history_timestamp(ts: u64) -> String {
-    use std::time::{Duration, UNIX_EPOCH};
-    let d = UNIX_EPOCH + Duration::from_millis(ts);
-    // Calculate month/day/hour/min from duration since epoch
-    let secs = d.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
-    // Simple date math (no timezone, UTC)
-    let days = secs / 86400;
-    let time_of_day = secs % 86400;
-    let hours = time_of_day / 3600;
-    let minutes = (time_of_day % 3600) / 60;
-
-    // Calculate month and day from days since epoch (1970-01-01)
-    let mut y = 1970i64;
-    let mut remaining_days = days as i64;
-
-    loop {
-        let days_in_year = if (y % 4 == 0 && y % 100 != 0) || y % 400 == 0 { 366 } else { 365 };
-        if remaining_days < days_in_year {
-            break;
-        }
-        remaining_days -= days_in_year;
-        y += 1;
-    }
-
-    let is_leap = (y % 4 == 0 && y % 100 != 0) || y % 400 == 0;
-    let month_days = [31, if is_leap { 29 } else { 28 }, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
-    let mut month = 0u32;
-    for (i, &md) in month_days.iter().enumerate() {
-        if remaining_days < md {
-            month = i as u32 + 1;
-            break;
-        }
-        remaining_days -= md;
-    }
-    let day = remaining_days + 1;
-
-    format!("{:02}/{:02} {:02}:{:02}", month, day, hours, minutes)
+    let local = timezone::to_local((ts / 1000) as i64);
+    format!("{:02}/{:02} {:02}:{:02}", local.month, local.day, local.hour, local.minute)
 }
 
-fn render_home(f: &mut Frame, area: Rect, app: &App) {
+fn render_home(f: &mut Frame, area: Rect, app: &App, palette: &Palette) {
     let mut lines: Vec<Line> = Vec::new();
 
-    render_banner_lines(&mut lines);
+    render_banner_lines(&mut lines, palette);
 
     if app.history.is_empty() {
         // No history — show original splash screen
@@ -259,7 +311,7 @@ fn render_home(f: &mut Frame, area: Rect, app: &App) {
             Style::default().fg(Color::Yellow),
         ));
         lines.push(Line::styled(
-            "Watching: ~/.claude/ducktrace/current.json",
+            format!("Watching: {}", watched_path_label()),
             Style::default().fg(Color::DarkGray),
         ));
 
@@ -275,19 +327,62 @@ fn render_home(f: &mut Frame, area: Rect, app: &App) {
     } else {
         // History exists — show data selector
         lines.push(Line::from(""));
-        lines.push(Line::styled(
-            "Recent Analyses:",
-            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
-        ));
+        let heading = match crate::watcher::get_data_dir() {
+            Some(_) => format!("Charts in {}:", watched_path_label()),
+            None => "Recent Analyses:".to_string(),
+        };
+        let mut heading_spans = vec![
+            Span::styled(heading, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("  times in {}", timezone::zone_label()), Style::default().fg(Color::DarkGray)),
+        ];
+        let visible = app.visible_history();
+        let typing = app.in_mode(&Mode::Search(SearchTarget::History));
+        if typing || !app.history_filter.is_empty() {
+            let cursor = if typing { "█" } else { "" };
+            heading_spans.push(Span::styled(
+                format!("  /{}{} ({} of {})", app.history_filter, cursor, visible.len(), app.history.len()),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        // The page holding the selection
+        let selected_pos = visible.iter().position(|&i| i == app.history_selected).unwrap_or(0);
+        let page_start = selected_pos - selected_pos % HISTORY_PAGE;
+        let page_end = (page_start + HISTORY_PAGE).min(visible.len());
+        if visible.len() > HISTORY_PAGE {
+            heading_spans.push(Span::styled(
+                format!(
+                    "  {}–{} of {} · page {}/{}",
+                    page_start + 1,
+                    page_end,
+                    visible.len(),
+                    page_start / HISTORY_PAGE + 1,
+                    visible.len().div_ceil(HISTORY_PAGE)
+                ),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        lines.push(Line::from(heading_spans));
 
-        for (i, entry) in app.history.iter().enumerate() {
+        if visible.is_empty() {
+            lines.push(Line::styled(
+                "   No analyses match the filter",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        for &i in &visible[page_start..page_end] {
+            let entry = &app.history[i];
             let is_selected = i == app.history_selected;
-            let prefix = if is_selected { " \u{25b8} " } else { "   " };
+            let is_open = app.data.is_some() && app.data_path.as_ref() == Some(&entry.path);
+            let prefix = match (is_selected, is_open) {
+                (true, _) => " \u{25b8} ",
+                (false, true) => " \u{2022} ",
+                (false, false) => "   ",
+            };
             let ts = format_history_timestamp(entry.timestamp);
             let row_info = format!("{} rows", entry.row_count);
 
             let style = if is_selected {
-                Style::default().fg(Color::Yellow)
+                Style::default().fg(app.palette.selection)
             } else {
                 Style::default().fg(Color::White)
             };
@@ -300,12 +395,31 @@ fn render_home(f: &mut Frame, area: Rect, app: &App) {
                 entry.title.clone()
             };
 
-            let line = Line::from(vec![
-                Span::styled(prefix, style),
+            let mut spans = vec![Span::styled(prefix, style)];
+            if app.history_marked.contains(&entry.path) {
+                spans.push(Span::styled("◆ ", Style::default().fg(Color::Cyan)));
+            }
+            if entry.meta.pinned {
+                spans.push(Span::styled("★ ", Style::default().fg(Color::Yellow)));
+            }
+            spans.extend([
                 Span::styled(title, style),
                 Span::styled(format!("  {}  ", ts), Style::default().fg(Color::DarkGray)),
                 Span::styled(row_info, Style::default().fg(Color::DarkGray)),
             ]);
+            for tag in &entry.meta.tags {
+                spans.push(Span::styled(format!("  #{}", tag), Style::default().fg(Color::Magenta)));
+            }
+            // Titles can repeat across a directory's charts, so name the file too
+            if crate::watcher::get_data_dir().is_some() {
+                if let Some(name) = entry.path.file_name() {
+                    spans.push(Span::styled(
+                        format!("  {}", name.to_string_lossy()),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+            }
+            let line = Line::from(spans);
             lines.push(line);
         }
 
@@ -320,8 +434,27 @@ fn render_home(f: &mut Frame, area: Rect, app: &App) {
             Span::styled(": load  ", desc_style),
             Span::styled("d", key_style),
             Span::styled(": delete  ", desc_style),
-            Span::styled("?", key_style),
-            Span::styled(": help", desc_style),
+            Span::styled("p", key_style),
+            Span::styled(": pin  ", desc_style),
+            Span::styled("t", key_style),
+            Span::styled(": tag  ", desc_style),
+            Span::styled("r", key_style),
+            Span::styled(": rename  ", desc_style),
+            Span::styled("/", key_style),
+            Span::styled(": filter  ", desc_style),
+            Span::styled("PgUp/Dn", key_style),
+            Span::styled(": page", desc_style),
+        ]));
+        let compare_hint = match app.history_marked.len() {
+            0 => ": compare two marked",
+            1 => ": compare marked with selected",
+            _ => ": compare marked",
+        };
+        lines.push(Line::from(vec![
+            Span::styled(" m", key_style),
+            Span::styled(": mark  ", desc_style),
+            Span::styled("C", key_style),
+            Span::styled(compare_hint, desc_style),
         ]));
 
         // Status
@@ -373,24 +506,259 @@ fn render_no_data(f: &mut Frame, area: Rect) {
     f.render_widget(paragraph, area);
 }
 
-fn render_status_bar(f: &mut Frame, area: Rect, app: &App) {
-    let status = if let Some(ref data) = app.data {
-        if let Some(ref status) = data.status {
-            format!(" {} | ", status)
-        } else {
-            String::new()
-        }
+/// Auto-refresh interval as shown in the status bar, e.g. "30s" or "5m"
+fn format_interval(interval: std::time::Duration) -> String {
+    let secs = interval.as_secs();
+    if secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
     } else {
-        String::new()
+        format!("{}s", secs)
+    }
+}
+
+/// How long ago a millisecond timestamp was, e.g. "12s ago" or "3 min ago"
+fn format_age(ts: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(ts);
+    let secs = now.saturating_sub(ts) / 1000;
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{} min ago", secs / 60),
+        _ => format!("{} h ago", secs / 3600),
+    }
+}
+
+/// Normal-mode hints: the current tab's keys, narrowed to a search's while one is
+/// kept, then the keys that work on every tab. Rebindable keys show as bound.
+fn key_hints(app: &App) -> String {
+    let key = |action: &str| app.config.keys.key_for(action);
+    let has_data = app.data.is_some();
+    let mut hints: Vec<(String, &str)> = match app.active_tab {
+        Tab::Home if !app.history_filter.is_empty() => {
+            vec![("↑↓".into(), "select"), ("Enter".into(), "load"), ("Esc".into(), "clear filter")]
+        }
+        Tab::Home if !app.history.is_empty() => vec![
+            ("Enter".into(), "load"),
+            ("d".into(), "delete"),
+            ("p".into(), "pin"),
+            ("m".into(), "mark"),
+            (key("search"), "filter"),
+        ],
+        Tab::Query if app.fold_pending => vec![
+            ("za".into(), "toggle fold"),
+            ("zc/zo".into(), "close/open"),
+            ("zM/zR".into(), "close/open all"),
+        ],
+        Tab::Query if !app.query_search.is_empty() => {
+            vec![("n/N".into(), "next/prev line"), ("Esc".into(), "clear search")]
+        }
+        Tab::Query if has_data => {
+            let mut hints = vec![
+                ("↑↓".into(), "scroll"),
+                ("e".into(), "edit"),
+                ("y".into(), "copy"),
+                ("r".into(), "rerun"),
+                ("p".into(), "plan"),
+                (key("search"), "find"),
+            ];
+            if app.data.as_ref().is_some_and(|data| data.original_query.is_some()) {
+                hints.insert(2, ("d".into(), if app.query_diff { "query" } else { "diff" }));
+            }
+            if !app.query_diff && app.data.as_ref().is_some_and(|data| !fold_regions(&query::format_sql(&data.query)).is_empty()) {
+                hints.insert(1, ("za".into(), "fold"));
+            }
+            hints
+        }
+        Tab::Mask if has_data => vec![
+            ("↑↓".into(), "column"),
+            ("X/Y".into(), "chart on x/y"),
+            ("d/s".into(), "describe/summarize"),
+        ],
+        Tab::Data if !app.search_query.is_empty() => vec![
+            ("n/N".into(), "next/prev match"),
+            ("Esc".into(), "clear filter"),
+            (key("explain"), "explain"),
+        ],
+        Tab::Data if has_data => vec![
+            ("↑↓".into(), "select"),
+            (key("explain"), "explain"),
+            (key("search"), "filter"),
+            (format!("{}N", key("goto_row")), "go to row"),
+        ],
+        Tab::Chart if !app.chart_search.is_empty() => vec![
+            ("n/N".into(), "next/prev match"),
+            ("Esc".into(), "clear search"),
+            (key("explain"), "explain"),
+        ],
+        Tab::Chart if has_data => {
+            let mut hints = vec![("↑↓".into(), "select"), (key("explain"), "explain"), (key("search"), "find")];
+            if app.data.as_ref().is_some_and(|data| data.is_sortable()) {
+                hints.push((key("chart_sort"), "sort"));
+            }
+            hints
+        }
+        Tab::Sql => vec![("Enter".into(), "edit"), ("r".into(), "run")],
+        _ => Vec::new(),
     };
+    let (prev, next) = (key("prev_tab"), key("next_tab"));
+    let tabs = if prev == "←" && next == "→" { "←→".to_string() } else { format!("{}/{}", prev, next) };
+    hints.push((tabs, "tabs"));
+    if has_data {
+        hints.push((key("clear"), "clear"));
+    }
+    hints.push((key("help"), "help"));
+    hints.push((key("quit"), "quit"));
 
-    let help_hint = "←→: tabs | ↑↓: select | x: explain | c: clear | ?: help | q: quit";
+    let mut text: Vec<String> = hints.iter().map(|(key, what)| format!("{}: {}", key, what)).collect();
+    if app.active_tab == Tab::Chart && !app.chart_search.is_empty() {
+        text.insert(0, format!("/{}", app.chart_search));
+    }
+    text.join(" | ")
+}
 
-    let status_line = format!("{}{}", status, help_hint);
+fn render_status_bar(f: &mut Frame, area: Rect, app: &App) {
+    let status = match app.data {
+        Some(ref data) => match (data.truncated_from, &data.status) {
+            (Some(total), _) if matches!(app.active_tab, Tab::Chart | Tab::Data) => {
+                format!(" {} of {} rows · A: all | ", data.rows.len(), total)
+            }
+            (_, Some(status)) => format!(" {} | ", status),
+            _ => String::new(),
+        },
+        None => String::new(),
+    };
 
-    let paragraph = Paragraph::new(status_line)
+    let help_hint: String = match app.mode() {
+        Some(Mode::Command(Command { kind: CommandKind::Export, text })) => {
+            format!("Export to: {}█ | .png, .svg, or .md | Enter: save | Esc: cancel", text)
+        }
+        Some(Mode::Command(Command { kind: CommandKind::Rename, text })) => {
+            format!("Title: {}█ | Enter: save | Esc: cancel", text)
+        }
+        Some(Mode::Command(Command { kind: CommandKind::Tags, text })) => {
+            format!("Tags: {}█ | comma or space separated | Enter: save | Esc: cancel", text)
+        }
+        Some(Mode::Command(Command { kind: CommandKind::GotoRow, text })) => {
+            format!("Go to row: {}█ | Enter: go | Esc: cancel", text)
+        }
+        Some(Mode::Confirm(_)) => "y/Enter: yes | n/Esc: no".into(),
+        Some(Mode::Editor) => "Ctrl+R/F5: run | Tab: complete | Ctrl+P/N: history | Esc: stop editing".into(),
+        Some(Mode::Search(SearchTarget::History)) => {
+            "type to filter by title, database, field, or #tag | Enter: keep filter | Esc: clear".into()
+        }
+        Some(Mode::Search(SearchTarget::Data)) => "type to filter rows | Enter: keep filter | Esc: clear".into(),
+        Some(Mode::Search(SearchTarget::Query)) => "type to find lines | Enter: keep search | Esc: clear".into(),
+        Some(Mode::Search(SearchTarget::Chart)) => {
+            format!("/{}█ | highlights matching x labels | Enter: keep search | Esc: clear", app.chart_search)
+        }
+        _ => key_hints(app),
+    };
+
+    let mut spans = Vec::new();
+    if let Some((ref path, ref error)) = app.file_error {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let summary = error.lines().next().unwrap_or_default();
+        let short: String = summary.chars().take(50).collect();
+        let ellipsis = if short.len() < summary.len() { "…" } else { "" };
+        let kept = if app.data.is_some() { " (showing last good chart)" } else { "" };
+        spans.push(Span::styled(
+            format!("⚠ Malformed {}: {}{}{} · !: details | ", name, short, ellipsis, kept),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if let Some(ref error) = app.config_error {
+        let short: String = error.chars().take(50).collect();
+        let ellipsis = if short.len() < error.len() { "…" } else { "" };
+        spans.push(Span::styled(
+            format!("⚠ Config: {}{} (keeping previous) | ", short, ellipsis),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if let Some(ref notice) = app.copy_notice {
+        spans.push(Span::styled(format!("✓ {} | ", notice), Style::default().fg(Color::Green)));
+    }
+    if let Some(ref error) = app.profile_error {
+        let short: String = error.chars().take(50).collect();
+        let ellipsis = if short.len() < error.len() { "…" } else { "" };
+        spans.push(Span::styled(
+            format!("⚠ Profile: {}{} | ", short, ellipsis),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if let Some(interval) = app.auto_refresh {
+        let age = app
+            .data
+            .as_ref()
+            .and_then(|d| d.timestamp)
+            .map(|ts| format!(" · updated {}", format_age(ts)))
+            .unwrap_or_default();
+        spans.push(Span::styled(
+            format!("⟳ every {}{} | ", format_interval(interval), age),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    if app.rerun_loading {
+        let dots = ".".repeat(((app.frame / 5) % 4) as usize);
+        spans.push(Span::styled(
+            format!("Re-running query{:<3} Esc: cancel | ", dots),
+            Style::default().fg(Color::Yellow),
+        ));
+    } else if let Some(ref error) = app.rerun_error {
+        let first_line = error.lines().next().unwrap_or_default();
+        let short: String = first_line.chars().take(60).collect();
+        let ellipsis = if short.len() < first_line.len() { "…" } else { "" };
+        spans.push(Span::styled(
+            format!("✗ Re-run failed: {}{} | ", short, ellipsis),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    match app.tasks.running() {
+        0 => {}
+        running => spans.push(Span::styled(
+            format!("⟳ {} running · T: tasks | ", running),
+            Style::default().fg(Color::Yellow),
+        )),
+    }
+    if let Some(state) = app.connection_state() {
+        let target = app.database_target();
+        let name = target_label(&target);
+        let (text, color) = match state {
+            ConnectionState::Connecting { attempt, max_attempts } => (
+                format!("◌ Connecting to {} ({}/{}) | ", name, attempt, max_attempts),
+                Color::Yellow,
+            ),
+            ConnectionState::Connected => match app.active_profile {
+                Some(ref profile) => (format!("● {} ({}) | ", name, profile), Color::Green),
+                None => (format!("● {} | ", name), Color::Green),
+            },
+            ConnectionState::Failed { .. } => (format!("✗ {} unreachable | ", name), Color::Red),
+        };
+        spans.push(Span::styled(text, Style::default().fg(color)));
+    } else if let Some(ref profile) = app.active_profile {
+        spans.push(Span::styled(format!("Profile: {} | ", profile), Style::default().fg(Color::Cyan)));
+    }
+    spans.push(Span::raw(format!("{}{}", status, help_hint)));
+
+    let paragraph = Paragraph::new(Line::from(spans))
         .style(Style::default().fg(Color::DarkGray))
         .alignment(Alignment::Center);
 
     f.render_widget(paragraph, area);
 }
+
+/// The watched directory or data file, with the home directory shortened to `~`, or stdin
+fn watched_path_label() -> String {
+    if crate::watcher::reads_stdin() {
+        return "stdin".to_string();
+    }
+    let path = match crate::watcher::get_data_dir() {
+        Some(dir) => dir.clone(),
+        None => crate::watcher::get_data_path(),
+    };
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(|p| p.to_path_buf())) {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
@@ -0,0 +1,107 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::app::App;
+use super::centered_rect;
+
+/// Render the EXPLAIN ANALYZE overlay for the chart's query
+pub fn render_plan(f: &mut Frame, app: &App) {
+    let area = centered_rect(90, 80, f.area());
+    f.render_widget(Clear, area);
+
+    if let Some(ref error) = app.plan_error {
+        render_error(f, area, error);
+    } else if let Some(ref plan) = app.plan_text {
+        render_tree(f, area, plan, app);
+    } else {
+        render_loading(f, area, app.frame);
+    }
+}
+
+fn render_loading(f: &mut Frame, area: Rect, frame: u32) {
+    let dots = ".".repeat(((frame / 5) % 4) as usize);
+    let text = format!(
+        "\n\n\n  Running EXPLAIN ANALYZE{}\n\n  The query runs in full to collect timings\n\n  Press Esc to cancel",
+        dots
+    );
+
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .title(" Query Plan ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .style(Style::default().fg(Color::Yellow))
+        .alignment(Alignment::Center);
+
+    f.render_widget(paragraph, area);
+}
+
+fn render_error(f: &mut Frame, area: Rect, error: &str) {
+    let text = format!("\n\n  Error:\n\n  {}\n\n  Press Esc to close", error);
+
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .title(" Query Plan - Error ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Red)),
+        )
+        .style(Style::default().fg(Color::Red))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(paragraph, area);
+}
+
+fn render_tree(f: &mut Frame, area: Rect, plan: &str, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .margin(1)
+        .split(area);
+
+    let line_count = plan.lines().count();
+    let title = format!(
+        " Query Plan (EXPLAIN ANALYZE) — line {}/{} ",
+        (app.plan_scroll + 1).min(line_count),
+        line_count
+    );
+    let outer_block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+    f.render_widget(outer_block, area);
+
+    let lines: Vec<Line> = plan
+        .lines()
+        .map(|l| Line::styled(l.to_string(), plan_line_style(l)))
+        .collect();
+    let tree = Paragraph::new(lines).scroll((
+        app.plan_scroll.min(u16::MAX as usize) as u16,
+        app.plan_hscroll.min(u16::MAX as usize) as u16,
+    ));
+    f.render_widget(tree, chunks[0]);
+
+    let help = Paragraph::new("↑↓ scroll | ←→ pan | PgUp/PgDn page | Esc close")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[1]);
+}
+
+/// Highlight the lines that carry timings and cardinalities in DuckDB's plan boxes
+fn plan_line_style(line: &str) -> Style {
+    if line.contains("Total Time") {
+        Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+    } else if line.contains("s)") {
+        // Operator timing, e.g. "(0.42s)"
+        Style::default().fg(Color::Yellow)
+    } else if line.contains(" Rows") {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default().fg(Color::White)
+    }
+}
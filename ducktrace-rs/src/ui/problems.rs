@@ -0,0 +1,45 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+use std::path::Path;
+
+use super::centered_rect;
+
+/// Render what's wrong with a chart file that didn't load: the summary, then a
+/// line per problem (or the parser's message when it isn't valid JSON at all)
+pub fn render_file_problems(f: &mut Frame, path: &Path, error: &str) {
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" ⚠ {} didn't load ", path.display()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    let mut lines = error.lines();
+    let summary = lines.next().unwrap_or_default();
+    let mut text = vec![
+        Line::styled(format!(" {}", summary), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+        Line::from(""),
+    ];
+    text.extend(lines.map(|problem| {
+        Line::from(vec![
+            Span::styled(" • ", Style::default().fg(Color::Red)),
+            Span::styled(problem.to_string(), Style::default().fg(Color::White)),
+        ])
+    }));
+    f.render_widget(Paragraph::new(text).wrap(Wrap { trim: false }), chunks[0]);
+
+    let help = Paragraph::new("Saving a fixed file reloads it | any key: close | !: reopen")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[1]);
+}
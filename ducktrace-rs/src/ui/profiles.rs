@@ -0,0 +1,84 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::app::App;
+use crate::config::Profile;
+use crate::db::target_label;
+use super::centered_rect;
+
+/// Render the connection profile picker: the config's own settings, then each
+/// `[profiles.<name>]`, with the active one marked
+pub fn render_profile_picker(f: &mut Frame, app: &App, selected: usize) {
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Connection Profile ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.palette.primary));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    let names = app.profile_names();
+    let default = Profile {
+        database_path: app.config.database_path.clone(),
+        ..Profile::default()
+    };
+    let rows = std::iter::once((None, &default))
+        .chain(names.iter().map(|name| (Some(*name), &app.config.profiles[*name])));
+
+    let mut lines: Vec<Line> = rows
+        .enumerate()
+        .map(|(i, (name, profile))| {
+            let active = name == app.active_profile.as_ref();
+            let marker = if active { "● " } else { "  " };
+            let label = name.map_or("(no profile)", |n| n.as_str());
+            let style = if i == selected {
+                Style::default().fg(Color::Black).bg(app.palette.selection)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            Line::from(vec![
+                Span::styled(format!("{}{:<16}", marker, label), style.add_modifier(Modifier::BOLD)),
+                Span::styled(format!("  {}", describe(profile)), Style::default().fg(Color::DarkGray)),
+            ])
+        })
+        .collect();
+    if names.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "  Add [profiles.<name>] tables to ~/.config/ducktrace/config.toml",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let help = Paragraph::new("↑↓ select | Enter switch | Esc close")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[1]);
+}
+
+/// Where a profile connects, e.g. "MotherDuck · analytics · token from $MD_WORK"
+fn describe(profile: &Profile) -> String {
+    let mut parts = vec![profile.database_path.as_deref().map_or("MotherDuck", target_label).to_string()];
+    if let Some(ref database) = profile.database {
+        parts.push(database.clone());
+    }
+    if let Some(ref var) = profile.token_env {
+        parts.push(format!("token from ${}", var));
+    } else if profile.token_command.is_some() {
+        parts.push("token from command".to_string());
+    }
+    if !profile.attach.is_empty() {
+        parts.push(format!("+{} attached", profile.attach.len()));
+    }
+    parts.join(" · ")
+}
@@ -3,7 +3,10 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Wrap},
 };
 
-use crate::data::ChartData;
+use std::collections::BTreeSet;
+
+use super::theme::Palette;
+use crate::data::{diff_lines, fold_regions, visible_lines, ChartData, DiffLine};
 
 /// SQL token types for syntax highlighting
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -26,7 +29,7 @@ struct Token<'a> {
 }
 
 /// SQL keywords to highlight
-const SQL_KEYWORDS: &[&str] = &[
+pub const SQL_KEYWORDS: &[&str] = &[
     "SELECT", "FROM", "WHERE", "AND", "OR", "NOT", "IN", "IS", "NULL", "AS",
     "JOIN", "LEFT", "RIGHT", "INNER", "OUTER", "FULL", "CROSS", "ON",
     "GROUP", "BY", "ORDER", "HAVING", "LIMIT", "OFFSET", "UNION", "ALL",
@@ -44,7 +47,7 @@ const SQL_KEYWORDS: &[&str] = &[
 ];
 
 /// SQL aggregate/window functions
-const SQL_FUNCTIONS: &[&str] = &[
+pub const SQL_FUNCTIONS: &[&str] = &[
     "COUNT", "SUM", "AVG", "MIN", "MAX", "TOTAL",
     "ROW_NUMBER", "RANK", "DENSE_RANK", "NTILE", "LAG", "LEAD",
     "FIRST_VALUE", "LAST_VALUE", "NTH_VALUE",
@@ -75,40 +78,104 @@ const SQL_OPERATORS: &[&str] = &[
     "||", "->", "->>", "::", "@", "#", "&", "|", "^", "~",
 ];
 
-pub fn render_query(f: &mut Frame, area: Rect, data: &ChartData, scroll_offset: usize) {
-    // Format the SQL query
-    let formatted = sqlformat::format(
-        &data.query,
+/// SQL laid out as on the Query tab: two-space indents and uppercase keywords
+pub fn format_sql(query: &str) -> String {
+    sqlformat::format(
+        query,
         &sqlformat::QueryParams::None,
         sqlformat::FormatOptions {
             indent: sqlformat::Indent::Spaces(2),
             uppercase: true,
             lines_between_queries: 1,
         },
-    );
+    )
+}
+
+/// How the Query tab is shown: where it's scrolled to, its search, and which
+/// regions are folded
+pub struct QueryView<'a> {
+    /// Top row, counting a folded region as one
+    pub scroll: usize,
+    pub search: &'a str,
+    /// The search is being typed
+    pub search_input: bool,
+    /// Show the original query's lines turned into the edited one's instead
+    pub show_diff: bool,
+    /// Start lines of the folded regions
+    pub folded: &'a BTreeSet<usize>,
+}
 
-    let lines: Vec<Line> = formatted
-        .lines()
-        .enumerate()
-        .map(|(i, line)| {
-            // Line numbers in gray
+/// The formatted query, with lines containing the search marked in the gutter and
+/// folded CTEs and subqueries shown as one line, or the diff from the original query
+pub fn render_query(f: &mut Frame, area: Rect, data: &ChartData, view: &QueryView, palette: &Palette) {
+    if let (true, Some(original)) = (view.show_diff, &data.original_query) {
+        render_query_diff(f, area, original, &data.query, view.scroll);
+        return;
+    }
+    let formatted = format_sql(&data.query);
+    let source: Vec<&str> = formatted.lines().collect();
+    let regions = fold_regions(&formatted);
+    let needle = view.search.to_lowercase();
+    let is_match = |line: &str| !needle.is_empty() && line.to_lowercase().contains(&needle);
+    let matches = source.iter().filter(|line| is_match(line)).count();
+
+    let lines: Vec<Line> = visible_lines(&regions, view.folded, source.len())
+        .into_iter()
+        .map(|i| {
+            let line = source[i];
+            // Line numbers in gray, or highlighted on a search match
             let line_num = format!("{:4} ", i + 1);
-            let mut spans = vec![Span::styled(line_num, Style::default().fg(Color::DarkGray))];
+            let num_style = if is_match(line) {
+                Style::default().fg(Color::Black).bg(palette.selection)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            let mut spans = vec![Span::styled(line_num, num_style)];
+
+            // A fold marker on each region's first line, when there are any
+            let region = regions.iter().find(|r| r.start == i);
+            let folded = region.filter(|_| view.folded.contains(&i));
+            if !regions.is_empty() {
+                let marker = match (region, folded) {
+                    (_, Some(_)) => "▸ ",
+                    (Some(_), None) => "▾ ",
+                    (None, None) => "  ",
+                };
+                spans.push(Span::styled(marker, Style::default().fg(Color::DarkGray)));
+            }
 
             // Add syntax-highlighted spans
             spans.extend(highlight_line(line));
 
+            // A folded region ends with its closing line, after a count of what's hidden
+            if let Some(region) = folded {
+                let hidden = format!(" ⋯ {} lines ", region.hidden());
+                spans.push(Span::styled(hidden, Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)));
+                spans.extend(highlight_line(source[region.end].trim_start()));
+            }
+
             Line::from(spans)
         })
         .collect();
 
-    let total_lines = lines.len();
+    let (total_lines, shown) = (source.len(), lines.len());
 
     // Build title with database name if available
-    let title = match &data.database {
-        Some(db) => format!(" SQL Query @ {} ({} lines) ", db, total_lines),
-        None => format!(" SQL Query ({} lines) ", total_lines),
+    let mut notes = String::new();
+    if shown < total_lines {
+        notes.push_str(&format!(", {} folded", total_lines - shown));
+    }
+    if data.original_query.is_some() {
+        notes.push_str(", edited · d: diff");
+    }
+    let mut title = match &data.database {
+        Some(db) => format!(" SQL Query @ {} ({} lines{}) ", db, total_lines, notes),
+        None => format!(" SQL Query ({} lines{}) ", total_lines, notes),
     };
+    if view.search_input || !view.search.is_empty() {
+        let cursor = if view.search_input { "█" } else { "" };
+        title.push_str(&format!("/{}{} ({} matches) ", view.search, cursor, matches));
+    }
 
     let paragraph = Paragraph::new(lines)
         .block(
@@ -118,13 +185,13 @@ pub fn render_query(f: &mut Frame, area: Rect, data: &ChartData, scroll_offset:
                 .border_style(Style::default().fg(Color::Blue)),
         )
         .wrap(Wrap { trim: false })
-        .scroll((scroll_offset as u16, 0));
+        .scroll((view.scroll as u16, 0));
 
     f.render_widget(paragraph, area);
 
     // Render scroll indicator if needed
-    if total_lines > area.height as usize - 2 {
-        let scroll_info = format!(" {}/{} ", scroll_offset + 1, total_lines);
+    if shown > area.height as usize - 2 {
+        let scroll_info = format!(" {}/{} ", view.scroll + 1, shown);
         let scroll_area = Rect::new(
             area.x + area.width - scroll_info.len() as u16 - 2,
             area.y,
@@ -137,8 +204,30 @@ pub fn render_query(f: &mut Frame, area: Rect, data: &ChartData, scroll_offset:
     }
 }
 
+/// The original query's formatted lines turned into the edited query's: removed
+/// lines in red, added in green
+fn render_query_diff(f: &mut Frame, area: Rect, original: &str, edited: &str, scroll_offset: usize) {
+    let diff = diff_lines(&format_sql(original.trim()), &format_sql(edited.trim()));
+    let changed = diff.iter().filter(|l| !matches!(l, DiffLine::Same(_))).count();
+    let lines: Vec<Line> = diff
+        .iter()
+        .map(|line| match line {
+            DiffLine::Same(text) => Line::styled(format!("  {}", text), Style::default().fg(Color::Gray)),
+            DiffLine::Removed(text) => Line::styled(format!("- {}", text), Style::default().fg(Color::Red)),
+            DiffLine::Added(text) => Line::styled(format!("+ {}", text), Style::default().fg(Color::Green)),
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(format!(" Edited query vs original ({} lines changed) · d: back ", changed))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Blue));
+    let paragraph = Paragraph::new(lines).block(block).scroll((scroll_offset as u16, 0));
+    f.render_widget(paragraph, area);
+}
+
 /// Highlight a single line of SQL and return colored spans
-fn highlight_line(line: &str) -> Vec<Span<'static>> {
+pub fn highlight_line(line: &str) -> Vec<Span<'static>> {
     let tokens = tokenize(line);
     tokens
         .into_iter()
@@ -326,12 +415,3 @@ fn match_operator(input: &str, start: usize) -> Option<&str> {
     }
     None
 }
-
-pub fn get_query_line_count(data: &ChartData) -> usize {
-    let formatted = sqlformat::format(
-        &data.query,
-        &sqlformat::QueryParams::None,
-        sqlformat::FormatOptions::default(),
-    );
-    formatted.lines().count()
-}
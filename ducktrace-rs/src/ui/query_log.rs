@@ -0,0 +1,128 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::app::App;
+use crate::data::{value_to_string, QueryLogEntry};
+use crate::db::target_label;
+use super::{centered_rect, format_history_timestamp};
+use super::query::highlight_line;
+use super::theme::Palette;
+
+/// Render the log of executed drill-downs: a list of runs and the selected one's SQL
+pub fn render_query_log(f: &mut Frame, app: &App) {
+    let area = centered_rect(90, 80, f.area());
+    f.render_widget(Clear, area);
+
+    let outer_block = Block::default()
+        .title(format!(" Drill-Down Log ({} queries, times in {}) ", app.query_log.len(), crate::timezone::zone_label()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.palette.primary));
+    f.render_widget(outer_block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(40),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .margin(1)
+        .split(area);
+
+    // Newest first
+    let entries: Vec<&QueryLogEntry> = app.query_log.iter().rev().collect();
+    if entries.is_empty() {
+        let empty = Paragraph::new("\n  No drill-downs have run yet this session")
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(empty, chunks[0]);
+    } else {
+        let selected = app.query_log_selected.min(entries.len() - 1);
+        render_list(f, chunks[0], &entries, selected, &app.palette);
+        render_detail(f, chunks[1], entries[selected]);
+    }
+
+    let help = Paragraph::new("↑↓ select | Esc close")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[2]);
+}
+
+fn render_list(f: &mut Frame, area: Rect, entries: &[&QueryLogEntry], selected: usize, palette: &Palette) {
+    let height = area.height as usize;
+    let offset = (selected + 1).saturating_sub(height);
+
+    let lines: Vec<Line> = entries
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(i, entry)| {
+            let (mark, color) = match entry.error {
+                Some(_) => ("✗", Color::Red),
+                None => ("✓", Color::Green),
+            };
+            let marker = if i == selected { "▶ " } else { "  " };
+            let style = if i == selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            Line::from(vec![
+                Span::styled(marker, Style::default().fg(palette.primary)),
+                Span::styled(format!("{} ", mark), Style::default().fg(color)),
+                Span::styled(
+                    format!(
+                        "{}  {:>7} ms  {:>7} rows  {}",
+                        format_history_timestamp(entry.timestamp),
+                        entry.duration_ms,
+                        entry.rows,
+                        target_label(&entry.target),
+                    ),
+                    style,
+                ),
+            ])
+        })
+        .collect();
+
+    f.render_widget(Paragraph::new(lines), area);
+}
+
+fn render_detail(f: &mut Frame, area: Rect, entry: &QueryLogEntry) {
+    let mut lines: Vec<Line> = entry.sql.lines().map(|l| Line::from(highlight_line(l))).collect();
+
+    if !entry.params.is_empty() {
+        lines.push(Line::from(""));
+        let params = entry
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("${} = {}", i + 1, param_literal(v)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        lines.push(Line::styled(format!("Parameters: {}", params), Style::default().fg(Color::Yellow)));
+    }
+    if let Some(ref error) = entry.error {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(format!("Error: {}", error), Style::default().fg(Color::Red)));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(" SQL ")
+                .borders(Borders::TOP)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, area);
+}
+
+/// A bound value as it would read in SQL, so the log shows exactly what was compared
+fn param_literal(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => format!("'{}'", s.replace('\'', "''")),
+        other => value_to_string(other),
+    }
+}
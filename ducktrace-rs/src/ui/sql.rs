@@ -0,0 +1,109 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+use crate::app::App;
+use crate::mode::Mode;
+use super::query::highlight_line;
+
+/// Width of the line-number gutter, including its trailing space
+const GUTTER: u16 = 5;
+
+/// Render the ad-hoc SQL editor and the outcome of its last run
+pub fn render_sql(f: &mut Frame, area: Rect, app: &App) {
+    let status_height = if app.sql_error.is_some() && app.sql_editor.completions().is_none() { 5 } else { 1 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(status_height)])
+        .split(area);
+
+    render_editor(f, chunks[0], app);
+    render_status(f, chunks[1], app);
+}
+
+fn render_editor(f: &mut Frame, area: Rect, app: &App) {
+    let editor = &app.sql_editor;
+    let (row, col) = editor.cursor();
+
+    // Keep the cursor line in view
+    let height = area.height.saturating_sub(2) as usize;
+    let scroll = (row + 1).saturating_sub(height);
+
+    let lines: Vec<Line> = editor
+        .lines()
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .map(|(i, line)| {
+            let line_num = format!("{:4} ", i + 1);
+            let mut spans = vec![Span::styled(line_num, Style::default().fg(Color::DarkGray))];
+            spans.extend(highlight_line(line));
+            Line::from(spans)
+        })
+        .collect();
+
+    let mut title = if app.in_mode(&Mode::Editor) {
+        " SQL Editor (editing) ".to_string()
+    } else {
+        " SQL Editor ".to_string()
+    };
+    if let Some((pos, total)) = editor.history_position() {
+        title = format!("{}— history {}/{} ", title, pos, total);
+    }
+    let border_color = if app.in_mode(&Mode::Editor) { Color::Yellow } else { Color::Blue };
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(border_color)),
+    );
+    f.render_widget(paragraph, area);
+
+    if app.in_mode(&Mode::Editor) {
+        let x = area.x + 1 + GUTTER + col as u16;
+        let y = area.y + 1 + (row - scroll) as u16;
+        if x < area.right().saturating_sub(1) && y < area.bottom().saturating_sub(1) {
+            f.set_cursor_position((x, y));
+        }
+    }
+}
+
+fn render_status(f: &mut Frame, area: Rect, app: &App) {
+    let paragraph = if app.sql_running {
+        let dots = ".".repeat(((app.frame / 5) % 4) as usize);
+        Paragraph::new(format!("Running query{} (Esc to cancel)", dots))
+            .style(Style::default().fg(Color::Yellow))
+    } else if let Some((candidates, index)) = app.sql_editor.completions() {
+        let mut spans = vec![Span::styled("Tab: ", Style::default().fg(Color::DarkGray))];
+        for (i, word) in candidates.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(" · ", Style::default().fg(Color::DarkGray)));
+            }
+            let style = if i == index {
+                Style::default().fg(Color::Black).bg(app.palette.selection)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            spans.push(Span::styled(word.clone(), style));
+        }
+        Paragraph::new(Line::from(spans))
+    } else if let Some(ref error) = app.sql_error {
+        Paragraph::new(error.as_str())
+            .block(
+                Block::default()
+                    .title(" Error ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Red)),
+            )
+            .style(Style::default().fg(Color::Red))
+            .wrap(Wrap { trim: true })
+    } else if app.sql_editor.is_empty() && !app.in_mode(&Mode::Editor) {
+        Paragraph::new("Press Enter to write a query; results open in the Data and Chart tabs")
+            .style(Style::default().fg(Color::DarkGray))
+    } else {
+        Paragraph::new("").style(Style::default().fg(Color::DarkGray))
+    };
+    f.render_widget(paragraph, area);
+}
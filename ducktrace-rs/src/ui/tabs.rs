@@ -3,17 +3,18 @@ use ratatui::{
     widgets::{Block, Borders, Tabs as RatatuiTabs},
 };
 
+use super::theme::Palette;
 use crate::app::Tab;
 
-pub fn render_tabs(f: &mut Frame, area: Rect, active_tab: Tab) {
-    let titles = vec!["Home", "Query", "Mask", "Data", "Chart"];
+pub fn render_tabs(f: &mut Frame, area: Rect, active_tab: Tab, palette: &Palette) {
+    let titles = vec!["Home", "Query", "Mask", "Data", "Chart", "SQL"];
 
     let tabs = RatatuiTabs::new(titles)
         .block(Block::default().borders(Borders::BOTTOM))
         .style(Style::default().fg(Color::White))
         .highlight_style(
             Style::default()
-                .fg(Color::Yellow)
+                .fg(palette.selection)
                 .add_modifier(Modifier::BOLD),
         )
         .select(active_tab as usize)
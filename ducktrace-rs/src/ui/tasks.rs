@@ -0,0 +1,89 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::app::App;
+use crate::tasks::{Task, TaskState};
+use super::centered_rect;
+
+/// Render the background task panel: every task newest first with its state and
+/// time, and the selected one's full label or error
+pub fn render_tasks(f: &mut Frame, app: &App, selected: usize) {
+    let area = centered_rect(80, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Background Tasks ({} running) ", app.tasks.running()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.palette.primary));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3), Constraint::Length(1)])
+        .split(inner);
+
+    let tasks: Vec<&Task> = app.tasks.list().collect();
+    if tasks.is_empty() {
+        let empty = Paragraph::new("\n  Nothing has run in the background yet this session")
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(empty, chunks[0]);
+    } else {
+        let selected = selected.min(tasks.len() - 1);
+        let height = chunks[0].height as usize;
+        let offset = (selected + 1).saturating_sub(height);
+        let width = chunks[0].width as usize;
+        let lines: Vec<Line> = tasks
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(height)
+            .map(|(i, task)| {
+                let (icon, color) = match task.state {
+                    TaskState::Running => ("⟳", app.palette.selection),
+                    TaskState::Done => ("✓", Color::Green),
+                    TaskState::Failed(_) => ("✗", Color::Red),
+                    TaskState::Cancelled => ("⊘", Color::DarkGray),
+                };
+                let head = format!(" {} {:<14} {:>7}  ", icon, task.kind.name(), format_elapsed(task));
+                let room = width.saturating_sub(head.chars().count());
+                let label: String = task.label.chars().take(room).collect();
+                let line = Line::from(vec![
+                    Span::styled(head, Style::default().fg(color)),
+                    Span::styled(label, Style::default().fg(Color::White)),
+                ]);
+                if i == selected {
+                    line.style(Style::default().bg(Color::DarkGray))
+                } else {
+                    line
+                }
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), chunks[0]);
+
+        let task = tasks[selected];
+        let detail = match task.state {
+            TaskState::Failed(ref error) => Line::styled(format!(" {}", error), Style::default().fg(Color::Red)),
+            _ => Line::styled(format!(" {}", task.label), Style::default().fg(Color::Gray)),
+        };
+        let detail_block = Block::default().borders(Borders::TOP).border_style(Style::default().fg(Color::DarkGray));
+        f.render_widget(Paragraph::new(detail).block(detail_block).wrap(Wrap { trim: false }), chunks[1]);
+    }
+
+    let help = Paragraph::new("↑↓ select | c cancel | Esc close")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[2]);
+}
+
+/// Run time with a tenth of a second, e.g. "12.4s", or minutes past a minute
+fn format_elapsed(task: &Task) -> String {
+    let elapsed = task.elapsed();
+    if elapsed.as_secs() >= 60 {
+        format!("{}m{:02}s", elapsed.as_secs() / 60, elapsed.as_secs() % 60)
+    } else {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    }
+}
@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+use log::warn;
+use ratatui::style::Color;
+
+use crate::data::ColorOverrides;
+
+/// Colors for chart data, selection highlights, and the banner, by role
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub primary: Color,
+    pub secondary: Color,
+    pub fill: Color,
+    pub selection: Color,
+    pub negative: Color,
+    pub series: Vec<Color>,
+    pub banner: Vec<Color>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            primary: Color::Cyan,
+            secondary: Color::Magenta,
+            fill: Color::Blue,
+            selection: Color::Yellow,
+            negative: Color::Red,
+            // The selection color is left out so highlighted series stay distinguishable
+            series: vec![
+                Color::Cyan,
+                Color::Magenta,
+                Color::Green,
+                Color::Blue,
+                Color::Red,
+                Color::LightCyan,
+                Color::LightMagenta,
+                Color::LightGreen,
+                Color::LightBlue,
+                Color::LightRed,
+            ],
+            // Yellow-to-cyan gradient (one color per letter: D U C K T R A C E)
+            banner: vec![
+                Color::Rgb(255, 255, 50),
+                Color::Rgb(220, 245, 60),
+                Color::Rgb(180, 235, 80),
+                Color::Rgb(130, 220, 110),
+                Color::Rgb(80, 210, 150),
+                Color::Rgb(50, 200, 180),
+                Color::Rgb(40, 190, 210),
+                Color::Rgb(30, 180, 235),
+                Color::Rgb(0, 170, 255),
+            ],
+        }
+    }
+}
+
+impl Palette {
+    /// Default palette with the config file's overrides, then the chart's, applied in turn
+    pub fn resolve(config: &ColorOverrides, chart: Option<&ColorOverrides>) -> Self {
+        let mut palette = Self::default();
+        palette.apply(config);
+        if let Some(chart) = chart {
+            palette.apply(chart);
+        }
+        palette
+    }
+
+    fn apply(&mut self, overrides: &ColorOverrides) {
+        for (slot, value) in [
+            (&mut self.primary, &overrides.primary),
+            (&mut self.secondary, &overrides.secondary),
+            (&mut self.fill, &overrides.fill),
+            (&mut self.selection, &overrides.selection),
+            (&mut self.negative, &overrides.negative),
+        ] {
+            if let Some(color) = value.as_deref().and_then(parse_logged) {
+                *slot = color;
+            }
+        }
+        for (slot, values) in [
+            (&mut self.series, &overrides.series),
+            (&mut self.banner, &overrides.banner),
+        ] {
+            let colors: Vec<Color> = values.iter().flatten().filter_map(|v| parse_logged(v)).collect();
+            if !colors.is_empty() {
+                *slot = colors;
+            }
+        }
+    }
+
+    /// Color for the i-th series, cycling through the series list
+    pub fn series_color(&self, i: usize) -> Color {
+        self.series[i % self.series.len()]
+    }
+}
+
+/// Parse a color name or `#rrggbb` hex
+pub fn parse_color(value: &str) -> Option<Color> {
+    Color::from_str(value).ok()
+}
+
+/// `parse_color`, logging values ratatui doesn't recognize. Only for colors
+/// resolved once, on load, so a bad one isn't logged every frame.
+fn parse_logged(value: &str) -> Option<Color> {
+    let color = parse_color(value);
+    if color.is_none() {
+        warn!("Ignoring unrecognized color {:?}", value);
+    }
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_and_hex() {
+        assert_eq!(parse_color("blue"), Some(Color::Blue));
+        assert_eq!(parse_color("lightred"), Some(Color::LightRed));
+        assert_eq!(parse_color("#1e66f5"), Some(Color::Rgb(0x1e, 0x66, 0xf5)));
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("not-a-color"), None);
+        assert_eq!(parse_color(""), None);
+
+        let overrides = ColorOverrides {
+            primary: Some("#ff0000".into()),
+            selection: Some("bogus".into()),
+            series: Some(vec!["green".into(), "bogus".into()]),
+            ..Default::default()
+        };
+        let palette = Palette::resolve(&overrides, None);
+        assert_eq!(palette.primary, Color::Rgb(255, 0, 0));
+        assert_eq!(palette.selection, Palette::default().selection);
+        assert_eq!(palette.series, vec![Color::Green]);
+    }
+}
@@ -0,0 +1,40 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::app::{App, ToastKind};
+
+/// Widest a toast gets, borders included
+const MAX_WIDTH: u16 = 56;
+
+/// Render the toasts stacked down the top-right corner, newest first, each a
+/// one-line box colored by kind. Stops at the bottom of the screen.
+pub fn render_toasts(f: &mut Frame, app: &App) {
+    let screen = f.area();
+    let width = MAX_WIDTH.min(screen.width.saturating_sub(2));
+    if width < 12 {
+        return;
+    }
+    let mut y = screen.y + 1;
+    for toast in app.toasts.iter().rev() {
+        if y + 3 > screen.bottom() {
+            break;
+        }
+        let (icon, color) = match toast.kind {
+            ToastKind::Info => ("•", app.palette.primary),
+            ToastKind::Success => ("✓", Color::Green),
+            ToastKind::Error => ("✗", Color::Red),
+        };
+        let room = width as usize - 6;
+        let short: String = toast.text.chars().take(room).collect();
+        let ellipsis = if short.len() < toast.text.len() { "…" } else { "" };
+        let text = format!("{} {}{}", icon, short, ellipsis);
+        let toast_width = (text.chars().count() as u16 + 4).min(width);
+        let area = Rect::new(screen.right().saturating_sub(toast_width + 1), y, toast_width, 3);
+        f.render_widget(Clear, area);
+        let block = Block::default().borders(Borders::ALL).border_style(Style::default().fg(color));
+        f.render_widget(Paragraph::new(format!(" {}", text)).style(Style::default().fg(color)).block(block), area);
+        y += 3;
+    }
+}
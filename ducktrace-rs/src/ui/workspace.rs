@@ -0,0 +1,60 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::app::App;
+use crate::data::truncate_string;
+use super::centered_rect;
+
+/// Render the chart switcher: every chart open in the workspace, numbered for
+/// `Tab` + digit, with the one on screen marked
+pub fn render_chart_picker(f: &mut Frame, app: &App, selected: usize) {
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Open Charts ({}) ", app.workspace.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.palette.primary));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    let lines: Vec<Line> = (0..app.workspace.len())
+        .filter_map(|i| Some((i, app.workspace_chart(i)?)))
+        .map(|(i, data)| {
+            let on_screen = app.data.is_some() && i == app.workspace_current;
+            let marker = if on_screen { "●" } else { " " };
+            let style = if i == selected {
+                Style::default().fg(Color::Black).bg(app.palette.selection)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let open = &app.workspace[i];
+            let source = match open.path.as_ref().and_then(|p| p.file_name()) {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None if open.sql => "SQL tab".to_string(),
+                None => "stdin".to_string(),
+            };
+            let kind = data.chart_type.as_deref().unwrap_or("auto");
+            Line::from(vec![
+                Span::styled(format!("{} {} {:<32}", marker, i + 1, truncate_string(&data.title, 32)), style.add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    format!("  {} · {} rows · {}", kind, data.rows.len(), source),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let help = Paragraph::new("1-9 or ↑↓ Enter switch | d close chart | Esc back")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    f.render_widget(help, chunks[1]);
+}
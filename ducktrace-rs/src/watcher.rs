@@ -1,22 +1,111 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use notify::event::{EventKind, ModifyKind, RenameMode};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
-use crate::data::{ChartData, HistoryEntry};
+use crate::catalog;
+use crate::data::{default_axes, file_drill_down, file_query, is_chart_file, parse_spec, sort_history, validate_chart, ChartData, HistoryEntry, HistoryMeta, QueryLogEntry};
+use crate::db::{QueryCancel, QueryExecutor};
+
+/// Data file chosen with `--file` or `DUCKTRACE_FILE`, set once at startup
+static DATA_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Watch `path` instead of the default data file. Relative paths are resolved
+/// against the current directory; only the first call has any effect.
+pub fn set_data_path(path: PathBuf) {
+    let path = std::path::absolute(&path).unwrap_or(path);
+    let _ = DATA_PATH.set(path);
+}
+
+/// Directory of chart files chosen with `--dir` or `DUCKTRACE_DIR`, set once at startup
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Watch every `*.json` chart in `dir` instead of a single data file. Only the
+/// first call has any effect.
+pub fn set_data_dir(dir: PathBuf) {
+    let dir = std::path::absolute(&dir).unwrap_or(dir);
+    let _ = DATA_DIR.set(dir);
+}
+
+/// The watched chart directory, when running in directory mode
+pub fn get_data_dir() -> Option<&'static PathBuf> {
+    DATA_DIR.get()
+}
+
+/// Set by `--stdin`: charts are piped in rather than watched on disk
+static READ_STDIN: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_stdin() {
+    READ_STDIN.store(true, Ordering::Relaxed);
+}
+
+/// Whether charts come from stdin instead of a watched file
+pub fn reads_stdin() -> bool {
+    READ_STDIN.load(Ordering::Relaxed)
+}
+
+/// Charts kept in the history directory, from `history_limit` in the config
+static HISTORY_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_HISTORY_LIMIT);
+
+/// Charts kept in history when the config doesn't say
+pub const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+pub fn set_history_limit(limit: usize) {
+    HISTORY_LIMIT.store(limit.max(1), Ordering::Relaxed);
+}
 
 /// Get the path to the data file
 pub fn get_data_path() -> PathBuf {
+    if let Some(path) = DATA_PATH.get() {
+        return path.clone();
+    }
     dirs::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join(".claude/ducktrace/current.json")
 }
 
-/// Load chart data from the file, applying row limits and timestamp
-pub fn load_data(path: &PathBuf) -> Result<ChartData> {
+/// Get the path of the NDJSON file whose lines are appended to the open chart:
+/// the data file with an `.ndjson` extension, `current.ndjson` by default
+pub fn get_append_path() -> PathBuf {
+    get_data_path().with_extension("ndjson")
+}
+
+/// Load chart data from the file, applying row limits and timestamp. CSV, TSV, and
+/// Parquet files are read with an in-memory DuckDB and charted like an ad-hoc
+/// query, titled with the file name, with a date or label column against a measure.
+/// Re-runs and drill-downs read the same file in memory.
+pub fn load_data(path: &Path) -> Result<ChartData> {
+    if let Some(query) = file_query(path) {
+        let exec = QueryExecutor::connect(":memory:")?;
+        let (columns, rows) = exec
+            .run_query(&query, None, &QueryCancel::default())
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let axes = default_axes(&columns, &rows);
+        let mut data = ChartData::from_query(&query, columns, rows, None);
+        if let Some((x, y)) = axes {
+            data.x_field = x;
+            data.y_field = y;
+        }
+        data.drill_down = Some(file_drill_down(&query, &data.x_field));
+        data.database_path = Some(":memory:".to_string());
+        if let Some(name) = path.file_name() {
+            data.title = name.to_string_lossy().into_owned();
+        }
+        return Ok(data);
+    }
     let content = std::fs::read_to_string(path)?;
-    let mut data: ChartData = serde_json::from_str(&content)?;
+    let document = parse_spec(path, &content)?;
+    let problems = validate_chart(&document);
+    if !problems.is_empty() {
+        // First line for the status bar, then one problem per line for the panel
+        let count = if problems.len() == 1 { "1 problem".to_string() } else { format!("{} problems", problems.len()) };
+        bail!("{} with the chart\n{}", count, problems.join("\n"));
+    }
+    let mut data: ChartData = serde_json::from_value(document)?;
     data.apply_row_limit();
     data.ensure_timestamp();
     Ok(data)
@@ -29,56 +118,204 @@ pub fn get_history_dir() -> PathBuf {
         .join(".claude/ducktrace/history")
 }
 
-/// Load history entries from the history directory, sorted newest-first
+/// Get the default path of the socket opened by `--socket`
+pub fn get_socket_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".claude/ducktrace/ducktrace.sock")
+}
+
+/// Get the path to the on-disk drill-down query log
+pub fn get_query_log_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".claude/ducktrace/queries.ndjson")
+}
+
+/// Append a drill-down to the on-disk query log, one JSON object per line
+pub fn append_query_log(entry: &QueryLogEntry) -> Result<()> {
+    use std::io::Write;
+    let path = get_query_log_path();
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Sidecar holding a history file's [`HistoryMeta`]
+pub fn meta_path(path: &Path) -> PathBuf {
+    path.with_extension("meta")
+}
+
+/// Metadata for the history file at `path`; defaults when there is none
+pub fn load_history_meta(path: &Path) -> HistoryMeta {
+    std::fs::read_to_string(meta_path(path))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Save metadata for the history file at `path`, removing the sidecar when it
+/// holds only defaults
+pub fn save_history_meta(path: &Path, meta: &HistoryMeta) -> Result<()> {
+    let sidecar = meta_path(path);
+    if *meta == HistoryMeta::default() {
+        if sidecar.exists() {
+            std::fs::remove_file(&sidecar)?;
+        }
+        return Ok(());
+    }
+    std::fs::write(&sidecar, serde_json::to_string(meta)?)
+        .with_context(|| format!("Failed to write {}", sidecar.display()))
+}
+
+/// Delete a history file along with its metadata
+pub fn remove_history_entry(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(meta_path(path));
+}
+
+/// Retitle a history chart in place. The archive's hash suffix isn't changed, so
+/// the original chart arriving again is still recognized as archived.
+pub fn rename_history_entry(path: &Path, title: &str) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut document: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    let Some(fields) = document.as_object_mut() else {
+        bail!("{} is not a chart object", path.display());
+    };
+    fields.insert("title".to_string(), serde_json::Value::String(title.to_string()));
+    std::fs::write(path, serde_json::to_string_pretty(&document)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Load history entries from the history directory, pinned first then newest-first.
+/// Entries come from the history catalog, which only re-reads charts that changed;
+/// if it can't be opened the charts are read directly.
 pub fn load_history_entries() -> Vec<HistoryEntry> {
     let history_dir = get_history_dir();
-    let entries = match std::fs::read_dir(&history_dir) {
+    if !history_dir.is_dir() {
+        return Vec::new();
+    }
+
+    let mut history = catalog::load_entries(&history_dir, read_history_entry).unwrap_or_else(|e| {
+        log::debug!("History catalog unavailable, reading charts directly: {:#}", e);
+        std::fs::read_dir(&history_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json"))
+                    .filter_map(|path| read_history_entry(&path))
+                    .collect()
+            })
+            .unwrap_or_default()
+    });
+
+    sort_history(&mut history);
+    history
+}
+
+/// Parse the history chart at `path` into its Home tab entry
+fn read_history_entry(path: &Path) -> Option<HistoryEntry> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let data: ChartData = serde_json::from_str(&content).ok()?;
+    Some(HistoryEntry {
+        title: data.title,
+        timestamp: data.timestamp.unwrap_or(0),
+        row_count: data.rows.len(),
+        chart_type: data.chart_type,
+        database: data.database,
+        x_field: data.x_field,
+        y_field: data.y_field,
+        meta: load_history_meta(path),
+        path: path.to_path_buf(),
+    })
+}
+
+/// Load an entry for each chart in `dir`, sorted by file name. Files that don't
+/// parse as charts are skipped.
+pub fn load_dir_entries(dir: &Path) -> Vec<HistoryEntry> {
+    let entries = match std::fs::read_dir(dir) {
         Ok(entries) => entries,
         Err(_) => return Vec::new(),
     };
 
-    let mut history: Vec<HistoryEntry> = entries
+    let mut charts: Vec<HistoryEntry> = entries
         .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            let path = entry.ok()?.path();
+            if !is_chart_file(&path) {
                 return None;
             }
-            let content = std::fs::read_to_string(&path).ok()?;
-            let data: ChartData = serde_json::from_str(&content).ok()?;
-            let timestamp = data.timestamp.unwrap_or(0);
+            let data = load_data(&path).ok()?;
+            // When the file last changed; `load_data` stamps untimed charts with now
+            let modified = std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64);
             Some(HistoryEntry {
-                path,
-                title: data.title,
-                timestamp,
+                timestamp: modified.or(data.timestamp).unwrap_or(0),
                 row_count: data.rows.len(),
+                title: data.title,
                 chart_type: data.chart_type,
+                database: data.database,
+                x_field: data.x_field,
+                y_field: data.y_field,
+                meta: HistoryMeta::default(),
+                path,
             })
         })
         .collect();
 
-    history.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-    history.truncate(20);
-    history
+    charts.sort_by(|a, b| a.path.cmp(&b.path));
+    charts
 }
 
-/// Archive current.json into the history directory, rotating to keep 20
+/// Archive the data file into the history directory, rotating to the history limit.
+/// A YAML or TOML spec is archived as the JSON it reads as.
 fn archive_to_history(data_path: &PathBuf) {
+    let Ok(content) = std::fs::read_to_string(data_path) else { return };
+    if data_path.extension().is_some_and(|e| e == "json") {
+        archive_content(&content);
+    } else if let Ok(document) = parse_spec(data_path, &content) {
+        archive_content(&serde_json::to_string_pretty(&document).unwrap_or_default());
+    }
+}
+
+/// Write `data` to `path` as pretty JSON and archive it to history, for charts made
+/// outside the TUI (`ducktrace query`)
+pub fn save_chart(path: &Path, data: &ChartData) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(data)?;
+    std::fs::write(path, &content).with_context(|| format!("Failed to write {}", path.display()))?;
+    archive_content(&content);
+    Ok(())
+}
+
+/// Archive a chart's JSON into the history directory as `<timestamp>-<hash>.json`,
+/// rotating to the history limit. A chart whose content is already archived is skipped, so
+/// the several events of one write, or the same chart sent twice, leave one entry.
+fn archive_content(content: &str) {
     let history_dir = get_history_dir();
     if std::fs::create_dir_all(&history_dir).is_err() {
         return;
     }
 
-    let content = match std::fs::read_to_string(data_path) {
-        Ok(c) => c,
-        Err(_) => return,
-    };
-
     // Parse to get timestamp for the filename
-    let data: ChartData = match serde_json::from_str(&content) {
-        Ok(d) => d,
-        Err(_) => return,
-    };
+    let Ok(document) = serde_json::from_str::<serde_json::Value>(content) else { return };
+    // Hashed in serde_json's sorted-key form, so formatting doesn't matter
+    let hash = content_hash(&document.to_string());
+    let Ok(data) = serde_json::from_value::<ChartData>(document) else { return };
+
+    let suffix = format!("-{}.json", hash);
+    let archived = std::fs::read_dir(&history_dir).is_ok_and(|mut entries| {
+        entries.any(|e| e.is_ok_and(|e| e.file_name().to_string_lossy().ends_with(&suffix)))
+    });
+    if archived {
+        return;
+    }
 
     let ts = data.timestamp.unwrap_or_else(|| {
         std::time::SystemTime::now()
@@ -87,12 +324,13 @@ fn archive_to_history(data_path: &PathBuf) {
             .unwrap_or(0)
     });
 
-    let history_file = history_dir.join(format!("{}.json", ts));
-    if std::fs::write(&history_file, &content).is_err() {
+    let history_file = history_dir.join(format!("{}{}", ts, suffix));
+    if std::fs::write(&history_file, content).is_err() {
         return;
     }
 
-    // Rotate: keep only the 20 most recent
+    // Rotate: keep only the most recent, `history_limit` of them. Pinned charts
+    // are kept regardless and don't count towards the limit.
     if let Ok(entries) = std::fs::read_dir(&history_dir) {
         let mut files: Vec<_> = entries
             .filter_map(|e| e.ok())
@@ -103,17 +341,70 @@ fn archive_to_history(data_path: &PathBuf) {
                     == Some("json")
             })
             .map(|e| e.path())
+            .filter(|path| !load_history_meta(path).pinned)
             .collect();
         files.sort();
         files.reverse();
-        for old in files.into_iter().skip(20) {
-            let _ = std::fs::remove_file(old);
+        for old in files.into_iter().skip(HISTORY_LIMIT.load(Ordering::Relaxed)) {
+            remove_history_entry(&old);
+        }
+    }
+}
+
+/// 64-bit FNV-1a of `content` in hex. Unlike `DefaultHasher` it's stable across
+/// builds, so hashes in archived file names stay comparable.
+fn content_hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Waits between attempts to parse a changed file that isn't valid yet
+const RETRY_DELAYS_MS: [u64; 5] = [50, 100, 200, 400, 800];
+
+/// A watched file's new chart, or why it couldn't be read
+pub type FileUpdate = (PathBuf, std::result::Result<ChartData, String>);
+
+/// Whether `event` created, changed, or removed a file. Reading a watched file
+/// raises access events of its own, which would otherwise trigger another read.
+fn is_write(event: &notify::Event) -> bool {
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+}
+
+/// Whether `event` renamed a file into place at `path`. Atomic writers finish a
+/// temporary file and rename it over the target, so its contents are complete.
+fn is_rename_into(event: &notify::Event, path: &Path) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To | RenameMode::Both))
+    ) && event.paths.last().is_some_and(|p| p.file_name() == path.file_name())
+}
+
+/// Read a changed chart, retrying with backoff while it doesn't parse: writers that
+/// don't rename into place may still be part-way through. A file renamed into
+/// place is read straight away.
+async fn read_changed(path: &Path, renamed: bool) -> std::result::Result<ChartData, String> {
+    if !renamed {
+        tokio::time::sleep(Duration::from_millis(RETRY_DELAYS_MS[0])).await;
+    }
+    let mut result = load_data(path);
+    for delay in RETRY_DELAYS_MS.iter().skip(1) {
+        if result.is_ok() {
+            break;
         }
+        tokio::time::sleep(Duration::from_millis(*delay)).await;
+        result = load_data(path);
     }
+    result.map_err(|e| {
+        log::warn!("Malformed chart file {}: {:#}", path.display(), e);
+        format!("{:#}", e)
+    })
 }
 
-/// Watch the data file and send updates through the channel
-pub async fn watch_file(tx: mpsc::Sender<ChartData>) -> Result<()> {
+/// Watch the data file and send updates, with its path, through the channel. A
+/// file that still doesn't parse after retrying is reported rather than skipped.
+pub async fn watch_file(tx: mpsc::Sender<FileUpdate>) -> Result<()> {
     let path = get_data_path();
 
     // Create directory if it doesn't exist
@@ -123,9 +414,8 @@ pub async fn watch_file(tx: mpsc::Sender<ChartData>) -> Result<()> {
 
     // Try to load initial data
     if path.exists() {
-        if let Ok(data) = load_data(&path) {
-            let _ = tx.send(data).await;
-        }
+        let result = load_data(&path).map_err(|e| format!("{:#}", e));
+        let _ = tx.send((path.clone(), result)).await;
     }
 
     // Set up file watcher
@@ -146,22 +436,234 @@ pub async fn watch_file(tx: mpsc::Sender<ChartData>) -> Result<()> {
     // Keep watcher alive and process events
     loop {
         if let Some(Ok(event)) = watcher_rx.recv().await {
-            // Check if this event is for our file
-            let is_our_file = event.paths.iter().any(|p| {
-                p.file_name()
-                    .map(|n| n == "current.json")
-                    .unwrap_or(false)
-            });
-
-            if is_our_file {
-                // Small delay to ensure file is fully written
-                tokio::time::sleep(Duration::from_millis(50)).await;
-
-                if let Ok(data) = load_data(&path) {
+            // Check if this event is for our file, and that it wasn't removed or
+            // renamed away
+            let is_our_file = is_write(&event) && event.paths.iter().any(|p| p.file_name() == path.file_name());
+
+            if is_our_file && path.exists() {
+                let result = read_changed(&path, is_rename_into(&event, &path)).await;
+                if result.is_ok() {
+                    archive_to_history(&path);
+                }
+                let _ = tx.send((path.clone(), result)).await;
+            }
+        }
+    }
+}
+
+/// Watch the user config file and send it, reparsed, each time it changes; a parse
+/// error is sent instead so the last good config can be kept. A deleted file reads
+/// as the defaults.
+pub async fn watch_config(
+    path: PathBuf,
+    tx: mpsc::Sender<std::result::Result<crate::config::Config, String>>,
+) -> Result<()> {
+    let Some(parent) = path.parent() else { return Ok(()) };
+    std::fs::create_dir_all(parent)?;
+
+    let (watcher_tx, mut watcher_rx) = tokio::sync::mpsc::channel::<notify::Result<notify::Event>>(16);
+    let mut watcher = RecommendedWatcher::new(
+        move |res| {
+            let _ = watcher_tx.blocking_send(res);
+        },
+        Config::default().with_poll_interval(Duration::from_millis(100)),
+    )?;
+    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+
+    loop {
+        if let Some(Ok(event)) = watcher_rx.recv().await {
+            if !is_write(&event) || !event.paths.iter().any(|p| p.file_name() == path.file_name()) {
+                continue;
+            }
+            // Let an editor finish saving, then read once for the burst of events
+            tokio::time::sleep(Duration::from_millis(RETRY_DELAYS_MS[0])).await;
+            while watcher_rx.try_recv().is_ok() {}
+            let result = crate::config::load_config(&path).map_err(|e| format!("{:#}", e));
+            if tx.send(result).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Tail the append file and send the rows of each complete line added to it. Only
+/// lines written after startup are read; a file that shrinks was truncated for a new
+/// chart, so it is read again from the start. Lines that aren't JSON are skipped.
+pub async fn watch_append(tx: mpsc::Sender<Vec<serde_json::Value>>) -> Result<()> {
+    let path = get_append_path();
+    let Some(parent) = path.parent() else { return Ok(()) };
+    std::fs::create_dir_all(parent)?;
+    let mut offset = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+    let (watcher_tx, mut watcher_rx) = tokio::sync::mpsc::channel::<notify::Result<notify::Event>>(16);
+    let mut watcher = RecommendedWatcher::new(
+        move |res| {
+            let _ = watcher_tx.blocking_send(res);
+        },
+        Config::default().with_poll_interval(Duration::from_millis(100)),
+    )?;
+    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+
+    loop {
+        if let Some(Ok(event)) = watcher_rx.recv().await {
+            if !is_write(&event) || !event.paths.iter().any(|p| p.file_name() == path.file_name()) {
+                continue;
+            }
+            let Ok(content) = std::fs::read(&path) else {
+                offset = 0;
+                continue;
+            };
+            if (content.len() as u64) < offset {
+                offset = 0;
+            }
+            // A line still being written is picked up with the next event
+            let pending = &content[offset as usize..];
+            let Some(end) = pending.iter().rposition(|&b| b == b'\n') else { continue };
+            offset += end as u64 + 1;
+
+            let rows: Vec<serde_json::Value> = pending[..end]
+                .split(|&b| b == b'\n')
+                .filter(|line| !line.trim_ascii().is_empty())
+                .filter_map(|line| match serde_json::from_slice(line) {
+                    Ok(row) => Some(row),
+                    Err(e) => {
+                        log::warn!("Skipping malformed line in {}: {}", path.display(), e);
+                        None
+                    }
+                })
+                .collect();
+            if !rows.is_empty() && tx.send(rows).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Watch every chart in `dir` and send each one that changes, with its path. The
+/// most recently modified chart is sent first so there is something to show.
+pub async fn watch_dir(dir: PathBuf, tx: mpsc::Sender<FileUpdate>) -> Result<()> {
+    std::fs::create_dir_all(&dir)?;
+
+    let newest = std::fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| is_chart_file(&e.path()))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .max();
+    if let Some((_, path)) = newest {
+        let result = load_data(&path).map_err(|e| format!("{:#}", e));
+        let _ = tx.send((path, result)).await;
+    }
+
+    let (watcher_tx, mut watcher_rx) = tokio::sync::mpsc::channel::<notify::Result<notify::Event>>(16);
+    let mut watcher = RecommendedWatcher::new(
+        move |res| {
+            let _ = watcher_tx.blocking_send(res);
+        },
+        Config::default().with_poll_interval(Duration::from_millis(100)),
+    )?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    loop {
+        if let Some(Ok(event)) = watcher_rx.recv().await {
+            if !is_write(&event) {
+                continue;
+            }
+            // Removed charts, and the old name of a renamed one, no longer exist
+            let mut changed: Vec<PathBuf> = event
+                .paths
+                .iter()
+                .filter(|p| is_chart_file(p) && p.exists())
+                .cloned()
+                .collect();
+            changed.dedup();
+
+            for path in changed {
+                let result = read_changed(&path, is_rename_into(&event, &path)).await;
+                if result.is_ok() {
                     archive_to_history(&path);
-                    let _ = tx.send(data).await;
                 }
+                let _ = tx.send((path, result)).await;
+            }
+        }
+    }
+}
+
+/// Read charts from stdin and send each one through the channel. Accepts a single
+/// (possibly pretty-printed) document or a stream of them, such as NDJSON; reading
+/// stops at end of input or at the first document that isn't a chart.
+pub fn read_stdin(tx: mpsc::Sender<ChartData>) -> Result<()> {
+    let stdin = std::io::stdin().lock();
+    for document in serde_json::Deserializer::from_reader(stdin).into_iter::<serde_json::Value>() {
+        let data = document.map_err(anyhow::Error::from).and_then(accept_chart);
+        let data = data.context("Invalid chart on stdin")?;
+        if tx.blocking_send(data).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Parse a chart from stdin, the socket, HTTP, or WebSocket, archiving it to history
+/// like a file update, and apply row limits and timestamp as [`load_data`] does
+pub fn accept_chart(document: serde_json::Value) -> Result<ChartData> {
+    let content = document.to_string();
+    let mut data: ChartData = serde_json::from_value(document)?;
+    archive_content(&content);
+    data.apply_row_limit();
+    data.ensure_timestamp();
+    Ok(data)
+}
+
+/// Accept charts on a unix socket at `path` and send each one, with the socket's
+/// path, through the channel. A client writes one or more JSON documents and closes
+/// its end; every chart is archived like a file update and answered with a line of
+/// `ok` or `error: <reason>`.
+#[cfg(unix)]
+pub async fn listen_socket(path: PathBuf, tx: mpsc::Sender<(PathBuf, ChartData)>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // A socket left behind by an earlier run would make bind fail
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(&path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        // Each client gets its own task so a slow one doesn't hold up the rest
+        tokio::spawn(serve_socket_client(stream, path.clone(), tx.clone()));
+    }
+}
+
+/// Read one client's charts to the end and answer each, see [`listen_socket`]
+#[cfg(unix)]
+async fn serve_socket_client(
+    mut stream: tokio::net::UnixStream,
+    path: PathBuf,
+    tx: mpsc::Sender<(PathBuf, ChartData)>,
+) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut payload = Vec::new();
+    if stream.read_to_end(&mut payload).await.is_err() {
+        return;
+    }
+
+    let mut replies = String::new();
+    for document in serde_json::Deserializer::from_slice(&payload).into_iter::<serde_json::Value>() {
+        match document.map_err(anyhow::Error::from).and_then(accept_chart) {
+            Ok(data) => {
+                let _ = tx.send((path.clone(), data)).await;
+                replies.push_str("ok\n");
+            }
+            Err(e) => {
+                log::warn!("Invalid chart on {}: {}", path.display(), e);
+                replies.push_str(&format!("error: {}\n", e));
+                break;
             }
         }
     }
+    let _ = stream.write_all(replies.as_bytes()).await;
 }